@@ -0,0 +1,128 @@
+use bytes::Bytes;
+use vivs::{
+    client::{Client, ClientError, Cmd, FromReply, Pipeline},
+    data_chunk::DataChunk,
+};
+
+/// One SCAN call, returns the next cursor ("0" once the iteration is over) and the keys
+async fn scan_page(
+    client: &mut Client,
+    cursor: &str,
+    pattern: Option<&str>,
+    count: usize,
+) -> Result<(String, Vec<Bytes>), ClientError> {
+    let mut cmd = Cmd::new("SCAN").arg(cursor);
+    if let Some(pattern) = pattern {
+        cmd = cmd.arg("MATCH").arg(pattern);
+    }
+    cmd = cmd.arg("COUNT").arg(count.to_string());
+
+    let mut reply = Vec::<DataChunk>::from_reply(client.query(&cmd).await?)?.into_iter();
+
+    let cursor = String::from_reply(reply.next().unwrap_or_default())?;
+    let keys = Vec::<Bytes>::from_reply(reply.next().unwrap_or_default())?;
+
+    Ok((cursor, keys))
+}
+
+/// Prints every key (that matches the pattern), one per line
+pub async fn scan(
+    client: &mut Client,
+    pattern: Option<&str>,
+    count: usize,
+) -> Result<(), ClientError> {
+    let mut cursor = "0".to_owned();
+
+    loop {
+        let (next_cursor, keys) = scan_page(client, &cursor, pattern, count).await?;
+
+        for key in keys {
+            println!("{}", String::from_utf8_lossy(&key));
+        }
+
+        if next_cursor == "0" {
+            return Ok(());
+        }
+        cursor = next_cursor;
+    }
+}
+
+/// Goes over the whole keyspace and reports the biggest value, same as redis-cli --bigkeys
+pub async fn bigkeys(client: &mut Client, count: usize) -> Result<(), ClientError> {
+    println!("# Scanning the entire keyspace to find biggest keys");
+    println!();
+
+    let mut cursor = "0".to_owned();
+    let mut sampled = 0;
+    let mut total_key_len = 0;
+    let mut total_value_len = 0;
+    let mut biggest: Option<(Bytes, usize)> = None;
+
+    loop {
+        let (next_cursor, keys) = scan_page(client, &cursor, None, count).await?;
+
+        // Values of the whole page are fetched in one go
+        let pipeline = keys.iter().fold(Pipeline::new(), |pipeline, key| {
+            pipeline.cmd(Cmd::new("GET").arg(key))
+        });
+        let values = client.query_pipeline(&pipeline).await?;
+
+        for (key, value) in keys.into_iter().zip(values) {
+            // Keys that got deleted or expired in the meantime
+            let DataChunk::Bulk(value) = value else {
+                continue;
+            };
+
+            sampled += 1;
+            total_key_len += key.len();
+            total_value_len += value.len();
+
+            if biggest.as_ref().is_none_or(|(_, size)| value.len() > *size) {
+                println!(
+                    "Biggest string found so far '{}' with {} bytes",
+                    String::from_utf8_lossy(&key),
+                    value.len()
+                );
+                biggest = Some((key, value.len()));
+            }
+        }
+
+        if next_cursor == "0" {
+            break;
+        }
+        cursor = next_cursor;
+    }
+
+    let avg = |total: usize| {
+        if sampled == 0 {
+            0.0
+        } else {
+            total as f64 / sampled as f64
+        }
+    };
+
+    println!();
+    println!("-------- summary -------");
+    println!();
+    println!("Sampled {sampled} keys in the keyspace!");
+    println!(
+        "Total key length in bytes is {total_key_len} (avg len {:.2})",
+        avg(total_key_len)
+    );
+
+    if let Some((key, size)) = biggest {
+        println!();
+        println!(
+            "Biggest string found '{}' has {size} bytes",
+            String::from_utf8_lossy(&key)
+        );
+    }
+
+    println!();
+    println!(
+        "{sampled} strings with {total_value_len} bytes (avg size {:.2})",
+        avg(total_value_len)
+    );
+
+    Ok(())
+}
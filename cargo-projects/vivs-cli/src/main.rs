@@ -0,0 +1,128 @@
+//! Non-interactive counterpart of `vivs-repl`, meant to be used from shell scripts.
+//!
+//! Exit codes:
+//! - 0 - the command succeeded
+//! - 1 - the server replied with an error (or some of the piped commands failed)
+//! - 2 - invalid arguments or the server could not be reached
+use clap::Parser as ClapParser;
+use std::process::ExitCode;
+use vivs::{
+    client::{
+        cli::{format_reply, OutputMode},
+        Client, ClientError, Cmd,
+    },
+    data_chunk::DataChunk,
+    PORT,
+};
+
+mod keys;
+mod pipe;
+
+#[derive(Debug, ClapParser)]
+#[command(about = "Runs Vivs commands from the command line")]
+struct Cli {
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    #[arg(long, short, default_value_t = PORT)]
+    port: u16,
+    /// Prints replies as they are, one value per line
+    #[arg(long, conflicts_with = "json")]
+    raw: bool,
+    /// Prints each reply as a JSON value
+    #[arg(long)]
+    json: bool,
+    /// Sends the commands read from stdin (RESP or one command per line) in one go
+    #[arg(long, conflicts_with_all = ["scan", "bigkeys"])]
+    pipe: bool,
+    /// Lists the keys (that match --pattern)
+    #[arg(long, conflicts_with = "bigkeys")]
+    scan: bool,
+    /// Pattern the keys need to match i.e. user:*
+    #[arg(long, requires = "scan")]
+    pattern: Option<String>,
+    /// Number of keys requested per SCAN call
+    #[arg(long, default_value_t = 100)]
+    count: usize,
+    /// Samples the keyspace and reports the biggest keys
+    #[arg(long)]
+    bigkeys: bool,
+    /// Command and its arguments i.e. SET greeting hello
+    command: Vec<String>,
+}
+
+pub const EXIT_REPLY_ERROR: u8 = 1;
+pub const EXIT_FAILURE: u8 = 2;
+
+async fn run_command(client: &mut Client, args: &[String], output: OutputMode) -> ExitCode {
+    let Some((name, args)) = args.split_first() else {
+        eprintln!("No command supplied");
+        return ExitCode::from(EXIT_FAILURE);
+    };
+
+    let cmd = args.iter().fold(Cmd::new(name), |cmd, arg| cmd.arg(arg));
+
+    match client.query::<DataChunk>(&cmd).await {
+        Ok(reply) => {
+            println!("{}", format_reply(&reply, output));
+
+            if matches!(reply, DataChunk::SimpleError(_)) {
+                ExitCode::from(EXIT_REPLY_ERROR)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Errors of the helpers (--scan, --bigkeys) are printed and turned into an exit code
+fn exit_code(result: Result<(), ClientError>) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(ClientError::Server(e)) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_REPLY_ERROR)
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let address = format!("{}:{}", cli.host, cli.port);
+
+    if cli.pipe {
+        return pipe::run(&address).await;
+    }
+
+    let mut client = match Client::builder().address(&address).connect().await {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Could not connect to {address}: {e}");
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    if cli.scan {
+        return exit_code(keys::scan(&mut client, cli.pattern.as_deref(), cli.count).await);
+    }
+
+    if cli.bigkeys {
+        return exit_code(keys::bigkeys(&mut client, cli.count).await);
+    }
+
+    let output = match (cli.raw, cli.json) {
+        (_, true) => OutputMode::Json,
+        (true, _) => OutputMode::Raw,
+        _ => OutputMode::Pretty,
+    };
+
+    run_command(&mut client, &cli.command, output).await
+}
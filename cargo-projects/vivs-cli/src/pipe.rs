@@ -0,0 +1,130 @@
+use crate::{EXIT_FAILURE, EXIT_REPLY_ERROR};
+use bytes::{Buf, BytesMut};
+use std::{io::Cursor, process::ExitCode};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use vivs::{
+    client::{cli::split_args, Cmd},
+    data_chunk::{DataChunk, DataChunkError},
+    GenericResult,
+};
+
+/// Commands to send and how many replies to expect back.
+///
+/// Input that starts with * is treated as RESP (i.e. generated by a script),
+/// the rest of the input is read as one command per line, same as the REPL reads it.
+fn encode_input(input: Vec<u8>) -> GenericResult<(Vec<u8>, usize)> {
+    if input.first() == Some(&b'*') {
+        let mut cursor = Cursor::new(&input[..]);
+        let mut count = 0;
+
+        while cursor.has_remaining() {
+            DataChunk::read_chunk(&mut cursor)
+                .map_err(|e| format!("Invalid RESP input after {count} commands: {e}"))?;
+            count += 1;
+        }
+
+        return Ok((input, count));
+    }
+
+    let input = String::from_utf8(input)?;
+    let mut encoded = vec![];
+    let mut count = 0;
+
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        let args = split_args(line)?;
+        let Some((name, args)) = args.split_first() else {
+            continue;
+        };
+        let cmd = args.iter().fold(Cmd::new(name), |cmd, arg| cmd.arg(arg));
+
+        encoded.extend_from_slice(&cmd.to_bytes());
+        count += 1;
+    }
+
+    Ok((encoded, count))
+}
+
+/// Reads `count` replies, returns the number of error replies
+async fn read_replies(
+    stream: &mut tokio::net::tcp::OwnedReadHalf,
+    count: usize,
+) -> GenericResult<usize> {
+    let mut buffer = BytesMut::with_capacity(64 * 1024);
+    let mut replies = 0;
+    let mut errors = 0;
+
+    while replies < count {
+        let mut cursor = Cursor::new(&buffer[..]);
+
+        match DataChunk::read_chunk(&mut cursor) {
+            Ok(reply) => {
+                let position = cursor.position() as usize;
+                buffer.advance(position);
+
+                replies += 1;
+                if let DataChunk::SimpleError(_) = reply {
+                    errors += 1;
+                }
+                continue;
+            }
+            // Rest of the reply has not arrived yet
+            Err(DataChunkError::Insufficient | DataChunkError::NoBytesRemaining) => {}
+            Err(e) => Err(e)?,
+        }
+
+        if stream.read_buf(&mut buffer).await? == 0 {
+            Err(format!(
+                "Connection closed after {replies} out of {count} replies"
+            ))?;
+        }
+    }
+
+    Ok(errors)
+}
+
+async fn pipe(address: &str) -> GenericResult<(usize, usize)> {
+    let mut input = vec![];
+    tokio::io::stdin().read_to_end(&mut input).await?;
+
+    let (encoded, count) = encode_input(input)?;
+
+    let stream = TcpStream::connect(address).await?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    // Replies are read while the commands are still being written,
+    // otherwise both sides could end up waiting for each other with full buffers
+    let write = tokio::spawn(async move {
+        writer.write_all(&encoded).await?;
+        writer.flush().await?;
+        // The write half is kept open, the server stops replying once the connection is closed
+        Ok::<_, std::io::Error>(writer)
+    });
+
+    let errors = read_replies(&mut reader, count).await?;
+    let _writer = write.await??;
+
+    Ok((count, errors))
+}
+
+/// Sends everything read from stdin and reports how many replies and errors came back
+pub async fn run(address: &str) -> ExitCode {
+    match pipe(address).await {
+        Ok((replies, errors)) => {
+            println!("All data transferred. Last reply received from server.");
+            println!("errors: {errors}, replies: {replies}");
+
+            if errors > 0 {
+                ExitCode::from(EXIT_REPLY_ERROR)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod cli {
+    use std::{net::SocketAddr, process::Stdio};
+    use tokio::{io::AsyncWriteExt, net::TcpListener, process::Command};
+    use vivs::{DataStore, Listener};
+
+    async fn init_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let address = listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        tokio::spawn(listener.run());
+
+        address
+    }
+
+    fn vivs_cli(addr: SocketAddr) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_vivs-cli"));
+        command.arg("--port").arg(addr.port().to_string());
+        command
+    }
+
+    async fn run(addr: SocketAddr, args: &[&str]) -> (Option<i32>, String) {
+        let output = vivs_cli(addr).args(args).output().await.unwrap();
+        (
+            output.status.code(),
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn runs_single_command() {
+        let addr = init_server().await;
+
+        assert_eq!(
+            run(addr, &["SET", "greeting", "hello world"]).await,
+            (Some(0), "\"OK\"\n".to_owned())
+        );
+        assert_eq!(
+            run(addr, &["--raw", "GET", "greeting"]).await,
+            (Some(0), "hello world\n".to_owned())
+        );
+        assert_eq!(
+            run(addr, &["--json", "GET", "missing"]).await,
+            (Some(0), "null\n".to_owned())
+        );
+
+        // Error replies and unreachable servers are reflected in the exit code
+        let (code, _) = run(addr, &["NOPE"]).await;
+        assert_eq!(code, Some(1));
+
+        let unused = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let unused_addr = unused.local_addr().unwrap();
+        drop(unused);
+
+        let (code, _) = run(unused_addr, &["PING"]).await;
+        assert_eq!(code, Some(2));
+    }
+
+    #[tokio::test]
+    async fn pipes_commands_from_stdin() {
+        let addr = init_server().await;
+
+        let mut input = String::new();
+        for i in 0..1000 {
+            input.push_str(&format!("SET key{i} \"value {i}\"\n"));
+        }
+        input.push_str("NOPE\n");
+
+        let mut child = vivs_cli(addr)
+            .arg("--pipe")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(input.as_bytes()).await.unwrap();
+        drop(stdin);
+
+        let output = child.wait_with_output().await.unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        assert!(stdout.contains("errors: 1, replies: 1001"));
+        assert_eq!(output.status.code(), Some(1));
+
+        // RESP input is sent as it is
+        let mut child = vivs_cli(addr)
+            .arg("--pipe")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(b"*2\r\n$3\r\nGET\r\n$4\r\nkey1\r\n*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        drop(stdin);
+
+        let output = child.wait_with_output().await.unwrap();
+        assert!(String::from_utf8_lossy(&output.stdout).contains("errors: 0, replies: 2"));
+        assert_eq!(output.status.code(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn scans_keys_and_finds_biggest_key() {
+        let addr = init_server().await;
+
+        for i in 0..50 {
+            run(addr, &["SET", &format!("user:{i}"), "x"]).await;
+        }
+        run(addr, &["SET", "session:1", "a much longer value"]).await;
+
+        let (code, stdout) = run(addr, &["--scan", "--pattern", "user:*", "--count", "7"]).await;
+        assert_eq!(code, Some(0));
+
+        let mut keys = stdout.lines().collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 50);
+        assert!(keys.iter().all(|key| key.starts_with("user:")));
+
+        let (code, stdout) = run(addr, &["--bigkeys"]).await;
+        assert_eq!(code, Some(0));
+        assert!(stdout.contains("Sampled 51 keys in the keyspace!"));
+        assert!(stdout.contains("Biggest string found 'session:1' has 19 bytes"));
+    }
+}
@@ -0,0 +1,426 @@
+use clap::Parser as ClapParser;
+use hdrhistogram::Histogram;
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt::Display,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use vivs::{
+    client::{Client, ClientError, ClusterClient, Cmd, Pipeline},
+    data_chunk::DataChunk,
+    GenericResult, PORT,
+};
+
+// Latencies are recorded in microseconds, up to a minute
+const MAX_LATENCY_US: u64 = 60_000_000;
+
+/// Commands the load generator knows how to build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Test {
+    Ping,
+    Set,
+    Get,
+    Delete,
+    Ttl,
+    Lpush,
+}
+
+impl FromStr for Test {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match &name.to_lowercase()[..] {
+            "ping" => Ok(Test::Ping),
+            "set" => Ok(Test::Set),
+            "get" => Ok(Test::Get),
+            "delete" | "del" => Ok(Test::Delete),
+            "ttl" => Ok(Test::Ttl),
+            "lpush" => Ok(Test::Lpush),
+            name => Err(format!("unknown test {name:?}")),
+        }
+    }
+}
+
+impl Display for Test {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Test::Ping => "PING",
+            Test::Set => "SET",
+            Test::Get => "GET",
+            Test::Delete => "DELETE",
+            Test::Ttl => "TTL",
+            Test::Lpush => "LPUSH",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Test {
+    #[cfg(test)]
+    const ALL: [Test; 6] = [
+        Test::Ping,
+        Test::Set,
+        Test::Get,
+        Test::Delete,
+        Test::Ttl,
+        Test::Lpush,
+    ];
+
+    fn cmd(&self, key: u64, value: &[u8]) -> Cmd {
+        match self {
+            Test::Ping => Cmd::new("PING"),
+            Test::Set => Cmd::new("SET").arg(format!("key:{key}")).arg(value),
+            Test::Get => Cmd::new("GET").arg(format!("key:{key}")),
+            Test::Delete => Cmd::new("DELETE").arg(format!("key:{key}")),
+            Test::Ttl => Cmd::new("TTL").arg(format!("key:{key}")),
+            Test::Lpush => Cmd::new("LPUSH").arg(format!("list:{key}")).arg(value),
+        }
+    }
+}
+
+/// Weighted list of tests i.e. get:80,set:20 (weight is 1 when left out)
+#[derive(Debug, Clone)]
+struct Mix(Vec<(Test, u64)>);
+
+impl FromStr for Mix {
+    type Err = String;
+
+    fn from_str(mix: &str) -> Result<Self, Self::Err> {
+        let tests = mix
+            .split(',')
+            .filter(|test| !test.trim().is_empty())
+            .map(|test| match test.split_once(':') {
+                Some((name, weight)) => Ok((
+                    name.trim().parse()?,
+                    weight
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("invalid weight {weight:?}"))?,
+                )),
+                None => Ok((test.trim().parse()?, 1)),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        if tests.iter().map(|(_, weight)| weight).sum::<u64>() == 0 {
+            return Err("the mix needs at least one test with a weight above 0".to_owned());
+        }
+
+        Ok(Mix(tests))
+    }
+}
+
+impl Mix {
+    fn pick(&self, random: u64) -> Test {
+        let total = self.0.iter().map(|(_, weight)| weight).sum::<u64>();
+        let mut point = random % total;
+
+        for (test, weight) in &self.0 {
+            if point < *weight {
+                return *test;
+            }
+            point -= weight;
+        }
+
+        self.0[0].0
+    }
+}
+
+#[derive(Debug, ClapParser)]
+#[command(name = "vivs-benchmark", about = "Load generator for Vivs")]
+struct Cli {
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+    #[arg(long, short, default_value_t = PORT)]
+    port: u16,
+    /// Number of parallel connections
+    #[arg(long, short, default_value_t = 50)]
+    clients: u64,
+    /// Total number of requests
+    #[arg(long, short = 'n', default_value_t = 100_000)]
+    requests: u64,
+    /// Number of commands sent in one go on each connection
+    #[arg(long = "pipeline", short = 'P', default_value_t = 1)]
+    pipeline: u64,
+    /// Keys are picked at random from key:0 to key:<keyspace - 1>
+    #[arg(long, short = 'r', default_value_t = 10_000)]
+    keyspace: u64,
+    /// Size of SET / LPUSH values in bytes
+    #[arg(long = "data-size", short = 'd', default_value_t = 3)]
+    data_size: usize,
+    /// Weighted mix of commands i.e. get:80,set:20
+    #[arg(long, short = 't', default_value = "ping,set,get")]
+    tests: Mix,
+    /// Treats the node as a seed of a cluster, commands are sent to the nodes that serve the keys
+    #[arg(long)]
+    cluster: bool,
+}
+
+/// xorshift64*, good enough to pick commands and keys
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+        Self((time ^ seed.wrapping_mul(0x9E37_79B9_7F4A_7C15)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+enum Connection {
+    Node(Client),
+    Cluster(ClusterClient),
+}
+
+impl Connection {
+    async fn query_pipeline(&mut self, pipeline: &Pipeline) -> Result<Vec<DataChunk>, ClientError> {
+        match self {
+            Connection::Node(client) => client.query_pipeline(pipeline).await,
+            Connection::Cluster(client) => client.query_pipeline(pipeline).await,
+        }
+    }
+}
+
+/// Latencies and errors of a single command
+struct Stats {
+    latencies: Histogram<u64>,
+    errors: u64,
+}
+
+impl Stats {
+    fn new() -> GenericResult<Self> {
+        Ok(Self {
+            latencies: Histogram::new_with_bounds(1, MAX_LATENCY_US, 3)?,
+            errors: 0,
+        })
+    }
+
+    fn merge(&mut self, other: &Stats) -> GenericResult<()> {
+        self.latencies.add(&other.latencies)?;
+        self.errors += other.errors;
+        Ok(())
+    }
+}
+
+type TestStats = BTreeMap<Test, Stats>;
+
+async fn connect(cli: &Cli) -> GenericResult<Connection> {
+    let address = format!("{}:{}", cli.host, cli.port);
+
+    Ok(if cli.cluster {
+        Connection::Cluster(ClusterClient::builder().seed(address).connect().await?)
+    } else {
+        Connection::Node(Client::builder().address(address).connect().await?)
+    })
+}
+
+/// Takes batches of requests until there are none left
+async fn worker(
+    mut connection: Connection,
+    id: u64,
+    remaining: Arc<AtomicU64>,
+    mix: Mix,
+    keyspace: u64,
+    pipeline: u64,
+    value: Arc<Vec<u8>>,
+) -> GenericResult<TestStats> {
+    let mut random = Random::new(id);
+    let mut stats = TestStats::new();
+
+    // Each batch takes up to `pipeline` requests, the workers stop once there are none left
+    while let Ok(left) = remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+        (left > 0).then(|| left.saturating_sub(pipeline))
+    }) {
+        let batch = left.min(pipeline);
+
+        let tests = (0..batch)
+            .map(|_| mix.pick(random.next()))
+            .collect::<Vec<_>>();
+        let cmds = tests.iter().fold(Pipeline::new(), |cmds, test| {
+            cmds.cmd(test.cmd(random.next() % keyspace.max(1), &value))
+        });
+
+        let started = Instant::now();
+        let replies = connection.query_pipeline(&cmds).await?;
+        // Every command of the pipeline waited for the whole round trip
+        let latency = started.elapsed().as_micros() as u64;
+
+        for (test, reply) in tests.into_iter().zip(replies) {
+            let test_stats = match stats.entry(test) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(Stats::new()?),
+            };
+
+            test_stats.latencies.saturating_record(latency.max(1));
+            if let DataChunk::SimpleError(_) = reply {
+                test_stats.errors += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
+
+fn ms(us: u64) -> f64 {
+    us as f64 / 1000.0
+}
+
+fn report(cli: &Cli, stats: &TestStats, elapsed: Duration) -> GenericResult<()> {
+    let mut total = Stats::new()?;
+    for test_stats in stats.values() {
+        total.merge(test_stats)?;
+    }
+
+    let requests = total.latencies.len();
+    let mix = cli
+        .tests
+        .0
+        .iter()
+        .map(|(test, weight)| format!("{test}:{weight}"))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    println!("====== {mix} ======");
+    println!(
+        "  {requests} requests completed in {:.2} seconds",
+        elapsed.as_secs_f64()
+    );
+    println!(
+        "  {} parallel clients, pipeline {}, {} bytes payload, keyspace {}{}",
+        cli.clients,
+        cli.pipeline,
+        cli.data_size,
+        cli.keyspace,
+        if cli.cluster { ", cluster" } else { "" }
+    );
+    println!();
+    println!(
+        "  throughput: {:.2} requests per second",
+        requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    println!();
+    println!(
+        "  {:<8} {:>10} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "command", "requests", "errors", "p50 ms", "p99 ms", "p999 ms", "max ms"
+    );
+
+    let rows = stats
+        .iter()
+        .map(|(test, stats)| (test.to_string(), stats))
+        .chain([("all".to_owned(), &total)]);
+
+    for (name, stats) in rows {
+        let latencies = &stats.latencies;
+        println!(
+            "  {:<8} {:>10} {:>8} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+            name,
+            latencies.len(),
+            stats.errors,
+            ms(latencies.value_at_quantile(0.5)),
+            ms(latencies.value_at_quantile(0.99)),
+            ms(latencies.value_at_quantile(0.999)),
+            ms(latencies.max()),
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> GenericResult<()> {
+    let cli = Cli::parse();
+
+    if cli.pipeline == 0 || cli.clients == 0 {
+        Err("--clients and --pipeline need to be greater than 0")?;
+    }
+
+    let remaining = Arc::new(AtomicU64::new(cli.requests));
+    let value = Arc::new(vec![b'x'; cli.data_size]);
+
+    // All the connections are established before the clock starts
+    let mut connections = Vec::with_capacity(cli.clients as usize);
+    for _ in 0..cli.clients {
+        connections.push(connect(&cli).await?);
+    }
+
+    let started = Instant::now();
+
+    let workers = connections
+        .into_iter()
+        .enumerate()
+        .map(|(id, connection)| {
+            tokio::spawn(worker(
+                connection,
+                id as u64,
+                remaining.clone(),
+                cli.tests.clone(),
+                cli.keyspace,
+                cli.pipeline,
+                value.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut stats = TestStats::new();
+    for worker in workers {
+        for (test, test_stats) in worker.await?? {
+            match stats.entry(test) {
+                Entry::Occupied(mut entry) => entry.get_mut().merge(&test_stats)?,
+                Entry::Vacant(entry) => {
+                    entry.insert(test_stats);
+                }
+            }
+        }
+    }
+
+    report(&cli, &stats, started.elapsed())
+}
+
+#[cfg(test)]
+mod benchmark_tests {
+    use super::*;
+    use vivs::commands::registry::lookup;
+
+    #[test]
+    fn parses_weighted_mix() {
+        let mix = "get:3, set".parse::<Mix>().unwrap();
+        assert_eq!(mix.0, vec![(Test::Get, 3), (Test::Set, 1)]);
+
+        let picks = (0..4).map(|i| mix.pick(i)).collect::<Vec<_>>();
+        assert_eq!(picks, vec![Test::Get, Test::Get, Test::Get, Test::Set]);
+
+        assert!("get:0".parse::<Mix>().is_err());
+        assert!("nope".parse::<Mix>().is_err());
+    }
+
+    #[test]
+    fn tests_send_commands_the_server_knows() {
+        for test in Test::ALL {
+            let cmd = test.cmd(1, b"value");
+            let name = String::from_utf8_lossy(&cmd.args()[0]);
+            let spec =
+                lookup(&name).unwrap_or_else(|| panic!("{test} sends unknown command {name}"));
+
+            let args = cmd.args().len() as i64;
+            let arity_ok = match spec.arity {
+                arity if arity >= 0 => args == arity,
+                arity => args >= -arity,
+            };
+            assert!(arity_ok, "{test} sends {args} arguments");
+            assert_eq!(test.to_string().parse::<Test>(), Ok(test));
+        }
+    }
+}
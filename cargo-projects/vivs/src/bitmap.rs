@@ -0,0 +1,389 @@
+use crate::{db::Value, error::CommandError};
+use bytes::{Bytes, BytesMut};
+use std::collections::HashMap;
+
+// Same as in Redis, bitmaps are strings so they can't grow over 512mb
+pub const MAX_BITS: u64 = 512 * 1024 * 1024 * 8;
+
+pub const BIT_OFFSET_ERR: &str = "bit offset is not an integer or out of range";
+pub const BIT_VALUE_ERR: &str = "bit is not an integer or out of range";
+const BITFIELD_TYPE_ERR: &str =
+    "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.";
+const OVERFLOW_TYPE_ERR: &str = "Invalid OVERFLOW type specified";
+
+/// String value of the key, None if the key does not exist
+pub fn get<'a>(
+    keys: &'a HashMap<Bytes, Value>,
+    key: &[u8],
+) -> Result<Option<&'a Bytes>, CommandError> {
+    match keys.get(key) {
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// Changes the string value of the key in place (an empty string is created if the key does not exist)
+pub fn update<T>(
+    keys: &mut HashMap<Bytes, Value>,
+    key: &Bytes,
+    change: impl FnOnce(&mut BytesMut) -> T,
+) -> Result<T, CommandError> {
+    let value = keys
+        .entry(key.clone())
+        .or_insert_with(|| Value::String(Bytes::new()));
+
+    let Value::String(value) = value else {
+        return Err(CommandError::WrongType);
+    };
+
+    // No copy is made when nothing else holds on to the value
+    let mut bytes = BytesMut::from(std::mem::take(value));
+    let result = change(&mut bytes);
+    *value = bytes.freeze();
+
+    Ok(result)
+}
+
+/// Parses an offset of a bit, the bit has to fit into a 512mb string
+pub fn parse_offset(offset: &str) -> Result<u64, CommandError> {
+    offset
+        .parse::<u64>()
+        .ok()
+        .filter(|offset| *offset < MAX_BITS)
+        .ok_or_else(|| CommandError::Other(BIT_OFFSET_ERR.to_owned()))
+}
+
+/// Bit at the offset, the first bit is the most significant bit of the first byte.
+/// Bits past the end of the string are 0.
+pub fn get_bit(bytes: &[u8], offset: u64) -> u8 {
+    let index = (offset / 8) as usize;
+    bytes
+        .get(index)
+        .map_or(0, |byte| (byte >> (7 - offset % 8)) & 1)
+}
+
+/// Sets the bit at the offset (the string is padded with zeros when it is too short),
+/// returns the bit it had before
+pub fn set_bit(bytes: &mut BytesMut, offset: u64, bit: u8) -> u8 {
+    let index = (offset / 8) as usize;
+    if index >= bytes.len() {
+        bytes.resize(index + 1, 0);
+    }
+
+    let shift = 7 - offset % 8;
+    let old = (bytes[index] >> shift) & 1;
+    bytes[index] = (bytes[index] & !(1 << shift)) | (bit << shift);
+
+    old
+}
+
+/// Unit of the start and end indexes of BITCOUNT / BITPOS
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unit {
+    Byte,
+    Bit,
+}
+
+impl Unit {
+    /// Parses BYTE | BIT
+    pub fn parse(value: &str) -> Result<Unit, CommandError> {
+        match &value.to_lowercase()[..] {
+            "byte" => Ok(Unit::Byte),
+            "bit" => Ok(Unit::Bit),
+            _ => Err(CommandError::Syntax),
+        }
+    }
+}
+
+/// First and last bit offsets of the range of a string `len` bytes long, same as in Redis
+/// negative indexes count from the end and indexes out of the string are clamped to it.
+///
+/// None when the range is empty.
+pub fn bit_range(len: usize, start: i64, end: i64, unit: Unit) -> Option<(u64, u64)> {
+    let len = match unit {
+        Unit::Byte => len as i64,
+        Unit::Bit => len as i64 * 8,
+    };
+
+    let start = if start < 0 { len + start } else { start }.max(0);
+    let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+
+    if len == 0 || start > end {
+        return None;
+    }
+
+    match unit {
+        Unit::Byte => Some((start as u64 * 8, end as u64 * 8 + 7)),
+        Unit::Bit => Some((start as u64, end as u64)),
+    }
+}
+
+/// Indexes of the bytes of the range of bits, each with the mask of the bits that are in the range
+fn masked_bytes(start: u64, end: u64) -> impl Iterator<Item = (usize, u8)> {
+    let (first, last) = (start / 8, end / 8);
+
+    (first..=last).map(move |index| {
+        let mut mask = u8::MAX;
+        if index == first {
+            mask &= u8::MAX >> (start % 8);
+        }
+        if index == last {
+            mask &= u8::MAX << (7 - end % 8);
+        }
+
+        (index as usize, mask)
+    })
+}
+
+/// Number of bits that are set between the two bit offsets (both included)
+pub fn count(bytes: &[u8], start: u64, end: u64) -> u64 {
+    masked_bytes(start, end)
+        .map(|(index, mask)| u64::from((bytes[index] & mask).count_ones()))
+        .sum()
+}
+
+/// Offset of the first bit that is set to `bit` between the two bit offsets (both included)
+pub fn position(bytes: &[u8], bit: u8, start: u64, end: u64) -> Option<u64> {
+    masked_bytes(start, end).find_map(|(index, mask)| {
+        let byte = if bit == 1 {
+            bytes[index]
+        } else {
+            !bytes[index]
+        } & mask;
+        (byte != 0).then(|| index as u64 * 8 + u64::from(byte.leading_zeros()))
+    })
+}
+
+/// Operation of BITOP
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOp {
+    /// Parses AND | OR | XOR | NOT
+    pub fn parse(value: &str) -> Result<BitOp, CommandError> {
+        match &value.to_lowercase()[..] {
+            "and" => Ok(BitOp::And),
+            "or" => Ok(BitOp::Or),
+            "xor" => Ok(BitOp::Xor),
+            "not" => Ok(BitOp::Not),
+            _ => Err(CommandError::Syntax),
+        }
+    }
+
+    /// Runs the operation byte by byte, shorter strings are padded with zeros.
+    /// NOT only uses the first string.
+    pub fn apply(self, strings: &[Bytes]) -> Vec<u8> {
+        let len = strings.iter().map(Bytes::len).max().unwrap_or_default();
+
+        (0..len)
+            .map(|index| {
+                let mut bytes = strings
+                    .iter()
+                    .map(|string| string.get(index).copied().unwrap_or_default());
+
+                match self {
+                    BitOp::And => bytes.fold(u8::MAX, |result, byte| result & byte),
+                    BitOp::Or => bytes.fold(0, |result, byte| result | byte),
+                    BitOp::Xor => bytes.fold(0, |result, byte| result ^ byte),
+                    BitOp::Not => !bytes.next().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Type of a BITFIELD integer, i.e. i8 is a signed 8 bit integer and u4 an unsigned 4 bit one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encoding {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+impl Encoding {
+    /// Parses i1 to i64 and u1 to u63 (same as in Redis, u64 can't be replied with)
+    pub fn parse(value: &str) -> Result<Encoding, CommandError> {
+        let type_err = || CommandError::Other(BITFIELD_TYPE_ERR.to_owned());
+
+        let (signed, bits) = match value.get(..1).map(str::to_lowercase).as_deref() {
+            Some("i") => (true, &value[1..]),
+            Some("u") => (false, &value[1..]),
+            _ => return Err(type_err()),
+        };
+
+        let max_bits = if signed { 64 } else { 63 };
+        let bits = bits
+            .parse::<u32>()
+            .ok()
+            .filter(|bits| (1..=max_bits).contains(bits))
+            .ok_or_else(type_err)?;
+
+        Ok(Encoding { signed, bits })
+    }
+
+    /// Parses the offset of the integer, #N is the Nth integer of this type (offset of N * bits)
+    pub fn parse_offset(self, offset: &str) -> Result<u64, CommandError> {
+        let bits = u64::from(self.bits);
+        let offset = match offset.strip_prefix('#') {
+            Some(index) => index
+                .parse::<u64>()
+                .ok()
+                .and_then(|index| index.checked_mul(bits)),
+            None => offset.parse::<u64>().ok(),
+        };
+
+        offset
+            .filter(|offset| offset.checked_add(bits).is_some_and(|end| end <= MAX_BITS))
+            .ok_or_else(|| CommandError::Other(BIT_OFFSET_ERR.to_owned()))
+    }
+
+    fn min(self) -> i128 {
+        match self.signed {
+            true => -(1 << (self.bits - 1)),
+            false => 0,
+        }
+    }
+
+    fn max(self) -> i128 {
+        match self.signed {
+            true => (1 << (self.bits - 1)) - 1,
+            false => (1 << self.bits) - 1,
+        }
+    }
+}
+
+/// What BITFIELD SET / INCRBY do with values that do not fit into the integer
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Overflow {
+    /// Wraps around, both for signed and unsigned integers
+    #[default]
+    Wrap,
+    /// Saturates to the smallest or the biggest value
+    Sat,
+    /// Nothing is changed and null is replied
+    Fail,
+}
+
+impl Overflow {
+    /// Parses WRAP | SAT | FAIL
+    pub fn parse(value: &str) -> Result<Overflow, CommandError> {
+        match &value.to_lowercase()[..] {
+            "wrap" => Ok(Overflow::Wrap),
+            "sat" => Ok(Overflow::Sat),
+            "fail" => Ok(Overflow::Fail),
+            _ => Err(CommandError::Other(OVERFLOW_TYPE_ERR.to_owned())),
+        }
+    }
+
+    /// Fits the value into the integer, None when it does not fit with FAIL
+    pub fn apply(self, value: i128, encoding: Encoding) -> Option<i64> {
+        let (min, max) = (encoding.min(), encoding.max());
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+
+        match self {
+            Overflow::Wrap => Some(((value - min).rem_euclid(1 << encoding.bits) + min) as i64),
+            Overflow::Sat => Some(value.clamp(min, max) as i64),
+            Overflow::Fail => None,
+        }
+    }
+}
+
+/// Integer stored at the bit offset, most significant bit first
+pub fn get_field(bytes: &[u8], offset: u64, encoding: Encoding) -> i64 {
+    let bits = u64::from(encoding.bits);
+    let value = (0..bits).fold(0u64, |value, index| {
+        (value << 1) | u64::from(get_bit(bytes, offset + index))
+    });
+
+    // Sign extended so that i.e. i4 1111 is -1
+    if encoding.signed && bits < 64 && (value >> (bits - 1)) & 1 == 1 {
+        (value | (u64::MAX << bits)) as i64
+    } else {
+        value as i64
+    }
+}
+
+/// Stores the integer at the bit offset, most significant bit first
+pub fn set_field(bytes: &mut BytesMut, offset: u64, encoding: Encoding, value: i64) {
+    let bits = u64::from(encoding.bits);
+    let value = value as u64;
+
+    for index in 0..bits {
+        let bit = (value >> (bits - 1 - index)) & 1;
+        set_bit(bytes, offset + index, bit as u8);
+    }
+}
+
+#[cfg(test)]
+mod bitmap_tests {
+    use super::*;
+
+    #[test]
+    fn sets_counts_and_finds_bits() {
+        let mut bytes = BytesMut::new();
+        assert_eq!(set_bit(&mut bytes, 7, 1), 0);
+        assert_eq!(set_bit(&mut bytes, 7, 1), 1);
+        set_bit(&mut bytes, 17, 1);
+        assert_eq!(&bytes[..], &[0b0000_0001, 0, 0b0100_0000]);
+        assert_eq!(get_bit(&bytes, 17), 1);
+        assert_eq!(get_bit(&bytes, 1000), 0);
+
+        // Whole string, the last byte only, bits 6 to 17
+        assert_eq!(bit_range(3, 0, -1, Unit::Byte), Some((0, 23)));
+        assert_eq!(bit_range(3, -1, -1, Unit::Byte), Some((16, 23)));
+        assert_eq!(bit_range(3, 6, 17, Unit::Bit), Some((6, 17)));
+        assert_eq!(bit_range(3, 2, 1, Unit::Byte), None);
+        assert_eq!(bit_range(0, 0, -1, Unit::Byte), None);
+
+        assert_eq!(count(&bytes, 0, 23), 2);
+        assert_eq!(count(&bytes, 8, 17), 1);
+        assert_eq!(count(&bytes, 8, 16), 0);
+
+        assert_eq!(position(&bytes, 1, 0, 23), Some(7));
+        assert_eq!(position(&bytes, 1, 8, 23), Some(17));
+        assert_eq!(position(&bytes, 0, 0, 23), Some(0));
+        assert_eq!(position(&[0xff], 0, 0, 7), None);
+    }
+
+    #[test]
+    fn runs_bit_operations() {
+        let strings = [
+            Bytes::from_static(&[0b1100, 0xff]),
+            Bytes::from_static(&[0b1010]),
+        ];
+
+        assert_eq!(BitOp::And.apply(&strings), vec![0b1000, 0]);
+        assert_eq!(BitOp::Or.apply(&strings), vec![0b1110, 0xff]);
+        assert_eq!(BitOp::Xor.apply(&strings), vec![0b0110, 0xff]);
+        assert_eq!(BitOp::Not.apply(&strings[1..]), vec![!0b1010]);
+    }
+
+    #[test]
+    fn reads_and_writes_fields() {
+        let i8 = Encoding::parse("i8").unwrap();
+        let u4 = Encoding::parse("u4").unwrap();
+        assert_eq!(u4.parse_offset("#2"), Ok(8));
+        assert!(Encoding::parse("u64").is_err());
+        assert!(Encoding::parse("i0").is_err());
+
+        let mut bytes = BytesMut::new();
+        set_field(&mut bytes, 4, i8, -2);
+        assert_eq!(&bytes[..], &[0b0000_1111, 0b1110_0000]);
+        assert_eq!(get_field(&bytes, 4, i8), -2);
+        assert_eq!(get_field(&bytes, 4, u4), 15);
+
+        assert_eq!(Overflow::Wrap.apply(128, i8), Some(-128));
+        assert_eq!(Overflow::Wrap.apply(17, u4), Some(1));
+        assert_eq!(Overflow::Wrap.apply(-1, u4), Some(15));
+        assert_eq!(Overflow::Sat.apply(-200, i8), Some(-128));
+        assert_eq!(Overflow::Sat.apply(16, u4), Some(15));
+        assert_eq!(Overflow::Fail.apply(16, u4), None);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{error::CommandError, list::End, Connection, GenericResult, Shutdown};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::oneshot, time::Instant};
+
+const TIMEOUT_ERR: &str = "timeout is not a float or out of range";
+const TIMEOUT_MS_ERR: &str = "timeout is not an integer or out of range";
+const NEGATIVE_TIMEOUT_ERR: &str = "timeout is negative";
+
+/// Parses the timeout (in seconds) of blocking commands, 0 means no timeout
+pub fn parse_timeout(value: &str) -> Result<Option<Duration>, CommandError> {
+    let timeout = value
+        .parse::<f64>()
+        .ok()
+        .filter(|timeout| timeout.is_finite())
+        .ok_or_else(|| CommandError::Other(TIMEOUT_ERR.to_owned()))?;
+
+    if timeout < 0.0 {
+        return Err(CommandError::Other(NEGATIVE_TIMEOUT_ERR.to_owned()));
+    }
+
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+
+    Duration::try_from_secs_f64(timeout)
+        .map(Some)
+        .map_err(|_| CommandError::Other(TIMEOUT_ERR.to_owned()))
+}
+
+/// Parses the timeout (in milliseconds) of XREAD BLOCK etc, 0 means no timeout
+pub fn parse_timeout_ms(value: &str) -> Result<Option<Duration>, CommandError> {
+    let timeout = value
+        .parse::<i64>()
+        .map_err(|_| CommandError::Other(TIMEOUT_MS_ERR.to_owned()))?;
+
+    match timeout {
+        ..0 => Err(CommandError::Other(NEGATIVE_TIMEOUT_ERR.to_owned())),
+        0 => Ok(None),
+        timeout => Ok(Some(Duration::from_millis(timeout as u64))),
+    }
+}
+
+/// What happens once one of the keys a client is blocked on gets a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockedOp {
+    /// BLPOP / BRPOP
+    Pop(End),
+    /// BLMOVE / BRPOPLPUSH
+    Move {
+        from: End,
+        destination: Bytes,
+        to: End,
+    },
+    /// XREAD / XREADGROUP, the client reads the stream again once it gets new entries
+    Read,
+}
+
+/// The key the value was popped from and the value itself
+pub type Served = Result<(Bytes, Bytes), CommandError>;
+
+struct Waiter {
+    keys: Vec<Bytes>,
+    op: BlockedOp,
+    // Taken by whoever gets to the client first, the one serving it or the client that stops waiting
+    sender: Mutex<Option<oneshot::Sender<Served>>>,
+}
+
+impl Waiter {
+    fn take_sender(&self) -> Option<oneshot::Sender<Served>> {
+        self.sender.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
+
+/// Clients blocked on the list keys of a database (BLPOP, BLMOVE etc).
+///
+/// Clients are queued per key and served in the order they blocked.
+/// Blocked clients only wait on a channel, they don't hold any locks of the data store
+/// while waiting. Commands that push to a list signal the key as ready,
+/// the blocked clients get served once the command (or the whole MULTI / EXEC block) is done.
+#[derive(Default)]
+pub struct BlockedClients {
+    waiters: Mutex<HashMap<Bytes, VecDeque<Arc<Waiter>>>>,
+    // Keys that were pushed to since the blocked clients were served the last time
+    ready: Mutex<Vec<Bytes>>,
+}
+
+impl BlockedClients {
+    /// Queues a client on each of the keys, it waits until `Blocked` is served or dropped
+    pub fn block(self: &Arc<Self>, keys: Vec<Bytes>, op: BlockedOp) -> Blocked {
+        let (sender, receiver) = oneshot::channel();
+        let waiter = Arc::new(Waiter {
+            keys,
+            op,
+            sender: Mutex::new(Some(sender)),
+        });
+
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        for key in &waiter.keys {
+            waiters
+                .entry(key.clone())
+                .or_default()
+                .push_back(waiter.clone());
+        }
+
+        Blocked {
+            waiter,
+            receiver,
+            clients: self.clone(),
+        }
+    }
+
+    /// Number of clients that are blocked
+    pub fn len(&self) -> usize {
+        let waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut blocked = waiters.values().flatten().collect::<Vec<_>>();
+        // A client blocked on multiple keys is queued on each one of them
+        blocked.sort_by_key(|waiter| Arc::as_ptr(waiter));
+        blocked.dedup_by(|a, b| Arc::ptr_eq(a, b));
+        blocked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Marks the key as pushed to, blocked clients get served by `Db::serve_blocked()`
+    pub fn signal(&self, key: &Bytes) {
+        if !self
+            .waiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(key)
+        {
+            return;
+        }
+
+        let mut ready = self.ready.lock().unwrap_or_else(|e| e.into_inner());
+        if !ready.contains(key) {
+            ready.push(key.clone());
+        }
+    }
+
+    /// Keys that were signaled since the last call
+    pub(crate) fn take_ready(&self) -> Vec<Bytes> {
+        std::mem::take(&mut *self.ready.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// The client that blocked on the key first, it is no longer blocked on any of its keys.
+    /// Clients that stopped waiting and clients reading streams are skipped.
+    pub(crate) fn next(&self, key: &[u8]) -> Option<(BlockedOp, oneshot::Sender<Served>)> {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+
+        loop {
+            let queue = waiters.get_mut(key)?;
+            let position = queue.iter().position(|waiter| waiter.op != BlockedOp::Read);
+            let Some(waiter) = position.and_then(|position| queue.remove(position)) else {
+                if queue.is_empty() {
+                    waiters.remove(key);
+                }
+                return None;
+            };
+
+            let sender = waiter.take_sender();
+            remove(&mut waiters, &waiter);
+
+            match sender {
+                Some(sender) if !sender.is_closed() => return Some((waiter.op.clone(), sender)),
+                _ => continue,
+            }
+        }
+    }
+
+    /// Wakes up every client reading the stream, they are no longer blocked on any of their keys
+    pub(crate) fn wake_readers(&self, key: &Bytes) {
+        let mut waiters = self.waiters.lock().unwrap_or_else(|e| e.into_inner());
+
+        let readers = waiters
+            .get(key)
+            .into_iter()
+            .flatten()
+            .filter(|waiter| waiter.op == BlockedOp::Read)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for reader in readers {
+            remove(&mut waiters, &reader);
+            if let Some(sender) = reader.take_sender() {
+                let _ = sender.send(Ok((key.clone(), Bytes::new())));
+            }
+        }
+    }
+}
+
+/// Removes the waiter from the queues of all of its keys
+fn remove(waiters: &mut HashMap<Bytes, VecDeque<Arc<Waiter>>>, waiter: &Arc<Waiter>) {
+    for key in &waiter.keys {
+        if let Some(queue) = waiters.get_mut(key) {
+            queue.retain(|queued| !Arc::ptr_eq(queued, waiter));
+            if queue.is_empty() {
+                waiters.remove(key);
+            }
+        }
+    }
+}
+
+/// A blocked client, it stops being blocked when dropped
+pub struct Blocked {
+    waiter: Arc<Waiter>,
+    receiver: oneshot::Receiver<Served>,
+    clients: Arc<BlockedClients>,
+}
+
+impl Blocked {
+    /// Waits until the client gets served
+    pub async fn served(&mut self) -> Option<Served> {
+        (&mut self.receiver).await.ok()
+    }
+
+    /// Waits until the client gets served, the timeout passes or the server shuts down.
+    /// Returns None when the client was not served.
+    ///
+    /// The time spent waiting is added to `blocked_for`, same as in Redis it doesn't count
+    /// towards the duration of the command (SLOWLOG, latency stats).
+    ///
+    /// Fails when the client disconnects, it stops being blocked then.
+    pub async fn wait(
+        self,
+        timeout: Option<Duration>,
+        conn: &mut Connection,
+        shutdown: &Shutdown,
+        blocked_for: &mut Duration,
+    ) -> GenericResult<Option<Served>> {
+        let started_at = Instant::now();
+        let served = self.wait_served(timeout, conn, shutdown).await;
+        *blocked_for += started_at.elapsed();
+
+        served
+    }
+
+    async fn wait_served(
+        mut self,
+        timeout: Option<Duration>,
+        conn: &mut Connection,
+        shutdown: &Shutdown,
+    ) -> GenericResult<Option<Served>> {
+        // Timeouts too far in the future are the same as no timeout
+        let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+        let timed_out = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(timed_out);
+
+        loop {
+            tokio::select! {
+                served = self.served() => return Ok(served),
+                _ = &mut timed_out => break,
+                _ = shutdown.wait() => break,
+                // Reading lets us notice when the client disconnects,
+                // anything the client sends in the meantime is kept for later
+                read = conn.read_more() => read?,
+            }
+        }
+
+        Ok(self.cancel().await)
+    }
+
+    /// Stops waiting (i.e. on timeout). The client might have been served in the meantime,
+    /// in which case the value is returned so that it is not lost.
+    pub async fn cancel(mut self) -> Option<Served> {
+        {
+            let mut waiters = self
+                .clients
+                .waiters
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            remove(&mut waiters, &self.waiter);
+        }
+
+        match self.waiter.take_sender() {
+            Some(_) => None,
+            None => self.served().await,
+        }
+    }
+}
+
+impl Drop for Blocked {
+    fn drop(&mut self) {
+        let mut waiters = self
+            .clients
+            .waiters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        remove(&mut waiters, &self.waiter);
+    }
+}
+
+#[cfg(test)]
+mod blocking_tests {
+    use super::*;
+
+    #[test]
+    fn parses_timeouts() {
+        assert_eq!(parse_timeout("0"), Ok(None));
+        assert_eq!(parse_timeout("0.5"), Ok(Some(Duration::from_millis(500))));
+        assert_eq!(
+            parse_timeout("-1").unwrap_err().to_string(),
+            "ERR timeout is negative"
+        );
+        assert!(parse_timeout("soon").is_err());
+        assert!(parse_timeout("inf").is_err());
+
+        assert_eq!(parse_timeout_ms("0"), Ok(None));
+        assert_eq!(
+            parse_timeout_ms("1500"),
+            Ok(Some(Duration::from_millis(1500)))
+        );
+        assert!(parse_timeout_ms("-1").is_err());
+        assert!(parse_timeout_ms("0.5").is_err());
+    }
+
+    #[tokio::test]
+    async fn serves_clients_in_the_order_they_blocked() {
+        let clients = Arc::new(BlockedClients::default());
+        let (a, b) = (Bytes::from("a"), Bytes::from("b"));
+
+        let mut first = clients.block(vec![a.clone(), b.clone()], BlockedOp::Pop(End::Left));
+        let second = clients.block(vec![b.clone()], BlockedOp::Pop(End::Right));
+        assert_eq!(clients.len(), 2);
+
+        // Only keys that clients are blocked on are signaled
+        clients.signal(&b);
+        clients.signal(&"c".into());
+        assert_eq!(clients.take_ready(), vec![b.clone()]);
+
+        let (op, sender) = clients.next(&b).unwrap();
+        assert_eq!(op, BlockedOp::Pop(End::Left));
+        sender.send(Ok((b.clone(), "1".into()))).unwrap();
+        assert_eq!(first.served().await, Some(Ok((b.clone(), "1".into()))));
+
+        // The first client is no longer queued on a
+        assert!(clients.next(&a).is_none());
+        assert_eq!(clients.len(), 1);
+
+        // Timed out clients are skipped
+        assert_eq!(second.cancel().await, None);
+        assert!(clients.next(&b).is_none());
+        assert!(clients.is_empty());
+    }
+}
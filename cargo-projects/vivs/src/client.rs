@@ -0,0 +1,325 @@
+//! Async client library for Vivs.
+//!
+//! ```no_run
+//! use vivs::client::{Client, Cmd};
+//!
+//! # async fn example() -> Result<(), vivs::client::ClientError> {
+//! let mut client = Client::builder().address("127.0.0.1:9000").connect().await?;
+//!
+//! client.set("greeting", "hello").await?;
+//! let greeting: Option<String> = client.get("greeting").await?;
+//!
+//! // Any command can be sent with the command builder
+//! let ttl: i64 = client.query(&Cmd::new("TTL").arg("greeting")).await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{connection::ConnectionError, data_chunk::DataChunk, Connection, PORT};
+use std::{fmt::Display, time::Duration};
+use tokio::net::TcpStream;
+
+pub mod cmd;
+pub use cmd::{Cmd, Pipeline};
+
+pub mod reply;
+pub use reply::FromReply;
+
+pub mod pool;
+pub use pool::{Pool, PooledClient};
+
+pub mod cluster;
+pub use cluster::{ClusterClient, ClusterClientBuilder};
+
+pub mod cli;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Number of times the client tries to (re)connect before giving up
+pub const DEFAULT_RECONNECT_ATTEMPTS: u32 = 3;
+// Delay between reconnection attempts, it doubles after every attempt
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+// How long a pool waits for a client to be returned when all of them are in use
+pub const DEFAULT_POOL_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(std::io::Error),
+    // Server sent a reply that could not be parsed
+    Protocol(String),
+    // Server replied with an error i.e. ERR unknown command
+    Server(String),
+    // Reply could not be converted to the requested type
+    Type(String),
+    Timeout,
+    // All connections of the pool are in use
+    PoolTimeout,
+    // Cluster kept redirecting the command (MOVED / ASK) to other nodes
+    TooManyRedirects,
+}
+
+impl std::error::Error for ClientError {}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "IO error: {e}"),
+            ClientError::Protocol(e) => write!(f, "Protocol error: {e}"),
+            ClientError::Server(e) => write!(f, "{e}"),
+            ClientError::Type(e) => write!(f, "Type error: {e}"),
+            ClientError::Timeout => write!(f, "Timed out waiting for the server"),
+            ClientError::PoolTimeout => write!(f, "Timed out waiting for a pooled connection"),
+            ClientError::TooManyRedirects => write!(f, "Too many cluster redirects"),
+        }
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+impl From<ConnectionError> for ClientError {
+    fn from(e: ConnectionError) -> Self {
+        match e {
+            ConnectionError::Io(e) => ClientError::Io(e),
+            ConnectionError::TcpClosed => ClientError::Io(std::io::ErrorKind::UnexpectedEof.into()),
+            e => ClientError::Protocol(e.to_string()),
+        }
+    }
+}
+
+/// Configures and creates a `Client` (or a `Pool` of clients).
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    address: String,
+    connect_timeout: Duration,
+    response_timeout: Option<Duration>,
+    reconnect_attempts: u32,
+    reconnect_delay: Duration,
+    pool_timeout: Duration,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            address: format!("127.0.0.1:{PORT}"),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            response_timeout: None,
+            reconnect_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            pool_timeout: DEFAULT_POOL_TIMEOUT,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// Address of the node i.e. 127.0.0.1:9000
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = address.into();
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// How long to wait for a reply, replies are waited on forever by default
+    pub fn response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = Some(timeout);
+        self
+    }
+
+    /// How many times and how often (the delay doubles every time) to try to reconnect
+    /// once the connection is lost
+    pub fn reconnect(mut self, attempts: u32, delay: Duration) -> Self {
+        self.reconnect_attempts = attempts;
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// How long a pool waits for a client to be returned when all of them are in use
+    pub fn pool_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_timeout = timeout;
+        self
+    }
+
+    /// Creates a client and connects it to the node.
+    pub async fn connect(self) -> Result<Client, ClientError> {
+        let mut client = Client {
+            config: self,
+            connection: None,
+        };
+        client.ensure_connected().await?;
+
+        Ok(client)
+    }
+
+    /// Creates a pool of up to `size` connections, connections are established lazily.
+    pub fn pool(self, size: usize) -> Pool {
+        Pool::new(self, size)
+    }
+}
+
+/// Client connected to a single Vivs node.
+///
+/// When the connection gets lost (or a reply does not arrive in time),
+/// the next command reconnects to the node first.
+pub struct Client {
+    config: ClientBuilder,
+    // None when the connection is lost
+    connection: Option<Connection>,
+}
+
+impl Client {
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn address(&self) -> &str {
+        &self.config.address
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    async fn ensure_connected(&mut self) -> Result<&mut Connection, ClientError> {
+        if self.connection.is_none() {
+            let mut delay = self.config.reconnect_delay;
+            let mut attempt = 0;
+
+            let stream = loop {
+                let stream = tokio::time::timeout(
+                    self.config.connect_timeout,
+                    TcpStream::connect(&self.config.address),
+                )
+                .await;
+
+                let error = match stream {
+                    Ok(Ok(stream)) => break stream,
+                    Ok(Err(e)) => ClientError::Io(e),
+                    Err(_) => ClientError::Timeout,
+                };
+
+                attempt += 1;
+                if attempt >= self.config.reconnect_attempts.max(1) {
+                    return Err(error);
+                }
+
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            };
+
+            stream.set_nodelay(true)?;
+            self.connection = Some(Connection::new(stream));
+        }
+
+        match self.connection.as_mut() {
+            Some(connection) => Ok(connection),
+            None => Err(ClientError::Io(std::io::ErrorKind::NotConnected.into())),
+        }
+    }
+
+    /// Writes already encoded commands and reads `count` replies.
+    async fn send(&mut self, frame: &[u8], count: usize) -> Result<Vec<DataChunk>, ClientError> {
+        let response_timeout = self.config.response_timeout;
+        let connection = self.ensure_connected().await?;
+
+        let exchange = async {
+            connection.write_frame(frame).await?;
+
+            let mut replies = Vec::with_capacity(count);
+            for _ in 0..count {
+                replies.push(connection.read_chunk(usize::MAX, usize::MAX).await?);
+            }
+
+            Ok::<_, ClientError>(replies)
+        };
+
+        let result = match response_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, exchange)
+                .await
+                .unwrap_or(Err(ClientError::Timeout)),
+            None => exchange.await,
+        };
+
+        // Whatever is left on the connection can't be matched with commands anymore
+        if matches!(
+            result,
+            Err(ClientError::Io(_) | ClientError::Protocol(_) | ClientError::Timeout)
+        ) {
+            self.connection = None;
+        }
+
+        result
+    }
+
+    /// Sends the command and converts the reply to `T`.
+    pub async fn query<T: FromReply>(&mut self, cmd: &Cmd) -> Result<T, ClientError> {
+        let reply = self
+            .send(&cmd.to_bytes(), 1)
+            .await?
+            .pop()
+            .unwrap_or_default();
+        T::from_reply(reply)
+    }
+
+    /// Sends all the commands of the pipeline in one go and returns the replies in the same order.
+    ///
+    /// Replies are returned as they are (errors included),
+    /// each one can be converted with `FromReply` afterwards.
+    pub async fn query_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+    ) -> Result<Vec<DataChunk>, ClientError> {
+        if pipeline.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.send(&pipeline.to_bytes(), pipeline.len()).await
+    }
+
+    pub async fn ping(&mut self) -> Result<String, ClientError> {
+        self.query(&Cmd::new("PING")).await
+    }
+
+    pub async fn get<T: FromReply>(&mut self, key: impl AsRef<[u8]>) -> Result<T, ClientError> {
+        self.query(&Cmd::new("GET").arg(key)).await
+    }
+
+    pub async fn set(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), ClientError> {
+        self.query::<String>(&Cmd::new("SET").arg(key).arg(value))
+            .await
+            .map(|_| ())
+    }
+
+    /// Sets the value and expires the key in `seconds`
+    pub async fn set_ex(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+        seconds: u64,
+    ) -> Result<(), ClientError> {
+        let cmd = Cmd::new("SET")
+            .arg(key)
+            .arg(value)
+            .arg("XS")
+            .arg(seconds.to_string());
+
+        self.query::<String>(&cmd).await.map(|_| ())
+    }
+
+    /// Deletes the key, returns the number of keys that were deleted
+    pub async fn delete(&mut self, key: impl AsRef<[u8]>) -> Result<i64, ClientError> {
+        self.query(&Cmd::new("DELETE").arg(key)).await
+    }
+
+    pub async fn ttl(&mut self, key: impl AsRef<[u8]>) -> Result<i64, ClientError> {
+        self.query(&Cmd::new("TTL").arg(key)).await
+    }
+}
@@ -0,0 +1,376 @@
+//! Helpers shared by the command line tools (`vivs-repl` and `vivs-cli`):
+//! splitting of the typed in commands, reply formatting and command hints.
+use crate::{
+    client::reply::{error_message, integer},
+    data_chunk::DataChunk,
+};
+use serde_json::Value;
+use std::fmt::Display;
+
+/// How replies are printed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputMode {
+    /// Same as redis-cli i.e. quoted strings, (integer) 1, (nil), numbered array elements
+    #[default]
+    Pretty,
+    /// Values as they are, one per line
+    Raw,
+    /// Each reply as a JSON value, errors are {"error": "<message>"}
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ArgsError {
+    // Quote was opened but never closed, more input is needed
+    UnterminatedQuote,
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ArgsError::UnterminatedQuote => write!(f, "Invalid argument(s): unbalanced quotes"),
+        }
+    }
+}
+
+/// Splits the line into command arguments the same way redis-cli does.
+///
+/// Arguments are separated by whitespace, "double quoted" arguments support
+/// \n, \r, \t, \", \\ and \xHH escapes, 'single quoted' ones only support \'.
+/// Quoted values can span multiple lines.
+pub fn split_args(line: &str) -> Result<Vec<String>, ArgsError> {
+    let mut args = vec![];
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if chars.peek().is_none() {
+            return Ok(args);
+        }
+
+        let mut arg = String::new();
+
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            match c {
+                '"' => loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => arg.push('\n'),
+                            Some('r') => arg.push('\r'),
+                            Some('t') => arg.push('\t'),
+                            Some('x') => {
+                                let hex = chars.clone().take(2).collect::<String>();
+                                match u8::from_str_radix(&hex, 16) {
+                                    Ok(byte) if hex.len() == 2 => {
+                                        arg.push(byte as char);
+                                        chars.nth(1);
+                                    }
+                                    _ => arg.push('x'),
+                                }
+                            }
+                            Some(c) => arg.push(c),
+                            None => return Err(ArgsError::UnterminatedQuote),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err(ArgsError::UnterminatedQuote),
+                    }
+                },
+                '\'' => loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                            arg.push('\'');
+                        }
+                        Some(c) => arg.push(c),
+                        None => return Err(ArgsError::UnterminatedQuote),
+                    }
+                },
+                c => arg.push(c),
+            }
+        }
+
+        args.push(arg);
+    }
+}
+
+/// Same as redis-cli, non printable bytes are escaped and the string is quoted
+fn quote(value: &[u8]) -> String {
+    let mut quoted = String::from("\"");
+
+    for byte in value {
+        match byte {
+            b'\\' => quoted.push_str("\\\\"),
+            b'"' => quoted.push_str("\\\""),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            0x07 => quoted.push_str("\\a"),
+            0x08 => quoted.push_str("\\b"),
+            byte if byte.is_ascii_graphic() || *byte == b' ' => quoted.push(*byte as char),
+            byte => quoted.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+fn integer_as_string(value: &[u8]) -> String {
+    integer(value)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|_| String::from_utf8_lossy(value).into_owned())
+}
+
+// Elements of nested arrays are indented by the width of the parent's "N) " prefix
+fn pretty(reply: &DataChunk, indent: usize) -> String {
+    match reply {
+        DataChunk::Null => "(nil)".to_owned(),
+        DataChunk::Integer(value) => format!("(integer) {}", integer_as_string(value)),
+        DataChunk::Bulk(value) => quote(value),
+        DataChunk::SimpleError(words) => format!("(error) {}", error_message(words)),
+        DataChunk::Array(items) if items.is_empty() => "(empty array)".to_owned(),
+        DataChunk::Array(items) => {
+            let width = items.len().to_string().len();
+
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let prefix = format!("{:>width$}) ", i + 1);
+                    let item = pretty(item, indent + prefix.len());
+                    let padding = if i == 0 { 0 } else { indent };
+
+                    format!("{:padding$}{prefix}{item}", "")
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+    }
+}
+
+fn raw(reply: &DataChunk) -> String {
+    match reply {
+        DataChunk::Null => "".to_owned(),
+        DataChunk::Integer(value) => integer_as_string(value),
+        DataChunk::Bulk(value) => String::from_utf8_lossy(value).into_owned(),
+        DataChunk::SimpleError(words) => error_message(words),
+        DataChunk::Array(items) => items.iter().map(raw).collect::<Vec<_>>().join("\n"),
+    }
+}
+
+fn json(reply: &DataChunk) -> Value {
+    match reply {
+        DataChunk::Null => Value::Null,
+        DataChunk::Integer(value) => match integer(value) {
+            Ok(value) => value.into(),
+            Err(_) => String::from_utf8_lossy(value).into(),
+        },
+        DataChunk::Bulk(value) => String::from_utf8_lossy(value).into(),
+        DataChunk::SimpleError(words) => serde_json::json!({ "error": error_message(words) }),
+        DataChunk::Array(items) => items.iter().map(json).collect(),
+    }
+}
+
+/// Formats the reply to be printed to the terminal
+pub fn format_reply(reply: &DataChunk, mode: OutputMode) -> String {
+    match mode {
+        OutputMode::Pretty => pretty(reply, 0),
+        OutputMode::Raw => raw(reply),
+        OutputMode::Json => json(reply).to_string(),
+    }
+}
+
+/// Arguments of a command that get shown as a hint
+/// and values that can be completed in place of the arguments
+pub struct CommandHint {
+    pub name: &'static str,
+    pub args: &'static str,
+    pub completions: &'static [&'static str],
+}
+
+pub const COMMANDS: &[CommandHint] = &[
+    CommandHint {
+        name: "PING",
+        args: "[message]",
+        completions: &[],
+    },
+    CommandHint {
+        name: "GET",
+        args: "key",
+        completions: &[],
+    },
+    CommandHint {
+        name: "SET",
+        args: "key value [XS seconds]",
+        completions: &["XS"],
+    },
+    CommandHint {
+        name: "DELETE",
+        args: "key",
+        completions: &[],
+    },
+    CommandHint {
+        name: "TTL",
+        args: "key",
+        completions: &[],
+    },
+    CommandHint {
+        name: "SCAN",
+        args: "cursor [MATCH pattern] [COUNT count]",
+        completions: &["MATCH", "COUNT"],
+    },
+    CommandHint {
+        name: "SELECT",
+        args: "index",
+        completions: &[],
+    },
+    CommandHint {
+        name: "SWAPDB",
+        args: "index1 index2",
+        completions: &[],
+    },
+    CommandHint {
+        name: "MOVE",
+        args: "key db",
+        completions: &[],
+    },
+    CommandHint {
+        name: "FLUSHDB",
+        args: "[ASYNC|SYNC]",
+        completions: &["ASYNC", "SYNC"],
+    },
+    CommandHint {
+        name: "FLUSHALL",
+        args: "[ASYNC|SYNC]",
+        completions: &["ASYNC", "SYNC"],
+    },
+    CommandHint {
+        name: "ASKING",
+        args: "",
+        completions: &[],
+    },
+    CommandHint {
+        name: "CLIENT",
+        args: "LIST|INFO|ID|SETNAME|GETNAME|KILL|PAUSE|UNPAUSE [arg ...]",
+        completions: &[
+            "LIST", "INFO", "ID", "SETNAME", "GETNAME", "KILL", "PAUSE", "UNPAUSE", "TYPE", "ADDR",
+            "LADDR", "USER", "SKIPME", "WRITE", "ALL",
+        ],
+    },
+    CommandHint {
+        name: "INFO",
+        args: "[section ...]",
+        completions: &[
+            "server",
+            "clients",
+            "memory",
+            "persistence",
+            "stats",
+            "replication",
+            "cluster",
+            "keyspace",
+            "commandstats",
+            "all",
+        ],
+    },
+    CommandHint {
+        name: "COMMAND",
+        args: "[COUNT|INFO [command ...]|GETKEYS command [arg ...]]",
+        completions: &["COUNT", "INFO", "GETKEYS"],
+    },
+    CommandHint {
+        name: "CONFIG",
+        args: "GET pattern [pattern ...]|SET parameter value [parameter value ...]|REWRITE",
+        completions: &["GET", "SET", "REWRITE"],
+    },
+    CommandHint {
+        name: "SLOWLOG",
+        args: "GET [count]|LEN|RESET",
+        completions: &["GET", "LEN", "RESET"],
+    },
+    CommandHint {
+        name: "MONITOR",
+        args: "",
+        completions: &[],
+    },
+    CommandHint {
+        name: "SHUTDOWN",
+        args: "[NOSAVE|SAVE]",
+        completions: &["NOSAVE", "SAVE"],
+    },
+    CommandHint {
+        name: "CLUSTER",
+        args: "SLOTS|KEYSLOT key|RELOAD",
+        completions: &["SLOTS", "KEYSLOT", "RELOAD"],
+    },
+];
+
+/// Looks up the command hint by (case insensitive) name
+pub fn command_hint(name: &str) -> Option<&'static CommandHint> {
+    COMMANDS
+        .iter()
+        .find(|command| command.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+    use bytes::Bytes;
+
+    fn bulk(value: &str) -> DataChunk {
+        DataChunk::Bulk(Bytes::copy_from_slice(value.as_bytes()))
+    }
+
+    #[test]
+    fn splits_quoted_args() {
+        assert_eq!(
+            split_args(r#"SET greeting "hello \"world\"\n" 'it\'s' a"b c"d"#).unwrap(),
+            vec!["SET", "greeting", "hello \"world\"\n", "it's", "ab cd"]
+        );
+        assert_eq!(split_args("  ").unwrap(), Vec::<String>::new());
+        assert_eq!(split_args(r#"GET "\x41\x""#).unwrap(), vec!["GET", "Ax"]);
+        assert_eq!(
+            split_args("SET key \"multi\nline"),
+            Err(ArgsError::UnterminatedQuote)
+        );
+    }
+
+    #[test]
+    fn pretty_prints_nested_arrays() {
+        let reply = DataChunk::Array(vec![
+            bulk("a"),
+            DataChunk::Array(vec![bulk("b"), DataChunk::Null]),
+            DataChunk::Array(vec![]),
+        ]);
+
+        assert_eq!(
+            format_reply(&reply, OutputMode::Pretty),
+            "1) \"a\"\n2) 1) \"b\"\n   2) (nil)\n3) (empty array)"
+        );
+    }
+
+    #[test]
+    fn formats_raw_and_json() {
+        let reply = DataChunk::Array(vec![
+            bulk("a b"),
+            DataChunk::Integer(Bytes::from("3")),
+            DataChunk::Null,
+            DataChunk::SimpleError(vec![bulk("ERR"), bulk("boom")]),
+        ]);
+
+        assert_eq!(format_reply(&reply, OutputMode::Raw), "a b\n3\n\nERR boom");
+        assert_eq!(
+            format_reply(&reply, OutputMode::Json),
+            r#"["a b",3,null,{"error":"ERR boom"}]"#
+        );
+        assert_eq!(
+            format_reply(&bulk("line\r\n"), OutputMode::Pretty),
+            r#""line\r\n""#
+        );
+    }
+}
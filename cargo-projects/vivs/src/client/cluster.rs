@@ -0,0 +1,600 @@
+use crate::{
+    client::{reply::integer, Client, ClientBuilder, ClientError, Cmd, FromReply, Pipeline},
+    cluster::{key_slot, CELLS_TOTAL, CLUSTER_ASK_ERR},
+    commands::registry::{lookup, CommandFlag},
+    data_chunk::DataChunk,
+};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+pub const CLUSTER_MOVED_ERR: &str = "MOVED";
+// How many times a single command can be redirected (MOVED, ASK or a lost node) before giving up
+pub const DEFAULT_MAX_REDIRECTS: u32 = 5;
+
+/// Range of hash slots (both ends are included) and the nodes that serve it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub master: String,
+    pub replicas: Vec<String>,
+}
+
+/// Which node serves which hash slots, as seen by the client.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SlotMap {
+    // Sorted by the first slot of the range
+    ranges: Vec<SlotRange>,
+}
+
+impl SlotMap {
+    /// All the slots are served by a single node (i.e. cluster mode is off)
+    pub fn single(address: impl Into<String>) -> Self {
+        Self {
+            ranges: vec![SlotRange {
+                start: 0,
+                end: CELLS_TOTAL as u16 - 1,
+                master: address.into(),
+                replicas: vec![],
+            }],
+        }
+    }
+
+    pub fn ranges(&self) -> &[SlotRange] {
+        &self.ranges
+    }
+
+    pub fn range(&self, slot: u16) -> Option<&SlotRange> {
+        self.ranges
+            .iter()
+            .find(|range| range.start <= slot && slot <= range.end)
+    }
+
+    /// Address of the node that serves the slot
+    pub fn master(&self, slot: u16) -> Option<&str> {
+        self.range(slot).map(|range| range.master.as_str())
+    }
+
+    /// Slot has moved to a different node (MOVED), only this slot changes owners.
+    pub fn set_master(&mut self, slot: u16, address: &str) {
+        let Some(index) = self
+            .ranges
+            .iter()
+            .position(|range| range.start <= slot && slot <= range.end)
+        else {
+            self.ranges.push(SlotRange {
+                start: slot,
+                end: slot,
+                master: address.to_owned(),
+                replicas: vec![],
+            });
+            self.ranges.sort_by_key(|range| range.start);
+            return;
+        };
+
+        let range = self.ranges.remove(index);
+        let mut split = vec![];
+
+        if range.start < slot {
+            split.push(SlotRange {
+                end: slot - 1,
+                ..range.clone()
+            });
+        }
+        split.push(SlotRange {
+            start: slot,
+            end: slot,
+            master: address.to_owned(),
+            replicas: vec![],
+        });
+        if slot < range.end {
+            split.push(SlotRange {
+                start: slot + 1,
+                ..range
+            });
+        }
+
+        self.ranges.splice(index..index, split);
+    }
+}
+
+/// Turns node entry of CLUSTER SLOTS reply ([host, port, id]) into host:port.
+fn node_address(node: DataChunk) -> Result<String, ClientError> {
+    let DataChunk::Array(node) = node else {
+        return Err(ClientError::Type(format!("expected a node, got {node:?}")));
+    };
+
+    let mut node = node.into_iter();
+    let host = String::from_reply(node.next().unwrap_or_default())?;
+    let port = match node.next() {
+        Some(DataChunk::Integer(port) | DataChunk::Bulk(port)) => integer(&port)?,
+        port => return Err(ClientError::Type(format!("expected a port, got {port:?}"))),
+    };
+
+    Ok(format!("{host}:{port}"))
+}
+
+impl FromReply for SlotMap {
+    /// CLUSTER SLOTS reply, each entry is [start, end, master, replica ...]
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        let entries = Vec::<DataChunk>::from_reply(reply)?;
+        let mut ranges = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let mut entry = Vec::<DataChunk>::from_reply(entry)?.into_iter();
+
+            let start = i64::from_reply(entry.next().unwrap_or_default())?;
+            let end = i64::from_reply(entry.next().unwrap_or_default())?;
+            let master = node_address(entry.next().unwrap_or_default())?;
+            let replicas = entry.map(node_address).collect::<Result<_, _>>()?;
+
+            ranges.push(SlotRange {
+                start: start as u16,
+                end: end as u16,
+                master,
+                replicas,
+            });
+        }
+
+        ranges.sort_by_key(|range| range.start);
+
+        Ok(SlotMap { ranges })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Redirect {
+    // Slot is served by a different node from now on
+    Moved(u16, String),
+    // Only this query needs to be sent to a different node
+    Ask(u16, String),
+}
+
+/// Checks whether the reply is a -MOVED <slot> <address> or -ASK <slot> <address> error.
+pub fn redirect(reply: &DataChunk) -> Option<Redirect> {
+    let DataChunk::SimpleError(words) = reply else {
+        return None;
+    };
+
+    let mut words = words.iter().filter_map(|word| match word {
+        DataChunk::Bulk(word) => std::str::from_utf8(word).ok(),
+        _ => None,
+    });
+
+    let kind = words.next()?;
+    let slot = words.next()?.parse().ok()?;
+    let address = words.next()?.to_owned();
+
+    match kind {
+        CLUSTER_MOVED_ERR => Some(Redirect::Moved(slot, address)),
+        CLUSTER_ASK_ERR => Some(Redirect::Ask(slot, address)),
+        _ => None,
+    }
+}
+
+/// Configures and creates a `ClusterClient`.
+#[derive(Debug, Clone)]
+pub struct ClusterClientBuilder {
+    seeds: Vec<String>,
+    client: ClientBuilder,
+    read_from_replicas: bool,
+    max_redirects: u32,
+}
+
+impl Default for ClusterClientBuilder {
+    fn default() -> Self {
+        Self {
+            seeds: vec![],
+            client: Client::builder(),
+            read_from_replicas: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+        }
+    }
+}
+
+impl ClusterClientBuilder {
+    /// Address of a node that the slot map can be fetched from, any node of the cluster will do
+    pub fn seed(mut self, address: impl Into<String>) -> Self {
+        self.seeds.push(address.into());
+        self
+    }
+
+    /// Settings (timeouts, reconnects) used for the connection to each node,
+    /// the address is ignored
+    pub fn client(mut self, client: ClientBuilder) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sends read only commands (GET, TTL) to replicas, when the slot has any
+    pub fn read_from_replicas(mut self, read_from_replicas: bool) -> Self {
+        self.read_from_replicas = read_from_replicas;
+        self
+    }
+
+    pub fn max_redirects(mut self, max_redirects: u32) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Fetches the slot map from the first seed node that is reachable.
+    pub async fn connect(self) -> Result<ClusterClient, ClientError> {
+        let mut client = ClusterClient {
+            config: self,
+            slots: SlotMap::default(),
+            nodes: HashMap::new(),
+            next_replica: 0,
+        };
+        client.refresh_slots().await?;
+
+        Ok(client)
+    }
+}
+
+/// Client that talks to a cluster of Vivs nodes.
+///
+/// Commands are sent to the node that serves the key's hash slot (same CRC16 as the nodes use for routing).
+/// MOVED replies update the slot map, ASK replies are retried on the other node with ASKING first.
+pub struct ClusterClient {
+    config: ClusterClientBuilder,
+    slots: SlotMap,
+    // Connections to the nodes, keyed by address
+    nodes: HashMap<String, Client>,
+    next_replica: usize,
+}
+
+impl ClusterClient {
+    pub fn builder() -> ClusterClientBuilder {
+        ClusterClientBuilder::default()
+    }
+
+    pub fn slots(&self) -> &SlotMap {
+        &self.slots
+    }
+
+    async fn node(&mut self, address: &str) -> Result<&mut Client, ClientError> {
+        if !self.nodes.contains_key(address) {
+            let client = self
+                .config
+                .client
+                .clone()
+                .address(address)
+                .connect()
+                .await?;
+            self.nodes.insert(address.to_owned(), client);
+        }
+
+        self.nodes
+            .get_mut(address)
+            .ok_or_else(|| ClientError::Io(std::io::ErrorKind::NotConnected.into()))
+    }
+
+    /// Fetches the slot map (CLUSTER SLOTS) from the seeds or the nodes that are already known.
+    pub async fn refresh_slots(&mut self) -> Result<(), ClientError> {
+        let mut addresses = self.config.seeds.clone();
+        for range in &self.slots.ranges {
+            if !addresses.contains(&range.master) {
+                addresses.push(range.master.clone());
+            }
+        }
+
+        let mut last_error = ClientError::Io(std::io::ErrorKind::NotFound.into());
+
+        for address in addresses {
+            let reply = match self.node(&address).await {
+                Ok(node) => {
+                    node.query::<DataChunk>(&Cmd::new("CLUSTER").arg("SLOTS"))
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+
+            match reply.and_then(|reply| match reply {
+                // Cluster support is disabled, the node serves all the slots
+                DataChunk::SimpleError(_) => Ok(SlotMap::single(&address)),
+                reply => SlotMap::from_reply(reply),
+            }) {
+                Ok(slots) => {
+                    self.slots = slots;
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.nodes.remove(&address);
+                    last_error = e;
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// Address of the node that the command should be sent to
+    fn route(&mut self, cmd: &Cmd) -> Option<String> {
+        let (key, readonly) = routing_key(cmd);
+
+        // Commands that do not take a key can be sent to any node
+        let Some(key) = key else {
+            return self.slots.ranges.first().map(|range| range.master.clone());
+        };
+
+        let range = self.slots.range(key_slot(&key))?;
+
+        // Commands that only read data can be served by replicas
+        if self.config.read_from_replicas && readonly && !range.replicas.is_empty() {
+            self.next_replica = self.next_replica.wrapping_add(1);
+            return Some(range.replicas[self.next_replica % range.replicas.len()].clone());
+        }
+
+        Some(range.master.clone())
+    }
+
+    /// Sends the command to the node that serves the key, following redirects.
+    pub async fn query<T: FromReply>(&mut self, cmd: &Cmd) -> Result<T, ClientError> {
+        let mut ask = None;
+
+        for _ in 0..=self.config.max_redirects {
+            let address = match ask.take() {
+                Some(address) => address,
+                None => match self.route(cmd) {
+                    Some(address) => address,
+                    None => {
+                        self.refresh_slots().await?;
+                        self.route(cmd).ok_or_else(|| {
+                            ClientError::Protocol("hash slot is not served by any node".to_owned())
+                        })?
+                    }
+                },
+            };
+
+            let reply = match self.node(&address).await {
+                Ok(node) => node.query::<DataChunk>(cmd).await,
+                Err(e) => Err(e),
+            };
+
+            let reply = match reply {
+                Ok(reply) => reply,
+                // Node is gone, the slots might have been taken over by a different node
+                Err(ClientError::Io(_) | ClientError::Timeout) => {
+                    self.nodes.remove(&address);
+                    let _ = self.refresh_slots().await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            match redirect(&reply) {
+                Some(Redirect::Moved(slot, address)) => self.slots.set_master(slot, &address),
+                Some(Redirect::Ask(_, address)) => {
+                    let pipeline = Pipeline::new().cmd(Cmd::new("ASKING")).cmd(cmd.clone());
+                    let mut replies = self.node(&address).await?.query_pipeline(&pipeline).await?;
+
+                    let reply = replies.pop().unwrap_or_default();
+                    match redirect(&reply) {
+                        Some(Redirect::Ask(_, address)) => ask = Some(address),
+                        Some(Redirect::Moved(slot, address)) => {
+                            self.slots.set_master(slot, &address)
+                        }
+                        None => return T::from_reply(reply),
+                    }
+                }
+                None => return T::from_reply(reply),
+            }
+        }
+
+        Err(ClientError::TooManyRedirects)
+    }
+
+    /// Sends the commands of the pipeline, grouped by the node that serves their keys,
+    /// and returns the replies in the same order as the commands.
+    ///
+    /// Commands that get redirected (or whose node is gone) are retried one by one with `query()`.
+    pub async fn query_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+    ) -> Result<Vec<DataChunk>, ClientError> {
+        let cmds = pipeline.cmds();
+        let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, cmd) in cmds.iter().enumerate() {
+            let address = match self.route(cmd) {
+                Some(address) => address,
+                None => {
+                    self.refresh_slots().await?;
+                    self.route(cmd).ok_or_else(|| {
+                        ClientError::Protocol("hash slot is not served by any node".to_owned())
+                    })?
+                }
+            };
+            groups.entry(address).or_default().push(index);
+        }
+
+        let mut replies = cmds.iter().map(|_| None).collect::<Vec<_>>();
+
+        for (address, indexes) in groups {
+            let group = indexes.iter().fold(Pipeline::new(), |group, index| {
+                group.cmd(cmds[*index].clone())
+            });
+
+            let group_replies = match self.node(&address).await {
+                Ok(node) => node.query_pipeline(&group).await,
+                Err(e) => Err(e),
+            };
+
+            match group_replies {
+                Ok(group_replies) => {
+                    for (index, reply) in indexes.into_iter().zip(group_replies) {
+                        if redirect(&reply).is_none() {
+                            replies[index] = Some(reply);
+                        }
+                    }
+                }
+                Err(ClientError::Io(_) | ClientError::Timeout) => {
+                    self.nodes.remove(&address);
+                    let _ = self.refresh_slots().await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        let mut results = Vec::with_capacity(replies.len());
+        for (cmd, reply) in cmds.iter().zip(replies) {
+            match reply {
+                Some(reply) => results.push(reply),
+                None => results.push(self.query::<DataChunk>(cmd).await?),
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn get<T: FromReply>(&mut self, key: impl AsRef<[u8]>) -> Result<T, ClientError> {
+        self.query(&Cmd::new("GET").arg(key)).await
+    }
+
+    pub async fn set(
+        &mut self,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), ClientError> {
+        self.query::<String>(&Cmd::new("SET").arg(key).arg(value))
+            .await
+            .map(|_| ())
+    }
+
+    /// Deletes the key, returns the number of keys that were deleted
+    pub async fn delete(&mut self, key: impl AsRef<[u8]>) -> Result<i64, ClientError> {
+        self.query(&Cmd::new("DELETE").arg(key)).await
+    }
+
+    pub async fn ttl(&mut self, key: impl AsRef<[u8]>) -> Result<i64, ClientError> {
+        self.query(&Cmd::new("TTL").arg(key)).await
+    }
+}
+
+/// First key of the command and whether the command only reads data, same as the server
+/// works them out from its command table. Unknown commands don't have a key.
+fn routing_key(cmd: &Cmd) -> (Option<Bytes>, bool) {
+    let Some((name, args)) = cmd.args().split_first() else {
+        return (None, false);
+    };
+
+    match lookup(&String::from_utf8_lossy(name)) {
+        Some(spec) => (
+            spec.keys(args).into_iter().next(),
+            spec.has_flag(CommandFlag::Readonly),
+        ),
+        None => (None, false),
+    }
+}
+
+#[cfg(test)]
+mod cluster_tests {
+    use super::*;
+
+    fn error(words: &[&str]) -> DataChunk {
+        DataChunk::SimpleError(
+            words
+                .iter()
+                .map(|word| DataChunk::Bulk(Bytes::copy_from_slice(word.as_bytes())))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_redirects() {
+        assert_eq!(
+            redirect(&error(&["MOVED", "3999", "127.0.0.1:9001"])),
+            Some(Redirect::Moved(3999, "127.0.0.1:9001".to_owned()))
+        );
+        assert_eq!(
+            redirect(&error(&["ASK", "12", "127.0.0.1:9002"])),
+            Some(Redirect::Ask(12, "127.0.0.1:9002".to_owned()))
+        );
+        assert_eq!(redirect(&error(&["ERR", "unknown", "command"])), None);
+        assert_eq!(redirect(&DataChunk::Null), None);
+    }
+
+    #[test]
+    fn routes_by_the_keys_of_the_command_table() {
+        let key = |cmd: Cmd| routing_key(&cmd).0;
+
+        assert_eq!(
+            key(Cmd::new("OBJECT").arg("ENCODING").arg("key")),
+            Some(Bytes::from("key"))
+        );
+        assert_eq!(
+            key(Cmd::new("XREAD").arg("STREAMS").arg("stream").arg("0")),
+            Some(Bytes::from("stream"))
+        );
+        assert_eq!(
+            key(Cmd::new("BITOP").arg("AND").arg("dest").arg("a")),
+            Some(Bytes::from("dest"))
+        );
+        assert_eq!(key(Cmd::new("SELECT").arg("1")), None);
+        assert_eq!(key(Cmd::new("FLUSHDB").arg("ASYNC")), None);
+        assert_eq!(key(Cmd::new("NOPE").arg("key")), None);
+
+        assert_eq!(
+            routing_key(&Cmd::new("MEMORY").arg("USAGE").arg("key")),
+            (Some(Bytes::from("key")), true)
+        );
+        assert_eq!(
+            routing_key(&Cmd::new("XGROUP").arg("CREATE").arg("stream").arg("group")),
+            (Some(Bytes::from("stream")), false)
+        );
+    }
+
+    #[test]
+    fn moved_slot_splits_the_range() {
+        let mut slots = SlotMap::single("127.0.0.1:9000");
+        slots.set_master(100, "127.0.0.1:9001");
+
+        let ranges = slots
+            .ranges()
+            .iter()
+            .map(|range| (range.start, range.end, range.master.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ranges,
+            vec![
+                (0, 99, "127.0.0.1:9000"),
+                (100, 100, "127.0.0.1:9001"),
+                (101, 16383, "127.0.0.1:9000"),
+            ]
+        );
+
+        slots.set_master(100, "127.0.0.1:9002");
+        assert_eq!(slots.master(100), Some("127.0.0.1:9002"));
+        assert_eq!(slots.ranges().len(), 3);
+    }
+
+    #[test]
+    fn parses_cluster_slots_reply() {
+        let node = |host: &str, port: u64| {
+            DataChunk::Array(vec![
+                DataChunk::Bulk(Bytes::copy_from_slice(host.as_bytes())),
+                DataChunk::Integer(Bytes::copy_from_slice(&crate::utils::u64_as_bytes(port))),
+                DataChunk::Bulk(Bytes::from("id")),
+            ])
+        };
+        let slot = |slot: u64| {
+            DataChunk::Integer(Bytes::copy_from_slice(&crate::utils::u64_as_bytes(slot)))
+        };
+
+        let reply = DataChunk::Array(vec![
+            DataChunk::Array(vec![slot(8192), slot(16383), node("127.0.0.1", 9001)]),
+            DataChunk::Array(vec![
+                slot(0),
+                slot(8191),
+                node("127.0.0.1", 9000),
+                node("127.0.0.1", 9002),
+            ]),
+        ]);
+
+        let slots = SlotMap::from_reply(reply).unwrap();
+        assert_eq!(slots.master(0), Some("127.0.0.1:9000"));
+        assert_eq!(slots.master(16383), Some("127.0.0.1:9001"));
+        assert_eq!(slots.ranges()[0].replicas, vec!["127.0.0.1:9002"]);
+    }
+}
@@ -0,0 +1,94 @@
+use bytes::{BufMut, Bytes};
+
+/// Command builder, encodes the command and its arguments as a RESP array of bulk strings.
+///
+/// Arguments are binary safe, anything that can be referenced as bytes can be passed in.
+///
+/// ```
+/// use vivs::client::Cmd;
+///
+/// let cmd = Cmd::new("SET").arg("greeting").arg("hello world");
+/// assert_eq!(
+///     cmd.to_bytes(),
+///     b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$11\r\nhello world\r\n"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Cmd {
+    args: Vec<Bytes>,
+}
+
+impl Cmd {
+    pub fn new(name: impl AsRef<[u8]>) -> Self {
+        Self::default().arg(name)
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        self.args.push(Bytes::copy_from_slice(arg.as_ref()));
+        self
+    }
+
+    /// Command name and arguments
+    pub fn args(&self) -> &[Bytes] {
+        &self.args
+    }
+
+    /// Command as it is sent over the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoded = vec![];
+        self.write_to(&mut encoded);
+        encoded
+    }
+
+    fn write_to(&self, encoded: &mut Vec<u8>) {
+        encoded.put_slice(format!("*{}\r\n", self.args.len()).as_bytes());
+        for arg in &self.args {
+            encoded.put_slice(format!("${}\r\n", arg.len()).as_bytes());
+            encoded.put_slice(arg);
+            encoded.put_slice(b"\r\n");
+        }
+    }
+}
+
+/// Commands that get sent to the server in one go, without waiting for replies in between.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    cmds: Vec<Cmd>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cmd(mut self, cmd: Cmd) -> Self {
+        self.cmds.push(cmd);
+        self
+    }
+
+    pub fn add(&mut self, cmd: Cmd) -> &mut Self {
+        self.cmds.push(cmd);
+        self
+    }
+
+    pub fn cmds(&self) -> &[Cmd] {
+        &self.cmds
+    }
+
+    pub fn len(&self) -> usize {
+        self.cmds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cmds.is_empty()
+    }
+
+    /// All the commands as they are sent over the wire
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoded = vec![];
+        for cmd in &self.cmds {
+            cmd.write_to(&mut encoded);
+        }
+        encoded
+    }
+}
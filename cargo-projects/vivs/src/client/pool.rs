@@ -0,0 +1,108 @@
+use crate::client::{Client, ClientBuilder, ClientError};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+struct PoolInner {
+    config: ClientBuilder,
+    // Connected clients that are not in use at the moment
+    idle: Mutex<Vec<Client>>,
+    // Limits the number of clients that can be in use at the same time
+    permits: Arc<Semaphore>,
+}
+
+/// Pool of clients connected to the same node.
+///
+/// Clones hand out the same clients, so one pool can be shared by any number of tasks.
+#[derive(Clone)]
+pub struct Pool {
+    inner: Arc<PoolInner>,
+}
+
+impl Pool {
+    pub fn new(config: ClientBuilder, size: usize) -> Self {
+        Self {
+            inner: Arc::new(PoolInner {
+                config,
+                idle: Mutex::new(vec![]),
+                permits: Arc::new(Semaphore::new(size)),
+            }),
+        }
+    }
+
+    /// Number of connected clients that are not in use
+    pub fn idle(&self) -> usize {
+        self.inner
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    /// Takes a client from the pool, connecting a new one if there are no idle clients.
+    /// The client goes back to the pool once it is dropped.
+    pub async fn get(&self) -> Result<PooledClient, ClientError> {
+        let permit = tokio::time::timeout(
+            self.inner.config.pool_timeout,
+            self.inner.permits.clone().acquire_owned(),
+        )
+        .await
+        .map_err(|_| ClientError::PoolTimeout)?
+        .map_err(|_| ClientError::PoolTimeout)?;
+
+        let idle = self
+            .inner
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop();
+
+        let client = match idle {
+            Some(client) => client,
+            None => self.inner.config.clone().connect().await?,
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            pool: self.inner.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+/// Client borrowed from a `Pool`, it can be used the same way as `Client`.
+pub struct PooledClient {
+    client: Option<Client>,
+    pool: Arc<PoolInner>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Self::Target {
+        // Only taken out when dropped
+        self.client.as_ref().expect("Pooled client is always set")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client.as_mut().expect("Pooled client is always set")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        // Clients that lost their connection are not reused
+        if let Some(client) = self.client.take().filter(|client| client.is_connected()) {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(client);
+        }
+    }
+}
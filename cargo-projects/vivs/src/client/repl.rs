@@ -1,20 +1,34 @@
 use clap::{Args, Parser as ClapParser, Subcommand};
 use env_logger::Env;
 use log::info;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Editor, Helper};
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::env::current_dir;
-use std::io::{stdin, stdout, Cursor, Write};
+use std::io::{stdout, Write};
+use std::path::PathBuf;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use tokio::{io::AsyncWriteExt, net::TcpStream};
-use vivs::commands::ask::ASK_CMD;
-use vivs::commands::asking::Asking;
-use vivs::commands::get::GET_CMD;
+use vivs::client::cli::{command_hint, format_reply, split_args, ArgsError, OutputMode, COMMANDS};
+use vivs::client::cluster::{redirect, Redirect};
+use vivs::client::{Client, Cmd, Pipeline};
+use vivs::cluster::admin;
+use vivs::commands::info::INFO_CMD;
 use vivs::commands::ping::PONG;
 use vivs::parser::Parser;
 use vivs::ClusterConfig;
 use vivs::{data_chunk::DataChunk, Connection, GenericResult};
 
+// History is kept in the home directory, same as redis-cli does (~/.rediscli_history)
+const HISTORY_FILE: &str = ".vivs_history";
+
 pub async fn write_complete_frame(stream: &mut TcpStream, data: &str) -> std::io::Result<()> {
     stream.write_all(data.as_bytes()).await?;
     stream.flush().await
@@ -30,7 +44,55 @@ struct ClusterCommands {
 
 #[derive(Debug, Subcommand, Clone)]
 enum Commands {
-    Create { ip_addresses: Vec<String> },
+    Create {
+        ip_addresses: Vec<String>,
+    },
+    /// Adds an empty node to the cluster that <existing> is part of
+    AddNode {
+        new_node: String,
+        existing: String,
+    },
+    /// Removes a node that does not serve any slots from the cluster
+    DelNode {
+        existing: String,
+        node: String,
+    },
+    /// Moves slots (and their keys) from one node to another
+    Reshard {
+        existing: String,
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        slots: usize,
+    },
+    /// Spreads the slots across the nodes in proportion to their weights (1 by default)
+    Rebalance {
+        existing: String,
+        #[arg(long = "weight", value_name = "ADDRESS=WEIGHT", value_parser = parse_weight)]
+        weights: Vec<(String, f64)>,
+    },
+    /// Reports uncovered or duplicated slots, open migrations and nodes that disagree
+    Check {
+        existing: String,
+    },
+    /// Finishes open migrations and makes sure that each slot is served by exactly one node
+    Fix {
+        existing: String,
+    },
+}
+
+fn parse_weight(weight: &str) -> Result<(String, f64), String> {
+    let Some((address, weight)) = weight.rsplit_once('=') else {
+        return Err(format!("{weight} is not in the ADDRESS=WEIGHT format"));
+    };
+
+    let weight = weight
+        .parse::<f64>()
+        .map_err(|_| format!("{weight} is not a valid weight"))?;
+
+    Ok((address.to_owned(), weight))
 }
 
 #[derive(Debug, ClapParser)]
@@ -41,6 +103,18 @@ struct Cli {
     port: Option<u16>,
     #[arg(long, short)]
     cluster: bool,
+    /// Prints replies as they are, one value per line
+    #[arg(long, conflicts_with = "json")]
+    raw: bool,
+    /// Prints each reply as a JSON value
+    #[arg(long)]
+    json: bool,
+    /// Runs the commands from the file (one per line) and exits
+    #[arg(long, value_name = "FILE")]
+    eval: Option<PathBuf>,
+    /// Runs each command N times
+    #[arg(short = 'n', default_value_t = 1, value_name = "N")]
+    repeat: u32,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -59,9 +133,59 @@ async fn rand_number_as_string() -> GenericResult<String> {
         .join(""))
 }
 
+async fn admin_cluster(command: Commands) -> GenericResult<()> {
+    match command {
+        Commands::AddNode { new_node, existing } => {
+            admin::add_node(&existing, &new_node).await?;
+            println!("[OK] {new_node} added to the cluster");
+        }
+        Commands::DelNode { existing, node } => {
+            admin::del_node(&existing, &node).await?;
+            println!("[OK] {node} removed from the cluster");
+        }
+        Commands::Reshard {
+            existing,
+            from,
+            to,
+            slots,
+        } => {
+            let keys = admin::reshard(&existing, &from, &to, slots).await?;
+            println!("[OK] Moved {slots} slots ({keys} keys) from {from} to {to}");
+        }
+        Commands::Rebalance { existing, weights } => {
+            let plan = admin::rebalance(&existing, &weights.into_iter().collect()).await?;
+            if plan.is_empty() {
+                println!("[OK] No rebalancing needed");
+            }
+            for (from, to, slots) in plan {
+                println!("[OK] Moved {slots} slots from {from} to {to}");
+            }
+        }
+        Commands::Check { existing } => {
+            let report = admin::check(&existing).await?;
+            println!("{report}");
+            if !report.is_ok() {
+                Err("Cluster check failed")?;
+            }
+        }
+        Commands::Fix { existing } => {
+            let report = admin::fix(&existing).await?;
+            println!("{report}");
+        }
+        Commands::Create { .. } => unreachable!("cluster create is handled by set_up_cluster"),
+    }
+
+    Ok(())
+}
+
 async fn set_up_cluster(cli_args: Cli) -> GenericResult<()> {
     info!("Enabling cluster mode");
 
+    match cli_args.command {
+        Some(Commands::Create { .. }) | None => {}
+        Some(command) => return admin_cluster(command).await,
+    }
+
     let mut active_instances = HashSet::new();
     let mut instances: HashMap<String, ClusterConfig> = HashMap::new();
     let mut current_config = HashMap::new();
@@ -139,7 +263,8 @@ async fn set_up_cluster(cli_args: Cli) -> GenericResult<()> {
                         id: node_id.clone(),
                         ip: ip_address.clone(),
                         is_self: false,
-                        position: (start, end),
+                        slots: vec![(start, end)],
+                        ..Default::default()
                     };
                     if i == cur {
                         config.is_self = true;
@@ -180,141 +305,282 @@ async fn set_up_cluster(cli_args: Cli) -> GenericResult<()> {
     Ok(())
 }
 
-async fn parse_stream(connection: &mut Connection) -> GenericResult<Parser> {
-    let mut buffer = connection.process_stream().await?;
-    let data_chunk = DataChunk::read_chunk(&mut buffer)?;
-    let parser = Parser::new(data_chunk)?;
-    Ok(parser)
+/// Completion of command names and their arguments, inline hints (i.e. SET key value [XS seconds])
+/// and multi-line input when a quote is left open.
+struct ReplHelper;
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let start = line[..pos]
+            .rfind(char::is_whitespace)
+            .map(|i| i + 1)
+            .unwrap_or(0);
+        let word = &line[start..pos];
+
+        // Completions keep the case of what has been typed in so far
+        let with_case = |value: &str| {
+            if word.chars().any(|c| c.is_ascii_lowercase()) {
+                value.to_lowercase()
+            } else {
+                value.to_owned()
+            }
+        };
+
+        let candidates = if start == 0 {
+            COMMANDS
+                .iter()
+                .map(|command| command.name)
+                .collect::<Vec<_>>()
+        } else {
+            let command = line.split_whitespace().next().unwrap_or_default();
+            command_hint(command)
+                .map(|command| command.completions.to_vec())
+                .unwrap_or_default()
+        };
+
+        let pairs = candidates
+            .into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word.to_lowercase()))
+            .map(|candidate| Pair {
+                display: candidate.to_owned(),
+                replacement: with_case(candidate),
+            })
+            .collect();
+
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+
+        let args = split_args(line).ok()?;
+        let command = command_hint(args.first()?)?;
+
+        // Only the arguments that have not been typed in yet are hinted
+        let typed = args.len() - 1;
+        let hint = match line.ends_with(char::is_whitespace) {
+            true => command
+                .args
+                .split_whitespace()
+                .skip(typed)
+                .collect::<Vec<_>>()
+                .join(" "),
+            false if typed == 0 => format!(" {}", command.args),
+            false => return None,
+        };
+
+        (!hint.trim().is_empty()).then_some(hint)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        // Grey
+        Cow::Owned(format!("\x1b[90m{hint}\x1b[0m"))
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        match split_args(ctx.input()) {
+            Err(ArgsError::UnterminatedQuote) => Ok(ValidationResult::Incomplete),
+            Ok(_) => Ok(ValidationResult::Valid(None)),
+        }
+    }
 }
 
-fn write_to_stdout(bytes: &[u8]) -> GenericResult<()> {
-    stdout().write_all(bytes)?;
-    stdout().write_all(b"\r\n")?;
-    stdout().flush()?;
+impl Helper for ReplHelper {}
+
+fn write_to_stdout(output: &str) -> GenericResult<()> {
+    let mut stdout = stdout();
+    stdout.write_all(output.as_bytes())?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()?;
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> GenericResult<()> {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+struct Repl {
+    client: Client,
+    output: OutputMode,
+    repeat: u32,
+}
 
-    let cli_args = Cli::parse();
+impl Repl {
+    async fn connect(address: &str) -> GenericResult<Client> {
+        Ok(Client::builder().address(address).connect().await?)
+    }
 
-    // TODO: check args order
-    if cli_args.cluster {
-        return set_up_cluster(cli_args).await;
+    /// Sends the command, following cluster redirects.
+    ///
+    /// ASK only sends this command (preceded by ASKING) to the other node,
+    /// MOVED switches the REPL to the other node for all the following commands.
+    async fn query(&mut self, cmd: &Cmd) -> GenericResult<DataChunk> {
+        let reply = self.client.query::<DataChunk>(cmd).await?;
+
+        match redirect(&reply) {
+            Some(Redirect::Ask(_, address)) => {
+                let mut node = Self::connect(&address).await?;
+                let pipeline = Pipeline::new().cmd(Cmd::new("ASKING")).cmd(cmd.clone());
+
+                Ok(node
+                    .query_pipeline(&pipeline)
+                    .await?
+                    .pop()
+                    .unwrap_or_default())
+            }
+            Some(Redirect::Moved(_, address)) => {
+                self.client = Self::connect(&address).await?;
+                Ok(self.client.query::<DataChunk>(cmd).await?)
+            }
+            None => Ok(reply),
+        }
     }
 
-    let node_port = cli_args.port.unwrap_or(9000);
-    let node_host = cli_args.host.unwrap_or("127.0.0.1".to_owned());
-
-    let mut address = format!("{node_host}:{node_port}");
-    let stream = TcpStream::connect(address.clone()).await?;
-    let mut connection = Connection::new(stream);
-    let mut other_addr = "".to_string();
-
-    // A command that needs to be processed
-    let mut command_to_process: Option<Cursor<&[u8]>> = None;
-    // This is in case we need to store the initial/previous command
-    // in situations like GET -> ASK
-    let mut initial_command: Vec<String> = vec![];
-
-    loop {
-        // Peek at the command to see if there's anything to process
-        if let Some(ref mut cursor) = command_to_process {
-            let command_as_data_chunk = DataChunk::read_chunk(cursor).unwrap();
-            // This parser contains the command itself i.e. GET A or ASK <slot> <address>
-            let mut parser = Parser::new(command_as_data_chunk)?;
-
-            // The response from the server is Null (TODO: update implementation)
-            let Some(command) = parser.peek_as_str() else {
-                let bytes_read: bytes::Bytes = DataChunk::read_chunk_frame(&mut parser).await?;
-                command_to_process = None;
-
-                write_to_stdout(&bytes_read)?;
-                continue;
-            };
+    async fn run(&mut self, args: &[String]) -> GenericResult<()> {
+        let Some(name) = args.first() else {
+            return Ok(());
+        };
+
+        let cmd = args
+            .iter()
+            .skip(1)
+            .fold(Cmd::new(name), |cmd, arg| cmd.arg(arg));
+
+        // Same as redis-cli, INFO is easier to read as it is
+        let output = match self.output {
+            OutputMode::Pretty if name.eq_ignore_ascii_case(INFO_CMD) => OutputMode::Raw,
+            output => output,
+        };
+
+        for _ in 0..self.repeat {
+            let reply = self.query(&cmd).await?;
+            write_to_stdout(&format_reply(&reply, output))?;
+        }
+
+        Ok(())
+    }
 
-            // ASK command needs to be treated differently,
-            // when a client receives ASK command it needs to send
-            // ASKING to interact with a node that has the data
-            if command.to_lowercase() == ASK_CMD.to_lowercase() {
-                let _ = parser.next_as_str().unwrap().unwrap(); // current command i.e. ASK
-                let slot = parser.next_as_str().unwrap().unwrap(); // a slot where the value is stored at
-                let address = parser.next_as_str().unwrap().unwrap(); // new address to query for the value
-
-                other_addr = address.clone();
-
-                // ASKING GET <key> <address> - should be set to the target node instead
-                let command_to_forward = initial_command.first().cloned().unwrap();
-                // This is tied to GET command
-                let key_to_forward = initial_command.get(1).unwrap().to_owned();
-                // Ideally, we shouldn't need command + value, it should be one
-                let command = Asking::default()
-                    .command(command_to_forward)
-                    .key(key_to_forward)
-                    .address(address.clone())
-                    .build()
-                    .format();
-
-                let command_parsed = DataChunk::from_string(&command);
-                let mut command_as_bytes = Cursor::new(command_parsed.as_bytes());
-                let command_as_data_chunk = DataChunk::read_chunk(&mut command_as_bytes).unwrap();
-                let mut command_as_data_chunk = Parser::new(command_as_data_chunk)?;
-
-                connection
-                    .write_chunk_frame(&mut command_as_data_chunk)
-                    .await?;
-
-                let buffer = connection.process_stream().await?;
-                command_to_process = Some(buffer);
+    /// Runs the commands from the file, quoted values can span multiple lines
+    async fn eval(&mut self, path: &PathBuf) -> GenericResult<()> {
+        let contents = tokio::fs::read_to_string(path).await?;
+        let mut pending = String::new();
+
+        for line in contents.lines() {
+            if pending.is_empty() && (line.trim().is_empty() || line.trim().starts_with('#')) {
                 continue;
             }
 
-            if command.to_lowercase() == GET_CMD.to_lowercase() {
-                address = other_addr.clone();
-                let stream = TcpStream::connect(address.clone()).await?;
-                connection = Connection::new(stream);
+            pending.push_str(line);
+
+            match split_args(&pending) {
+                Ok(args) => {
+                    self.run(&args).await?;
+                    pending.clear();
+                }
+                Err(ArgsError::UnterminatedQuote) => pending.push('\n'),
+            }
+        }
+
+        if !pending.is_empty() {
+            Err(ArgsError::UnterminatedQuote)?;
+        }
+
+        Ok(())
+    }
 
-                connection.write_chunk_frame(&mut parser).await?;
+    async fn interactive(&mut self) -> GenericResult<()> {
+        let mut editor = Editor::<ReplHelper, FileHistory>::new()?;
+        editor.set_helper(Some(ReplHelper));
 
-                let buffer = connection.process_stream().await?;
-                command_to_process = Some(buffer);
+        let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(history) = &history {
+            // There's no history on the first run
+            let _ = editor.load_history(history);
+        }
+
+        loop {
+            let line = match editor.readline(&format!("{}> ", self.client.address())) {
+                Ok(line) => line,
+                // Ctrl-C clears the line, same as in the shell
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => Err(e)?,
+            };
+
+            if line.trim().is_empty() {
                 continue;
             }
 
-            let bytes_read = DataChunk::read_chunk_frame(&mut parser).await?;
-            command_to_process = None;
+            editor.add_history_entry(line.as_str())?;
+            if let Some(history) = &history {
+                let _ = editor.save_history(history);
+            }
 
-            write_to_stdout(&bytes_read)?;
-        } else {
-            // write to stdout
-            write!(stdout(), "{address}> ")?;
+            let args = match split_args(&line) {
+                Ok(args) => args,
+                Err(e) => {
+                    write_to_stdout(&e.to_string())?;
+                    continue;
+                }
+            };
 
-            // flush everything, ensuring all content reach destination (stdout)
-            stdout().flush()?;
+            if args.first().is_some_and(|name| {
+                name.eq_ignore_ascii_case("quit") || name.eq_ignore_ascii_case("exit")
+            }) {
+                break;
+            }
 
-            let mut buffer = String::new();
-            // Read a line of input and append to the buffer.
-            // stdin() is a handle in this case to the standard input of the current process
-            // which gets "locked" and waits for newline or the "Enter" key (or 0xA byte) to be pressed.
-            stdin().read_line(&mut buffer)?;
+            // Connection errors are printed, the client reconnects on the next command
+            if let Err(e) = self.run(&args).await {
+                write_to_stdout(&format!("(error) {e}"))?;
+            }
+        }
 
-            initial_command = buffer
-                .split_whitespace()
-                .map(|val| val.to_string())
-                .collect();
+        Ok(())
+    }
+}
 
-            let data_chunk_frame_as_str = DataChunk::from_string(&buffer);
+#[tokio::main]
+async fn main() -> GenericResult<()> {
+    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
-            // write bytes to server socket
-            // e.g. *0\r\n$4\r\nPING\r\n$4\r\nMary\r\n
-            connection
-                .write_complete_frame(&data_chunk_frame_as_str)
-                .await?;
+    let cli_args = Cli::parse();
 
-            let buffer = connection.process_stream().await?;
-            command_to_process = Some(buffer);
-        }
+    // TODO: check args order
+    if cli_args.cluster {
+        return set_up_cluster(cli_args).await;
+    }
+
+    let node_port = cli_args.port.unwrap_or(9000);
+    let node_host = cli_args.host.clone().unwrap_or("127.0.0.1".to_owned());
+
+    let output = match (cli_args.raw, cli_args.json) {
+        (_, true) => OutputMode::Json,
+        (true, _) => OutputMode::Raw,
+        _ => OutputMode::Pretty,
+    };
+
+    let mut repl = Repl {
+        client: Repl::connect(&format!("{node_host}:{node_port}")).await?,
+        output,
+        repeat: cli_args.repeat,
+    };
+
+    match &cli_args.eval {
+        Some(path) => repl.eval(path).await,
+        None => repl.interactive().await,
     }
 }
@@ -0,0 +1,149 @@
+use crate::{client::ClientError, data_chunk::DataChunk};
+use bytes::Bytes;
+
+/// Converts a reply sent by the server into a Rust type.
+///
+/// Error replies (i.e. -ERR unknown command) are turned into `ClientError::Server`
+/// by every implementation apart from the one for `DataChunk` which returns the reply as is.
+pub trait FromReply: Sized {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError>;
+}
+
+/// Error reply is split into words, this puts the message back together i.e. ERR unknown command
+pub(crate) fn error_message(words: &[DataChunk]) -> String {
+    words
+        .iter()
+        .filter_map(|word| match word {
+            DataChunk::Bulk(word) => Some(String::from_utf8_lossy(word).into_owned()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Turns error replies into errors, the rest of the replies are returned as they are.
+fn check(reply: DataChunk) -> Result<DataChunk, ClientError> {
+    match reply {
+        DataChunk::SimpleError(words) => Err(ClientError::Server(error_message(&words))),
+        reply => Ok(reply),
+    }
+}
+
+fn unexpected<T>(reply: &DataChunk, expected: &str) -> Result<T, ClientError> {
+    Err(ClientError::Type(format!(
+        "expected {expected}, got {reply:?}"
+    )))
+}
+
+/// Vivs sends integers as 8 raw (native endian) bytes, other servers send them as text.
+pub(crate) fn integer(value: &[u8]) -> Result<i64, ClientError> {
+    if let Ok(raw) = <[u8; 8]>::try_from(value) {
+        return Ok(if cfg!(target_endian = "big") {
+            u64::from_be_bytes(raw)
+        } else {
+            u64::from_le_bytes(raw)
+        } as i64);
+    }
+
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| ClientError::Type(format!("{value:?} is not an integer")))
+}
+
+impl FromReply for DataChunk {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        Ok(reply)
+    }
+}
+
+impl FromReply for () {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        check(reply).map(|_| ())
+    }
+}
+
+impl FromReply for Bytes {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        match check(reply)? {
+            DataChunk::Bulk(value) => Ok(value),
+            reply => unexpected(&reply, "a string"),
+        }
+    }
+}
+
+impl FromReply for String {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        match check(reply)? {
+            DataChunk::Bulk(value) => String::from_utf8(value.to_vec())
+                .map_err(|e| ClientError::Type(format!("string is not valid UTF-8: {e}"))),
+            DataChunk::Integer(value) => integer(&value).map(|value| value.to_string()),
+            reply => unexpected(&reply, "a string"),
+        }
+    }
+}
+
+impl FromReply for i64 {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        match check(reply)? {
+            DataChunk::Integer(value) | DataChunk::Bulk(value) => integer(&value),
+            reply => unexpected(&reply, "an integer"),
+        }
+    }
+}
+
+impl<T: FromReply> FromReply for Option<T> {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        match check(reply)? {
+            DataChunk::Null => Ok(None),
+            reply => T::from_reply(reply).map(Some),
+        }
+    }
+}
+
+impl<T: FromReply> FromReply for Vec<T> {
+    fn from_reply(reply: DataChunk) -> Result<Self, ClientError> {
+        match check(reply)? {
+            DataChunk::Array(replies) => replies.into_iter().map(T::from_reply).collect(),
+            DataChunk::Null => Ok(vec![]),
+            reply => unexpected(&reply, "an array"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod reply_tests {
+    use super::*;
+
+    #[test]
+    fn converts_integers() {
+        let raw = Bytes::copy_from_slice(&crate::utils::u64_as_bytes(2573));
+
+        assert_eq!(i64::from_reply(DataChunk::Integer(raw)).unwrap(), 2573);
+        assert_eq!(
+            i64::from_reply(DataChunk::Integer(Bytes::from("-2"))).unwrap(),
+            -2
+        );
+    }
+
+    #[test]
+    fn converts_nested_types() {
+        let reply = DataChunk::Array(vec![DataChunk::Bulk(Bytes::from("a")), DataChunk::Null]);
+
+        let values = Vec::<Option<String>>::from_reply(reply).unwrap();
+        assert_eq!(values, vec![Some("a".to_owned()), None]);
+    }
+
+    #[test]
+    fn error_replies_become_errors() {
+        let reply = DataChunk::SimpleError(vec![
+            DataChunk::Bulk(Bytes::from("ERR")),
+            DataChunk::Bulk(Bytes::from("boom")),
+        ]);
+
+        let Err(ClientError::Server(message)) = String::from_reply(reply) else {
+            panic!("Expected a server error");
+        };
+        assert_eq!(message, "ERR boom");
+    }
+}
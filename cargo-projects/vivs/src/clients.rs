@@ -0,0 +1,242 @@
+use crate::Connection;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::{Notify, RwLock},
+    time::Instant,
+};
+
+// Connections are not authenticated yet, so every client is the default user
+pub const DEFAULT_USER: &str = "default";
+
+/// A snapshot of a single connection that is kept in the registry.
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub id: u64,
+    pub name: Option<String>,
+    pub addr: String,
+    pub laddr: String,
+    pub user: String,
+    pub created_at: Instant,
+    pub last_interaction: Instant,
+    pub last_command: String,
+    pub query_buffer: usize,
+    pub query_buffer_free: usize,
+    pub output_buffer: usize,
+    kill: Arc<Notify>,
+}
+
+impl ClientInfo {
+    /// Formats the client the same way CLIENT LIST and CLIENT INFO do,
+    /// i.e. a single line of space separated field=value pairs.
+    pub fn format(&self) -> String {
+        let now = Instant::now();
+
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} qbuf={} qbuf-free={} obl={} user={} cmd={}",
+            self.id,
+            self.addr,
+            self.laddr,
+            self.name.as_deref().unwrap_or_default(),
+            now.duration_since(self.created_at).as_secs(),
+            now.duration_since(self.last_interaction).as_secs(),
+            self.query_buffer,
+            self.query_buffer_free,
+            self.output_buffer,
+            self.user,
+            self.last_command
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PauseMode {
+    /// Every command apart from CLIENT is held back
+    All,
+    /// Only commands that modify the data store are held back
+    Write,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pause {
+    until: Instant,
+    mode: PauseMode,
+}
+
+/// Filters that CLIENT KILL can match connections by.
+/// A connection has to match all the filters in order to be killed.
+#[derive(Debug, Default, Clone)]
+pub struct KillFilter {
+    pub id: Option<u64>,
+    pub addr: Option<String>,
+    pub laddr: Option<String>,
+    pub user: Option<String>,
+    pub skip_me: bool,
+}
+
+/// Registry of all the connections that are currently open.
+///
+/// Each connection keeps a clone to register itself, update its info and honor CLIENT PAUSE.
+#[derive(Clone, Default)]
+pub struct Clients {
+    registry: Arc<RwLock<HashMap<u64, ClientInfo>>>,
+    next_id: Arc<AtomicU64>,
+    pause: Arc<RwLock<Option<Pause>>>,
+    unpaused: Arc<Notify>,
+}
+
+impl Clients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a newly accepted connection to the registry.
+    ///
+    /// Returns an id of the client and a `Notify` that gets notified
+    /// once the client is killed via CLIENT KILL.
+    pub async fn register(&self, connection: &Connection) -> (u64, Arc<Notify>) {
+        // ids start from 1 and are never reused
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let kill = Arc::new(Notify::new());
+        let now = Instant::now();
+
+        let laddr = connection
+            .own_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+
+        let client = ClientInfo {
+            id,
+            name: None,
+            addr: connection.connected_peer_addr(),
+            laddr,
+            user: DEFAULT_USER.to_owned(),
+            created_at: now,
+            last_interaction: now,
+            last_command: "NULL".to_owned(),
+            query_buffer: 0,
+            query_buffer_free: 0,
+            output_buffer: 0,
+            kill: kill.clone(),
+        };
+
+        self.registry.write().await.insert(id, client);
+
+        (id, kill)
+    }
+
+    pub async fn unregister(&self, id: u64) {
+        self.registry.write().await.remove(&id);
+    }
+
+    /// Records the latest command a client has sent as well as its buffer sizes.
+    pub async fn touch(&self, id: u64, command: &str, connection: &Connection) {
+        if let Some(client) = self.registry.write().await.get_mut(&id) {
+            client.last_interaction = Instant::now();
+            client.last_command = command.to_owned();
+            client.query_buffer = connection.read_buffer_len();
+            client.query_buffer_free = connection.read_buffer_free();
+            client.output_buffer = connection.write_buffer_len();
+        }
+    }
+
+    pub async fn get(&self, id: u64) -> Option<ClientInfo> {
+        self.registry.read().await.get(&id).cloned()
+    }
+
+    /// Returns clients sorted by id, optionally only the ones with the given ids.
+    pub async fn list(&self, ids: &[u64]) -> Vec<ClientInfo> {
+        let registry = self.registry.read().await;
+
+        let mut clients: Vec<ClientInfo> = registry
+            .values()
+            .filter(|client| ids.is_empty() || ids.contains(&client.id))
+            .cloned()
+            .collect();
+        clients.sort_by_key(|client| client.id);
+
+        clients
+    }
+
+    pub async fn count(&self) -> usize {
+        self.registry.read().await.len()
+    }
+
+    pub async fn set_name(&self, id: u64, name: Option<String>) {
+        if let Some(client) = self.registry.write().await.get_mut(&id) {
+            client.name = name;
+        }
+    }
+
+    /// Notifies all the clients that match the filter to disconnect.
+    ///
+    /// `own_id` is the id of the client that issued the command,
+    /// it gets skipped when `skip_me` is set. Returns the number of killed clients.
+    pub async fn kill(&self, filter: &KillFilter, own_id: u64) -> usize {
+        let registry = self.registry.read().await;
+
+        let matching = registry.values().filter(|client| {
+            filter.id.is_none_or(|id| client.id == id)
+                && filter.addr.as_ref().is_none_or(|addr| &client.addr == addr)
+                && filter
+                    .laddr
+                    .as_ref()
+                    .is_none_or(|laddr| &client.laddr == laddr)
+                && filter.user.as_ref().is_none_or(|user| &client.user == user)
+                && !(filter.skip_me && client.id == own_id)
+        });
+
+        let mut killed = 0;
+        for client in matching {
+            // notify_one() stores a permit so the client picks it up
+            // even when it is currently busy processing a command
+            client.kill.notify_one();
+            killed += 1;
+        }
+
+        killed
+    }
+
+    pub async fn pause(&self, timeout: Duration, mode: PauseMode) {
+        // Longer pauses last until CLIENT UNPAUSE, which keeps huge timeouts from overflowing
+        const MAX_PAUSE: Duration = Duration::from_secs(365 * 24 * 60 * 60);
+
+        let until = Instant::now() + timeout.min(MAX_PAUSE);
+        *self.pause.write().await = Some(Pause { until, mode });
+    }
+
+    pub async fn unpause(&self) {
+        *self.pause.write().await = None;
+        self.unpaused.notify_waiters();
+    }
+
+    /// Waits until clients are unpaused (either by CLIENT UNPAUSE or the pause timing out).
+    /// Read-only commands only wait when all commands are paused.
+    pub async fn wait_if_paused(&self, is_write: bool) {
+        loop {
+            // Has to be created before checking the pause
+            // so that unpause() cannot sneak in between the check and the wait
+            let unpaused = self.unpaused.notified();
+
+            let until = match *self.pause.read().await {
+                Some(Pause { until, mode })
+                    if until > Instant::now() && (mode == PauseMode::All || is_write) =>
+                {
+                    until
+                }
+                _ => return,
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(until) => {},
+                _ = unpaused => {},
+            }
+        }
+    }
+}
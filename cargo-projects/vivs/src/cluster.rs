@@ -1 +1,91 @@
+pub mod admin;
+pub mod topology;
+pub use topology::Topology;
+
+use crate::{data_chunk::DataChunk, Connection, GenericResult};
+use log::{error, info};
+use std::time::Duration;
+use tokio::net::TcpStream;
+
 pub const CLUSTER_ASK_ERR: &str = "ASK";
+
+// Message a node sends to its peers (over the cluster bus) when it shuts down
+pub const CLUSTER_NODE_LEAVING: &str = "LEAVING";
+
+// Message that makes a node reload its cluster config (i.e. once a peer changed it)
+pub const CLUSTER_TOPOLOGY_CHANGED: &str = "TOPOLOGY";
+
+// Unless configured, cluster bus port is the client port + 5000
+pub const CLUSTER_BUS_PORT_OFFSET: u16 = 5000;
+
+// Total number of hash slots in the cluster
+pub const CELLS_TOTAL: usize = 16384;
+
+/// Works out the cell / hash slot of the key.
+pub fn key_slot(key: &[u8]) -> u16 {
+    const X25: crc::Crc<u16> = crc::Crc::<u16>::new(&crc::CRC_16_IBM_SDLC);
+    X25.checksum(key) % CELLS_TOTAL as u16
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClusterState {
+    /// Node is running in standalone mode
+    Disabled,
+    /// Every hash slot is served by a node
+    Ok,
+    /// Cluster config is missing or not every hash slot is served
+    Fail(String),
+}
+
+impl ClusterState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ClusterState::Disabled => "disabled",
+            ClusterState::Ok => "ok",
+            ClusterState::Fail(_) => "fail",
+        }
+    }
+}
+
+async fn send_leaving(peer_bus_addr: &str, own_addr: &str) -> GenericResult<()> {
+    let stream = TcpStream::connect(peer_bus_addr).await?;
+    let mut connection = Connection::new(stream);
+
+    let message = DataChunk::from_string(&format!("{CLUSTER_NODE_LEAVING} {own_addr}"));
+    connection.write_complete_frame(&message).await?;
+
+    Ok(())
+}
+
+/// Lets every other node in the cluster know that this node is shutting down.
+/// Each peer is given `timeout` to accept the message, unreachable peers are skipped.
+pub async fn notify_peers_leaving(topology: &Topology, timeout: Duration) {
+    let Some(nodes) = topology.nodes() else {
+        info!("No cluster config found, no peers to notify");
+        return;
+    };
+
+    let Some(own_addr) = nodes
+        .values()
+        .find(|config| config.is_self)
+        .map(|config| config.ip.clone())
+    else {
+        return;
+    };
+
+    for config in nodes.values().filter(|config| !config.is_self) {
+        let Some((host, peer_port)) = config.ip.rsplit_once(':') else {
+            continue;
+        };
+        let Ok(peer_port) = peer_port.parse::<u16>() else {
+            continue;
+        };
+        let peer_bus_addr = format!("{host}:{}", peer_port + CLUSTER_BUS_PORT_OFFSET);
+
+        match tokio::time::timeout(timeout, send_leaving(&peer_bus_addr, &own_addr)).await {
+            Ok(Ok(())) => info!("Notified {} that the node is leaving", config.ip),
+            Ok(Err(e)) => error!("Failed to notify {}: {e}", config.ip),
+            Err(_) => error!("Timed out notifying {}", config.ip),
+        }
+    }
+}
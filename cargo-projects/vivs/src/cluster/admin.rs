@@ -0,0 +1,752 @@
+//! Cluster administration (add-node, del-node, reshard, rebalance, check and fix)
+//! used by `vivs-repl --cluster <subcommand>`.
+//!
+//! Same as `create`, the topology lives in the `<port>.toml` configs of the nodes.
+//! Every change is written to the config of every node, nodes are then told to reload it (CLUSTER RELOAD).
+use crate::{
+    client::{Client, Cmd, FromReply, Pipeline},
+    cluster::{key_slot, CELLS_TOTAL},
+    data_chunk::DataChunk,
+    ClusterConfig, ClusterInstanceConfig, GenericResult, SlotMigration,
+};
+use bytes::Bytes;
+use log::{error, info};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env::current_dir,
+    fmt::Display,
+};
+use tokio::{fs, io::AsyncReadExt};
+
+// Number of keys requested per SCAN call when keys get migrated
+const SCAN_COUNT: usize = 1000;
+
+fn config_path(address: &str) -> GenericResult<String> {
+    let Some((_, port)) = address.rsplit_once(':') else {
+        Err(format!("{address} does not contain a port"))?
+    };
+
+    Ok(format!("{}/{}.toml", current_dir()?.display(), port))
+}
+
+/// Reads the topology from the config of the node
+pub async fn load(address: &str) -> GenericResult<ClusterInstanceConfig> {
+    let contents = fs::read_to_string(config_path(address)?)
+        .await
+        .map_err(|e| format!("Could not read the cluster config of {address}: {e}"))?;
+
+    Ok(toml::from_str(&contents)?)
+}
+
+/// Writes the topology to the config of every node (each node marks itself with `is_self`)
+/// and makes the nodes reload it
+pub async fn save(topology: &ClusterInstanceConfig) -> GenericResult<()> {
+    for address in topology.keys() {
+        let mut nodes = topology.clone();
+        for (node_address, node) in nodes.iter_mut() {
+            node.is_self = node_address == address;
+        }
+
+        // Nodes also watch their configs, a half written config must never be seen
+        let path = config_path(address)?;
+        let tmp_path = format!("{path}.tmp");
+        fs::write(&tmp_path, toml::to_string(&nodes)?).await?;
+        fs::rename(&tmp_path, &path).await?;
+    }
+
+    for address in topology.keys() {
+        reload(address).await;
+    }
+
+    Ok(())
+}
+
+/// Makes the node reload its cluster config, nodes that are down pick it up once they are back
+async fn reload(address: &str) {
+    let reloaded = match Client::builder()
+        .address(address)
+        .reconnect(1, Default::default())
+        .connect()
+        .await
+    {
+        Ok(mut client) => {
+            client
+                .query::<i64>(&Cmd::new("CLUSTER").arg("RELOAD"))
+                .await
+        }
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = reloaded {
+        error!("Failed to reload the cluster config of {address}: {e}");
+    }
+}
+
+/// Random node id (20 hex characters)
+pub async fn node_id() -> GenericResult<String> {
+    let mut random = fs::File::open("/dev/urandom").await?;
+
+    let mut bytes = [0u8; 10];
+    random.read_exact(&mut bytes).await?;
+
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+async fn is_alive(address: &str) -> bool {
+    let Ok(mut client) = Client::builder()
+        .address(address)
+        .reconnect(1, Default::default())
+        .connect()
+        .await
+    else {
+        return false;
+    };
+
+    client.ping().await.is_ok()
+}
+
+/// Slots served by the node
+pub fn node_slots(config: &ClusterConfig) -> BTreeSet<usize> {
+    config
+        .slots
+        .iter()
+        .flat_map(|(start, end)| *start..=*end)
+        .collect()
+}
+
+/// Turns slots into as few ranges as possible i.e. 1, 2, 3, 7 becomes (1, 3), (7, 7)
+pub fn slot_ranges(slots: &BTreeSet<usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+
+    for slot in slots {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *slot => *end = *slot,
+            _ => ranges.push((*slot, *slot)),
+        }
+    }
+
+    ranges
+}
+
+/// i.e. 1-3, 7
+fn format_ranges(ranges: &[(usize, usize)]) -> String {
+    ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{start}-{end}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn set_node_slots(config: &mut ClusterConfig, slots: &BTreeSet<usize>) {
+    config.slots = slot_ranges(slots);
+}
+
+/// Nodes that serve each slot
+fn slot_owners(topology: &ClusterInstanceConfig) -> Vec<Vec<&str>> {
+    let mut owners = vec![vec![]; CELLS_TOTAL];
+
+    for (address, config) in topology {
+        for slot in node_slots(config) {
+            if let Some(owners) = owners.get_mut(slot) {
+                owners.push(address.as_str());
+            }
+        }
+    }
+
+    for owners in owners.iter_mut() {
+        owners.sort();
+    }
+
+    owners
+}
+
+/// Problems found by `check`
+#[derive(Debug, Default, PartialEq)]
+pub struct CheckReport {
+    pub uncovered: Vec<(usize, usize)>,
+    // Slot ranges served by more than one node
+    pub duplicated: Vec<((usize, usize), Vec<String>)>,
+    // i.e. 127.0.0.1:9000 -> 127.0.0.1:9001 slot 12
+    pub open_migrations: Vec<String>,
+    // Nodes that do not respond or whose config disagrees with the rest of the cluster
+    pub nodes: Vec<String>,
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        *self == CheckReport::default()
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.is_ok() {
+            return write!(f, "[OK] All {CELLS_TOTAL} slots covered");
+        }
+
+        for (start, end) in &self.uncovered {
+            writeln!(f, "[ERR] Slots {start}-{end} are not covered")?;
+        }
+        for ((start, end), nodes) in &self.duplicated {
+            writeln!(
+                f,
+                "[ERR] Slots {start}-{end} are served by more than one node: {}",
+                nodes.join(", ")
+            )?;
+        }
+        for migration in &self.open_migrations {
+            writeln!(f, "[WARN] Open slot migration: {migration}")?;
+        }
+        for node in &self.nodes {
+            writeln!(f, "[ERR] {node}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Looks for uncovered slots, slots that are served by more than one node and open migrations
+pub fn check_topology(topology: &ClusterInstanceConfig) -> CheckReport {
+    let owners = slot_owners(topology);
+    let mut report = CheckReport::default();
+
+    let uncovered = (0..CELLS_TOTAL)
+        .filter(|slot| owners[*slot].is_empty())
+        .collect::<BTreeSet<_>>();
+    report.uncovered = slot_ranges(&uncovered);
+
+    // Consecutive slots with the same (more than one) owners are reported as one range
+    for (slot, nodes) in owners
+        .iter()
+        .enumerate()
+        .filter(|(_, nodes)| nodes.len() > 1)
+    {
+        let nodes = nodes
+            .iter()
+            .map(|node| node.to_string())
+            .collect::<Vec<_>>();
+
+        match report.duplicated.last_mut() {
+            Some(((_, end), previous)) if *end + 1 == slot && *previous == nodes => *end = slot,
+            _ => report.duplicated.push(((slot, slot), nodes)),
+        }
+    }
+
+    let mut addresses = topology.keys().collect::<Vec<_>>();
+    addresses.sort();
+
+    for address in addresses {
+        let config = &topology[address];
+        for SlotMigration { slots, node } in &config.migrating {
+            report.open_migrations.push(format!(
+                "{address} -> {node} slots {}",
+                format_ranges(slots)
+            ));
+        }
+        for SlotMigration { slots, node } in &config.importing {
+            report.open_migrations.push(format!(
+                "{node} -> {address} slots {} (importing)",
+                format_ranges(slots)
+            ));
+        }
+    }
+
+    report
+}
+
+/// Checks the topology and makes sure that every node is alive and agrees on it
+pub async fn check(address: &str) -> GenericResult<CheckReport> {
+    let topology = load(address).await?;
+    let mut report = check_topology(&topology);
+
+    let mut addresses = topology.keys().collect::<Vec<_>>();
+    addresses.sort();
+
+    for node in addresses {
+        if !is_alive(node).await {
+            report.nodes.push(format!("{node} is not reachable"));
+            continue;
+        }
+
+        let agrees = match load(node).await {
+            Ok(mut config) => {
+                // Every node has its own is_self
+                config.values_mut().for_each(|node| node.is_self = false);
+                let mut expected = topology.clone();
+                expected.values_mut().for_each(|node| node.is_self = false);
+
+                config == expected
+            }
+            Err(_) => false,
+        };
+
+        if !agrees {
+            report
+                .nodes
+                .push(format!("{node} has a different view of the cluster"));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Adds an empty node (without slots) to the cluster that `existing` is part of
+pub async fn add_node(existing: &str, new_node: &str) -> GenericResult<()> {
+    let mut topology = load(existing).await?;
+
+    if topology.contains_key(new_node) {
+        Err(format!("{new_node} is already part of the cluster"))?;
+    }
+    if !is_alive(new_node).await {
+        Err(format!("Could not connect to {new_node}"))?;
+    }
+
+    topology.insert(
+        new_node.to_owned(),
+        ClusterConfig {
+            id: node_id().await?,
+            ip: new_node.to_owned(),
+            ..Default::default()
+        },
+    );
+
+    save(&topology).await?;
+    info!("{new_node} joined the cluster");
+
+    Ok(())
+}
+
+/// Removes a node that does not serve any slots (reshard the slots away first)
+pub async fn del_node(existing: &str, node: &str) -> GenericResult<()> {
+    let mut topology = load(existing).await?;
+
+    let Some(config) = topology.remove(node) else {
+        Err(format!("{node} is not part of the cluster"))?
+    };
+
+    if config.slots_count() > 0 {
+        Err(format!(
+            "{node} serves {} slots, reshard them to other nodes first",
+            config.slots_count()
+        ))?;
+    }
+
+    save(&topology).await?;
+    let _ = fs::remove_file(config_path(node)?).await;
+    // Without its config the node goes back to the standalone mode
+    reload(node).await;
+    info!("{node} left the cluster");
+
+    Ok(())
+}
+
+/// Keys the node holds, grouped by their slots
+async fn keys_by_slot(address: &str) -> GenericResult<HashMap<usize, Vec<Bytes>>> {
+    let mut client = Client::builder().address(address).connect().await?;
+    let mut keys: HashMap<usize, Vec<Bytes>> = HashMap::new();
+    let mut cursor = "0".to_owned();
+
+    loop {
+        let cmd = Cmd::new("SCAN")
+            .arg(&cursor)
+            .arg("COUNT")
+            .arg(SCAN_COUNT.to_string());
+        let mut reply = client.query::<Vec<DataChunk>>(&cmd).await?.into_iter();
+
+        cursor = String::from_reply(reply.next().unwrap_or_default())?;
+        for key in Vec::<Bytes>::from_reply(reply.next().unwrap_or_default())? {
+            keys.entry(key_slot(&key).into()).or_default().push(key);
+        }
+
+        if cursor == "0" {
+            return Ok(keys);
+        }
+    }
+}
+
+/// Copies the keys (values and TTLs) of the slots from one node to another and deletes the originals,
+/// returns the number of keys that got moved
+async fn move_keys(from: &str, to: &str, slots: &BTreeSet<usize>) -> GenericResult<usize> {
+    let mut source = Client::builder().address(from).connect().await?;
+    let mut target = Client::builder().address(to).connect().await?;
+    let mut moved = 0;
+
+    let keys = keys_by_slot(from).await?;
+
+    for key in slots.iter().filter_map(|slot| keys.get(slot)).flatten() {
+        // TTL goes first, a key that expires in between is gone by the time of GET
+        let ttl = source.ttl(key).await?;
+        let Some(value) = source.get::<Option<Bytes>>(key).await? else {
+            continue;
+        };
+
+        // TTL is 0 when the key does not expire
+        let mut set = Cmd::new("SET").arg(key).arg(value);
+        if ttl > 0 {
+            set = set.arg("XS").arg(ttl.to_string());
+        }
+
+        // The target only accepts keys of the slots it imports after ASKING
+        let pipeline = Pipeline::new().cmd(Cmd::new("ASKING")).cmd(set);
+        for reply in target.query_pipeline(&pipeline).await? {
+            String::from_reply(reply)?;
+        }
+
+        source.delete(key).await?;
+        moved += 1;
+    }
+
+    Ok(moved)
+}
+
+/// Moves the slots (and their keys) from one node to another.
+///
+/// Slots are marked as migrating / importing while the keys are moved,
+/// so a failed migration shows up in `check` and can be finished by `fix`.
+pub async fn migrate_slots(
+    topology: &mut ClusterInstanceConfig,
+    from: &str,
+    to: &str,
+    slots: &BTreeSet<usize>,
+) -> GenericResult<usize> {
+    if slots.is_empty() {
+        return Ok(0);
+    }
+    if !topology.contains_key(to) {
+        Err(format!("{to} is not part of the cluster"))?;
+    }
+    let Some(source) = topology.get_mut(from) else {
+        Err(format!("{from} is not part of the cluster"))?
+    };
+
+    source.migrating.push(SlotMigration {
+        slots: slot_ranges(slots),
+        node: to.to_owned(),
+    });
+    if let Some(target) = topology.get_mut(to) {
+        target.importing.push(SlotMigration {
+            slots: slot_ranges(slots),
+            node: from.to_owned(),
+        });
+    }
+    save(topology).await?;
+
+    let moved = move_keys(from, to, slots).await?;
+
+    if let Some(source) = topology.get_mut(from) {
+        let remaining = node_slots(source)
+            .difference(slots)
+            .copied()
+            .collect::<BTreeSet<_>>();
+        set_node_slots(source, &remaining);
+        source.migrating.retain(|migration| migration.node != to);
+    }
+    if let Some(target) = topology.get_mut(to) {
+        let mut target_slots = node_slots(target);
+        target_slots.extend(slots);
+        set_node_slots(target, &target_slots);
+        target.importing.retain(|migration| migration.node != from);
+    }
+    save(topology).await?;
+
+    info!(
+        "Moved {} slots ({moved} keys) from {from} to {to}",
+        slots.len()
+    );
+
+    Ok(moved)
+}
+
+/// Moves `count` slots (the last ones the node serves) and their keys from one node to another
+pub async fn reshard(existing: &str, from: &str, to: &str, count: usize) -> GenericResult<usize> {
+    let mut topology = load(existing).await?;
+
+    let Some(source) = topology.get(from) else {
+        Err(format!("{from} is not part of the cluster"))?
+    };
+
+    let source_slots = node_slots(source);
+    if count > source_slots.len() {
+        Err(format!(
+            "{from} only serves {} slots, {count} slots can't be moved",
+            source_slots.len()
+        ))?;
+    }
+
+    let slots = source_slots.into_iter().rev().take(count).collect();
+    migrate_slots(&mut topology, from, to, &slots).await
+}
+
+/// Works out how many slots need to move between which nodes so that every node serves
+/// a number of slots proportional to its weight. Nodes that are left out of `weights` have a weight of 1.
+pub fn rebalance_plan(
+    topology: &ClusterInstanceConfig,
+    weights: &HashMap<String, f64>,
+) -> Vec<(String, String, usize)> {
+    let mut addresses = topology.keys().cloned().collect::<Vec<_>>();
+    addresses.sort();
+
+    let weight = |address: &String| weights.get(address).copied().unwrap_or(1.0).max(0.0);
+    let total_weight = addresses.iter().map(weight).sum::<f64>();
+    if total_weight == 0.0 {
+        return vec![];
+    }
+
+    // Slots left over after rounding down go to the nodes with the largest weights
+    let mut targets = addresses
+        .iter()
+        .map(|address| {
+            let share = CELLS_TOTAL as f64 * weight(address) / total_weight;
+            (address.clone(), share.floor() as usize, share.fract())
+        })
+        .collect::<Vec<_>>();
+    let assigned = targets.iter().map(|(_, target, _)| target).sum::<usize>();
+    let mut by_remainder = (0..targets.len()).collect::<Vec<_>>();
+    by_remainder.sort_by(|a, b| targets[*b].2.total_cmp(&targets[*a].2));
+    for index in by_remainder.into_iter().take(CELLS_TOTAL - assigned) {
+        targets[index].1 += 1;
+    }
+
+    // Positive balance means that the node has to give slots away
+    let mut balances = targets
+        .into_iter()
+        .map(|(address, target, _)| {
+            let current = topology[&address].slots_count() as i64;
+            (address, current - target as i64)
+        })
+        .collect::<Vec<_>>();
+
+    let mut plan = vec![];
+    loop {
+        balances.sort_by_key(|(_, balance)| *balance);
+
+        let (Some((to, receiver)), Some((from, giver))) = (balances.first(), balances.last())
+        else {
+            break;
+        };
+        if *giver <= 0 || *receiver >= 0 {
+            break;
+        }
+
+        let count = (*giver).min(-*receiver);
+        plan.push((from.clone(), to.clone(), count as usize));
+
+        let last = balances.len() - 1;
+        balances[0].1 += count;
+        balances[last].1 -= count;
+    }
+
+    plan
+}
+
+/// Moves slots between the nodes so that each node serves a share of the slots proportional to its weight
+pub async fn rebalance(
+    existing: &str,
+    weights: &HashMap<String, f64>,
+) -> GenericResult<Vec<(String, String, usize)>> {
+    let topology = load(existing).await?;
+    let plan = rebalance_plan(&topology, weights);
+
+    for (from, to, count) in &plan {
+        reshard(existing, from, to, *count).await?;
+    }
+
+    Ok(plan)
+}
+
+/// Finishes open migrations, assigns uncovered slots and makes sure each slot is served by one node only.
+///
+/// Keys are moved to the node that ends up serving their slot.
+pub async fn fix(existing: &str) -> GenericResult<CheckReport> {
+    let mut topology = load(existing).await?;
+
+    // 1. Open migrations get finished
+    let mut migrations = BTreeMap::<(String, String), BTreeSet<usize>>::new();
+    for (address, config) in &topology {
+        for SlotMigration { slots, node } in &config.migrating {
+            migrations
+                .entry((address.clone(), node.clone()))
+                .or_default()
+                .extend(slots.iter().flat_map(|(start, end)| *start..=*end));
+        }
+    }
+    for config in topology.values_mut() {
+        config.migrating.clear();
+        config.importing.clear();
+    }
+    for ((from, to), slots) in migrations {
+        if topology.contains_key(&from) && topology.contains_key(&to) {
+            migrate_slots(&mut topology, &from, &to, &slots).await?;
+        }
+    }
+
+    // Keys of every node are needed to work out who should serve uncovered and duplicated slots
+    let mut keys = HashMap::new();
+    for address in topology.keys() {
+        keys.insert(address.clone(), keys_by_slot(address).await?);
+    }
+    let key_count = |address: &str, slot: usize| {
+        keys.get(address)
+            .and_then(|keys| keys.get(&slot))
+            .map_or(0, Vec::len)
+    };
+
+    let owners = slot_owners(&topology)
+        .into_iter()
+        .map(|owners| owners.into_iter().map(str::to_owned).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut addresses = topology.keys().cloned().collect::<Vec<_>>();
+    addresses.sort();
+
+    // Slot -> the node that keeps it and the nodes whose keys need to move to it
+    let mut moves = BTreeMap::<(String, String), BTreeSet<usize>>::new();
+    let mut slots = topology
+        .iter()
+        .map(|(address, config)| (address.clone(), node_slots(config)))
+        .collect::<HashMap<_, _>>();
+
+    for (slot, owners) in owners.iter().enumerate() {
+        let candidates = if owners.is_empty() {
+            &addresses
+        } else if owners.len() > 1 {
+            owners
+        } else {
+            continue;
+        };
+
+        // 2. The node with the most keys in the slot keeps it,
+        // otherwise the node that serves the fewest slots gets it
+        let Some(keeper) = candidates
+            .iter()
+            .max_by_key(|address| {
+                (
+                    key_count(address, slot),
+                    std::cmp::Reverse(slots.get(*address).map_or(0, BTreeSet::len)),
+                )
+            })
+            .cloned()
+        else {
+            continue;
+        };
+
+        for address in &addresses {
+            if *address == keeper {
+                slots.entry(address.clone()).or_default().insert(slot);
+                continue;
+            }
+
+            slots.entry(address.clone()).or_default().remove(&slot);
+            if key_count(address, slot) > 0 {
+                moves
+                    .entry((address.clone(), keeper.clone()))
+                    .or_default()
+                    .insert(slot);
+            }
+        }
+    }
+
+    for ((from, to), slots) in moves {
+        move_keys(&from, &to, &slots).await?;
+    }
+
+    for (address, node_slots) in slots {
+        if let Some(config) = topology.get_mut(&address) {
+            set_node_slots(config, &node_slots);
+        }
+    }
+    save(&topology).await?;
+
+    check(existing).await
+}
+
+#[cfg(test)]
+mod admin_tests {
+    use super::*;
+
+    fn topology(nodes: &[(&str, Vec<(usize, usize)>)]) -> ClusterInstanceConfig {
+        nodes
+            .iter()
+            .map(|(address, slots)| {
+                (
+                    address.to_string(),
+                    ClusterConfig {
+                        id: address.to_string(),
+                        ip: address.to_string(),
+                        slots: slots.clone(),
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compresses_slots_into_ranges() {
+        let slots = BTreeSet::from([1, 2, 3, 7, 9, 10]);
+        assert_eq!(slot_ranges(&slots), vec![(1, 3), (7, 7), (9, 10)]);
+    }
+
+    #[test]
+    fn finds_uncovered_and_duplicated_slots() {
+        let mut nodes = topology(&[("a:1", vec![(0, 100)]), ("b:2", vec![(50, 16000)])]);
+        nodes.get_mut("a:1").unwrap().migrating.push(SlotMigration {
+            slots: vec![(7, 9), (12, 12)],
+            node: "b:2".to_owned(),
+        });
+
+        let report = check_topology(&nodes);
+
+        assert_eq!(report.uncovered, vec![(16001, 16383)]);
+        assert_eq!(
+            report.duplicated,
+            vec![((50, 100), vec!["a:1".to_owned(), "b:2".to_owned()])]
+        );
+        assert_eq!(report.open_migrations, vec!["a:1 -> b:2 slots 7-9, 12"]);
+        assert!(!report.is_ok());
+
+        let nodes = topology(&[("a:1", vec![(0, 8191)]), ("b:2", vec![(8192, 16383)])]);
+        assert!(check_topology(&nodes).is_ok());
+    }
+
+    #[test]
+    fn plans_weighted_rebalance() {
+        let nodes = topology(&[("a:1", vec![(0, 16383)]), ("b:2", vec![]), ("c:3", vec![])]);
+
+        // Equal weights
+        let mut plan = rebalance_plan(&nodes, &HashMap::new());
+        plan.sort();
+        assert_eq!(
+            plan,
+            vec![
+                ("a:1".to_owned(), "b:2".to_owned(), 5461),
+                ("a:1".to_owned(), "c:3".to_owned(), 5461),
+            ]
+        );
+
+        // b:2 takes half of the slots, c:3 gets drained
+        let weights = HashMap::from([("b:2".to_owned(), 2.0), ("c:3".to_owned(), 0.0)]);
+        let plan = rebalance_plan(&nodes, &weights);
+        assert_eq!(plan, vec![("a:1".to_owned(), "b:2".to_owned(), 10923)]);
+    }
+
+    #[test]
+    fn reads_single_range_configs() {
+        let config = toml::from_str::<ClusterInstanceConfig>(
+            r#"
+            ["127.0.0.1:9000"]
+            id = "a"
+            ip = "127.0.0.1:9000"
+            is_self = true
+            position = [0, 8191]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config["127.0.0.1:9000"].slots, vec![(0, 8191)]);
+        assert!(config["127.0.0.1:9000"].serves(8191));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{
+    cluster::{key_slot, ClusterState, CELLS_TOTAL},
+    commands::ask::AskResponse,
+    ClusterInstanceConfig, GenericResult, Shutdown,
+};
+use log::{error, info};
+use std::{
+    env::current_dir,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tokio::fs;
+
+// How often the `<port>.toml` cluster config is checked for changes made on disk
+pub const TOPOLOGY_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+const NOT_CREATED_ERR: &str = "Cluster has not been created";
+const PARSE_ERR: &str = "Cluster config could not be parsed";
+
+/// Where a key based command should run
+#[derive(Debug, PartialEq)]
+pub enum Route {
+    /// The node serves the slot of the key (or the node is not part of a cluster)
+    Local,
+    /// The slot is served by a different node for good
+    Moved(AskResponse),
+    /// The slot is being migrated away, keys that are not on this node anymore are on the other node
+    Migrating(AskResponse),
+}
+
+#[derive(Default)]
+struct TopologyState {
+    // Bumped every time the nodes change
+    epoch: u64,
+    // None while the node is not part of a cluster
+    nodes: Option<Arc<ClusterInstanceConfig>>,
+    // Why the last reload failed, the previous nodes are kept in that case
+    error: Option<&'static str>,
+    // Modification time of the config that was loaded last
+    modified: Option<SystemTime>,
+    // Nodes were set in memory (i.e. by `vivs::testing`), the config on disk is ignored
+    pinned: bool,
+}
+
+/// In-memory copy of the cluster topology (the `<port>.toml` cluster config of the node).
+///
+/// The config is loaded once and then reloaded when it changes on disk, on `CLUSTER RELOAD`
+/// (sent by `vivs-repl --cluster` admin commands) or when a peer says so over the cluster bus.
+/// Every change bumps the epoch, readers get a consistent snapshot without any IO.
+///
+/// Clones share the state, a reload done through one of them is seen by all the connections.
+#[derive(Clone, Default)]
+pub struct Topology {
+    state: Arc<RwLock<TopologyState>>,
+}
+
+fn config_path(port: u16) -> GenericResult<PathBuf> {
+    Ok(current_dir()?.join(format!("{port}.toml")))
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.state
+            .read()
+            .map(|state| state.epoch)
+            .unwrap_or_default()
+    }
+
+    /// Nodes of the cluster, None when the node is not part of a cluster
+    pub fn nodes(&self) -> Option<Arc<ClusterInstanceConfig>> {
+        self.state.read().ok().and_then(|state| state.nodes.clone())
+    }
+
+    /// Replaces the nodes, the epoch is only bumped if they are different
+    pub fn set(&self, nodes: Option<ClusterInstanceConfig>) -> u64 {
+        let Ok(mut state) = self.state.write() else {
+            return 0;
+        };
+
+        state.error = None;
+        if state.nodes.as_deref() != nodes.as_ref() {
+            state.nodes = nodes.map(Arc::new);
+            state.epoch += 1;
+        }
+
+        state.epoch
+    }
+
+    /// Replaces the nodes for good, reloads keep them instead of reading the `<port>.toml` cluster config
+    pub fn pin(&self, nodes: ClusterInstanceConfig) -> u64 {
+        if let Ok(mut state) = self.state.write() {
+            state.pinned = true;
+        }
+
+        self.set(Some(nodes))
+    }
+
+    fn is_pinned(&self) -> bool {
+        self.state
+            .read()
+            .map(|state| state.pinned)
+            .unwrap_or_default()
+    }
+
+    /// Reads the `<port>.toml` cluster config of the node listening on `port`, returns the epoch.
+    ///
+    /// A config that can't be parsed (i.e. it is still being written) leaves the topology as it is.
+    pub async fn reload(&self, port: u16) -> GenericResult<u64> {
+        if self.is_pinned() {
+            return Ok(self.epoch());
+        }
+
+        let path = config_path(port)?;
+
+        let modified = fs::metadata(&path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let nodes = match fs::read_to_string(&path).await {
+            Ok(contents) => match toml::from_str::<ClusterInstanceConfig>(&contents) {
+                Ok(nodes) => Some(nodes),
+                Err(e) => {
+                    if let Ok(mut state) = self.state.write() {
+                        state.error = Some(PARSE_ERR);
+                    }
+                    Err(format!("{PARSE_ERR}: {e}"))?
+                }
+            },
+            // No config means that the node is not (or no longer) part of a cluster
+            Err(_) => None,
+        };
+
+        let epoch = self.set(nodes);
+        if let Ok(mut state) = self.state.write() {
+            state.modified = modified;
+        }
+
+        Ok(epoch)
+    }
+
+    /// Whether the config changed on disk since it was last loaded
+    async fn is_stale(&self, port: u16) -> bool {
+        let Ok(path) = config_path(port) else {
+            return false;
+        };
+        if self.is_pinned() {
+            return false;
+        }
+
+        let modified = fs::metadata(&path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        self.state
+            .read()
+            .map(|state| state.error.is_some() || state.modified != modified)
+            .unwrap_or_default()
+    }
+
+    /// Loads the cluster config and keeps reloading it when it changes on disk, until shutdown
+    pub async fn watch(self, port: u16, shutdown: Shutdown) {
+        let mut interval = tokio::time::interval(TOPOLOGY_RELOAD_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = shutdown.wait() => return,
+            }
+
+            if !self.is_stale(port).await {
+                continue;
+            }
+
+            match self.reload(port).await {
+                Ok(epoch) => info!("Cluster topology loaded, epoch {epoch}"),
+                Err(e) => error!("Failed to reload cluster topology: {e}"),
+            }
+        }
+    }
+
+    /// Works out where a command for the key should run on the node listening on `own_addr`.
+    ///
+    /// `asking` is set when the client sent ASKING right before the command,
+    /// which lets it use slots that are being imported by this node.
+    pub fn route(&self, key: &[u8], own_addr: &str, asking: bool) -> Route {
+        let Some(nodes) = self.nodes() else {
+            return Route::Local;
+        };
+
+        let slot = key_slot(key);
+        let slot_index = usize::from(slot);
+
+        if let Some(own) = nodes.get(own_addr) {
+            if own.serves(slot_index) {
+                // Keys that were moved already are on the other node
+                return match own
+                    .migrating
+                    .iter()
+                    .find(|migration| migration.covers(slot_index))
+                {
+                    Some(migration) => Route::Migrating(AskResponse {
+                        key_hash: slot,
+                        ip: migration.node.clone(),
+                    }),
+                    None => Route::Local,
+                };
+            }
+
+            if asking
+                && own
+                    .importing
+                    .iter()
+                    .any(|migration| migration.covers(slot_index))
+            {
+                return Route::Local;
+            }
+        }
+
+        // Slots that nobody serves are served locally
+        nodes
+            .iter()
+            .find(|(_, config)| config.serves(slot_index))
+            .map_or(Route::Local, |(ip, _)| {
+                Route::Moved(AskResponse {
+                    key_hash: slot,
+                    ip: ip.clone(),
+                })
+            })
+    }
+
+    /// Works out the state of the cluster, same as `CLUSTER INFO` cluster_state in Redis
+    pub fn state(&self, cluster_enabled: bool) -> ClusterState {
+        if !cluster_enabled {
+            return ClusterState::Disabled;
+        }
+
+        let Ok(state) = self.state.read() else {
+            return ClusterState::Fail(PARSE_ERR.to_owned());
+        };
+
+        if let Some(error) = state.error {
+            return ClusterState::Fail(error.to_owned());
+        }
+
+        let Some(nodes) = state.nodes.as_ref() else {
+            return ClusterState::Fail(NOT_CREATED_ERR.to_owned());
+        };
+
+        let mut covered = vec![false; CELLS_TOTAL];
+        for (start, end) in nodes.values().flat_map(|config| &config.slots) {
+            for slot in covered.iter_mut().take(end + 1).skip(*start) {
+                *slot = true;
+            }
+        }
+
+        let uncovered = covered.iter().filter(|slot| !**slot).count();
+        if uncovered > 0 {
+            return ClusterState::Fail(format!("{uncovered} hash slots are not served"));
+        }
+
+        ClusterState::Ok
+    }
+}
+
+#[cfg(test)]
+mod topology_tests {
+    use super::*;
+    use crate::{ClusterConfig, SlotMigration};
+
+    fn nodes() -> ClusterInstanceConfig {
+        let node = |ip: &str, slots| ClusterConfig {
+            id: ip.to_owned(),
+            ip: ip.to_owned(),
+            slots,
+            ..Default::default()
+        };
+
+        ClusterInstanceConfig::from([
+            ("a:1".to_owned(), node("a:1", vec![(0, 8191)])),
+            ("b:2".to_owned(), node("b:2", vec![(8192, 16383)])),
+        ])
+    }
+
+    fn key_in(slots: std::ops::RangeInclusive<u16>) -> String {
+        (0..)
+            .map(|i| format!("key{i}"))
+            .find(|key| slots.contains(&key_slot(key.as_bytes())))
+            .unwrap()
+    }
+
+    #[test]
+    fn bumps_epoch_only_when_nodes_change() {
+        let topology = Topology::new();
+        assert_eq!(topology.epoch(), 0);
+        assert_eq!(topology.route(b"key", "a:1", false), Route::Local);
+        assert_eq!(
+            topology.state(true),
+            ClusterState::Fail(NOT_CREATED_ERR.to_owned())
+        );
+
+        assert_eq!(topology.set(Some(nodes())), 1);
+        assert_eq!(topology.set(Some(nodes())), 1);
+        assert_eq!(topology.state(true), ClusterState::Ok);
+        assert_eq!(topology.state(false), ClusterState::Disabled);
+
+        assert_eq!(topology.set(None), 2);
+        assert!(topology.nodes().is_none());
+    }
+
+    #[test]
+    fn routes_keys_to_the_node_that_serves_the_slot() {
+        let topology = Topology::new();
+        topology.set(Some(nodes()));
+
+        let key = key_in(8192..=16383);
+        let slot = key_slot(key.as_bytes());
+
+        assert_eq!(topology.route(key.as_bytes(), "b:2", false), Route::Local);
+        assert_eq!(
+            topology.route(key.as_bytes(), "a:1", false),
+            Route::Moved(AskResponse {
+                key_hash: slot,
+                ip: "b:2".to_owned()
+            })
+        );
+
+        // Slot is being moved from b:2 to a:1
+        let mut nodes = nodes();
+        let migration = |node: &str| SlotMigration {
+            slots: vec![(slot.into(), slot.into())],
+            node: node.to_owned(),
+        };
+        nodes
+            .get_mut("b:2")
+            .unwrap()
+            .migrating
+            .push(migration("a:1"));
+        nodes
+            .get_mut("a:1")
+            .unwrap()
+            .importing
+            .push(migration("b:2"));
+        topology.set(Some(nodes));
+
+        assert_eq!(
+            topology.route(key.as_bytes(), "b:2", false),
+            Route::Migrating(AskResponse {
+                key_hash: slot,
+                ip: "a:1".to_owned()
+            })
+        );
+        // Importing slots are only served after ASKING, b:2 still owns the slot
+        assert!(matches!(
+            topology.route(key.as_bytes(), "a:1", false),
+            Route::Moved(_)
+        ));
+        assert_eq!(topology.route(key.as_bytes(), "a:1", true), Route::Local);
+    }
+}
@@ -1,49 +1,89 @@
-use self::delete::DELETE_CMD;
-use self::get::GET_CMD;
-use self::ping::PING_CMD;
-use self::set::SET_CMD;
-use self::ttl::TTL_CMD;
-use crate::data_chunk::DataChunkError;
+use self::registry::{lookup, CommandFlag, CommandSpec};
+use self::select::DB_INDEX_ERR;
+use crate::cluster::{key_slot, topology::Route};
+use crate::data_chunk::{DataChunk, DataChunkError};
+use crate::error::CommandError;
 use crate::parser::Parser;
-use crate::{Connection, DataStore, GenericResult};
-use ask::{Ask, ASK_CMD};
-use asking::{Asking, ASKING_CMD};
+use crate::{Connection, Db, GenericResult, Handler};
+use bytes::Bytes;
 use core::str;
-use delete::Delete;
-use get::Get;
-use ping::Ping;
-use set::Set;
-use ttl::Ttl;
 
 pub mod ask;
 pub mod asking;
+pub mod bitcount;
+pub mod bitfield;
+pub mod bitop;
+pub mod bitpos;
+pub mod blmove;
+pub mod blpop;
+pub mod client;
+pub mod cluster;
+pub mod command;
+pub mod config;
 pub mod delete;
+pub mod dump;
+pub mod expire;
+pub mod flushall;
+pub mod flushdb;
+pub mod geoadd;
+pub mod geodist;
+pub mod geohash;
+pub mod geopos;
+pub mod geosearch;
 pub mod get;
+pub mod getbit;
+pub mod info;
+pub mod llen;
+pub mod lmove;
+pub mod lrange;
+pub mod memory;
+pub mod monitor;
+pub mod move_key;
+pub mod multi;
+pub mod object;
+pub mod pfadd;
+pub mod pfcount;
+pub mod pfmerge;
 pub mod ping;
+pub mod pop;
+pub mod publish;
+pub mod push;
+pub mod registry;
+pub mod restore;
+pub mod scan;
+pub mod select;
 pub mod set;
+pub mod setbit;
+pub mod shutdown;
+pub mod slowlog;
+pub mod subscribe;
+pub mod swapdb;
 pub mod ttl;
+pub mod xack;
+pub mod xadd;
+pub mod xclaim;
+pub mod xgroup;
+pub mod xinfo;
+pub mod xlen;
+pub mod xpending;
+pub mod xrange;
+pub mod xread;
 
-pub const FALSE_CMD: &str = "FALSECMD";
-
-pub const NO_CMD_ERR: &str = "No command supplied\r\n";
-pub const NO_CMD: &str = "NOCMD";
-
-pub const INCORRECT_ARGS_ERR: &str = "Incorrect number of arguments\r\n";
-pub const ARGS_NUM: &str = "ARGSNUM";
-
-pub const VALUE_NOT_INT_ERR: &str = "Value is not an integer\r\n";
-pub const NON_INT: &str = "NONINT";
+pub const NO_CMD_ERR: &str = "no command supplied";
 
 #[derive(Debug)]
 pub enum Command {
-    Ping(Ping),
-    Get(Get),
-    Set(Set),
-    Delete(Delete),
-    Ttl(Ttl),
-    Ask(Ask),
+    /// A command from the command table, together with its arguments (without the command name)
+    Known {
+        spec: &'static CommandSpec,
+        args: Parser,
+        // Keys picked out of the arguments, the first one decides where the command runs in a cluster
+        keys: Vec<Bytes>,
+        // Number of arguments including the command name
+        arg_count: usize,
+        subcommand: Option<&'static str>,
+    },
     Unknown(String),
-    Asking(Asking),
     None,
 }
 
@@ -85,7 +125,7 @@ pub trait CommonCommand {
     fn respond(
         &self,
         connection: &mut Connection,
-        datastore: &DataStore,
+        datastore: &Db,
     ) -> impl std::future::Future<Output = GenericResult<()>> + Send;
 }
 
@@ -93,46 +133,176 @@ impl Command {
     pub fn parse_cmd(mut data_chunk: Parser) -> Result<Command, ParseCommandErr> {
         // The iterator should contain all the necessary commands and values e.g. [SET, key, value]
         // The first value is the command itself
-        let Some(command) = data_chunk.next_as_str()?.map(|val| val.to_lowercase()) else {
+        // Names that are not valid UTF-8 end up as unknown commands
+        let Some(command) = data_chunk
+            .next_bytes()?
+            .map(|val| String::from_utf8_lossy(&val).to_lowercase())
+        else {
             return Ok(Command::None);
         };
 
-        // To figure out which command needs to be processed,
-        // we have to convert byte slice to a string slice that needs to be a valid UTF-8
-        let command = match &command[..] {
-            PING_CMD => Command::Ping(Ping::parse(data_chunk)),
-            GET_CMD => Command::Get(Get::parse(data_chunk)),
-            SET_CMD => Command::Set(Set::parse(data_chunk)),
-            DELETE_CMD => Command::Delete(Delete::parse(data_chunk)),
-            TTL_CMD => Command::Ttl(Ttl::parse(data_chunk)),
-            ASK_CMD => Command::Ask(Ask::parse()),
-            ASKING_CMD => Command::Asking(Asking::parse(data_chunk)),
-            "" => Command::None,
-            val => Command::Unknown(val.to_owned()),
+        if command.is_empty() {
+            return Ok(Command::None);
+        }
+
+        let Some(spec) = lookup(&command) else {
+            return Ok(Command::Unknown(command));
         };
 
-        Ok(command)
+        let args = data_chunk.collect::<Vec<_>>();
+        let bulk_args = args
+            .iter()
+            .filter_map(|arg| match arg {
+                DataChunk::Bulk(value) => Some(value.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Command::Known {
+            spec,
+            keys: spec.keys(&bulk_args),
+            arg_count: args.len() + 1,
+            subcommand: spec.subcommand(bulk_args.first()),
+            args: Parser::from(args),
+        })
     }
 
-    pub async fn run(self, conn: &mut Connection, db: &DataStore) -> GenericResult<()> {
+    /// Name of the command (lowercase), used for bookkeeping such as CLIENT LIST.
+    /// Subcommands are included i.e. client|list
+    pub fn name(&self) -> String {
         match self {
-            Command::Ping(command) => command.respond(conn).await,
-            Command::Get(command) => command.respond(conn, db).await,
-            Command::Set(command) => command.respond(conn, db).await,
-            Command::Delete(command) => command.respond(conn, db).await,
-            Command::Ttl(command) => command.respond(conn, db).await,
-            Command::Ask(command) => command.respond(conn).await,
-            Command::Asking(command) => command.respond(conn).await,
-            Command::None => {
-                conn.write_error(NO_CMD.as_bytes()).await?;
-                Ok(())
-            }
-            Command::Unknown(command) => {
-                let error_msg = format!("Unknown command {:?}", command);
-                conn.write_error_with_msg(FALSE_CMD.as_bytes(), error_msg.as_bytes())
+            Command::Known {
+                spec,
+                subcommand: Some(subcommand),
+                ..
+            } => format!("{}|{subcommand}", spec.name),
+            Command::Known { spec, .. } => spec.name.to_owned(),
+            Command::Unknown(command) => command.to_owned(),
+            Command::None => "NULL".to_owned(),
+        }
+    }
+
+    /// Whether this is the command called `name` (lowercase)
+    pub fn is(&self, name: &str) -> bool {
+        matches!(self, Command::Known { spec, .. } if spec.name == name)
+    }
+
+    /// Key the command operates on, these commands run on the node that serves the key's hash slot
+    pub fn key(&self) -> Option<&[u8]> {
+        match self {
+            Command::Known { keys, .. } => keys.first().map(|key| &key[..]),
+            _ => None,
+        }
+    }
+
+    /// Whether the command modifies the data store
+    pub fn is_write(&self) -> bool {
+        matches!(self, Command::Known { spec, .. } if spec.has_flag(CommandFlag::Write))
+    }
+
+    /// Why the command can't run, checked before a command gets queued by MULTI too
+    pub fn check(&self) -> Result<(), CommandError> {
+        match self {
+            Command::Known {
+                spec, arg_count, ..
+            } if !spec.arity_matches(*arg_count) => Err(CommandError::WrongArity(spec.name)),
+            Command::Known { .. } => Ok(()),
+            Command::None => Err(CommandError::Other(NO_CMD_ERR.to_owned())),
+            Command::Unknown(command) => Err(CommandError::UnknownCommand(command.to_owned())),
+        }
+    }
+
+    pub async fn run(self, handler: &mut Handler) -> GenericResult<()> {
+        // ASKING only applies to the command that follows it
+        let asking = std::mem::take(&mut handler.asking);
+        let key = self.key().map(Bytes::copy_from_slice);
+
+        if let Err(error) = self.check() {
+            handler.connection.write_error(&error).await?;
+            return Ok(());
+        }
+
+        let Command::Known {
+            spec, args, keys, ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        // SWAPDB might have swapped the selected database since the last command
+        let Some(db) = handler.db.db(handler.db_index) else {
+            let error = CommandError::OutOfRange(DB_INDEX_ERR);
+            handler.connection.write_error(&error).await?;
+            return Ok(());
+        };
+
+        if let Some(key) = key {
+            // All the keys have to be on the node that serves the first one
+            let cluster = handler.topology.nodes().is_some();
+            if cluster && keys.iter().any(|other| key_slot(other) != key_slot(&key)) {
+                handler
+                    .connection
+                    .write_error(&CommandError::CrossSlot)
                     .await?;
-                Ok(())
+                return Ok(());
+            }
+
+            let own_addr = handler.connection.own_addr()?.to_string();
+
+            let redirect = match handler.topology.route(&key, &own_addr, asking) {
+                Route::Local => None,
+                Route::Moved(redirect) => Some(redirect.moved()),
+                // Keys that are still on this node are served until they get moved
+                Route::Migrating(redirect) => {
+                    let exists = db.db.read().await.contains_key(&key);
+                    (!exists).then(|| redirect.ask())
+                }
+            };
+
+            if let Some(redirect) = redirect {
+                handler.connection.write_error(&redirect).await?;
+                return Ok(());
             }
         }
+
+        // Commands that use keys don't run in the middle of a MULTI / EXEC block (EXEC holds it then).
+        // Blocking commands take it themselves, so that it is not held while they wait.
+        let touches_keys =
+            spec.has_flag(CommandFlag::Write) || spec.has_flag(CommandFlag::Readonly);
+        let guard = match touches_keys && !spec.has_flag(CommandFlag::Blocking) && !handler.in_exec
+        {
+            true => Some(handler.db.command_guard().await),
+            false => None,
+        };
+
+        // Keys the command used count as accessed (OBJECT IDLETIME / FREQ)
+        let touched = (touches_keys && !spec.has_flag(CommandFlag::NoTouch)).then(|| db.clone());
+
+        // The reply is written once the lock is released, EXEC holds the output of its commands itself
+        let held = touches_keys && !handler.in_exec && handler.connection.hold();
+
+        let ran = (spec.handler)(args, handler, db).await;
+
+        if let Some(db) = touched {
+            db.touch(&keys).await;
+        }
+
+        // Clients blocked on the keys the command pushed to,
+        // EXEC serves them once all of its commands ran
+        if spec.has_flag(CommandFlag::Write) && !handler.in_exec {
+            let _guard = match guard {
+                Some(guard) => guard,
+                None => handler.db.command_guard().await,
+            };
+            handler.db.serve_blocked().await;
+        } else {
+            drop(guard);
+        }
+
+        if held {
+            handler.connection.release().await?;
+        }
+
+        ran
     }
 }
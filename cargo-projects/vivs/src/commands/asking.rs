@@ -1,4 +1,4 @@
-use crate::{parser::Parser, Connection, GenericResult};
+use crate::{commands::DataType, parser::Parser, Connection, GenericResult};
 use core::str;
 
 pub const ASKING_CMD: &str = "asking";
@@ -45,26 +45,26 @@ impl Asking {
 
     pub fn parse(mut data: Parser) -> Self {
         // TODO - treat errors accordingly here (i.e. data.next_as_str()?)
-        // First, get the command (i.e. GET)
-        let Ok(command) = data.next_as_str() else {
+        // First, get the command (i.e. GET).
+        // Plain ASKING (without arguments) is what cluster clients send.
+        let Ok(Some(command)) = data.next_as_str() else {
             return Self::default();
         };
 
         // Second, get the slot (i.e. 2345)
-        let Ok(slot) = data.next_as_str() else {
+        let Ok(Some(key)) = data.next_as_str() else {
             return Self::default();
         };
 
         // Third, get the address (i.e. 127.0.0.1)
-        let Ok(address) = data.next_as_str() else {
+        let Ok(Some(address)) = data.next_as_str() else {
             return Self::default();
         };
 
-        // It's OK to unwrap here, since we have checks above
         Self {
-            command: command.unwrap(),
-            key: slot.unwrap(),
-            address: address.unwrap(),
+            command,
+            key,
+            address,
         }
     }
 
@@ -77,7 +77,18 @@ impl Asking {
 }
 
 impl Asking {
+    /// Plain ASKING (without arguments), sent by cluster clients before the redirected command
+    pub fn is_plain(&self) -> bool {
+        self.command.is_empty()
+    }
+
     pub async fn respond(self, conn: &mut Connection) -> GenericResult<()> {
+        // Same as in Redis, ASKING lets the next command use a slot that this node is importing
+        if self.command.is_empty() {
+            conn.write_chunk(DataType::SimpleString, b"OK").await?;
+            return Ok(());
+        }
+
         let length_of_address = self.key.len();
         let cmd = self.command.to_uppercase();
         let cmd_len = cmd.len();
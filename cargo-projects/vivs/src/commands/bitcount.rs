@@ -0,0 +1,103 @@
+use crate::{
+    bitmap::{self, Unit},
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const BITCOUNT_CMD: &str = "bitcount";
+
+#[derive(Debug)]
+struct BitcountArgs {
+    key: Bytes,
+    // Start and end indexes (negative ones count from the end), the whole string when not set
+    range: Option<(i64, i64, Unit)>,
+}
+
+/// BITCOUNT key [start end [BYTE | BIT]] counts the bits that are set to 1,
+/// the range is in bytes unless BIT is given.
+#[derive(Debug)]
+pub struct Bitcount {
+    args: Result<BitcountArgs, CommandError>,
+}
+
+fn parse_index(index: &str) -> Result<i64, CommandError> {
+    index.parse().map_err(|_| CommandError::NotInteger)
+}
+
+fn parse_args(mut data: Parser) -> Result<BitcountArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(BITCOUNT_CMD));
+    };
+
+    let next = |data: &mut Parser| data.next_as_str().map_err(|_| CommandError::Syntax);
+
+    let range = match (next(&mut data)?, next(&mut data)?) {
+        (None, _) => None,
+        (Some(_), None) => return Err(CommandError::Syntax),
+        (Some(start), Some(end)) => {
+            let unit = match next(&mut data)? {
+                Some(unit) => Unit::parse(&unit)?,
+                None => Unit::Byte,
+            };
+
+            Some((parse_index(&start)?, parse_index(&end)?, unit))
+        }
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(BitcountArgs { key, range })
+}
+
+impl Bitcount {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            BITCOUNT_CMD.to_uppercase(),
+            self.args
+        );
+
+        let BitcountArgs { key, range } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let count = {
+            let db_guard = db.write_live(&[&key]).await;
+            bitmap::get(&db_guard, &key).map(|value| {
+                let value = value.map(|value| &value[..]).unwrap_or_default();
+                let (start, end, unit) = range.unwrap_or((0, -1, Unit::Byte));
+
+                bitmap::bit_range(value.len(), start, end, unit)
+                    .map_or(0, |(start, end)| bitmap::count(value, start, end))
+            })
+        };
+
+        match count {
+            Ok(count) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(count))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
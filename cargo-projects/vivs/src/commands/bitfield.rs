@@ -0,0 +1,198 @@
+use crate::{
+    bitmap::{self, Encoding, Overflow},
+    commands::DataType,
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    utils::i64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::{Bytes, BytesMut};
+use log::info;
+
+pub const BITFIELD_CMD: &str = "bitfield";
+
+/// An integer of the string, at the bit offset
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    encoding: Encoding,
+    offset: u64,
+}
+
+#[derive(Debug)]
+enum Op {
+    /// GET encoding offset
+    Get(Field),
+    /// SET encoding offset value, replies with the old value
+    Set(Field, i64, Overflow),
+    /// INCRBY encoding offset increment, replies with the new value
+    IncrBy(Field, i64, Overflow),
+}
+
+impl Op {
+    fn is_write(&self) -> bool {
+        !matches!(self, Op::Get(_))
+    }
+
+    /// Result of GET, the other operations need to change the string (`run`)
+    fn get(&self, bytes: &[u8]) -> Option<i64> {
+        match *self {
+            Op::Get(Field { encoding, offset }) => Some(bitmap::get_field(bytes, offset, encoding)),
+            _ => None,
+        }
+    }
+
+    /// Runs the operation, None when the value overflows with OVERFLOW FAIL
+    fn run(&self, bytes: &mut BytesMut) -> Option<i64> {
+        match *self {
+            Op::Get(_) => self.get(bytes),
+            Op::Set(Field { encoding, offset }, value, overflow) => {
+                let old = bitmap::get_field(bytes, offset, encoding);
+                let value = overflow.apply(value.into(), encoding)?;
+                bitmap::set_field(bytes, offset, encoding, value);
+                Some(old)
+            }
+            Op::IncrBy(Field { encoding, offset }, increment, overflow) => {
+                let old = bitmap::get_field(bytes, offset, encoding);
+                let value = overflow.apply(i128::from(old) + i128::from(increment), encoding)?;
+                bitmap::set_field(bytes, offset, encoding, value);
+                Some(value)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BitfieldArgs {
+    key: Bytes,
+    ops: Vec<Op>,
+}
+
+/// BITFIELD key [GET encoding offset] [SET encoding offset value] [INCRBY encoding offset increment]
+/// [OVERFLOW WRAP | SAT | FAIL] ... treats the string as an array of integers of any size,
+/// i.e. i5 is a signed 5 bit integer and u8 an unsigned byte.
+///
+/// Offsets are in bits, #N is the Nth integer of the type. OVERFLOW applies to the SET and INCRBY
+/// operations after it (WRAP by default). Replies with an array of the results of the operations,
+/// null for the ones that failed because of OVERFLOW FAIL.
+#[derive(Debug)]
+pub struct Bitfield {
+    args: Result<BitfieldArgs, CommandError>,
+}
+
+fn next_arg(data: &mut Parser) -> Result<String, CommandError> {
+    data.next_as_str()
+        .map_err(|_| CommandError::Syntax)?
+        .ok_or(CommandError::Syntax)
+}
+
+fn parse_args(mut data: Parser) -> Result<BitfieldArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(BITFIELD_CMD));
+    };
+
+    let mut ops = vec![];
+    let mut overflow = Overflow::default();
+
+    while data.size() > 0 {
+        let op = next_arg(&mut data)?.to_lowercase();
+        if op == "overflow" {
+            overflow = Overflow::parse(&next_arg(&mut data)?)?;
+            continue;
+        }
+
+        let encoding = Encoding::parse(&next_arg(&mut data)?)?;
+        let field = Field {
+            encoding,
+            offset: encoding.parse_offset(&next_arg(&mut data)?)?,
+        };
+
+        let op = match &op[..] {
+            "get" => Op::Get(field),
+            "set" | "incrby" => {
+                let value = next_arg(&mut data)?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::NotInteger)?;
+
+                match &op[..] {
+                    "set" => Op::Set(field, value, overflow),
+                    _ => Op::IncrBy(field, value, overflow),
+                }
+            }
+            _ => return Err(CommandError::Syntax),
+        };
+
+        ops.push(op);
+    }
+
+    Ok(BitfieldArgs { key, ops })
+}
+
+impl Bitfield {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            BITFIELD_CMD.to_uppercase(),
+            self.args
+        );
+
+        let BitfieldArgs { key, ops } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let results = {
+            let mut db_guard = db.write_live(&[&key]).await;
+
+            match ops.iter().any(Op::is_write) {
+                true => bitmap::update(&mut db_guard, &key, |bytes| {
+                    ops.iter().map(|op| op.run(bytes)).collect::<Vec<_>>()
+                }),
+                // Only reads, the key is not created
+                false => bitmap::get(&db_guard, &key).map(|value| {
+                    let value = value.map(|value| &value[..]).unwrap_or_default();
+                    ops.iter().map(|op| op.get(value)).collect()
+                }),
+            }
+        };
+
+        let results = match results {
+            Ok(results) => results,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let changed = ops
+            .iter()
+            .zip(&results)
+            .any(|(op, result)| op.is_write() && result.is_some());
+        if changed {
+            db.notify(EventClass::String, "setbit", &key);
+        }
+
+        conn.write_array_len(results.len()).await?;
+        for result in results {
+            match result {
+                Some(value) => {
+                    conn.write_chunk(DataType::Integer, &i64_as_bytes(value))
+                        .await?
+                }
+                None => conn.write_null().await?,
+            }
+        }
+
+        Ok(())
+    }
+}
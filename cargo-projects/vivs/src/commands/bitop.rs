@@ -0,0 +1,131 @@
+use crate::{
+    bitmap::{self, BitOp},
+    commands::DataType,
+    db::Value,
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const BITOP_CMD: &str = "bitop";
+
+const BITOP_NOT_ERR: &str = "BITOP NOT must be called with a single source key.";
+
+#[derive(Debug)]
+struct BitopArgs {
+    op: BitOp,
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+/// BITOP AND | OR | XOR | NOT destkey key [key ...] runs the bitwise operation on the strings
+/// and stores the result in destkey (shorter strings are padded with zeros, missing keys are empty).
+///
+/// Replies with the length of the result, destkey is removed when it is empty.
+#[derive(Debug)]
+pub struct Bitop {
+    args: Result<BitopArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<BitopArgs, CommandError> {
+    let (Ok(Some(op)), Ok(Some(destination))) = (data.next_as_str(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(BITOP_CMD));
+    };
+
+    let op = BitOp::parse(&op)?;
+
+    let mut keys = vec![];
+    while let Ok(Some(key)) = data.next_bytes() {
+        keys.push(key);
+    }
+
+    if keys.is_empty() {
+        return Err(CommandError::WrongArity(BITOP_CMD));
+    }
+    if op == BitOp::Not && keys.len() != 1 {
+        return Err(CommandError::Other(BITOP_NOT_ERR.to_owned()));
+    }
+
+    Ok(BitopArgs {
+        op,
+        destination,
+        keys,
+    })
+}
+
+impl Bitop {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            BITOP_CMD.to_uppercase(),
+            self.args
+        );
+
+        let BitopArgs {
+            op,
+            destination,
+            keys,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let (len, existed) = {
+            let mut live_keys = vec![&destination[..]];
+            live_keys.extend(keys.iter().map(|key| &key[..]));
+            let mut db_guard = db.write_live(&live_keys).await;
+
+            let strings = keys
+                .iter()
+                .map(|key| bitmap::get(&db_guard, key).map(Option::<&Bytes>::cloned))
+                .collect::<Result<Vec<_>, _>>();
+            let strings = match strings {
+                Ok(strings) => strings.into_iter().flatten().collect::<Vec<_>>(),
+                Err(err) => {
+                    drop(db_guard);
+                    conn.write_error(&err).await?;
+                    return Ok(());
+                }
+            };
+
+            let result = op.apply(&strings);
+            let len = result.len();
+
+            // Same as SET, the result replaces the destination together with its expiry
+            let existed = match len {
+                0 => db_guard.remove(&destination).is_some(),
+                _ => db_guard
+                    .insert(destination.clone(), Value::String(result.into()))
+                    .is_some(),
+            };
+            db.expirations.write().await.remove(&destination);
+
+            (len, existed)
+        };
+
+        match len {
+            0 if existed => db.notify(EventClass::Generic, "del", &destination),
+            0 => {}
+            _ => db.notify(EventClass::String, "set", &destination),
+        }
+
+        conn.write_chunk(DataType::Integer, &u64_as_bytes(len as u64))
+            .await?;
+
+        Ok(())
+    }
+}
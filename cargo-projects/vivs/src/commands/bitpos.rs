@@ -0,0 +1,131 @@
+use crate::{
+    bitmap::{self, Unit, BIT_VALUE_ERR},
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    utils::i64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const BITPOS_CMD: &str = "bitpos";
+
+#[derive(Debug)]
+struct BitposArgs {
+    key: Bytes,
+    bit: u8,
+    start: i64,
+    // None searches until the end of the string
+    end: Option<i64>,
+    unit: Unit,
+}
+
+/// BITPOS key bit [start [end [BYTE | BIT]]] replies with the position of the first bit
+/// that is set to 0 or 1, the range is in bytes unless BIT is given.
+///
+/// Same as in Redis, -1 is replied when there's no such bit. When looking for 0 without an end,
+/// the string is treated as if it was padded with zeros (the position right after it is replied).
+#[derive(Debug)]
+pub struct Bitpos {
+    args: Result<BitposArgs, CommandError>,
+}
+
+fn parse_index(index: &str) -> Result<i64, CommandError> {
+    index.parse().map_err(|_| CommandError::NotInteger)
+}
+
+fn parse_args(mut data: Parser) -> Result<BitposArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(bit))) = (data.next_bytes(), data.next_as_str()) else {
+        return Err(CommandError::WrongArity(BITPOS_CMD));
+    };
+
+    let bit = match &bit[..] {
+        "0" => 0,
+        "1" => 1,
+        _ => return Err(CommandError::Other(BIT_VALUE_ERR.to_owned())),
+    };
+
+    let next = |data: &mut Parser| data.next_as_str().map_err(|_| CommandError::Syntax);
+
+    let start = next(&mut data)?
+        .map(|start| parse_index(&start))
+        .transpose()?;
+    let end = next(&mut data)?.map(|end| parse_index(&end)).transpose()?;
+    let unit = match next(&mut data)? {
+        Some(unit) => Unit::parse(&unit)?,
+        None => Unit::Byte,
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(BitposArgs {
+        key,
+        bit,
+        start: start.unwrap_or_default(),
+        end,
+        unit,
+    })
+}
+
+/// Position of the first bit, or -1
+fn position(value: Option<&Bytes>, args: &BitposArgs) -> i64 {
+    let Some(value) = value.filter(|value| !value.is_empty()) else {
+        // Missing keys are empty strings, which are treated as if they were padded with zeros
+        return if args.bit == 1 { -1 } else { 0 };
+    };
+
+    let Some((start, end)) =
+        bitmap::bit_range(value.len(), args.start, args.end.unwrap_or(-1), args.unit)
+    else {
+        return -1;
+    };
+
+    match bitmap::position(value, args.bit, start, end) {
+        Some(position) => position as i64,
+        None if args.bit == 0 && args.end.is_none() => value.len() as i64 * 8,
+        None => -1,
+    }
+}
+
+impl Bitpos {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            BITPOS_CMD.to_uppercase(),
+            self.args
+        );
+
+        let args = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let position = {
+            let db_guard = db.write_live(&[&args.key]).await;
+            bitmap::get(&db_guard, &args.key).map(|value| position(value, &args))
+        };
+
+        match position {
+            Ok(position) => {
+                conn.write_chunk(DataType::Integer, &i64_as_bytes(position))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    blocking::{parse_timeout, BlockedOp},
+    commands::{
+        lmove::{parse_move, MoveArgs},
+        DataType,
+    },
+    error::CommandError,
+    list,
+    parser::Parser,
+    Connection, DataStore, Db, GenericResult, Shutdown,
+};
+use log::info;
+use std::time::Duration;
+
+pub const BLMOVE_CMD: &str = "blmove";
+pub const BRPOPLPUSH_CMD: &str = "brpoplpush";
+
+/// BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout / BRPOPLPUSH source destination timeout
+/// are the blocking versions of LMOVE / RPOPLPUSH. When the source is empty, the client is blocked
+/// until a value is pushed to it or the timeout (in seconds, 0 blocks forever) passes.
+///
+/// Replies with the value, null on timeout.
+#[derive(Debug)]
+pub struct BlockingMove {
+    name: &'static str,
+    args: Result<(MoveArgs, Option<Duration>), CommandError>,
+}
+
+fn parse_args(
+    mut data: Parser,
+    name: &'static str,
+) -> Result<(MoveArgs, Option<Duration>), CommandError> {
+    let args = parse_move(&mut data, name, name == BLMOVE_CMD)?;
+
+    let Ok(Some(timeout)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(name));
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(name));
+    }
+
+    Ok((args, parse_timeout(&timeout)?))
+}
+
+impl BlockingMove {
+    pub fn parse(data: Parser, name: &'static str) -> Self {
+        Self {
+            name,
+            args: parse_args(data, name),
+        }
+    }
+
+    /// Inside of MULTI / EXEC (`in_exec`) the client is never blocked, same as in Redis
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        db: &Db,
+        store: &DataStore,
+        shutdown: &Shutdown,
+        in_exec: bool,
+        blocked_for: &mut Duration,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            self.name.to_uppercase(),
+            self.args
+        );
+
+        let (
+            MoveArgs {
+                source,
+                destination,
+                from,
+                to,
+            },
+            timeout,
+        ) = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let blocked = {
+            // EXEC holds the lock already
+            let _guard = match in_exec {
+                true => None,
+                false => Some(store.command_guard().await),
+            };
+
+            let mut db_guard = db.write_live(&[&source, &destination]).await;
+
+            match list::move_value(&mut db_guard, &source, &destination, from, to) {
+                Ok(Some(value)) => {
+                    list::notify_move(db, &db_guard, &source, &destination, from, to);
+                    drop(db_guard);
+                    // Clients blocked on the destination get served once the command is done
+                    db.blocked.signal(&destination);
+                    conn.write_chunk(DataType::BulkString, &value).await?;
+                    return Ok(());
+                }
+                Ok(None) if in_exec => {
+                    drop(db_guard);
+                    conn.write_null().await?;
+                    return Ok(());
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    drop(db_guard);
+                    conn.write_error(&err).await?;
+                    return Ok(());
+                }
+            }
+
+            // Blocked while the keys are still locked, so that a push can't sneak in before
+            let op = BlockedOp::Move {
+                from,
+                destination,
+                to,
+            };
+            db.blocked.block(vec![source], op)
+        };
+
+        match blocked.wait(timeout, conn, shutdown, blocked_for).await? {
+            Some(Ok((_, value))) => conn.write_chunk(DataType::BulkString, &value).await?,
+            Some(Err(err)) => conn.write_error(&err).await?,
+            None => conn.write_null().await?,
+        }
+
+        Ok(())
+    }
+}
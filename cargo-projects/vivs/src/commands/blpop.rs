@@ -0,0 +1,142 @@
+use crate::{
+    blocking::{parse_timeout, BlockedOp},
+    commands::DataType,
+    error::CommandError,
+    list::{self, End},
+    parser::Parser,
+    Connection, DataStore, Db, GenericResult, Shutdown,
+};
+use bytes::Bytes;
+use log::info;
+use std::time::Duration;
+
+pub const BLPOP_CMD: &str = "blpop";
+pub const BRPOP_CMD: &str = "brpop";
+
+#[derive(Debug)]
+struct BlockingPopArgs {
+    keys: Vec<Bytes>,
+    timeout: Option<Duration>,
+}
+
+/// BLPOP key [key ...] timeout / BRPOP key [key ...] timeout pop a value from the head / tail
+/// of the first list that is not empty. When all of them are empty, the client is blocked
+/// until a value is pushed to one of them or the timeout (in seconds, 0 blocks forever) passes.
+///
+/// Replies with the key and the value, null on timeout.
+#[derive(Debug)]
+pub struct BlockingPop {
+    end: End,
+    args: Result<BlockingPopArgs, CommandError>,
+}
+
+fn name(end: End) -> &'static str {
+    match end {
+        End::Left => BLPOP_CMD,
+        End::Right => BRPOP_CMD,
+    }
+}
+
+fn parse_args(mut data: Parser, end: End) -> Result<BlockingPopArgs, CommandError> {
+    let mut keys = vec![];
+    while let Ok(Some(key)) = data.next_bytes() {
+        keys.push(key);
+    }
+
+    // The timeout comes last
+    let timeout = keys.pop().filter(|_| !keys.is_empty());
+    let Some(timeout) = timeout else {
+        return Err(CommandError::WrongArity(name(end)));
+    };
+
+    let timeout = parse_timeout(&String::from_utf8_lossy(&timeout))?;
+
+    Ok(BlockingPopArgs { keys, timeout })
+}
+
+impl BlockingPop {
+    pub fn parse(data: Parser, end: End) -> Self {
+        Self {
+            end,
+            args: parse_args(data, end),
+        }
+    }
+
+    /// Inside of MULTI / EXEC (`in_exec`) the client is never blocked, same as in Redis
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        db: &Db,
+        store: &DataStore,
+        shutdown: &Shutdown,
+        in_exec: bool,
+        blocked_for: &mut Duration,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            name(self.end).to_uppercase(),
+            self.args
+        );
+
+        let BlockingPopArgs { keys, timeout } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let blocked = {
+            // EXEC holds the lock already
+            let _guard = match in_exec {
+                true => None,
+                false => Some(store.command_guard().await),
+            };
+
+            let live_keys = keys.iter().map(|key| &key[..]).collect::<Vec<_>>();
+            let mut db_guard = db.write_live(&live_keys).await;
+
+            for key in &keys {
+                match list::pop(&mut db_guard, key, self.end) {
+                    Ok(Some(value)) => {
+                        list::notify_pop(db, &db_guard, key, self.end);
+                        drop(db_guard);
+                        return write_served(conn, key, &value).await;
+                    }
+                    Ok(None) => continue,
+                    Err(err) => {
+                        drop(db_guard);
+                        conn.write_error(&err).await?;
+                        return Ok(());
+                    }
+                }
+            }
+
+            if in_exec {
+                drop(db_guard);
+                conn.write_null().await?;
+                return Ok(());
+            }
+
+            // Blocked while the keys are still locked, so that a push can't sneak in before
+            db.blocked.block(keys, BlockedOp::Pop(self.end))
+        };
+
+        match blocked.wait(timeout, conn, shutdown, blocked_for).await? {
+            Some(Ok((key, value))) => write_served(conn, &key, &value).await?,
+            Some(Err(err)) => conn.write_error(&err).await?,
+            None => conn.write_null().await?,
+        }
+
+        Ok(())
+    }
+}
+
+async fn write_served(conn: &mut Connection, key: &[u8], value: &[u8]) -> GenericResult<()> {
+    conn.write_array_len(2).await?;
+    conn.write_chunk(DataType::BulkString, key).await?;
+    conn.write_chunk(DataType::BulkString, value).await?;
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use crate::{
+    clients::{KillFilter, PauseMode},
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Clients, Connection, GenericResult,
+};
+use log::info;
+use std::time::Duration;
+
+pub const CLIENT_CMD: &str = "client";
+
+#[derive(Debug)]
+pub enum Subcommand {
+    /// CLIENT LIST [ID id ...]
+    List(Vec<u64>),
+    /// CLIENT INFO
+    Info,
+    /// CLIENT ID
+    Id,
+    /// CLIENT SETNAME <name>
+    SetName(String),
+    /// CLIENT GETNAME
+    GetName,
+    /// CLIENT KILL <ip:port> (old form, replies with OK)
+    /// or CLIENT KILL <ID id | ADDR ip:port | LADDR ip:port | USER user | SKIPME yes/no> ...
+    Kill { filter: KillFilter, old_form: bool },
+    /// CLIENT PAUSE <timeout in ms> [WRITE | ALL]
+    Pause(Duration, PauseMode),
+    /// CLIENT UNPAUSE
+    Unpause,
+}
+
+/// CLIENT command inspects and controls connections to the server.
+#[derive(Debug)]
+pub struct Client {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+/// Non UTF-8 arguments are treated as a syntax error
+fn next_arg(data: &mut Parser) -> Result<Option<String>, CommandError> {
+    data.next_as_str().map_err(|_| CommandError::Syntax)
+}
+
+fn parse_kill_filter(data: &mut Parser) -> Result<KillFilter, CommandError> {
+    let mut filter = KillFilter {
+        skip_me: true,
+        ..Default::default()
+    };
+
+    while let Some(option) = next_arg(data)? {
+        let Some(value) = next_arg(data)? else {
+            return Err(CommandError::Syntax);
+        };
+
+        match &option.to_lowercase()[..] {
+            "id" => filter.id = Some(value.parse::<u64>().map_err(|_| CommandError::NotInteger)?),
+            "addr" => filter.addr = Some(value),
+            "laddr" => filter.laddr = Some(value),
+            "user" => filter.user = Some(value),
+            "skipme" => match &value.to_lowercase()[..] {
+                "yes" => filter.skip_me = true,
+                "no" => filter.skip_me = false,
+                _ => return Err(CommandError::Syntax),
+            },
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    Ok(filter)
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Some(subcommand) = next_arg(&mut data)? else {
+        return Err(CommandError::WrongArity(CLIENT_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "list" => {
+            let mut ids = vec![];
+
+            if let Some(option) = next_arg(&mut data)? {
+                if option.to_lowercase() != "id" {
+                    return Err(CommandError::Syntax);
+                }
+
+                while let Some(id) = next_arg(&mut data)? {
+                    ids.push(id.parse::<u64>().map_err(|_| CommandError::NotInteger)?);
+                }
+
+                if ids.is_empty() {
+                    return Err(CommandError::WrongArity(CLIENT_CMD));
+                }
+            }
+
+            Subcommand::List(ids)
+        }
+        "info" => Subcommand::Info,
+        "id" => Subcommand::Id,
+        "getname" => Subcommand::GetName,
+        "setname" => {
+            let Some(name) = next_arg(&mut data)? else {
+                return Err(CommandError::WrongArity(CLIENT_CMD));
+            };
+
+            // Names end up in CLIENT LIST output which is space separated
+            if name.chars().any(|char| char.is_whitespace()) {
+                return Err(CommandError::Syntax);
+            }
+
+            Subcommand::SetName(name)
+        }
+        "kill" => {
+            if data.size() == 0 {
+                return Err(CommandError::WrongArity(CLIENT_CMD));
+            }
+
+            // Old form: CLIENT KILL <ip:port>
+            if data.size() == 1 {
+                let addr = next_arg(&mut data)?;
+                let filter = KillFilter {
+                    addr,
+                    ..Default::default()
+                };
+
+                Subcommand::Kill {
+                    filter,
+                    old_form: true,
+                }
+            } else {
+                // New form skips the client that issued the command by default
+                let filter = parse_kill_filter(&mut data)?;
+
+                Subcommand::Kill {
+                    filter,
+                    old_form: false,
+                }
+            }
+        }
+        "pause" => {
+            let Some(timeout) = next_arg(&mut data)? else {
+                return Err(CommandError::WrongArity(CLIENT_CMD));
+            };
+            let timeout = timeout
+                .parse::<u64>()
+                .map_err(|_| CommandError::NotInteger)?;
+
+            let mode = match next_arg(&mut data)?.map(|mode| mode.to_lowercase()) {
+                None => PauseMode::All,
+                Some(mode) if mode == "all" => PauseMode::All,
+                Some(mode) if mode == "write" => PauseMode::Write,
+                Some(_) => return Err(CommandError::Syntax),
+            };
+
+            Subcommand::Pause(Duration::from_millis(timeout), mode)
+        }
+        "unpause" => Subcommand::Unpause,
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: CLIENT_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    // Anything left over means too many arguments were passed in
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(CLIENT_CMD));
+    }
+
+    Ok(subcommand)
+}
+
+impl Client {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        clients: &Clients,
+        own_id: u64,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            CLIENT_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            Subcommand::List(ids) => {
+                let list = clients
+                    .list(&ids)
+                    .await
+                    .iter()
+                    .map(|client| format!("{}\n", client.format()))
+                    .collect::<String>();
+
+                conn.write_chunk(DataType::BulkString, list.as_bytes())
+                    .await?;
+            }
+            Subcommand::Info => {
+                let info = clients
+                    .get(own_id)
+                    .await
+                    .map(|client| format!("{}\n", client.format()))
+                    .unwrap_or_default();
+
+                conn.write_chunk(DataType::BulkString, info.as_bytes())
+                    .await?;
+            }
+            Subcommand::Id => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(own_id))
+                    .await?;
+            }
+            Subcommand::SetName(name) => {
+                // An empty name removes the name from the connection
+                let name = if name.is_empty() { None } else { Some(name) };
+                clients.set_name(own_id, name).await;
+
+                conn.write_chunk(DataType::SimpleString, b"OK").await?;
+            }
+            Subcommand::GetName => match clients.get(own_id).await.and_then(|client| client.name) {
+                Some(name) => {
+                    conn.write_chunk(DataType::BulkString, name.as_bytes())
+                        .await?
+                }
+                None => conn.write_null().await?,
+            },
+            Subcommand::Kill { filter, old_form } => {
+                let killed = clients.kill(&filter, own_id).await;
+
+                if old_form {
+                    if killed == 0 {
+                        let error = CommandError::Other("No such client".to_owned());
+                        conn.write_error(&error).await?;
+                    } else {
+                        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+                    }
+                } else {
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(killed as u64))
+                        .await?;
+                }
+            }
+            Subcommand::Pause(timeout, mode) => {
+                clients.pause(timeout, mode).await;
+                conn.write_chunk(DataType::SimpleString, b"OK").await?;
+            }
+            Subcommand::Unpause => {
+                clients.unpause().await;
+                conn.write_chunk(DataType::SimpleString, b"OK").await?;
+            }
+        }
+
+        Ok(())
+    }
+}
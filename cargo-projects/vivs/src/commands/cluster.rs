@@ -0,0 +1,143 @@
+use crate::{
+    cluster::{key_slot, Topology},
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const CLUSTER_CMD: &str = "cluster";
+
+const CLUSTER_DISABLED_ERR: &str = "This instance has cluster support disabled";
+
+#[derive(Debug)]
+pub enum Subcommand {
+    /// CLUSTER SLOTS
+    Slots,
+    /// CLUSTER KEYSLOT <key>
+    KeySlot(Bytes),
+    /// CLUSTER RELOAD, re-reads the <port>.toml cluster config (sent by `vivs-repl --cluster` admin commands)
+    Reload,
+}
+
+/// CLUSTER command lets clients find out which node serves which hash slots.
+#[derive(Debug)]
+pub struct ClusterCmd {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Ok(Some(subcommand)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(CLUSTER_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "slots" => Subcommand::Slots,
+        "keyslot" => match data.next_bytes() {
+            Ok(Some(key)) => Subcommand::KeySlot(key),
+            _ => return Err(CommandError::WrongArity(CLUSTER_CMD)),
+        },
+        "reload" => Subcommand::Reload,
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: CLUSTER_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(CLUSTER_CMD));
+    }
+
+    Ok(subcommand)
+}
+
+impl ClusterCmd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, topology: &Topology) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            CLUSTER_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            Subcommand::KeySlot(key) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(key_slot(&key).into()))
+                    .await?;
+            }
+            Subcommand::Reload => {
+                let port = conn.own_addr()?.port();
+
+                // Replies with the epoch of the topology
+                match topology.reload(port).await {
+                    Ok(epoch) => {
+                        conn.write_chunk(DataType::Integer, &u64_as_bytes(epoch))
+                            .await?
+                    }
+                    Err(e) => {
+                        conn.write_error(&CommandError::Other(e.to_string()))
+                            .await?
+                    }
+                }
+            }
+            Subcommand::Slots => {
+                // No cluster topology means that the cluster mode is off
+                let Some(nodes) = topology.nodes() else {
+                    let error = CommandError::NotAllowed(CLUSTER_DISABLED_ERR.to_owned());
+                    conn.write_error(&error).await?;
+                    return Ok(());
+                };
+
+                // A node that got resharded serves more than one range
+                let mut ranges = nodes
+                    .values()
+                    .flat_map(|node| node.slots.iter().map(move |range| (*range, node)))
+                    .collect::<Vec<_>>();
+                ranges.sort_by_key(|((start, _), _)| *start);
+
+                // Same as in Redis, each entry is an array of:
+                // start slot, end slot, [ip, port, node id] of the node serving the slots
+                conn.write_array_len(ranges.len()).await?;
+                for ((start, end), node) in ranges {
+                    let (ip, port) = node.ip.rsplit_once(':').unwrap_or((&node.ip, "0"));
+                    let port = port.parse::<u64>().unwrap_or_default();
+
+                    conn.write_array_len(3).await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(start as u64))
+                        .await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(end as u64))
+                        .await?;
+
+                    conn.write_array_len(3).await?;
+                    conn.write_chunk(DataType::BulkString, ip.as_bytes())
+                        .await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(port))
+                        .await?;
+                    conn.write_chunk(DataType::BulkString, node.id.as_bytes())
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    commands::{
+        registry::{lookup, CommandSpec, KeySpec, COMMAND_TABLE},
+        DataType,
+    },
+    error::CommandError,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const COMMAND_CMD: &str = "command";
+
+#[derive(Debug)]
+pub enum Subcommand {
+    /// COMMAND (or COMMAND INFO), details of every command
+    All,
+    /// COMMAND COUNT
+    Count,
+    /// COMMAND INFO [command ...]
+    Info(Vec<String>),
+    /// COMMAND GETKEYS <command> [arg ...]
+    GetKeys(String, Vec<Bytes>),
+}
+
+/// COMMAND describes the commands Vivs knows about (arity, flags and key positions).
+#[derive(Debug)]
+pub struct CommandCmd {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Ok(subcommand) = data.next_as_str() else {
+        return Err(CommandError::Syntax);
+    };
+
+    let Some(subcommand) = subcommand else {
+        return Ok(Subcommand::All);
+    };
+
+    match &subcommand.to_lowercase()[..] {
+        "count" if data.size() == 0 => Ok(Subcommand::Count),
+        "count" => Err(CommandError::WrongArity("command|count")),
+        "info" => {
+            let mut names = vec![];
+            while let Ok(Some(name)) = data.next_as_str() {
+                names.push(name);
+            }
+            // Same as COMMAND when no commands are passed in
+            if names.is_empty() {
+                return Ok(Subcommand::All);
+            }
+            Ok(Subcommand::Info(names))
+        }
+        "getkeys" => {
+            let Ok(Some(name)) = data.next_as_str() else {
+                return Err(CommandError::WrongArity("command|getkeys"));
+            };
+
+            let mut args = vec![];
+            while let Ok(Some(arg)) = data.next_bytes() {
+                args.push(arg);
+            }
+            Ok(Subcommand::GetKeys(name, args))
+        }
+        _ => Err(CommandError::UnknownSubcommand {
+            command: COMMAND_CMD,
+            subcommand,
+        }),
+    }
+}
+
+/// Writes the details of a command, same as in Redis:
+/// name, arity, flags, first key, last key and step between keys.
+/// Keys that can't be found by their position are reported as 0 with the movablekeys flag.
+async fn write_spec(conn: &mut Connection, spec: &CommandSpec) -> GenericResult<()> {
+    conn.write_array_len(6).await?;
+    conn.write_chunk(DataType::BulkString, spec.name.as_bytes())
+        .await?;
+    conn.write_chunk(DataType::Integer, &u64_as_bytes(spec.arity as u64))
+        .await?;
+
+    let movable = spec.keys.is_movable();
+    conn.write_array_len(spec.flags.len() + usize::from(movable))
+        .await?;
+    for flag in spec.flags {
+        conn.write_chunk(DataType::BulkString, flag.name().as_bytes())
+            .await?;
+    }
+    if movable {
+        conn.write_chunk(DataType::BulkString, b"movablekeys")
+            .await?;
+    }
+
+    let KeySpec {
+        first, last, step, ..
+    } = match movable {
+        true => KeySpec::NONE,
+        false => spec.keys,
+    };
+    conn.write_chunk(DataType::Integer, &u64_as_bytes(first as u64))
+        .await?;
+    conn.write_chunk(DataType::Integer, &u64_as_bytes(last as u64))
+        .await?;
+    conn.write_chunk(DataType::Integer, &u64_as_bytes(step as u64))
+        .await?;
+
+    Ok(())
+}
+
+impl CommandCmd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            COMMAND_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            Subcommand::All => {
+                conn.write_array_len(COMMAND_TABLE.len()).await?;
+                for spec in COMMAND_TABLE {
+                    write_spec(conn, spec).await?;
+                }
+            }
+            Subcommand::Count => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(COMMAND_TABLE.len() as u64))
+                    .await?;
+            }
+            // Unknown commands are null, same as in Redis
+            Subcommand::Info(names) => {
+                conn.write_array_len(names.len()).await?;
+                for name in names {
+                    match lookup(&name) {
+                        Some(spec) => write_spec(conn, spec).await?,
+                        None => conn.write_null().await?,
+                    }
+                }
+            }
+            Subcommand::GetKeys(name, args) => {
+                let Some(spec) = lookup(&name) else {
+                    let error = CommandError::Other("Invalid command specified".to_owned());
+                    conn.write_error(&error).await?;
+                    return Ok(());
+                };
+
+                if !spec.arity_matches(args.len() + 1) {
+                    let error = CommandError::Other(
+                        "Invalid number of arguments specified for command".to_owned(),
+                    );
+                    conn.write_error(&error).await?;
+                    return Ok(());
+                }
+
+                let keys = spec.keys(&args);
+                if keys.is_empty() {
+                    let error = CommandError::Other("The command has no key arguments".to_owned());
+                    conn.write_error(&error).await?;
+                    return Ok(());
+                }
+
+                conn.write_array_len(keys.len()).await?;
+                for key in keys {
+                    conn.write_chunk(DataType::BulkString, &key).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{
+    commands::DataType, config::RuntimeConfig, error::CommandError, parser::Parser, Connection,
+    DataStore, GenericResult,
+};
+use log::info;
+
+pub const CONFIG_CMD: &str = "config";
+
+#[derive(Debug)]
+pub enum Subcommand {
+    /// CONFIG GET <pattern> [pattern ...]
+    Get(Vec<String>),
+    /// CONFIG SET <parameter> <value> [parameter value ...]
+    Set(Vec<(String, String)>),
+    /// CONFIG REWRITE
+    Rewrite,
+}
+
+/// CONFIG command reads and changes the parameters of the server while it is running,
+/// REWRITE writes them to the config file the server was started with.
+#[derive(Debug)]
+pub struct ConfigCmd {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+/// Non UTF-8 arguments are treated as a syntax error
+fn next_arg(data: &mut Parser) -> Result<Option<String>, CommandError> {
+    data.next_as_str().map_err(|_| CommandError::Syntax)
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Some(subcommand) = next_arg(&mut data)? else {
+        return Err(CommandError::WrongArity(CONFIG_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "get" => {
+            let mut patterns = vec![];
+            while let Some(pattern) = next_arg(&mut data)? {
+                patterns.push(pattern);
+            }
+
+            if patterns.is_empty() {
+                return Err(CommandError::WrongArity("config|get"));
+            }
+
+            Subcommand::Get(patterns)
+        }
+        "set" => {
+            let mut params = vec![];
+            while let Some(name) = next_arg(&mut data)? {
+                let Some(value) = next_arg(&mut data)? else {
+                    return Err(CommandError::WrongArity("config|set"));
+                };
+                params.push((name, value));
+            }
+
+            if params.is_empty() {
+                return Err(CommandError::WrongArity("config|set"));
+            }
+
+            Subcommand::Set(params)
+        }
+        "rewrite" if data.size() == 0 => Subcommand::Rewrite,
+        "rewrite" => return Err(CommandError::WrongArity("config|rewrite")),
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: CONFIG_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    Ok(subcommand)
+}
+
+impl ConfigCmd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        config: &RuntimeConfig,
+        db: &DataStore,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            CONFIG_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let result = match subcommand {
+            Subcommand::Get(patterns) => {
+                // Parameters that can't be changed without a restart come from the server itself
+                let addr = conn.own_addr()?;
+                let cluster_enabled = if db.is_cluster() { "yes" } else { "no" };
+                let immutable = [
+                    ("bind", addr.ip().to_string()),
+                    ("port", addr.port().to_string()),
+                    ("databases", db.len().to_string()),
+                    ("cluster-enabled", cluster_enabled.to_owned()),
+                ];
+
+                // Flat list of parameter names and values
+                let params = config.get(&patterns, &immutable);
+                conn.write_array_len(params.len() * 2).await?;
+                for (name, value) in params {
+                    conn.write_chunk(DataType::BulkString, name.as_bytes())
+                        .await?;
+                    conn.write_chunk(DataType::BulkString, value.as_bytes())
+                        .await?;
+                }
+
+                return Ok(());
+            }
+            Subcommand::Set(params) => config.set(&params),
+            Subcommand::Rewrite => config.rewrite(),
+        };
+
+        match result {
+            Ok(()) => conn.write_chunk(DataType::SimpleString, b"OK").await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -1,28 +1,31 @@
 use super::CommonCommand;
 use crate::{
-    commands::ARGS_NUM, parser::Parser, utils::u64_as_bytes, Connection, DataStore, GenericResult,
+    error::CommandError, notifications::EventClass, parser::Parser, utils::u64_as_bytes,
+    Connection, Db, GenericResult,
 };
+use bytes::Bytes;
 use log::info;
 
 pub const DELETE_CMD: &str = "delete";
 
 #[derive(Debug, Default)]
 pub struct Delete {
-    key: Option<String>,
+    pub key: Option<Bytes>,
 }
 
 impl CommonCommand for Delete {
     fn parse(mut data: Parser) -> Self {
-        let Ok(key) = data.next_as_str() else {
+        let Ok(key) = data.next_bytes() else {
             return Self { key: None };
         };
 
         Self { key }
     }
 
-    async fn respond(&self, conn: &mut Connection, db: &DataStore) -> GenericResult<()> {
+    async fn respond(&self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
         let Some(key) = self.key.as_ref() else {
-            conn.write_error(ARGS_NUM.as_bytes()).await?;
+            conn.write_error(&CommandError::WrongArity(DELETE_CMD))
+                .await?;
             return Ok(());
         };
 
@@ -31,6 +34,7 @@ impl CommonCommand for Delete {
         if let Some(_value) = db_guard.remove(key) {
             let mut expiries_guard = db.expirations.write().await;
             expiries_guard.remove(key);
+            db.notify(EventClass::Generic, "del", key);
 
             let total_entries_deleted = u64_as_bytes(1);
             conn.write_chunk(super::DataType::Integer, &total_entries_deleted)
@@ -47,7 +51,7 @@ impl CommonCommand for Delete {
                 "{:?} {:?} {:?}",
                 conn.connected_peer_addr(),
                 DELETE_CMD.to_uppercase(),
-                self.key.as_ref().unwrap()
+                key
             )
         );
 
@@ -0,0 +1,62 @@
+use crate::{
+    commands::DataType, dump, error::CommandError, parser::Parser, stream::now_ms, Connection, Db,
+    GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const DUMP_CMD: &str = "dump";
+
+/// DUMP key serializes the value of the key together with its time to live,
+/// RESTORE turns the payload back into a key (on this or any other Vivs server).
+///
+/// Replies with the payload, null if the key does not exist.
+#[derive(Debug)]
+pub struct Dump {
+    key: Result<Bytes, CommandError>,
+}
+
+impl Dump {
+    pub fn parse(mut data: Parser) -> Self {
+        let key = match data.next_bytes() {
+            Ok(Some(key)) if data.size() == 0 => Ok(key),
+            _ => Err(CommandError::WrongArity(DUMP_CMD)),
+        };
+
+        Self { key }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            DUMP_CMD.to_uppercase(),
+            self.key
+        );
+
+        let key = match self.key {
+            Ok(key) => key,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let payload = {
+            let db_guard = db.write_live(&[&key]).await;
+            let expiry = db.expirations.read().await.get(&key).copied();
+
+            // Milliseconds that are left, so that the payload doesn't depend on the clock of the server
+            let ttl =
+                expiry.map(|expiry| expiry.saturating_mul(1000).saturating_sub(now_ms()).max(1));
+            db_guard.get(&key).map(|value| dump::dump(value, ttl))
+        };
+
+        match payload {
+            Some(payload) => conn.write_chunk(DataType::BulkString, &payload).await?,
+            None => conn.write_null().await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    commands::DataType, error::CommandError, notifications::EventClass, parser::Parser,
+    utils::u64_as_bytes, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const EXPIRE_CMD: &str = "expire";
+
+const INVALID_EXPIRE_ERR: &str = "invalid expire time in 'expire' command";
+
+#[derive(Debug)]
+struct ExpireArgs {
+    key: Bytes,
+    seconds: i64,
+}
+
+/// EXPIRE key seconds sets the time to live of the key, same as in Redis
+/// a time that is not positive removes the key straight away.
+///
+/// Replies with 1 if the key exists, 0 otherwise.
+#[derive(Debug)]
+pub struct Expire {
+    args: Result<ExpireArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<ExpireArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(seconds))) = (data.next_bytes(), data.next_as_str()) else {
+        return Err(CommandError::WrongArity(EXPIRE_CMD));
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(EXPIRE_CMD));
+    }
+
+    let seconds = seconds.parse().map_err(|_| CommandError::NotInteger)?;
+
+    Ok(ExpireArgs { key, seconds })
+}
+
+impl Expire {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            EXPIRE_CMD.to_uppercase(),
+            self.args
+        );
+
+        let ExpireArgs { key, seconds } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        // Unix time (in seconds) when the key expires, None removes the key
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let expiry = match u64::try_from(seconds) {
+            Ok(0) | Err(_) => None,
+            Ok(seconds) => match now.checked_add(seconds) {
+                Some(expiry) => Some(expiry),
+                None => {
+                    let error = CommandError::Other(INVALID_EXPIRE_ERR.to_owned());
+                    conn.write_error(&error).await?;
+                    return Ok(());
+                }
+            },
+        };
+
+        let exists = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            let mut expiries_guard = db.expirations.write().await;
+            let exists = db_guard.contains_key(&key);
+
+            match expiry {
+                Some(expiry) if exists => {
+                    expiries_guard.insert(key.clone(), expiry);
+                    db.notify(EventClass::Generic, EXPIRE_CMD, &key);
+                }
+                None if exists => {
+                    db_guard.remove(&key);
+                    expiries_guard.remove(&key);
+                    db.notify(EventClass::Generic, "del", &key);
+                }
+                _ => {}
+            }
+
+            exists
+        };
+
+        conn.write_chunk(DataType::Integer, &u64_as_bytes(exists as u64))
+            .await?;
+
+        Ok(())
+    }
+}
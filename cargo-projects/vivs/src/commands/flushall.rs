@@ -0,0 +1,48 @@
+use crate::{
+    commands::{
+        flushdb::{parse_mode, FlushMode},
+        DataType,
+    },
+    error::CommandError,
+    parser::Parser,
+    Connection, DataStore, GenericResult,
+};
+use log::info;
+
+pub const FLUSHALL_CMD: &str = "flushall";
+
+/// FLUSHALL [ASYNC | SYNC] removes every key of every database.
+#[derive(Debug)]
+pub struct FlushAll {
+    mode: Result<FlushMode, CommandError>,
+}
+
+impl FlushAll {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            mode: parse_mode(data, FLUSHALL_CMD),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, store: &DataStore) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            FLUSHALL_CMD.to_uppercase(),
+            self.mode
+        );
+
+        let mode = match self.mode {
+            Ok(mode) => mode,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        store.flush_all(mode == FlushMode::Async).await;
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
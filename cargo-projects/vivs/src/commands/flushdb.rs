@@ -0,0 +1,74 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, Connection, Db, GenericResult,
+};
+use log::info;
+
+pub const FLUSHDB_CMD: &str = "flushdb";
+
+/// How FLUSHDB / FLUSHALL free the memory
+#[derive(Debug, Default, PartialEq)]
+pub enum FlushMode {
+    /// Keys are freed before the reply is sent
+    #[default]
+    Sync,
+    /// Keys are removed straight away but freed in the background
+    Async,
+}
+
+/// Parses [ASYNC | SYNC] of the `command`
+pub(crate) fn parse_mode(
+    mut data: Parser,
+    command: &'static str,
+) -> Result<FlushMode, CommandError> {
+    let mode = match data.next_as_str() {
+        Ok(None) => FlushMode::Sync,
+        Ok(Some(mode)) => match &mode.to_lowercase()[..] {
+            "sync" => FlushMode::Sync,
+            "async" => FlushMode::Async,
+            _ => return Err(CommandError::Syntax),
+        },
+        Err(_) => return Err(CommandError::Syntax),
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(command));
+    }
+
+    Ok(mode)
+}
+
+/// FLUSHDB [ASYNC | SYNC] removes every key of the selected database.
+#[derive(Debug)]
+pub struct FlushDb {
+    mode: Result<FlushMode, CommandError>,
+}
+
+impl FlushDb {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            mode: parse_mode(data, FLUSHDB_CMD),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            FLUSHDB_CMD.to_uppercase(),
+            self.mode
+        );
+
+        let mode = match self.mode {
+            Ok(mode) => mode,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        db.flush(mode == FlushMode::Async).await;
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    commands::DataType,
+    db::Value,
+    error::CommandError,
+    geo::Point,
+    notifications::EventClass,
+    parser::Parser,
+    sorted_set::{self, SortedSet},
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GEOADD_CMD: &str = "geoadd";
+
+// Same as in Redis, geo members are sorted set members so the event is the one of ZADD
+const ZADD_EVENT: &str = "zadd";
+
+/// Which members get changed, NX only adds new members and XX only updates existing ones
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Condition {
+    Always,
+    Nx,
+    Xx,
+}
+
+#[derive(Debug)]
+struct GeoaddArgs {
+    key: Bytes,
+    condition: Condition,
+    changed: bool,
+    members: Vec<(Point, Bytes)>,
+}
+
+/// GEOADD key [NX|XX] [CH] longitude latitude member [longitude latitude member ...]
+/// adds the members to the geospatial index (a sorted set with 52 bit geohash scores).
+///
+/// Replies with the number of members that were added, with CH the number of members that were added or moved.
+#[derive(Debug)]
+pub struct Geoadd {
+    args: Result<GeoaddArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<GeoaddArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(GEOADD_CMD));
+    };
+
+    let mut condition = Condition::Always;
+    let mut changed = false;
+    let mut args = vec![];
+    while let Some(arg) = data.next_bytes().map_err(|_| CommandError::Syntax)? {
+        // Options come before the first longitude
+        if args.is_empty() {
+            match &arg.to_ascii_lowercase()[..] {
+                b"nx" if condition != Condition::Xx => {
+                    condition = Condition::Nx;
+                    continue;
+                }
+                b"xx" if condition != Condition::Nx => {
+                    condition = Condition::Xx;
+                    continue;
+                }
+                b"nx" | b"xx" => return Err(CommandError::Syntax),
+                b"ch" => {
+                    changed = true;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        args.push(arg);
+    }
+
+    if args.is_empty() || args.len() % 3 != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    let mut members = vec![];
+    for chunk in args.chunks_exact(3) {
+        let point = Point::parse(
+            &String::from_utf8_lossy(&chunk[0]),
+            &String::from_utf8_lossy(&chunk[1]),
+        )?;
+        members.push((point, chunk[2].clone()));
+    }
+
+    Ok(GeoaddArgs {
+        key,
+        condition,
+        changed,
+        members,
+    })
+}
+
+/// Adds or moves the members, returns how many were added and how many were moved
+fn add(set: &mut SortedSet, members: Vec<(Point, Bytes)>, condition: Condition) -> (u64, u64) {
+    let (mut added, mut moved) = (0, 0);
+    for (point, member) in members {
+        let score = point.score() as f64;
+        match (set.score(&member), condition) {
+            (None, Condition::Xx) | (Some(_), Condition::Nx) => {}
+            (None, _) => {
+                set.insert(member, score);
+                added += 1;
+            }
+            (Some(old), _) if old != score => {
+                set.insert(member, score);
+                moved += 1;
+            }
+            (Some(_), _) => {}
+        }
+    }
+
+    (added, moved)
+}
+
+impl Geoadd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GEOADD_CMD.to_uppercase(),
+            self.args
+        );
+
+        let GeoaddArgs {
+            key,
+            condition,
+            changed,
+            members,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let counts = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            let counts = sorted_set::get_or_create(&mut db_guard, &key)
+                .map(|set| add(set, members, condition));
+
+            // XX does not create the key
+            if matches!(db_guard.get(&key), Some(Value::SortedSet(set)) if set.is_empty()) {
+                db_guard.remove(&key);
+            }
+
+            counts
+        };
+
+        match counts {
+            Ok((added, moved)) => {
+                if added + moved > 0 {
+                    db.notify(EventClass::SortedSet, ZADD_EVENT, &key);
+                }
+
+                let count = if changed { added + moved } else { added };
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(count))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    geo::{self, Point, Unit},
+    parser::Parser,
+    sorted_set, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GEODIST_CMD: &str = "geodist";
+
+#[derive(Debug)]
+struct GeodistArgs {
+    key: Bytes,
+    from: Bytes,
+    to: Bytes,
+    unit: Unit,
+}
+
+/// GEODIST key member1 member2 [M|KM|FT|MI] replies with the distance between two members
+/// (in meters by default), null if either of them is not in the index.
+#[derive(Debug)]
+pub struct Geodist {
+    args: Result<GeodistArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<GeodistArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(from)), Ok(Some(to))) =
+        (data.next_bytes(), data.next_bytes(), data.next_bytes())
+    else {
+        return Err(CommandError::WrongArity(GEODIST_CMD));
+    };
+
+    let unit = match data.next_as_str() {
+        Ok(Some(unit)) => Unit::parse(&unit)?,
+        Ok(None) => Unit::METERS,
+        Err(_) => return Err(CommandError::Syntax),
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(GeodistArgs {
+        key,
+        from,
+        to,
+        unit,
+    })
+}
+
+impl Geodist {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GEODIST_CMD.to_uppercase(),
+            self.args
+        );
+
+        let GeodistArgs {
+            key,
+            from,
+            to,
+            unit,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let distance = {
+            let db_guard = db.write_live(&[&key]).await;
+            sorted_set::get(&db_guard, &key).map(|set| {
+                let set = set?;
+                let from = Point::from_score(set.score(&from)? as u64);
+                let to = Point::from_score(set.score(&to)? as u64);
+                Some(from.distance(to))
+            })
+        };
+
+        match distance {
+            Ok(Some(distance)) => {
+                let distance = geo::format_distance(distance, unit);
+                conn.write_chunk(DataType::BulkString, distance.as_bytes())
+                    .await?
+            }
+            Ok(None) => conn.write_null().await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
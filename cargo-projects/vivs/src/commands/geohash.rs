@@ -0,0 +1,93 @@
+use crate::{
+    commands::DataType, error::CommandError, geo::Point, parser::Parser, sorted_set, Connection,
+    Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GEOHASH_CMD: &str = "geohash";
+
+#[derive(Debug)]
+struct GeohashArgs {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+/// GEOHASH key [member ...] replies with the standard 11 character geohash of each member
+/// (the ones geohash.org uses), null for members that are not in the index.
+#[derive(Debug)]
+pub struct Geohash {
+    args: Result<GeohashArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<GeohashArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(GEOHASH_CMD));
+    };
+
+    let mut members = vec![];
+    while let Some(member) = data.next_bytes().map_err(|_| CommandError::Syntax)? {
+        members.push(member);
+    }
+
+    Ok(GeohashArgs { key, members })
+}
+
+impl Geohash {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GEOHASH_CMD.to_uppercase(),
+            self.args
+        );
+
+        let GeohashArgs { key, members } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let hashes = {
+            let db_guard = db.write_live(&[&key]).await;
+            sorted_set::get(&db_guard, &key).map(|set| {
+                members
+                    .iter()
+                    .map(|member| {
+                        let score = set.and_then(|set| set.score(member))?;
+                        Some(Point::from_score(score as u64).geohash())
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let hashes = match hashes {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        conn.write_array_len(hashes.len()).await?;
+        for hash in hashes {
+            match hash {
+                Some(hash) => {
+                    conn.write_chunk(DataType::BulkString, hash.as_bytes())
+                        .await?
+                }
+                None => conn.write_null().await?,
+            }
+        }
+
+        Ok(())
+    }
+}
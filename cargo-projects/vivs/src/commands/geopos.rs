@@ -0,0 +1,98 @@
+use crate::{
+    commands::DataType, error::CommandError, geo::Point, parser::Parser, sorted_set, Connection,
+    Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GEOPOS_CMD: &str = "geopos";
+
+#[derive(Debug)]
+struct GeoposArgs {
+    key: Bytes,
+    members: Vec<Bytes>,
+}
+
+/// GEOPOS key [member ...] replies with the longitude and latitude of each member,
+/// null for members that are not in the index.
+///
+/// Positions are the centers of the geohash cells, so they can be up to 0.6 meters off from what was added.
+#[derive(Debug)]
+pub struct Geopos {
+    args: Result<GeoposArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<GeoposArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(GEOPOS_CMD));
+    };
+
+    let mut members = vec![];
+    while let Some(member) = data.next_bytes().map_err(|_| CommandError::Syntax)? {
+        members.push(member);
+    }
+
+    Ok(GeoposArgs { key, members })
+}
+
+impl Geopos {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GEOPOS_CMD.to_uppercase(),
+            self.args
+        );
+
+        let GeoposArgs { key, members } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let points = {
+            let db_guard = db.write_live(&[&key]).await;
+            sorted_set::get(&db_guard, &key).map(|set| {
+                members
+                    .iter()
+                    .map(|member| {
+                        let score = set.and_then(|set| set.score(member))?;
+                        Some(Point::from_score(score as u64))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let points = match points {
+            Ok(points) => points,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        conn.write_array_len(points.len()).await?;
+        for point in points {
+            let Some(point) = point else {
+                conn.write_null().await?;
+                continue;
+            };
+
+            conn.write_array_len(2).await?;
+            for coordinate in point.coordinates() {
+                conn.write_chunk(DataType::BulkString, coordinate.as_bytes())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
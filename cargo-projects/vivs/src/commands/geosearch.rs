@@ -0,0 +1,265 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    geo::{self, parse_float, Found, Point, Shape, Unit},
+    parser::Parser,
+    sorted_set,
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GEOSEARCH_CMD: &str = "geosearch";
+
+const FROM_ERR: &str = "exactly one of FROMMEMBER or FROMLONLAT can be specified for GEOSEARCH";
+const BY_ERR: &str = "exactly one of BYRADIUS and BYBOX can be specified for GEOSEARCH";
+const MEMBER_ERR: &str = "could not decode requested zset member";
+
+/// Center of the search
+#[derive(Debug, Clone, PartialEq)]
+enum Center {
+    Member(Bytes),
+    LonLat(Point),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sort {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug)]
+struct GeosearchArgs {
+    key: Bytes,
+    center: Center,
+    /// In meters
+    shape: Shape,
+    unit: Unit,
+    sort: Option<Sort>,
+    count: Option<usize>,
+    any: bool,
+    with_coord: bool,
+    with_dist: bool,
+    with_hash: bool,
+}
+
+/// GEOSEARCH key <FROMMEMBER member | FROMLONLAT longitude latitude>
+/// <BYRADIUS radius <M|KM|FT|MI> | BYBOX width height <M|KM|FT|MI>>
+/// [ASC|DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+/// replies with the members within the circle or the box around the center.
+///
+/// Same as in Redis only the geohash cells around the center get scanned, so the search doesn't go through every member.
+/// COUNT without ASC or DESC returns the closest members, with ANY the search stops as soon as it finds enough of them.
+#[derive(Debug)]
+pub struct Geosearch {
+    args: Result<GeosearchArgs, CommandError>,
+}
+
+fn next_arg(data: &mut Parser) -> Result<String, CommandError> {
+    match data.next_as_str() {
+        Ok(Some(arg)) => Ok(arg),
+        _ => Err(CommandError::Syntax),
+    }
+}
+
+fn parse_args(mut data: Parser) -> Result<GeosearchArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(GEOSEARCH_CMD));
+    };
+
+    let mut center = None;
+    let mut by = None;
+    let mut sort = None;
+    let mut count = None;
+    let mut any = false;
+    let (mut with_coord, mut with_dist, mut with_hash) = (false, false, false);
+
+    while data.size() > 0 {
+        let option = next_arg(&mut data)?;
+        match &option.to_lowercase()[..] {
+            "frommember" if center.is_none() => {
+                let Ok(Some(member)) = data.next_bytes() else {
+                    return Err(CommandError::Syntax);
+                };
+                center = Some(Center::Member(member));
+            }
+            "fromlonlat" if center.is_none() => {
+                let (longitude, latitude) = (next_arg(&mut data)?, next_arg(&mut data)?);
+                center = Some(Center::LonLat(Point::parse(&longitude, &latitude)?));
+            }
+            "frommember" | "fromlonlat" => return Err(CommandError::Other(FROM_ERR.to_owned())),
+            "byradius" if by.is_none() => {
+                let radius = parse_float(&next_arg(&mut data)?)?;
+                let unit = Unit::parse(&next_arg(&mut data)?)?;
+                if radius < 0.0 {
+                    return Err(CommandError::Other("radius cannot be negative".to_owned()));
+                }
+                by = Some((Shape::Radius(radius * unit.meters), unit));
+            }
+            "bybox" if by.is_none() => {
+                let width = parse_float(&next_arg(&mut data)?)?;
+                let height = parse_float(&next_arg(&mut data)?)?;
+                let unit = Unit::parse(&next_arg(&mut data)?)?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(CommandError::Other(
+                        "height or width cannot be negative".to_owned(),
+                    ));
+                }
+                let shape = Shape::Box {
+                    width: width * unit.meters,
+                    height: height * unit.meters,
+                };
+                by = Some((shape, unit));
+            }
+            "byradius" | "bybox" => return Err(CommandError::Other(BY_ERR.to_owned())),
+            "asc" => sort = Some(Sort::Asc),
+            "desc" => sort = Some(Sort::Desc),
+            "count" => {
+                let value = next_arg(&mut data)?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::NotInteger)?;
+                if value <= 0 {
+                    return Err(CommandError::Other("COUNT must be > 0".to_owned()));
+                }
+                count = Some(value as usize);
+            }
+            "any" => any = true,
+            "withcoord" => with_coord = true,
+            "withdist" => with_dist = true,
+            "withhash" => with_hash = true,
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    let center = center.ok_or_else(|| CommandError::Other(FROM_ERR.to_owned()))?;
+    let (shape, unit) = by.ok_or_else(|| CommandError::Other(BY_ERR.to_owned()))?;
+    if any && count.is_none() {
+        return Err(CommandError::Other(
+            "the ANY argument requires COUNT argument".to_owned(),
+        ));
+    }
+
+    Ok(GeosearchArgs {
+        key,
+        center,
+        shape,
+        unit,
+        sort,
+        count,
+        any,
+        with_coord,
+        with_dist,
+        with_hash,
+    })
+}
+
+impl Geosearch {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GEOSEARCH_CMD.to_uppercase(),
+            self.args
+        );
+
+        let args = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let found = {
+            let db_guard = db.write_live(&[&args.key]).await;
+            sorted_set::get(&db_guard, &args.key).and_then(|set| {
+                let Some(set) = set else {
+                    return Ok(vec![]);
+                };
+
+                let center = match &args.center {
+                    Center::LonLat(point) => *point,
+                    Center::Member(member) => match set.score(member) {
+                        Some(score) => Point::from_score(score as u64),
+                        None => return Err(CommandError::Other(MEMBER_ERR.to_owned())),
+                    },
+                };
+
+                let limit = args.count.filter(|_| args.any);
+                Ok(geo::search(set, center, args.shape, limit))
+            })
+        };
+
+        let mut found = match found {
+            Ok(found) => found,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        // The closest members are the ones that are kept when there are too many
+        let sort = match (args.sort, args.count) {
+            (None, Some(_)) if !args.any => Some(Sort::Asc),
+            (sort, _) => sort,
+        };
+        match sort {
+            Some(Sort::Asc) => found.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+            Some(Sort::Desc) => found.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+            None => {}
+        }
+        if let Some(count) = args.count {
+            found.truncate(count);
+        }
+
+        conn.write_array_len(found.len()).await?;
+        for found in found {
+            write_found(conn, &args, found).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The member alone, or an array of the member and what the WITH options asked for
+async fn write_found(
+    conn: &mut Connection,
+    args: &GeosearchArgs,
+    found: Found,
+) -> GenericResult<()> {
+    let len = 1 + [args.with_dist, args.with_hash, args.with_coord]
+        .iter()
+        .filter(|with| **with)
+        .count();
+    if len > 1 {
+        conn.write_array_len(len).await?;
+    }
+
+    conn.write_chunk(DataType::BulkString, &found.member)
+        .await?;
+    if args.with_dist {
+        let distance = geo::format_distance(found.distance, args.unit);
+        conn.write_chunk(DataType::BulkString, distance.as_bytes())
+            .await?;
+    }
+    if args.with_hash {
+        conn.write_chunk(DataType::Integer, &u64_as_bytes(found.score))
+            .await?;
+    }
+    if args.with_coord {
+        conn.write_array_len(2).await?;
+        for coordinate in found.point.coordinates() {
+            conn.write_chunk(DataType::BulkString, coordinate.as_bytes())
+                .await?;
+        }
+    }
+
+    Ok(())
+}
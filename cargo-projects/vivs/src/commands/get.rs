@@ -1,31 +1,42 @@
-use super::ask::check_ask;
 use super::CommonCommand;
-use crate::cluster::CLUSTER_ASK_ERR;
-use crate::commands::ARGS_NUM;
+use crate::db::Value;
+use crate::error::CommandError;
+use crate::notifications::EventClass;
 use crate::parser::Parser;
-use crate::{Connection, DataStore, GenericResult};
+use crate::{Connection, Db, GenericResult};
+use bytes::Bytes;
 use log::info;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const GET_CMD: &str = "get";
 
+/// Only strings can be read with GET
+async fn write_value(conn: &mut Connection, value: &Value) -> GenericResult<()> {
+    match value {
+        Value::String(value) => conn.write_chunk(super::DataType::BulkString, value).await?,
+        _ => conn.write_error(&CommandError::WrongType).await?,
+    }
+
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct Get {
-    pub key: Option<String>,
+    pub key: Option<Bytes>,
 }
 
 impl CommonCommand for Get {
     fn parse(mut data: Parser) -> Self {
-        let Ok(key) = data.next_as_str() else {
+        let Ok(key) = data.next_bytes() else {
             return Self { key: None };
         };
 
         Self { key }
     }
 
-    async fn respond(&self, conn: &mut Connection, db: &DataStore) -> GenericResult<()> {
+    async fn respond(&self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
         let Some(key) = self.key.as_ref() else {
-            conn.write_error(ARGS_NUM.as_bytes()).await?;
+            conn.write_error(&CommandError::WrongArity(GET_CMD)).await?;
             return Ok(());
         };
 
@@ -35,26 +46,10 @@ impl CommonCommand for Get {
                 "{:?} {:?} {:?}",
                 conn.connected_peer_addr(),
                 GET_CMD.to_uppercase(),
-                self.key.as_ref().unwrap()
+                key
             )
         );
 
-        // Before responding, Vivs needs to check whether the node that the client is connected to contains
-        // needed data or it needs to redirect to a different node.
-        if let Some(redirect_addr) = check_ask(self.key.as_ref().unwrap(), conn).await {
-            // ASK is returned as a simple error with the redirect address
-            // TODO - ASK module needs to be constructed in the ASK module
-            conn.write_error(
-                format!(
-                    "{CLUSTER_ASK_ERR} {} {}",
-                    redirect_addr.key_hash, redirect_addr.ip
-                )
-                .as_bytes(),
-            )
-            .await?;
-            return Ok(());
-        }
-
         let mut db_guard = db.db.write().await;
 
         if let Some(value) = db_guard.get(key) {
@@ -69,17 +64,22 @@ impl CommonCommand for Get {
                 if Duration::from_secs(*unix_time) <= duration_now_s {
                     db_guard.remove(key);
                     expiries_guard.remove(key);
+                    db.expired(key);
+                    db.stats.miss();
+                    db.notify(EventClass::KeyMiss, "keymiss", key);
 
                     conn.write_null().await?
                 } else {
-                    conn.write_chunk(super::DataType::SimpleString, value.as_bytes())
-                        .await?
+                    db.stats.hit();
+                    write_value(conn, value).await?
                 }
             } else {
-                conn.write_chunk(super::DataType::SimpleString, value.as_bytes())
-                    .await?
+                db.stats.hit();
+                write_value(conn, value).await?
             }
         } else {
+            db.stats.miss();
+            db.notify(EventClass::KeyMiss, "keymiss", key);
             conn.write_null().await?
         }
 
@@ -0,0 +1,77 @@
+use crate::{
+    bitmap, commands::DataType, error::CommandError, parser::Parser, utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const GETBIT_CMD: &str = "getbit";
+
+#[derive(Debug)]
+struct GetbitArgs {
+    key: Bytes,
+    offset: u64,
+}
+
+/// GETBIT key offset replies with the bit at the offset of the string,
+/// 0 when the offset is past the end of the string or the key does not exist.
+#[derive(Debug)]
+pub struct Getbit {
+    args: Result<GetbitArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<GetbitArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(offset))) = (data.next_bytes(), data.next_as_str()) else {
+        return Err(CommandError::WrongArity(GETBIT_CMD));
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(GETBIT_CMD));
+    }
+
+    Ok(GetbitArgs {
+        key,
+        offset: bitmap::parse_offset(&offset)?,
+    })
+}
+
+impl Getbit {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            GETBIT_CMD.to_uppercase(),
+            self.args
+        );
+
+        let GetbitArgs { key, offset } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let bit = {
+            let db_guard = db.write_live(&[&key]).await;
+            bitmap::get(&db_guard, &key)
+                .map(|value| value.map_or(0, |value| bitmap::get_bit(value, offset)))
+        };
+
+        match bit {
+            Ok(bit) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(bit.into()))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
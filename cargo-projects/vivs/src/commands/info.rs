@@ -0,0 +1,207 @@
+use crate::{
+    commands::DataType,
+    parser::Parser,
+    stats::{bytes_human, rss_bytes},
+    Clients, Connection, DataStore, GenericResult, Stats,
+};
+use log::info;
+use std::fmt::Write;
+
+pub const INFO_CMD: &str = "info";
+
+const SERVER: &str = "server";
+const CLIENTS: &str = "clients";
+const MEMORY: &str = "memory";
+const PERSISTENCE: &str = "persistence";
+const STATS: &str = "stats";
+const REPLICATION: &str = "replication";
+const CLUSTER: &str = "cluster";
+const KEYSPACE: &str = "keyspace";
+const COMMAND_STATS: &str = "commandstats";
+
+// Sections that are returned when INFO is called without arguments.
+// Same as in Redis, per command stats are only returned when asked for.
+const DEFAULT_SECTIONS: [&str; 8] = [
+    SERVER,
+    CLIENTS,
+    MEMORY,
+    PERSISTENCE,
+    STATS,
+    REPLICATION,
+    CLUSTER,
+    KEYSPACE,
+];
+
+/// INFO [section ...] returns information and statistics about the server.
+///
+/// Sections are: server, clients, memory, persistence, stats, replication,
+/// cluster, keyspace and commandstats. "all" and "everything" return every section,
+/// "default" returns every section apart from commandstats.
+#[derive(Debug, Default)]
+pub struct Info {
+    sections: Vec<String>,
+}
+
+impl Info {
+    pub fn parse(mut data: Parser) -> Self {
+        let mut sections = vec![];
+
+        while let Ok(Some(section)) = data.next_as_str() {
+            sections.push(section.to_lowercase());
+        }
+
+        Self { sections }
+    }
+
+    fn includes(&self, section: &str) -> bool {
+        let is_default = self.sections.is_empty() || self.sections.iter().any(|s| s == "default");
+        if is_default && DEFAULT_SECTIONS.contains(&section) {
+            return true;
+        }
+
+        self.sections
+            .iter()
+            .any(|s| s == section || s == "all" || s == "everything")
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        db: &DataStore,
+        clients: &Clients,
+        stats: &Stats,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            INFO_CMD.to_uppercase(),
+            self.sections
+        );
+
+        let mut info = String::new();
+
+        if self.includes(SERVER) {
+            let uptime = stats.uptime().as_secs();
+            let port = conn.own_addr().map(|addr| addr.port()).unwrap_or_default();
+
+            writeln!(info, "# Server\r")?;
+            writeln!(info, "vivs_version:{}\r", env!("CARGO_PKG_VERSION"))?;
+            writeln!(
+                info,
+                "os:{} {}\r",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            )?;
+            writeln!(info, "process_id:{}\r", std::process::id())?;
+            writeln!(info, "tcp_port:{port}\r")?;
+            writeln!(info, "uptime_in_seconds:{uptime}\r")?;
+            writeln!(info, "uptime_in_days:{}\r", uptime / 86400)?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(CLIENTS) {
+            writeln!(info, "# Clients\r")?;
+            writeln!(info, "connected_clients:{}\r", clients.count().await)?;
+            writeln!(info, "blocked_clients:{}\r", db.blocked_clients())?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(MEMORY) {
+            let used_memory = db.memory_usage().await;
+            let used_memory_rss = rss_bytes();
+
+            writeln!(info, "# Memory\r")?;
+            writeln!(info, "used_memory:{used_memory}\r")?;
+            writeln!(info, "used_memory_human:{}\r", bytes_human(used_memory))?;
+            writeln!(info, "used_memory_rss:{used_memory_rss}\r")?;
+            writeln!(
+                info,
+                "used_memory_rss_human:{}\r",
+                bytes_human(used_memory_rss)
+            )?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(PERSISTENCE) {
+            // Vivs only keeps data in memory at the moment
+            writeln!(info, "# Persistence\r")?;
+            writeln!(info, "loading:0\r")?;
+            writeln!(info, "persistence_enabled:0\r")?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(STATS) {
+            writeln!(info, "# Stats\r")?;
+            writeln!(
+                info,
+                "total_connections_received:{}\r",
+                stats.total_connections()
+            )?;
+            writeln!(
+                info,
+                "rejected_connections:{}\r",
+                stats.rejected_connections()
+            )?;
+            writeln!(
+                info,
+                "total_commands_processed:{}\r",
+                stats.total_commands()
+            )?;
+            writeln!(
+                info,
+                "instantaneous_ops_per_sec:{}\r",
+                stats.ops_per_sec().await
+            )?;
+            writeln!(info, "keyspace_hits:{}\r", db.stats.hits())?;
+            writeln!(info, "keyspace_misses:{}\r", db.stats.misses())?;
+            writeln!(info, "expired_keys:{}\r", db.stats.expired_keys())?;
+            writeln!(info, "evicted_keys:{}\r", db.stats.evicted_keys())?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(REPLICATION) {
+            // There's no replication yet so every node is a master
+            writeln!(info, "# Replication\r")?;
+            writeln!(info, "role:master\r")?;
+            writeln!(info, "connected_slaves:0\r")?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(CLUSTER) {
+            let cluster_enabled = db.is_cluster();
+
+            writeln!(info, "# Cluster\r")?;
+            writeln!(info, "cluster_enabled:{}\r", cluster_enabled as u8)?;
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(KEYSPACE) {
+            writeln!(info, "# Keyspace\r")?;
+            for (index, keys, expires) in db.keyspace().await {
+                if keys > 0 {
+                    writeln!(info, "db{index}:keys={keys},expires={expires}\r")?;
+                }
+            }
+            writeln!(info, "\r")?;
+        }
+
+        if self.includes(COMMAND_STATS) {
+            writeln!(info, "# Commandstats\r")?;
+            for (command, stat) in stats.commands().await {
+                writeln!(
+                    info,
+                    "cmdstat_{command}:calls={},usec={},usec_per_call={:.2}\r",
+                    stat.calls,
+                    stat.usec,
+                    stat.usec_per_call()
+                )?;
+            }
+            writeln!(info, "\r")?;
+        }
+
+        conn.write_chunk(DataType::BulkString, info.trim_end().as_bytes())
+            .await?;
+
+        Ok(())
+    }
+}
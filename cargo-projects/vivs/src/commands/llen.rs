@@ -0,0 +1,57 @@
+use crate::{
+    commands::DataType, error::CommandError, list, parser::Parser, utils::u64_as_bytes, Connection,
+    Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const LLEN_CMD: &str = "llen";
+
+/// LLEN key replies with the length of the list, 0 if the key does not exist.
+#[derive(Debug)]
+pub struct Llen {
+    key: Result<Bytes, CommandError>,
+}
+
+impl Llen {
+    pub fn parse(mut data: Parser) -> Self {
+        let key = match data.next_bytes() {
+            Ok(Some(key)) if data.size() == 0 => Ok(key),
+            _ => Err(CommandError::WrongArity(LLEN_CMD)),
+        };
+
+        Self { key }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            LLEN_CMD.to_uppercase(),
+            self.key
+        );
+
+        let key = match self.key {
+            Ok(key) => key,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let len = {
+            let db_guard = db.write_live(&[&key]).await;
+            list::len(&db_guard, &key)
+        };
+
+        match len {
+            Ok(len) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(len as u64))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    list::{self, End},
+    parser::Parser,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const LMOVE_CMD: &str = "lmove";
+pub const RPOPLPUSH_CMD: &str = "rpoplpush";
+
+#[derive(Debug)]
+pub(crate) struct MoveArgs {
+    pub source: Bytes,
+    pub destination: Bytes,
+    pub from: End,
+    pub to: End,
+}
+
+/// Parses source destination LEFT|RIGHT LEFT|RIGHT (LMOVE, BLMOVE)
+/// or source destination (RPOPLPUSH, BRPOPLPUSH), the rest of the arguments are left
+pub(crate) fn parse_move(
+    data: &mut Parser,
+    command: &'static str,
+    with_ends: bool,
+) -> Result<MoveArgs, CommandError> {
+    let (Ok(Some(source)), Ok(Some(destination))) = (data.next_bytes(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(command));
+    };
+
+    let (from, to) = if with_ends {
+        let (Ok(Some(from)), Ok(Some(to))) = (data.next_as_str(), data.next_as_str()) else {
+            return Err(CommandError::WrongArity(command));
+        };
+        (End::parse(&from)?, End::parse(&to)?)
+    } else {
+        (End::Right, End::Left)
+    };
+
+    Ok(MoveArgs {
+        source,
+        destination,
+        from,
+        to,
+    })
+}
+
+/// LMOVE source destination LEFT|RIGHT LEFT|RIGHT pops a value from one end of the source
+/// and pushes it to one end of the destination, RPOPLPUSH source destination is the same as
+/// LMOVE source destination RIGHT LEFT.
+///
+/// Replies with the value, null if the source does not exist.
+#[derive(Debug)]
+pub struct Lmove {
+    name: &'static str,
+    args: Result<MoveArgs, CommandError>,
+}
+
+impl Lmove {
+    pub fn parse(mut data: Parser, name: &'static str) -> Self {
+        let args = parse_move(&mut data, name, name == LMOVE_CMD);
+
+        let args = if data.size() != 0 {
+            Err(CommandError::WrongArity(name))
+        } else {
+            args
+        };
+
+        Self { name, args }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            self.name.to_uppercase(),
+            self.args
+        );
+
+        let MoveArgs {
+            source,
+            destination,
+            from,
+            to,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let moved = {
+            let mut db_guard = db.write_live(&[&source, &destination]).await;
+            let moved = list::move_value(&mut db_guard, &source, &destination, from, to);
+
+            if let Ok(Some(_)) = moved {
+                list::notify_move(db, &db_guard, &source, &destination, from, to);
+            }
+
+            moved
+        };
+
+        match moved {
+            Ok(Some(value)) => {
+                // Clients blocked on the destination get served once the command is done
+                db.blocked.signal(&destination);
+                conn.write_chunk(DataType::BulkString, &value).await?;
+            }
+            Ok(None) => conn.write_null().await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
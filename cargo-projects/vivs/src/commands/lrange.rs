@@ -0,0 +1,124 @@
+use crate::{
+    commands::DataType, error::CommandError, list, parser::Parser, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const LRANGE_CMD: &str = "lrange";
+
+#[derive(Debug)]
+struct RangeArgs {
+    key: Bytes,
+    start: i64,
+    stop: i64,
+}
+
+/// LRANGE key start stop replies with the values in between start and stop (both inclusive).
+///
+/// Same as in Redis, negative indexes count from the end i.e. -1 is the last value.
+#[derive(Debug)]
+pub struct Lrange {
+    args: Result<RangeArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<RangeArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(start)), Ok(Some(stop))) =
+        (data.next_bytes(), data.next_as_str(), data.next_as_str())
+    else {
+        return Err(CommandError::WrongArity(LRANGE_CMD));
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(LRANGE_CMD));
+    }
+
+    Ok(RangeArgs {
+        key,
+        start: start.parse().map_err(|_| CommandError::NotInteger)?,
+        stop: stop.parse().map_err(|_| CommandError::NotInteger)?,
+    })
+}
+
+/// Turns start and stop into a range of positions, None if the range is empty
+fn positions(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+impl Lrange {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            LRANGE_CMD.to_uppercase(),
+            self.args
+        );
+
+        let RangeArgs { key, start, stop } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let values = {
+            let db_guard = db.write_live(&[&key]).await;
+            list::values(&db_guard, &key).map(|values| {
+                values
+                    .zip(values.and_then(|values| positions(start, stop, values.len())))
+                    .map(|(values, (start, stop))| {
+                        values.range(start..=stop).cloned().collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default()
+            })
+        };
+
+        let values = match values {
+            Ok(values) => values,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        conn.write_array_len(values.len()).await?;
+        for value in values {
+            conn.write_chunk(DataType::BulkString, &value).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod lrange_tests {
+    use super::*;
+
+    #[test]
+    fn negative_indexes_count_from_the_end() {
+        assert_eq!(positions(0, -1, 3), Some((0, 2)));
+        assert_eq!(positions(-2, 10, 3), Some((1, 2)));
+        assert_eq!(positions(-10, 0, 3), Some((0, 0)));
+        assert_eq!(positions(2, 1, 3), None);
+        assert_eq!(positions(3, 5, 3), None);
+        assert_eq!(positions(0, -1, 0), None);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, utils::u64_as_bytes, Connection, Db,
+    GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const MEMORY_CMD: &str = "memory";
+
+// Same as in Redis, only a few elements of lists and streams are looked at by default
+const DEFAULT_SAMPLES: usize = 5;
+
+#[derive(Debug)]
+enum Subcommand {
+    /// USAGE key [SAMPLES count]
+    Usage { key: Bytes, samples: usize },
+}
+
+/// MEMORY USAGE key [SAMPLES count] replies with the estimated number of bytes the key and its value take up,
+/// null if the key does not exist.
+///
+/// Sizes of the elements of lists and streams are estimated out of `count` of them, SAMPLES 0 looks at every element.
+#[derive(Debug)]
+pub struct Memory {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Ok(Some(subcommand)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(MEMORY_CMD));
+    };
+
+    match &subcommand.to_lowercase()[..] {
+        "usage" => {
+            let Ok(Some(key)) = data.next_bytes() else {
+                return Err(CommandError::WrongArity("memory|usage"));
+            };
+
+            let samples = match data.next_as_str() {
+                Ok(None) => DEFAULT_SAMPLES,
+                Ok(Some(option)) if option.eq_ignore_ascii_case("samples") => {
+                    let Ok(Some(count)) = data.next_as_str() else {
+                        return Err(CommandError::Syntax);
+                    };
+                    let count = count.parse::<i64>().map_err(|_| CommandError::NotInteger)?;
+                    if count < 0 {
+                        return Err(CommandError::OutOfRange("count"));
+                    }
+                    count as usize
+                }
+                _ => return Err(CommandError::Syntax),
+            };
+
+            if data.size() != 0 {
+                return Err(CommandError::Syntax);
+            }
+
+            Ok(Subcommand::Usage { key, samples })
+        }
+        _ => Err(CommandError::UnknownSubcommand {
+            command: MEMORY_CMD,
+            subcommand,
+        }),
+    }
+}
+
+impl Memory {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            MEMORY_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let Subcommand::Usage { key, samples } = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match db.key_memory_usage(&key, samples).await {
+            Some(usage) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(usage))
+                    .await?
+            }
+            None => conn.write_null().await?,
+        }
+
+        Ok(())
+    }
+}
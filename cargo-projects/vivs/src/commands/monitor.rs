@@ -0,0 +1,58 @@
+use crate::{
+    commands::DataType,
+    limits::{ClientClass, OUTPUT_BUFFER_ERR},
+    Connection, GenericResult, Monitors, Shutdown,
+};
+use log::info;
+
+pub const MONITOR_CMD: &str = "monitor";
+
+/// MONITOR streams back every command processed by the server.
+///
+/// The connection stays in the monitoring mode until the client disconnects
+/// or the server shuts down, anything else the client sends is ignored.
+#[derive(Debug, Default)]
+pub struct Monitor {}
+
+impl Monitor {
+    pub fn parse() -> Self {
+        Self {}
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        monitors: &Monitors,
+        shutdown: &Shutdown,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?}",
+            conn.connected_peer_addr(),
+            MONITOR_CMD.to_uppercase()
+        );
+
+        // Subscribe before replying so that no command sent after OK is missed
+        conn.output_buffer().set_class(ClientClass::Monitor);
+        let mut receiver = monitors.subscribe(conn.output_buffer().clone());
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        loop {
+            tokio::select! {
+                line = receiver.recv() => match line {
+                    Some(line) => conn.write_chunk(DataType::SimpleString, line.as_bytes()).await?,
+                    // Slow consumer, it could not keep up with the commands
+                    None if conn.output_buffer().is_overflowed() => {
+                        info!("{:?} {OUTPUT_BUFFER_ERR}", conn.connected_peer_addr());
+                        Err(conn.overflow().await)?
+                    }
+                    None => return Ok(()),
+                },
+                _ = shutdown.wait() => return Ok(()),
+                // Reading lets us notice when the client disconnects (it returns an error)
+                read = conn.process_stream() => {
+                    read?;
+                }
+            }
+        }
+    }
+}
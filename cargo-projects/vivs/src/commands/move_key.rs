@@ -0,0 +1,146 @@
+use crate::{
+    commands::{select::parse_index, select::DB_INDEX_ERR, DataType},
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, DataStore, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MOVE_CMD: &str = "move";
+
+#[derive(Debug)]
+struct MoveArgs {
+    key: Bytes,
+    db: usize,
+}
+
+/// MOVE key db moves the key (and its expiry) from the selected database to another one.
+///
+/// Replies with 1 if the key was moved, 0 if the key does not exist
+/// or the other database already has it.
+#[derive(Debug)]
+pub struct Move {
+    args: Result<MoveArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<MoveArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(MOVE_CMD));
+    };
+    let db = parse_index(&mut data, MOVE_CMD)?;
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(MOVE_CMD));
+    }
+
+    Ok(MoveArgs { key, db })
+}
+
+impl Move {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub fn key(&self) -> Option<&[u8]> {
+        self.args.as_ref().ok().map(|args| &args.key[..])
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        store: &DataStore,
+        db_index: usize,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            MOVE_CMD.to_uppercase(),
+            self.args
+        );
+
+        let MoveArgs { key, db } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        if store.is_cluster() {
+            let error = CommandError::NotAllowed("MOVE is not allowed in cluster mode".to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        if db == db_index {
+            let error =
+                CommandError::Other("source and destination objects are the same".to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        let (Some(source), Some(target)) = (store.db(db_index), store.db(db)) else {
+            conn.write_error(&CommandError::OutOfRange(DB_INDEX_ERR))
+                .await?;
+            return Ok(());
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let is_live = |expiry: Option<&u64>| expiry.is_none_or(|expiry| *expiry > now);
+
+        // Databases are always locked in the same order so that two MOVEs can't deadlock
+        let (mut source_guard, mut target_guard) = if db_index < db {
+            let source_guard = source.db.write().await;
+            (source_guard, target.db.write().await)
+        } else {
+            let target_guard = target.db.write().await;
+            (source.db.write().await, target_guard)
+        };
+        let (mut source_expiries, mut target_expiries) = if db_index < db {
+            let source_expiries = source.expirations.write().await;
+            (source_expiries, target.expirations.write().await)
+        } else {
+            let target_expiries = target.expirations.write().await;
+            (source.expirations.write().await, target_expiries)
+        };
+
+        let exists_in_source =
+            source_guard.contains_key(&key) && is_live(source_expiries.get(&key));
+        let exists_in_target =
+            target_guard.contains_key(&key) && is_live(target_expiries.get(&key));
+
+        let moved = exists_in_source && !exists_in_target;
+        if moved {
+            if let Some(value) = source_guard.remove(&key) {
+                target_guard.insert(key.clone(), value);
+                // Clients blocked on the key in the other database get served
+                target.blocked.signal(&key);
+            }
+
+            // An expired key that was left in the target is replaced together with its expiry
+            target_expiries.remove(&key);
+            if let Some(expiry) = source_expiries.remove(&key) {
+                target_expiries.insert(key.clone(), expiry);
+            }
+
+            // The key keeps its access history (OBJECT IDLETIME / FREQ)
+            if let Some(access) = source.accesses.write().await.remove(&key) {
+                target.accesses.write().await.insert(key.clone(), access);
+            }
+
+            source.notify(EventClass::Generic, "move_from", &key);
+            target.notify(EventClass::Generic, "move_to", &key);
+        }
+
+        conn.write_chunk(DataType::Integer, &u64_as_bytes(moved as u64))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{
+    commands::{
+        monitor::MONITOR_CMD,
+        subscribe::{PSUBSCRIBE_CMD, SUBSCRIBE_CMD},
+        Command, DataType,
+    },
+    error::CommandError,
+    Connection, GenericResult, Handler,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const MULTI_CMD: &str = "multi";
+pub const EXEC_CMD: &str = "exec";
+pub const DISCARD_CMD: &str = "discard";
+
+const NESTED_MULTI_ERR: &str = "MULTI calls can not be nested";
+const EXEC_WITHOUT_MULTI_ERR: &str = "EXEC without MULTI";
+const DISCARD_WITHOUT_MULTI_ERR: &str = "DISCARD without MULTI";
+const NOT_ALLOWED_ERR: &str = "Command not allowed inside a transaction";
+
+/// Commands queued after MULTI, EXEC runs all of them with no other command running in between.
+#[derive(Debug, Default)]
+pub struct Transaction {
+    // Queued commands together with their raw arguments (needed for MONITOR and SLOWLOG)
+    commands: Vec<(Command, Vec<Bytes>)>,
+    // One of the commands could not be queued, EXEC discards the transaction then
+    aborted: bool,
+}
+
+impl Transaction {
+    /// Whether the command gets queued (instead of running right away) while a transaction is open
+    pub fn queues(command: &Command) -> bool {
+        ![MULTI_CMD, EXEC_CMD, DISCARD_CMD]
+            .iter()
+            .any(|name| command.is(name))
+    }
+
+    /// Queues the command, unknown commands or commands with a wrong number of arguments
+    /// are rejected right away and the whole transaction gets discarded on EXEC (same as in Redis)
+    pub async fn queue(
+        &mut self,
+        command: Command,
+        args: Vec<Bytes>,
+        conn: &mut Connection,
+    ) -> GenericResult<()> {
+        // These never return, EXEC would never finish
+        let not_allowed = [MONITOR_CMD, SUBSCRIBE_CMD, PSUBSCRIBE_CMD]
+            .iter()
+            .any(|name| command.is(name));
+        let checked = command.check().and_then(|_| match not_allowed {
+            true => Err(CommandError::NotAllowed(NOT_ALLOWED_ERR.to_owned())),
+            false => Ok(()),
+        });
+
+        if let Err(err) = checked {
+            self.aborted = true;
+            conn.write_error(&err).await?;
+            return Ok(());
+        }
+
+        self.commands.push((command, args));
+        conn.write_chunk(DataType::SimpleString, b"QUEUED").await?;
+
+        Ok(())
+    }
+}
+
+/// MULTI starts a transaction, the commands that follow are queued until EXEC or DISCARD.
+#[derive(Debug, Default)]
+pub struct Multi {}
+
+impl Multi {
+    pub fn parse() -> Self {
+        Self {}
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        transaction: &mut Option<Transaction>,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?}",
+            conn.connected_peer_addr(),
+            MULTI_CMD.to_uppercase()
+        );
+
+        if transaction.is_some() {
+            let error = CommandError::Other(NESTED_MULTI_ERR.to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        *transaction = Some(Transaction::default());
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
+
+/// DISCARD drops the queued commands and ends the transaction.
+#[derive(Debug, Default)]
+pub struct Discard {}
+
+impl Discard {
+    pub fn parse() -> Self {
+        Self {}
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        transaction: &mut Option<Transaction>,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?}",
+            conn.connected_peer_addr(),
+            DISCARD_CMD.to_uppercase()
+        );
+
+        if transaction.take().is_none() {
+            let error = CommandError::Other(DISCARD_WITHOUT_MULTI_ERR.to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
+
+/// EXEC runs the queued commands and replies with an array of their replies.
+///
+/// Blocking commands (BLPOP etc) don't block inside of a transaction, they reply right away.
+/// Clients blocked on the keys the transaction pushed to are served once all the commands ran.
+#[derive(Debug, Default)]
+pub struct Exec {}
+
+impl Exec {
+    pub fn parse() -> Self {
+        Self {}
+    }
+
+    pub async fn respond(self, handler: &mut Handler) -> GenericResult<()> {
+        info!(
+            "{:?} {:?}",
+            handler.connection.connected_peer_addr(),
+            EXEC_CMD.to_uppercase()
+        );
+
+        let Some(transaction) = handler.transaction.take() else {
+            let error = CommandError::Other(EXEC_WITHOUT_MULTI_ERR.to_owned());
+            handler.connection.write_error(&error).await?;
+            return Ok(());
+        };
+
+        if transaction.aborted {
+            handler
+                .connection
+                .write_error(&CommandError::ExecAbort)
+                .await?;
+            return Ok(());
+        }
+
+        // No other command runs until the guard is dropped,
+        // so the replies are only written to the client once it is
+        let held = handler.connection.hold();
+        let guard = handler.db.exec_guard().await;
+
+        handler.in_exec = true;
+        let ran = run_queued(handler, transaction.commands).await;
+        handler.in_exec = false;
+
+        handler.db.serve_blocked().await;
+        drop(guard);
+
+        if held {
+            handler.connection.release().await?;
+        }
+
+        ran
+    }
+}
+
+async fn run_queued(
+    handler: &mut Handler,
+    commands: Vec<(Command, Vec<Bytes>)>,
+) -> GenericResult<()> {
+    handler.connection.write_array_len(commands.len()).await?;
+
+    for (command, args) in commands {
+        handler.run_recorded(command, args).await?;
+    }
+
+    Ok(())
+}
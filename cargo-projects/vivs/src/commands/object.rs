@@ -0,0 +1,111 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, stream::now_ms, utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const OBJECT_CMD: &str = "object";
+
+#[derive(Debug)]
+enum Subcommand {
+    /// ENCODING key
+    Encoding(Bytes),
+    /// IDLETIME key
+    IdleTime(Bytes),
+    /// FREQ key
+    Freq(Bytes),
+}
+
+/// OBJECT ENCODING key - how the value is stored, using the names Redis uses (embstr, listpack, ...)
+///
+/// OBJECT IDLETIME key - seconds since the key was last read or written
+///
+/// OBJECT FREQ key - logarithmic access counter of the key (it goes down when the key is not accessed)
+///
+/// None of them count as an access. Replies with null if the key does not exist.
+#[derive(Debug)]
+pub struct Object {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let (Ok(Some(subcommand)), Ok(Some(key))) = (data.next_as_str(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(OBJECT_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "encoding" => Subcommand::Encoding(key),
+        "idletime" => Subcommand::IdleTime(key),
+        "freq" => Subcommand::Freq(key),
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: OBJECT_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(OBJECT_CMD));
+    }
+
+    Ok(subcommand)
+}
+
+impl Object {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            OBJECT_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let (Subcommand::Encoding(key) | Subcommand::IdleTime(key) | Subcommand::Freq(key)) =
+            &subcommand;
+        let encoding = db
+            .write_live(&[key])
+            .await
+            .get(key)
+            .map(|value| value.encoding());
+        let Some(encoding) = encoding else {
+            conn.write_null().await?;
+            return Ok(());
+        };
+
+        let now = now_ms();
+        match subcommand {
+            Subcommand::Encoding(_) => {
+                conn.write_chunk(DataType::BulkString, encoding.as_bytes())
+                    .await?
+            }
+            Subcommand::IdleTime(key) => {
+                let idle_time = db.access(&key).await.idle_time(now);
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(idle_time))
+                    .await?
+            }
+            Subcommand::Freq(key) => {
+                let frequency = db.access(&key).await.frequency(now);
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(frequency.into()))
+                    .await?
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    commands::DataType, db::Value, error::CommandError, hyperloglog, notifications::EventClass,
+    parser::Parser, utils::u64_as_bytes, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const PFADD_CMD: &str = "pfadd";
+
+#[derive(Debug)]
+struct PfaddArgs {
+    key: Bytes,
+    elements: Vec<Bytes>,
+}
+
+/// PFADD key [element ...] adds the elements to the HyperLogLog (it is created if the key does not exist).
+///
+/// Replies with 1 if the estimated cardinality changed (or the key was created), 0 otherwise.
+#[derive(Debug)]
+pub struct Pfadd {
+    args: Result<PfaddArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<PfaddArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(PFADD_CMD));
+    };
+
+    let mut elements = vec![];
+    while let Ok(Some(element)) = data.next_bytes() {
+        elements.push(element);
+    }
+
+    Ok(PfaddArgs { key, elements })
+}
+
+impl Pfadd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            PFADD_CMD.to_uppercase(),
+            self.args
+        );
+
+        let PfaddArgs { key, elements } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let changed = {
+            let mut db_guard = db.write_live(&[&key]).await;
+
+            hyperloglog::get(&db_guard, &key).map(|hll| {
+                let created = hll.is_none();
+                let mut hll = hll.unwrap_or_default();
+
+                // Every element has to be added, so this can't stop at the first change
+                let mut added = false;
+                for element in &elements {
+                    added |= hll.add(element);
+                }
+
+                let changed = created || added;
+                if changed {
+                    db_guard.insert(key.clone(), Value::String(hll.encode()));
+                }
+
+                changed
+            })
+        };
+
+        match changed {
+            Ok(changed) => {
+                if changed {
+                    db.notify(EventClass::String, PFADD_CMD, &key);
+                }
+
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(changed.into()))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
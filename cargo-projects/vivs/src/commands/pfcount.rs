@@ -0,0 +1,91 @@
+use crate::{
+    commands::DataType, db::Value, error::CommandError, hyperloglog, parser::Parser,
+    utils::u64_as_bytes, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const PFCOUNT_CMD: &str = "pfcount";
+
+/// PFCOUNT key [key ...] replies with the estimated number of unique elements added to the
+/// HyperLogLog, or to the union of the HyperLogLogs when there's more than one key.
+///
+/// Missing keys are empty HyperLogLogs. Same as in Redis, the cardinality of a single key
+/// is cached in its value until an element gets added.
+#[derive(Debug)]
+pub struct Pfcount {
+    keys: Result<Vec<Bytes>, CommandError>,
+}
+
+impl Pfcount {
+    pub fn parse(mut data: Parser) -> Self {
+        let mut keys = vec![];
+        while let Ok(Some(key)) = data.next_bytes() {
+            keys.push(key);
+        }
+
+        let keys = match keys.is_empty() {
+            true => Err(CommandError::WrongArity(PFCOUNT_CMD)),
+            false => Ok(keys),
+        };
+
+        Self { keys }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            PFCOUNT_CMD.to_uppercase(),
+            self.keys
+        );
+
+        let keys = match self.keys {
+            Ok(keys) => keys,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let count = {
+            let live_keys = keys.iter().map(|key| &key[..]).collect::<Vec<_>>();
+            let mut db_guard = db.write_live(&live_keys).await;
+
+            match &keys[..] {
+                [key] => hyperloglog::get(&db_guard, key).map(|hll| {
+                    let Some(mut hll) = hll else {
+                        return 0;
+                    };
+
+                    let cached = hll.is_count_cached();
+                    let count = hll.count();
+                    if !cached {
+                        db_guard.insert(key.clone(), Value::String(hll.encode()));
+                    }
+
+                    count
+                }),
+                keys => keys
+                    .iter()
+                    .try_fold(hyperloglog::HyperLogLog::new(), |mut union, key| {
+                        if let Some(hll) = hyperloglog::get(&db_guard, key)? {
+                            union.merge(&hll);
+                        }
+                        Ok(union)
+                    })
+                    .map(|mut union| union.count()),
+            }
+        };
+
+        match count {
+            Ok(count) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(count))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    commands::{pfadd::PFADD_CMD, DataType},
+    db::Value,
+    error::CommandError,
+    hyperloglog,
+    notifications::EventClass,
+    parser::Parser,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::collections::HashMap;
+
+pub const PFMERGE_CMD: &str = "pfmerge";
+
+#[derive(Debug)]
+struct PfmergeArgs {
+    destination: Bytes,
+    keys: Vec<Bytes>,
+}
+
+/// PFMERGE destkey [sourcekey ...] merges the HyperLogLogs into destkey (which is merged too
+/// when it exists), the result estimates the cardinality of the union of them.
+#[derive(Debug)]
+pub struct Pfmerge {
+    args: Result<PfmergeArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<PfmergeArgs, CommandError> {
+    let Ok(Some(destination)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(PFMERGE_CMD));
+    };
+
+    let mut keys = vec![];
+    while let Ok(Some(key)) = data.next_bytes() {
+        keys.push(key);
+    }
+
+    Ok(PfmergeArgs { destination, keys })
+}
+
+/// Merges the HyperLogLogs of the keys into the destination
+fn merge(
+    db: &mut HashMap<Bytes, Value>,
+    destination: &Bytes,
+    keys: &[Bytes],
+) -> Result<(), CommandError> {
+    let mut merged = hyperloglog::get(db, destination)?.unwrap_or_default();
+    for key in keys {
+        if let Some(hll) = hyperloglog::get(db, key)? {
+            merged.merge(&hll);
+        }
+    }
+
+    // The result stays sparse only when all of the HyperLogLogs were sparse
+    db.insert(destination.clone(), Value::String(merged.encode()));
+
+    Ok(())
+}
+
+impl Pfmerge {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            PFMERGE_CMD.to_uppercase(),
+            self.args
+        );
+
+        let PfmergeArgs { destination, keys } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let merged = {
+            let mut live_keys = vec![&destination[..]];
+            live_keys.extend(keys.iter().map(|key| &key[..]));
+            let mut db_guard = db.write_live(&live_keys).await;
+
+            merge(&mut db_guard, &destination, &keys)
+        };
+
+        match merged {
+            Ok(()) => {
+                db.notify(EventClass::String, PFADD_CMD, &destination);
+                conn.write_chunk(DataType::SimpleString, b"OK").await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    list::{self, End},
+    parser::Parser,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const LPOP_CMD: &str = "lpop";
+pub const RPOP_CMD: &str = "rpop";
+
+/// LPOP key / RPOP key remove and return the first / last value of the list,
+/// null if the key does not exist.
+#[derive(Debug)]
+pub struct Pop {
+    end: End,
+    key: Result<Bytes, CommandError>,
+}
+
+fn name(end: End) -> &'static str {
+    match end {
+        End::Left => LPOP_CMD,
+        End::Right => RPOP_CMD,
+    }
+}
+
+impl Pop {
+    pub fn parse(mut data: Parser, end: End) -> Self {
+        let key = match data.next_bytes() {
+            Ok(Some(key)) if data.size() == 0 => Ok(key),
+            _ => Err(CommandError::WrongArity(name(end))),
+        };
+
+        Self { end, key }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            name(self.end).to_uppercase(),
+            self.key
+        );
+
+        let key = match self.key {
+            Ok(key) => key,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let popped = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            let popped = list::pop(&mut db_guard, &key, self.end);
+
+            if let Ok(Some(_)) = popped {
+                list::notify_pop(db, &db_guard, &key, self.end);
+            }
+
+            popped
+        };
+
+        match popped {
+            Ok(Some(value)) => conn.write_chunk(DataType::BulkString, &value).await?,
+            Ok(None) => conn.write_null().await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, pubsub::PubSub, utils::u64_as_bytes,
+    Connection, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const PUBLISH_CMD: &str = "publish";
+
+/// PUBLISH channel message sends the message to the clients subscribed to the channel
+/// (or to a pattern that matches it).
+///
+/// Replies with the number of clients that received the message.
+#[derive(Debug)]
+pub struct Publish {
+    args: Result<(Bytes, Bytes), CommandError>,
+}
+
+impl Publish {
+    pub fn parse(mut data: Parser) -> Self {
+        let args = match (data.next_bytes(), data.next_bytes()) {
+            (Ok(Some(channel)), Ok(Some(message))) if data.size() == 0 => Ok((channel, message)),
+            _ => Err(CommandError::WrongArity(PUBLISH_CMD)),
+        };
+
+        Self { args }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, pubsub: &PubSub) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            PUBLISH_CMD.to_uppercase(),
+            self.args
+        );
+
+        let (channel, message) = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let received = pubsub.publish(&channel, &message);
+        conn.write_chunk(DataType::Integer, &u64_as_bytes(received as u64))
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    list::{self, End},
+    notifications::EventClass,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const LPUSH_CMD: &str = "lpush";
+pub const RPUSH_CMD: &str = "rpush";
+
+#[derive(Debug)]
+struct PushArgs {
+    key: Bytes,
+    values: Vec<Bytes>,
+}
+
+/// LPUSH key value [value ...] / RPUSH key value [value ...] push the values
+/// to the head / tail of the list (created if the key does not exist).
+///
+/// Replies with the length of the list.
+#[derive(Debug)]
+pub struct Push {
+    end: End,
+    args: Result<PushArgs, CommandError>,
+}
+
+fn name(end: End) -> &'static str {
+    match end {
+        End::Left => LPUSH_CMD,
+        End::Right => RPUSH_CMD,
+    }
+}
+
+fn parse_args(mut data: Parser, end: End) -> Result<PushArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(name(end)));
+    };
+
+    let mut values = vec![];
+    while let Ok(Some(value)) = data.next_bytes() {
+        values.push(value);
+    }
+
+    if values.is_empty() {
+        return Err(CommandError::WrongArity(name(end)));
+    }
+
+    Ok(PushArgs { key, values })
+}
+
+impl Push {
+    pub fn parse(data: Parser, end: End) -> Self {
+        Self {
+            end,
+            args: parse_args(data, end),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            name(self.end).to_uppercase(),
+            self.args
+        );
+
+        let PushArgs { key, values } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let pushed = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            list::push(&mut db_guard, &key, values, self.end)
+        };
+
+        if pushed.is_ok() {
+            db.notify(EventClass::List, self.end.push_event(), &key);
+        }
+
+        match pushed {
+            Ok(len) => {
+                // Clients blocked on the key get served once the command is done
+                db.blocked.signal(&key);
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(len as u64))
+                    .await?;
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1458 @@
+use crate::{
+    commands::{
+        ask::{Ask, ASK_CMD},
+        asking::{Asking, ASKING_CMD},
+        bitcount::{Bitcount, BITCOUNT_CMD},
+        bitfield::{Bitfield, BITFIELD_CMD},
+        bitop::{Bitop, BITOP_CMD},
+        bitpos::{Bitpos, BITPOS_CMD},
+        blmove::{BlockingMove, BLMOVE_CMD, BRPOPLPUSH_CMD},
+        blpop::{BlockingPop, BLPOP_CMD, BRPOP_CMD},
+        client::{Client, CLIENT_CMD},
+        cluster::{ClusterCmd, CLUSTER_CMD},
+        command::{CommandCmd, COMMAND_CMD},
+        config::{ConfigCmd, CONFIG_CMD},
+        delete::{Delete, DELETE_CMD},
+        dump::{Dump, DUMP_CMD},
+        expire::{Expire, EXPIRE_CMD},
+        flushall::{FlushAll, FLUSHALL_CMD},
+        flushdb::{FlushDb, FLUSHDB_CMD},
+        geoadd::{Geoadd, GEOADD_CMD},
+        geodist::{Geodist, GEODIST_CMD},
+        geohash::{Geohash, GEOHASH_CMD},
+        geopos::{Geopos, GEOPOS_CMD},
+        geosearch::{Geosearch, GEOSEARCH_CMD},
+        get::{Get, GET_CMD},
+        getbit::{Getbit, GETBIT_CMD},
+        info::{Info, INFO_CMD},
+        llen::{Llen, LLEN_CMD},
+        lmove::{Lmove, LMOVE_CMD, RPOPLPUSH_CMD},
+        lrange::{Lrange, LRANGE_CMD},
+        memory::{Memory, MEMORY_CMD},
+        monitor::{Monitor, MONITOR_CMD},
+        move_key::{Move, MOVE_CMD},
+        multi::{Discard, Exec, Multi, DISCARD_CMD, EXEC_CMD, MULTI_CMD},
+        object::{Object, OBJECT_CMD},
+        pfadd::{Pfadd, PFADD_CMD},
+        pfcount::{Pfcount, PFCOUNT_CMD},
+        pfmerge::{Pfmerge, PFMERGE_CMD},
+        ping::{Ping, PING_CMD},
+        pop::{Pop, LPOP_CMD, RPOP_CMD},
+        publish::{Publish, PUBLISH_CMD},
+        push::{Push, LPUSH_CMD, RPUSH_CMD},
+        restore::{Restore, RESTORE_CMD},
+        scan::{Scan, SCAN_CMD},
+        select::{Select, SELECT_CMD},
+        set::{Set, SET_CMD},
+        setbit::{Setbit, SETBIT_CMD},
+        shutdown::{ShutdownCmd, SHUTDOWN_CMD},
+        slowlog::{Slowlog, SLOWLOG_CMD},
+        subscribe::{Subscribe, PSUBSCRIBE_CMD, PUNSUBSCRIBE_CMD, SUBSCRIBE_CMD, UNSUBSCRIBE_CMD},
+        swapdb::{SwapDb, SWAPDB_CMD},
+        ttl::{Ttl, TTL_CMD},
+        xack::{Xack, XACK_CMD},
+        xadd::{Xadd, XADD_CMD},
+        xclaim::{Xclaim, XAUTOCLAIM_CMD, XCLAIM_CMD},
+        xgroup::{Xgroup, XGROUP_CMD},
+        xinfo::{Xinfo, XINFO_CMD},
+        xlen::{Xlen, XLEN_CMD},
+        xpending::{Xpending, XPENDING_CMD},
+        xrange::{Xrange, XRANGE_CMD, XREVRANGE_CMD},
+        xread::{Xread, XREADGROUP_CMD, XREAD_CMD},
+        CommonCommand,
+    },
+    list::End,
+    parser::Parser,
+    Db, GenericResult, Handler,
+};
+use bytes::Bytes;
+use std::{fmt::Debug, future::Future, pin::Pin};
+
+pub type CommandFuture<'a> = Pin<Box<dyn Future<Output = GenericResult<()>> + Send + 'a>>;
+
+/// Runs the command, gets the arguments (without the command name),
+/// the connection handler and the database the connection selected.
+pub type CommandHandler = for<'a> fn(Parser, &'a mut Handler, Db) -> CommandFuture<'a>;
+
+/// Same as command flags in Redis (COMMAND INFO)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandFlag {
+    /// Modifies the data store
+    Write,
+    /// Only reads the data store
+    Readonly,
+    /// Inspects or controls the server
+    Admin,
+    /// Runs in constant (or close to) time
+    Fast,
+    /// Can block the client until a value is available (BLPOP etc)
+    Blocking,
+    /// Publishes or subscribes to pub/sub channels
+    Pubsub,
+    /// Reads keys without counting as an access to them (OBJECT IDLETIME etc)
+    NoTouch,
+}
+
+impl CommandFlag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommandFlag::Write => "write",
+            CommandFlag::Readonly => "readonly",
+            CommandFlag::Admin => "admin",
+            CommandFlag::Fast => "fast",
+            CommandFlag::Blocking => "blocking",
+            CommandFlag::Pubsub => "pubsub",
+            CommandFlag::NoTouch => "no_touch",
+        }
+    }
+}
+
+/// Positions of the keys in the arguments, the command name is at 0.
+///
+/// Same as in Redis, a negative last key counts from the end i.e. -1 is the last argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeySpec {
+    pub first: usize,
+    pub last: i64,
+    pub step: usize,
+    /// Keys follow this argument (looked for from `first` on) and make up the first half
+    /// of the arguments after it i.e. STREAMS key [key ...] id [id ...]
+    pub keyword: Option<&'static str>,
+}
+
+impl KeySpec {
+    /// The command does not take any keys
+    pub const NONE: KeySpec = KeySpec {
+        first: 0,
+        last: 0,
+        step: 0,
+        keyword: None,
+    };
+
+    /// The command takes a single key, right after the command name
+    pub const FIRST: KeySpec = KeySpec {
+        first: 1,
+        last: 1,
+        step: 1,
+        keyword: None,
+    };
+
+    /// The key comes after the subcommand i.e. XGROUP CREATE key
+    pub const SECOND: KeySpec = KeySpec {
+        first: 2,
+        last: 2,
+        step: 1,
+        keyword: None,
+    };
+
+    /// The command takes a source and a destination key i.e. LMOVE
+    pub const FIRST_TWO: KeySpec = KeySpec {
+        first: 1,
+        last: 2,
+        step: 1,
+        keyword: None,
+    };
+
+    /// Keys come after STREAMS, which is looked for from `first` on i.e. XREAD
+    pub const fn streams(first: usize) -> KeySpec {
+        KeySpec {
+            first,
+            last: 0,
+            step: 0,
+            keyword: Some("streams"),
+        }
+    }
+
+    /// Whether the positions of the keys depend on the other arguments, same as movablekeys in Redis
+    pub fn is_movable(&self) -> bool {
+        self.keyword.is_some()
+    }
+}
+
+/// An entry of the command table
+pub struct CommandSpec {
+    /// Lowercase name of the command
+    pub name: &'static str,
+    /// Number of arguments including the command name,
+    /// same as in Redis a negative arity means at least that many arguments
+    pub arity: i64,
+    pub flags: &'static [CommandFlag],
+    pub keys: KeySpec,
+    /// Subcommands that are tracked separately in stats i.e. client|list
+    pub subcommands: &'static [&'static str],
+    pub handler: CommandHandler,
+}
+
+impl Debug for CommandSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CommandSpec")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("flags", &self.flags)
+            .field("keys", &self.keys)
+            .finish()
+    }
+}
+
+impl CommandSpec {
+    pub fn has_flag(&self, flag: CommandFlag) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Whether `count` arguments (including the command name) are accepted
+    pub fn arity_matches(&self, count: usize) -> bool {
+        let count = count as i64;
+
+        if self.arity >= 0 {
+            count == self.arity
+        } else {
+            count >= -self.arity
+        }
+    }
+
+    /// Picks the keys out of the arguments (without the command name)
+    pub fn keys(&self, args: &[Bytes]) -> Vec<Bytes> {
+        let KeySpec {
+            first,
+            last,
+            step,
+            keyword,
+        } = self.keys;
+
+        if let Some(keyword) = keyword {
+            let start = first.saturating_sub(1);
+            let Some(position) = args
+                .iter()
+                .skip(start)
+                .position(|arg| arg.eq_ignore_ascii_case(keyword.as_bytes()))
+            else {
+                return vec![];
+            };
+
+            let rest = &args[start + position + 1..];
+            return rest[..rest.len() / 2].to_vec();
+        }
+
+        if first == 0 || step == 0 {
+            return vec![];
+        }
+
+        // Positions include the command name
+        let count = args.len() as i64 + 1;
+        let last = if last < 0 { count + last } else { last };
+
+        (first..)
+            .step_by(step)
+            .take_while(|position| (*position as i64) <= last)
+            .filter_map(|position| args.get(position - 1).cloned())
+            .collect()
+    }
+
+    /// Subcommand as it is tracked in stats, if the command has subcommands
+    pub fn subcommand(&self, arg: Option<&Bytes>) -> Option<&'static str> {
+        let arg = std::str::from_utf8(arg?).ok()?.to_lowercase();
+        self.subcommands
+            .iter()
+            .find(|subcommand| **subcommand == arg)
+            .copied()
+    }
+}
+
+/// Finds the command by its name (case insensitive)
+pub fn lookup(name: &str) -> Option<&'static CommandSpec> {
+    let name = name.to_lowercase();
+    COMMAND_TABLE.iter().find(|spec| spec.name == name)
+}
+
+use CommandFlag::*;
+
+/// Every command Vivs knows about.
+///
+/// Adding a command only takes an entry here (plus the module that parses and runs it).
+pub static COMMAND_TABLE: &[CommandSpec] = &[
+    CommandSpec {
+        name: PING_CMD,
+        arity: -1,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: ping,
+    },
+    CommandSpec {
+        name: GET_CMD,
+        arity: 2,
+        flags: &[Readonly, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: get,
+    },
+    CommandSpec {
+        name: SET_CMD,
+        arity: -3,
+        flags: &[Write],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: set,
+    },
+    CommandSpec {
+        name: DELETE_CMD,
+        arity: 2,
+        flags: &[Write],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: delete,
+    },
+    CommandSpec {
+        name: TTL_CMD,
+        arity: 2,
+        flags: &[Readonly, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: ttl,
+    },
+    CommandSpec {
+        name: EXPIRE_CMD,
+        arity: 3,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: expire,
+    },
+    CommandSpec {
+        name: SETBIT_CMD,
+        arity: 4,
+        flags: &[Write],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: setbit,
+    },
+    CommandSpec {
+        name: GETBIT_CMD,
+        arity: 3,
+        flags: &[Readonly, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: getbit,
+    },
+    CommandSpec {
+        name: BITCOUNT_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: bitcount,
+    },
+    CommandSpec {
+        name: BITPOS_CMD,
+        arity: -3,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: bitpos,
+    },
+    CommandSpec {
+        name: BITOP_CMD,
+        arity: -4,
+        flags: &[Write],
+        keys: KeySpec {
+            first: 2,
+            last: -1,
+            step: 1,
+            keyword: None,
+        },
+        subcommands: &[],
+        handler: bitop,
+    },
+    CommandSpec {
+        name: BITFIELD_CMD,
+        arity: -2,
+        flags: &[Write],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: bitfield,
+    },
+    CommandSpec {
+        name: PFADD_CMD,
+        arity: -2,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: pfadd,
+    },
+    CommandSpec {
+        name: PFCOUNT_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec {
+            first: 1,
+            last: -1,
+            step: 1,
+            keyword: None,
+        },
+        subcommands: &[],
+        handler: pfcount,
+    },
+    CommandSpec {
+        name: PFMERGE_CMD,
+        arity: -2,
+        flags: &[Write],
+        keys: KeySpec {
+            first: 1,
+            last: -1,
+            step: 1,
+            keyword: None,
+        },
+        subcommands: &[],
+        handler: pfmerge,
+    },
+    CommandSpec {
+        name: GEOADD_CMD,
+        arity: -5,
+        flags: &[Write],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: geoadd,
+    },
+    CommandSpec {
+        name: GEOPOS_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: geopos,
+    },
+    CommandSpec {
+        name: GEODIST_CMD,
+        arity: -4,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: geodist,
+    },
+    CommandSpec {
+        name: GEOHASH_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: geohash,
+    },
+    CommandSpec {
+        name: GEOSEARCH_CMD,
+        arity: -7,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: geosearch,
+    },
+    CommandSpec {
+        name: DUMP_CMD,
+        arity: 2,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: dump,
+    },
+    CommandSpec {
+        name: RESTORE_CMD,
+        arity: -4,
+        flags: &[Write, NoTouch],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: restore,
+    },
+    CommandSpec {
+        name: OBJECT_CMD,
+        arity: -2,
+        flags: &[Readonly, NoTouch],
+        keys: KeySpec::SECOND,
+        subcommands: &["encoding", "idletime", "freq"],
+        handler: object,
+    },
+    CommandSpec {
+        name: MEMORY_CMD,
+        arity: -2,
+        flags: &[Readonly, NoTouch],
+        keys: KeySpec::SECOND,
+        subcommands: &["usage"],
+        handler: memory,
+    },
+    CommandSpec {
+        name: LPUSH_CMD,
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: lpush,
+    },
+    CommandSpec {
+        name: RPUSH_CMD,
+        arity: -3,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: rpush,
+    },
+    CommandSpec {
+        name: LPOP_CMD,
+        arity: 2,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: lpop,
+    },
+    CommandSpec {
+        name: RPOP_CMD,
+        arity: 2,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: rpop,
+    },
+    CommandSpec {
+        name: LLEN_CMD,
+        arity: 2,
+        flags: &[Readonly, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: llen,
+    },
+    CommandSpec {
+        name: LRANGE_CMD,
+        arity: 4,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: lrange,
+    },
+    CommandSpec {
+        name: LMOVE_CMD,
+        arity: 5,
+        flags: &[Write],
+        keys: KeySpec::FIRST_TWO,
+        subcommands: &[],
+        handler: lmove,
+    },
+    CommandSpec {
+        name: RPOPLPUSH_CMD,
+        arity: 3,
+        flags: &[Write],
+        keys: KeySpec::FIRST_TWO,
+        subcommands: &[],
+        handler: rpoplpush,
+    },
+    CommandSpec {
+        name: BLPOP_CMD,
+        arity: -3,
+        flags: &[Write, Blocking],
+        keys: KeySpec {
+            first: 1,
+            last: -2,
+            step: 1,
+            keyword: None,
+        },
+        subcommands: &[],
+        handler: blpop,
+    },
+    CommandSpec {
+        name: BRPOP_CMD,
+        arity: -3,
+        flags: &[Write, Blocking],
+        keys: KeySpec {
+            first: 1,
+            last: -2,
+            step: 1,
+            keyword: None,
+        },
+        subcommands: &[],
+        handler: brpop,
+    },
+    CommandSpec {
+        name: BLMOVE_CMD,
+        arity: 6,
+        flags: &[Write, Blocking],
+        keys: KeySpec::FIRST_TWO,
+        subcommands: &[],
+        handler: blmove,
+    },
+    CommandSpec {
+        name: BRPOPLPUSH_CMD,
+        arity: 4,
+        flags: &[Write, Blocking],
+        keys: KeySpec::FIRST_TWO,
+        subcommands: &[],
+        handler: brpoplpush,
+    },
+    CommandSpec {
+        name: XADD_CMD,
+        arity: -5,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xadd,
+    },
+    CommandSpec {
+        name: XRANGE_CMD,
+        arity: -4,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xrange,
+    },
+    CommandSpec {
+        name: XREVRANGE_CMD,
+        arity: -4,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xrevrange,
+    },
+    CommandSpec {
+        name: XLEN_CMD,
+        arity: 2,
+        flags: &[Readonly, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xlen,
+    },
+    CommandSpec {
+        name: XREAD_CMD,
+        arity: -4,
+        flags: &[Readonly, Blocking],
+        keys: KeySpec::streams(1),
+        subcommands: &[],
+        handler: xread,
+    },
+    CommandSpec {
+        name: XREADGROUP_CMD,
+        arity: -7,
+        flags: &[Write, Blocking],
+        // After GROUP group consumer
+        keys: KeySpec::streams(4),
+        subcommands: &[],
+        handler: xreadgroup,
+    },
+    CommandSpec {
+        name: XGROUP_CMD,
+        arity: -2,
+        flags: &[Write],
+        keys: KeySpec::SECOND,
+        subcommands: &["create", "destroy"],
+        handler: xgroup,
+    },
+    CommandSpec {
+        name: XACK_CMD,
+        arity: -4,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xack,
+    },
+    CommandSpec {
+        name: XPENDING_CMD,
+        arity: -3,
+        flags: &[Readonly],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xpending,
+    },
+    CommandSpec {
+        name: XCLAIM_CMD,
+        arity: -6,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xclaim,
+    },
+    CommandSpec {
+        name: XAUTOCLAIM_CMD,
+        arity: -6,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: xautoclaim,
+    },
+    CommandSpec {
+        name: XINFO_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec::SECOND,
+        subcommands: &["stream", "groups", "consumers"],
+        handler: xinfo,
+    },
+    CommandSpec {
+        name: MULTI_CMD,
+        arity: 1,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: multi,
+    },
+    CommandSpec {
+        name: EXEC_CMD,
+        arity: 1,
+        flags: &[],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: exec,
+    },
+    CommandSpec {
+        name: DISCARD_CMD,
+        arity: 1,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: discard,
+    },
+    CommandSpec {
+        name: SCAN_CMD,
+        arity: -2,
+        flags: &[Readonly],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: scan,
+    },
+    CommandSpec {
+        name: MOVE_CMD,
+        arity: 3,
+        flags: &[Write, Fast],
+        keys: KeySpec::FIRST,
+        subcommands: &[],
+        handler: move_key,
+    },
+    CommandSpec {
+        name: SELECT_CMD,
+        arity: 2,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: select,
+    },
+    CommandSpec {
+        name: SWAPDB_CMD,
+        arity: 3,
+        flags: &[Write, Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: swapdb,
+    },
+    CommandSpec {
+        name: FLUSHDB_CMD,
+        arity: -1,
+        flags: &[Write],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: flushdb,
+    },
+    CommandSpec {
+        name: FLUSHALL_CMD,
+        arity: -1,
+        flags: &[Write],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: flushall,
+    },
+    CommandSpec {
+        name: ASK_CMD,
+        arity: -1,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: ask,
+    },
+    CommandSpec {
+        name: ASKING_CMD,
+        arity: -1,
+        flags: &[Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: asking,
+    },
+    CommandSpec {
+        name: CLUSTER_CMD,
+        arity: -2,
+        flags: &[],
+        keys: KeySpec::NONE,
+        subcommands: &["slots", "keyslot", "reload"],
+        handler: cluster,
+    },
+    CommandSpec {
+        name: CLIENT_CMD,
+        arity: -2,
+        flags: &[Admin],
+        keys: KeySpec::NONE,
+        subcommands: &[
+            "list", "info", "id", "setname", "getname", "kill", "pause", "unpause",
+        ],
+        handler: client,
+    },
+    CommandSpec {
+        name: INFO_CMD,
+        arity: -1,
+        flags: &[],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: info,
+    },
+    CommandSpec {
+        name: COMMAND_CMD,
+        arity: -1,
+        flags: &[],
+        keys: KeySpec::NONE,
+        subcommands: &["count", "info", "getkeys"],
+        handler: command,
+    },
+    CommandSpec {
+        name: CONFIG_CMD,
+        arity: -2,
+        flags: &[Admin],
+        keys: KeySpec::NONE,
+        subcommands: &["get", "set", "rewrite"],
+        handler: config,
+    },
+    CommandSpec {
+        name: SLOWLOG_CMD,
+        arity: -2,
+        flags: &[Admin],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: slowlog,
+    },
+    CommandSpec {
+        name: SUBSCRIBE_CMD,
+        arity: -2,
+        flags: &[Pubsub],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: subscribe,
+    },
+    CommandSpec {
+        name: PSUBSCRIBE_CMD,
+        arity: -2,
+        flags: &[Pubsub],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: psubscribe,
+    },
+    CommandSpec {
+        name: UNSUBSCRIBE_CMD,
+        arity: -1,
+        flags: &[Pubsub],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: unsubscribe,
+    },
+    CommandSpec {
+        name: PUNSUBSCRIBE_CMD,
+        arity: -1,
+        flags: &[Pubsub],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: punsubscribe,
+    },
+    CommandSpec {
+        name: PUBLISH_CMD,
+        arity: 3,
+        flags: &[Pubsub, Fast],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: publish,
+    },
+    CommandSpec {
+        name: MONITOR_CMD,
+        arity: 1,
+        flags: &[Admin],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: monitor,
+    },
+    CommandSpec {
+        name: SHUTDOWN_CMD,
+        arity: -1,
+        flags: &[Admin],
+        keys: KeySpec::NONE,
+        subcommands: &[],
+        handler: shutdown,
+    },
+];
+
+fn ping(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Ping::parse(args).respond(&mut handler.connection))
+}
+
+fn get(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move { Get::parse(args).respond(&mut handler.connection, &db).await })
+}
+
+fn set(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move { Set::parse(args).respond(&mut handler.connection, &db).await })
+}
+
+fn delete(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Delete::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn ttl(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move { Ttl::parse(args).respond(&mut handler.connection, &db).await })
+}
+
+fn expire(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Expire::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn lpush(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Push::parse(args, End::Left)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn rpush(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Push::parse(args, End::Right)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn lpop(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Pop::parse(args, End::Left)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn rpop(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Pop::parse(args, End::Right)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn setbit(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Setbit::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn getbit(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Getbit::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn bitcount(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Bitcount::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn bitpos(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Bitpos::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn bitop(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Bitop::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn bitfield(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Bitfield::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn pfadd(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Pfadd::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn pfcount(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Pfcount::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn pfmerge(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Pfmerge::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn geoadd(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Geoadd::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn geopos(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Geopos::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn geodist(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Geodist::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn geohash(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Geohash::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn geosearch(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Geosearch::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn dump(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Dump::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn restore(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Restore::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn object(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Object::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn memory(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Memory::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn llen(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Llen::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn lrange(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Lrange::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn lmove(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Lmove::parse(args, LMOVE_CMD)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn rpoplpush(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Lmove::parse(args, RPOPLPUSH_CMD)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn blpop(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        BlockingPop::parse(args, End::Left)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn brpop(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        BlockingPop::parse(args, End::Right)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn blmove(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        BlockingMove::parse(args, BLMOVE_CMD)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn brpoplpush(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        BlockingMove::parse(args, BRPOPLPUSH_CMD)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn xadd(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xadd::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xrange(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xrange::parse(args, false)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xrevrange(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xrange::parse(args, true)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xlen(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xlen::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xread(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xread::parse(args, XREAD_CMD)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn xreadgroup(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xread::parse(args, XREADGROUP_CMD)
+            .respond(
+                &mut handler.connection,
+                &db,
+                &handler.db,
+                &handler.shutdown,
+                handler.in_exec,
+                &mut handler.blocked_for,
+            )
+            .await
+    })
+}
+
+fn xgroup(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xgroup::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xack(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xack::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xpending(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xpending::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xclaim(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xclaim::parse(args, XCLAIM_CMD)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xautoclaim(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xclaim::parse(args, XAUTOCLAIM_CMD)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn xinfo(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Xinfo::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn multi(_: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Multi::parse().respond(&mut handler.connection, &mut handler.transaction))
+}
+
+fn exec(_: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Exec::parse().respond(handler))
+}
+
+fn discard(_: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Discard::parse().respond(&mut handler.connection, &mut handler.transaction))
+}
+
+fn scan(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        Scan::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn move_key(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Move::parse(args).respond(&mut handler.connection, &handler.db, handler.db_index))
+}
+
+fn select(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Select::parse(args).respond(
+        &mut handler.connection,
+        &handler.db,
+        &mut handler.db_index,
+    ))
+}
+
+fn swapdb(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(SwapDb::parse(args).respond(&mut handler.connection, &handler.db))
+}
+
+fn flushdb(args: Parser, handler: &mut Handler, db: Db) -> CommandFuture<'_> {
+    Box::pin(async move {
+        FlushDb::parse(args)
+            .respond(&mut handler.connection, &db)
+            .await
+    })
+}
+
+fn flushall(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(FlushAll::parse(args).respond(&mut handler.connection, &handler.db))
+}
+
+fn ask(_: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Ask::parse().respond(&mut handler.connection))
+}
+
+fn asking(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    let command = Asking::parse(args);
+    // ASKING lets the next command use a slot that this node is importing
+    handler.asking = command.is_plain();
+    Box::pin(command.respond(&mut handler.connection))
+}
+
+fn cluster(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(ClusterCmd::parse(args).respond(&mut handler.connection, &handler.topology))
+}
+
+fn client(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Client::parse(args).respond(
+        &mut handler.connection,
+        &handler.clients,
+        handler.client_id,
+    ))
+}
+
+fn info(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Info::parse(args).respond(
+        &mut handler.connection,
+        &handler.db,
+        &handler.clients,
+        &handler.stats,
+    ))
+}
+
+fn command(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(CommandCmd::parse(args).respond(&mut handler.connection))
+}
+
+fn config(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(ConfigCmd::parse(args).respond(&mut handler.connection, &handler.config, &handler.db))
+}
+
+fn slowlog(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Slowlog::parse(args).respond(&mut handler.connection, &handler.slowlog))
+}
+
+fn monitor(_: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Monitor::parse().respond(
+        &mut handler.connection,
+        &handler.monitors,
+        &handler.shutdown,
+    ))
+}
+
+fn subscribe(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Subscribe::parse(args, SUBSCRIBE_CMD).respond(handler))
+}
+
+fn psubscribe(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Subscribe::parse(args, PSUBSCRIBE_CMD).respond(handler))
+}
+
+fn unsubscribe(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Subscribe::parse(args, UNSUBSCRIBE_CMD).respond(handler))
+}
+
+fn punsubscribe(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Subscribe::parse(args, PUNSUBSCRIBE_CMD).respond(handler))
+}
+
+fn publish(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(Publish::parse(args).respond(&mut handler.connection, &handler.pubsub))
+}
+
+fn shutdown(args: Parser, handler: &mut Handler, _: Db) -> CommandFuture<'_> {
+    Box::pin(ShutdownCmd::parse(args).respond(&mut handler.connection, &handler.shutdown))
+}
+
+#[cfg(test)]
+mod registry_tests {
+    use super::*;
+
+    #[test]
+    fn checks_arity_and_finds_keys() {
+        let get = lookup("GET").unwrap();
+        assert!(get.arity_matches(2));
+        assert!(!get.arity_matches(3));
+        assert_eq!(get.keys(&["key".into()]), vec![Bytes::from("key")]);
+
+        let set = lookup("set").unwrap();
+        assert!(!set.arity_matches(2));
+        assert!(set.arity_matches(5));
+        assert!(set.has_flag(Write));
+
+        let multi = KeySpec {
+            first: 1,
+            last: -2,
+            step: 2,
+            keyword: None,
+        };
+        let spec = CommandSpec {
+            keys: multi,
+            ..*lookup("ping").unwrap()
+        };
+        let args = ["a", "1", "b", "2", "timeout"].map(Bytes::from);
+        assert_eq!(spec.keys(&args), vec![Bytes::from("a"), Bytes::from("b")]);
+
+        assert!(lookup("nope").is_none());
+        assert_eq!(
+            lookup(CLIENT_CMD).unwrap().subcommand(Some(&"LIST".into())),
+            Some("list")
+        );
+    }
+
+    #[test]
+    fn command_names_are_unique() {
+        for (index, spec) in COMMAND_TABLE.iter().enumerate() {
+            assert_eq!(spec.name, spec.name.to_lowercase());
+            assert!(COMMAND_TABLE[..index]
+                .iter()
+                .all(|other| other.name != spec.name));
+        }
+    }
+}
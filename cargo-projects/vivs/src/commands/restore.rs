@@ -0,0 +1,176 @@
+use crate::{
+    commands::DataType, db::Access, dump, error::CommandError, notifications::EventClass,
+    parser::Parser, stream::now_ms, Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const RESTORE_CMD: &str = "restore";
+
+#[derive(Debug)]
+struct RestoreArgs {
+    key: Bytes,
+    /// Milliseconds, 0 keeps the time to live that is in the payload
+    ttl: u64,
+    payload: Bytes,
+    replace: bool,
+    /// The time to live is a unix time in milliseconds
+    absolute: bool,
+    /// Seconds since the last access
+    idle_time: Option<u64>,
+    frequency: Option<u8>,
+}
+
+/// RESTORE key ttl serialized-value [REPLACE] [ABSTTL] [IDLETIME seconds] [FREQ frequency]
+/// creates the key out of a DUMP payload.
+///
+/// A ttl of 0 keeps the time to live the key had when it was dumped (none if it had none).
+/// The key has to not exist unless REPLACE is given, IDLETIME and FREQ can't be given together.
+#[derive(Debug)]
+pub struct Restore {
+    args: Result<RestoreArgs, CommandError>,
+}
+
+fn next_arg(data: &mut Parser) -> Result<String, CommandError> {
+    match data.next_as_str() {
+        Ok(Some(arg)) => Ok(arg),
+        _ => Err(CommandError::Syntax),
+    }
+}
+
+fn parse_args(mut data: Parser) -> Result<RestoreArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(ttl)), Ok(Some(payload))) =
+        (data.next_bytes(), data.next_as_str(), data.next_bytes())
+    else {
+        return Err(CommandError::WrongArity(RESTORE_CMD));
+    };
+
+    let ttl = ttl
+        .parse::<i64>()
+        .map_err(|_| CommandError::NotInteger)?
+        .try_into()
+        .map_err(|_| CommandError::Other("Invalid TTL value, must be >= 0".to_owned()))?;
+
+    let mut args = RestoreArgs {
+        key,
+        ttl,
+        payload,
+        replace: false,
+        absolute: false,
+        idle_time: None,
+        frequency: None,
+    };
+
+    while data.size() > 0 {
+        match &next_arg(&mut data)?.to_lowercase()[..] {
+            "replace" => args.replace = true,
+            "absttl" => args.absolute = true,
+            "idletime" => {
+                let idle_time = next_arg(&mut data)?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::NotInteger)?;
+                args.idle_time = Some(idle_time.try_into().map_err(|_| {
+                    CommandError::Other("Invalid IDLETIME value, must be >= 0".to_owned())
+                })?);
+            }
+            "freq" => {
+                let frequency = next_arg(&mut data)?
+                    .parse::<i64>()
+                    .map_err(|_| CommandError::NotInteger)?;
+                args.frequency = Some(frequency.try_into().map_err(|_| {
+                    CommandError::Other("Invalid FREQ value, must be >= 0 and <= 255".to_owned())
+                })?);
+            }
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    // Same as in Redis, a single access time backs both of them so they can't be set together
+    if args.idle_time.is_some() && args.frequency.is_some() {
+        return Err(CommandError::Syntax);
+    }
+
+    Ok(args)
+}
+
+impl Restore {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            RESTORE_CMD.to_uppercase(),
+            self.args
+        );
+
+        let args = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match restore(db, args).await {
+            Ok(()) => conn.write_chunk(DataType::SimpleString, b"OK").await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
+
+async fn restore(db: &Db, args: RestoreArgs) -> Result<(), CommandError> {
+    let (value, dumped_ttl) = dump::restore(&args.payload)?;
+    let key = args.key;
+
+    // Unix time in milliseconds when the key expires,
+    // saturated so that the largest TTL that DUMP writes can be restored
+    let now = now_ms();
+    let expires_at = match (args.ttl, args.absolute) {
+        (0, _) => dumped_ttl.map(|ttl| now.saturating_add(ttl)),
+        (ttl, true) => Some(ttl),
+        (ttl, false) => Some(now.saturating_add(ttl)),
+    };
+
+    let mut db_guard = db.write_live(&[&key]).await;
+    let mut expiries_guard = db.expirations.write().await;
+
+    if db_guard.contains_key(&key) && !args.replace {
+        return Err(CommandError::BusyKey);
+    }
+
+    // Same as in Redis, a key that would have expired already is not created (the old one is still replaced)
+    if expires_at.is_some_and(|expires_at| expires_at <= now) {
+        expiries_guard.remove(&key);
+        if db_guard.remove(&key).is_some() {
+            db.notify(EventClass::Generic, "del", &key);
+        }
+        return Ok(());
+    }
+
+    db_guard.insert(key.clone(), value);
+    match expires_at {
+        // Expirations are kept in seconds, so the key lives a little longer rather than shorter
+        Some(expires_at) => expiries_guard.insert(key.clone(), expires_at.div_ceil(1000)),
+        None => expiries_guard.remove(&key),
+    };
+
+    let access = Access::with(
+        now,
+        args.idle_time.map(|seconds| seconds.saturating_mul(1000)),
+        args.frequency,
+    );
+    db.accesses.write().await.insert(key.clone(), access);
+
+    db.notify(EventClass::Generic, RESTORE_CMD, &key);
+    // Clients blocked on the key get served, same as after a push
+    db.blocked.signal(&key);
+
+    Ok(())
+}
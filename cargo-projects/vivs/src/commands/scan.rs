@@ -0,0 +1,138 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, utils::glob_match, Connection, Db,
+    GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const SCAN_CMD: &str = "scan";
+
+// Number of keys SCAN looks at when COUNT is not passed in
+const DEFAULT_COUNT: usize = 10;
+
+#[derive(Debug)]
+struct ScanArgs {
+    cursor: u64,
+    pattern: Option<Bytes>,
+    count: usize,
+}
+
+/// SCAN cursor [MATCH pattern] [COUNT count] iterates over the keyspace a few keys at a time.
+///
+/// Keys are visited in the order of their hashes and the cursor is the hash of the next key to visit.
+/// This way keys that get added or removed during the iteration do not shift the rest of the keys,
+/// so keys that exist for the whole iteration are always returned.
+#[derive(Debug)]
+pub struct Scan {
+    args: Result<ScanArgs, CommandError>,
+}
+
+fn key_hash(key: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn parse_args(mut data: Parser) -> Result<ScanArgs, CommandError> {
+    let Ok(Some(cursor)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(SCAN_CMD));
+    };
+
+    let mut args = ScanArgs {
+        cursor: cursor.parse().map_err(|_| CommandError::NotInteger)?,
+        pattern: None,
+        count: DEFAULT_COUNT,
+    };
+
+    while let Ok(Some(option)) = data.next_as_str() {
+        let Ok(Some(value)) = data.next_bytes() else {
+            return Err(CommandError::Syntax);
+        };
+
+        match &option.to_lowercase()[..] {
+            // Patterns are matched against raw keys, so they don't have to be valid UTF-8 either
+            "match" => args.pattern = Some(value),
+            "count" => match std::str::from_utf8(&value).map(str::parse::<usize>) {
+                Ok(Ok(count)) if count > 0 => args.count = count,
+                Ok(Ok(_)) => return Err(CommandError::Syntax),
+                _ => return Err(CommandError::NotInteger),
+            },
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    Ok(args)
+}
+
+impl Scan {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SCAN_CMD.to_uppercase(),
+            self.args
+        );
+
+        let args = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let (keys, next_cursor) = {
+            let db_guard = db.db.read().await;
+            let expiries_guard = db.expirations.read().await;
+
+            let mut keys = db_guard
+                .keys()
+                .map(|key| (key_hash(key), key))
+                .filter(|(hash, _)| *hash >= args.cursor)
+                .collect::<Vec<_>>();
+            keys.sort_unstable();
+
+            // The cursor is the hash of the first key that did not make it into this batch
+            let next_cursor = keys.get(args.count).map(|(hash, _)| *hash).unwrap_or(0);
+
+            let keys = keys
+                .into_iter()
+                .take(args.count)
+                .map(|(_, key)| key)
+                // Expired keys are left for GET / TTL to evict
+                .filter(|key| expiries_guard.get(*key).is_none_or(|expiry| *expiry > now))
+                .filter(|key| {
+                    args.pattern
+                        .as_ref()
+                        .is_none_or(|pattern| glob_match(pattern, key))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            (keys, next_cursor)
+        };
+
+        conn.write_array_len(2).await?;
+        conn.write_chunk(DataType::BulkString, next_cursor.to_string().as_bytes())
+            .await?;
+
+        conn.write_array_len(keys.len()).await?;
+        for key in keys {
+            conn.write_chunk(DataType::BulkString, &key).await?;
+        }
+
+        Ok(())
+    }
+}
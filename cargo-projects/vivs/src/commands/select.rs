@@ -0,0 +1,79 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, Connection, DataStore, GenericResult,
+};
+use log::info;
+
+pub const SELECT_CMD: &str = "select";
+
+pub const DB_INDEX_ERR: &str = "DB index";
+
+/// Parses a database index (i.e. SELECT 1) of the `command`
+pub(crate) fn parse_index(data: &mut Parser, command: &'static str) -> Result<usize, CommandError> {
+    let Ok(Some(index)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(command));
+    };
+
+    index.parse::<usize>().map_err(|_| CommandError::NotInteger)
+}
+
+/// SELECT index switches the connection to a different database (db 0 by default).
+///
+/// Same as in Redis, cluster mode only has db 0.
+#[derive(Debug)]
+pub struct Select {
+    index: Result<usize, CommandError>,
+}
+
+impl Select {
+    pub fn parse(mut data: Parser) -> Self {
+        let index = parse_index(&mut data, SELECT_CMD);
+
+        let index = if data.size() != 0 {
+            Err(CommandError::WrongArity(SELECT_CMD))
+        } else {
+            index
+        };
+
+        Self { index }
+    }
+
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        store: &DataStore,
+        db_index: &mut usize,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SELECT_CMD.to_uppercase(),
+            self.index
+        );
+
+        let index = match self.index {
+            Ok(index) => index,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        if store.is_cluster() && index != 0 {
+            let error =
+                CommandError::NotAllowed("SELECT is not allowed in cluster mode".to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        if index >= store.len() {
+            conn.write_error(&CommandError::OutOfRange(DB_INDEX_ERR))
+                .await?;
+            return Ok(());
+        }
+
+        *db_index = index;
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
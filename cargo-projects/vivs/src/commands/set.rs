@@ -1,9 +1,9 @@
 use super::CommonCommand;
 use crate::{
-    commands::{ARGS_NUM, NON_INT},
-    parser::Parser,
-    Connection, DataStore, GenericResult,
+    db::Value, error::CommandError, notifications::EventClass, parser::Parser, Connection, Db,
+    GenericResult,
 };
+use bytes::Bytes;
 use log::info;
 use std::time::{Duration, SystemTime};
 
@@ -13,82 +13,106 @@ pub const SET_CMD: &str = "set";
 // xm - (x)expire in (m)minutes (TODO: not implemented)
 const EXPIRE_SECONDS: &str = "xs";
 
-#[derive(Default, Debug)]
+const INVALID_EXPIRE_ERR: &str = "invalid expire time in 'set' command";
+
+#[derive(Debug)]
 pub struct Set {
-    key: Option<String>,
-    value: Option<String>,
-    expiry: Option<u64>,
+    pub key: Option<Bytes>,
+    value: Option<Bytes>,
+    // Unix time (in seconds) when the key expires, or why the options were rejected
+    expiry: Result<Option<u64>, CommandError>,
+}
+
+/// Parses [XS seconds] into the unix time when the key expires
+fn parse_expiry(data: &mut Parser) -> Result<Option<u64>, CommandError> {
+    let Some(option) = data.next_as_str().map_err(|_| CommandError::Syntax)? else {
+        return Ok(None);
+    };
+
+    // Check for options (e.g. expire)
+    if option.to_lowercase() != EXPIRE_SECONDS {
+        return Err(CommandError::Syntax);
+    }
+
+    // Expire value (seconds)
+    let Some(expiry_s) = data.next_as_str().map_err(|_| CommandError::NotInteger)? else {
+        return Err(CommandError::Syntax);
+    };
+    let expiry_s = expiry_s
+        .parse::<u64>()
+        .map_err(|_| CommandError::NotInteger)?;
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    // Same as in Redis, the expire time has to be positive and has to fit the clock
+    let expiry_time = SystemTime::now()
+        .checked_add(Duration::from_secs(expiry_s))
+        .filter(|_| expiry_s > 0)
+        .ok_or_else(|| CommandError::Other(INVALID_EXPIRE_ERR.to_owned()))?;
+    let ttl = expiry_time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_err(|_| CommandError::Other(INVALID_EXPIRE_ERR.to_owned()))?
+        .as_secs();
+
+    Ok(Some(ttl))
 }
 
 impl CommonCommand for Set {
     fn parse(mut data: Parser) -> Self {
         // Get the key first
-        let Ok(key) = data.next_as_str() else {
-            return Self::default();
+        let Ok(key) = data.next_bytes() else {
+            return Self {
+                key: None,
+                value: None,
+                expiry: Ok(None),
+            };
         };
         // Get the value second
-        let Ok(value) = data.next_as_str() else {
-            return Self::default();
+        let Ok(value) = data.next_bytes() else {
+            return Self {
+                key,
+                value: None,
+                expiry: Ok(None),
+            };
         };
         // Get the expiry value last
-        if let Ok(Some(option)) = data.next_as_str() {
-            // Check for options (e.g. expire)
-            // *"expire" dereferences the static reference which is a string allocated in the read-only memory
-            if option.to_lowercase() == *EXPIRE_SECONDS {
-                // Expire value (seconds)
-                if let Ok(Some(expiry_val_as_string)) = data.next_as_str() {
-                    // Parse to u64
-                    if let Ok(expiry_s_u64) = expiry_val_as_string.parse::<u64>() {
-                        let current_system_time = SystemTime::now();
-                        let expiry_time = current_system_time + Duration::from_secs(expiry_s_u64);
-
-                        let ttl = expiry_time
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs();
-
-                        return Self {
-                            key,
-                            value,
-                            expiry: Some(ttl),
-                        };
-                    }
-                }
-            }
-        }
+        let expiry = parse_expiry(&mut data);
 
-        Self {
-            key,
-            value,
-            expiry: None,
-        }
+        Self { key, value, expiry }
     }
 
-    async fn respond(&self, connection: &mut Connection, db: &DataStore) -> GenericResult<()> {
-        let Some(key) = self.key.as_ref() else {
-            connection.write_error(ARGS_NUM.as_bytes()).await?;
+    async fn respond(&self, connection: &mut Connection, db: &Db) -> GenericResult<()> {
+        let (Some(key), Some(value)) = (self.key.as_ref(), self.value.as_ref()) else {
+            let error = CommandError::WrongArity(SET_CMD);
+            connection.write_error(&error).await?;
             return Ok(());
         };
 
-        let Some(value) = self.value.as_ref() else {
-            connection.write_error(ARGS_NUM.as_bytes()).await?;
-            return Ok(());
+        // Nothing gets written when the options are invalid
+        let expiry = match &self.expiry {
+            Ok(expiry) => *expiry,
+            Err(err) => {
+                connection.write_error(err).await?;
+                return Ok(());
+            }
         };
 
         let mut db_guard = db.db.write().await;
 
-        db_guard.insert(key.clone(), value.to_owned());
-
-        if let Some(expiration) = self.expiry {
-            if expiration == 0 {
-                connection.write_error(NON_INT.as_bytes()).await?;
-                return Ok(());
-            }
+        db_guard.insert(key.clone(), Value::String(value.clone()));
 
+        if let Some(expiration) = expiry {
             let mut expirations_data_store_guard = db.expirations.write().await;
             expirations_data_store_guard.insert(key.clone(), expiration);
         };
 
+        db.notify(EventClass::String, SET_CMD, key);
+        if expiry.is_some() {
+            db.notify(EventClass::Generic, "expire", key);
+        }
+
         info!(
             "{}",
             format!(
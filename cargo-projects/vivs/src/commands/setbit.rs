@@ -0,0 +1,93 @@
+use crate::{
+    bitmap::{self, BIT_VALUE_ERR},
+    commands::DataType,
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const SETBIT_CMD: &str = "setbit";
+
+#[derive(Debug)]
+struct SetbitArgs {
+    key: Bytes,
+    offset: u64,
+    bit: u8,
+}
+
+/// SETBIT key offset value sets the bit at the offset of the string to 0 or 1,
+/// the string is padded with zeros when it is too short (or created when the key does not exist).
+///
+/// Replies with the bit the offset had before.
+#[derive(Debug)]
+pub struct Setbit {
+    args: Result<SetbitArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<SetbitArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(offset)), Ok(Some(bit))) =
+        (data.next_bytes(), data.next_as_str(), data.next_as_str())
+    else {
+        return Err(CommandError::WrongArity(SETBIT_CMD));
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(SETBIT_CMD));
+    }
+
+    let offset = bitmap::parse_offset(&offset)?;
+    let bit = match &bit[..] {
+        "0" => 0,
+        "1" => 1,
+        _ => return Err(CommandError::Other(BIT_VALUE_ERR.to_owned())),
+    };
+
+    Ok(SetbitArgs { key, offset, bit })
+}
+
+impl Setbit {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SETBIT_CMD.to_uppercase(),
+            self.args
+        );
+
+        let SetbitArgs { key, offset, bit } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let old = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            bitmap::update(&mut db_guard, &key, |bytes| {
+                bitmap::set_bit(bytes, offset, bit)
+            })
+        };
+
+        match old {
+            Ok(old) => {
+                db.notify(EventClass::String, SETBIT_CMD, &key);
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(old.into()))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
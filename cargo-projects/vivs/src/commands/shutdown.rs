@@ -0,0 +1,76 @@
+use crate::{error::CommandError, parser::Parser, Connection, GenericResult, Shutdown};
+use log::info;
+
+pub const SHUTDOWN_CMD: &str = "shutdown";
+
+#[derive(Debug, Default, PartialEq)]
+pub enum SaveMode {
+    /// SHUTDOWN (saves if persistence is configured)
+    #[default]
+    Default,
+    /// SHUTDOWN NOSAVE
+    NoSave,
+    /// SHUTDOWN SAVE
+    Save,
+}
+
+/// SHUTDOWN [NOSAVE | SAVE] stops the server gracefully.
+///
+/// The server stops accepting connections, waits for in-flight commands to finish
+/// and then exits. Same as in Redis, there's no reply on success, the connection gets closed.
+#[derive(Debug)]
+pub struct ShutdownCmd {
+    mode: Result<SaveMode, CommandError>,
+}
+
+impl ShutdownCmd {
+    pub fn parse(mut data: Parser) -> Self {
+        let mode = match data.next_as_str() {
+            Ok(None) => Ok(SaveMode::Default),
+            Ok(Some(mode)) => match &mode.to_lowercase()[..] {
+                "nosave" => Ok(SaveMode::NoSave),
+                "save" => Ok(SaveMode::Save),
+                _ => Err(CommandError::Syntax),
+            },
+            Err(_) => Err(CommandError::Syntax),
+        };
+
+        let mode = if data.size() != 0 {
+            Err(CommandError::WrongArity(SHUTDOWN_CMD))
+        } else {
+            mode
+        };
+
+        Self { mode }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, shutdown: &Shutdown) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SHUTDOWN_CMD.to_uppercase(),
+            self.mode
+        );
+
+        let mode = match self.mode {
+            Ok(mode) => mode,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        // Vivs keeps data in memory only, so there's nothing that can be saved
+        if mode == SaveMode::Save {
+            let error = CommandError::Other(
+                "Errors trying to SHUTDOWN, persistence is not configured".to_owned(),
+            );
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        shutdown.trigger();
+
+        Ok(())
+    }
+}
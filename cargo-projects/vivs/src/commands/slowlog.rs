@@ -0,0 +1,123 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, utils::u64_as_bytes, Connection,
+    GenericResult, SlowLog,
+};
+use log::info;
+
+pub const SLOWLOG_CMD: &str = "slowlog";
+
+// Number of entries SLOWLOG GET returns when count is not passed in
+const DEFAULT_GET_COUNT: usize = 10;
+
+#[derive(Debug)]
+pub enum Subcommand {
+    /// SLOWLOG GET [count], a negative count returns all the entries
+    Get(Option<usize>),
+    /// SLOWLOG LEN
+    Len,
+    /// SLOWLOG RESET
+    Reset,
+}
+
+/// SLOWLOG reads and resets the log of commands that exceeded the configured execution time.
+#[derive(Debug)]
+pub struct Slowlog {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Ok(Some(subcommand)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(SLOWLOG_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "get" => match data.next_as_str() {
+            Ok(Some(count)) => {
+                let count = count.parse::<i64>().map_err(|_| CommandError::NotInteger)?;
+                Subcommand::Get(usize::try_from(count).ok())
+            }
+            _ => Subcommand::Get(Some(DEFAULT_GET_COUNT)),
+        },
+        "len" => Subcommand::Len,
+        "reset" => Subcommand::Reset,
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: SLOWLOG_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(SLOWLOG_CMD));
+    }
+
+    Ok(subcommand)
+}
+
+impl Slowlog {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, slowlog: &SlowLog) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SLOWLOG_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            Subcommand::Get(count) => {
+                let entries = slowlog.get(count).await;
+
+                // Each entry is an array of:
+                // id, unix timestamp, duration (microseconds), arguments, client address and name
+                conn.write_array_len(entries.len()).await?;
+                for entry in entries {
+                    conn.write_array_len(6).await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(entry.id))
+                        .await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(entry.timestamp))
+                        .await?;
+                    conn.write_chunk(
+                        DataType::Integer,
+                        &u64_as_bytes(entry.duration.as_micros() as u64),
+                    )
+                    .await?;
+
+                    conn.write_array_len(entry.args.len()).await?;
+                    for arg in entry.args {
+                        conn.write_chunk(DataType::BulkString, &arg).await?;
+                    }
+
+                    conn.write_chunk(DataType::BulkString, entry.client_addr.as_bytes())
+                        .await?;
+                    conn.write_chunk(DataType::BulkString, entry.client_name.as_bytes())
+                        .await?;
+                }
+            }
+            Subcommand::Len => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(slowlog.len().await as u64))
+                    .await?;
+            }
+            Subcommand::Reset => {
+                slowlog.reset().await;
+                conn.write_chunk(DataType::SimpleString, b"OK").await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    commands::{ping::PING_CMD, Command, DataType},
+    error::CommandError,
+    limits::ClientClass,
+    parser::Parser,
+    pubsub::{Message, Subscription},
+    utils::u64_as_bytes,
+    Connection, GenericResult, Handler,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const SUBSCRIBE_CMD: &str = "subscribe";
+pub const PSUBSCRIBE_CMD: &str = "psubscribe";
+pub const UNSUBSCRIBE_CMD: &str = "unsubscribe";
+pub const PUNSUBSCRIBE_CMD: &str = "punsubscribe";
+
+/// SUBSCRIBE channel [channel ...] / PSUBSCRIBE pattern [pattern ...] subscribe the client
+/// to the channels / to the channels that match the glob-style patterns.
+///
+/// The connection stays in the subscribed mode until it unsubscribes from everything
+/// (UNSUBSCRIBE [channel ...] / PUNSUBSCRIBE [pattern ...], no arguments unsubscribe from all),
+/// only the subscribe commands and PING can be used in the meantime.
+#[derive(Debug)]
+pub struct Subscribe {
+    name: &'static str,
+    channels: Vec<Bytes>,
+}
+
+impl Subscribe {
+    pub fn parse(mut data: Parser, name: &'static str) -> Self {
+        let mut channels = vec![];
+        while let Ok(Some(channel)) = data.next_bytes() {
+            channels.push(channel);
+        }
+
+        Self { name, channels }
+    }
+
+    pub async fn respond(self, handler: &mut Handler) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            handler.connection.connected_peer_addr(),
+            self.name.to_uppercase(),
+            self.channels
+        );
+
+        let output = handler.connection.output_buffer().clone();
+        let mut subscription = handler.pubsub.subscription(output.clone());
+        let shutdown = handler.shutdown.clone();
+        output.set_class(ClientClass::Pubsub);
+
+        apply(
+            &mut handler.connection,
+            &mut subscription,
+            self.name,
+            self.channels,
+        )
+        .await?;
+
+        // Outside of the subscribed mode UNSUBSCRIBE only replies
+        while subscription.count() > 0 {
+            tokio::select! {
+                Some(message) = subscription.recv() => {
+                    write_message(&mut handler.connection, message).await?
+                }
+                _ = shutdown.wait() => return Ok(()),
+                command = handler.read_command() => {
+                    let (command, args) = command?;
+                    run(&mut handler.connection, &mut subscription, command, args).await?;
+                }
+            }
+        }
+
+        output.set_class(ClientClass::Normal);
+        Ok(())
+    }
+}
+
+/// Runs a command sent while the connection is in the subscribed mode
+async fn run(
+    conn: &mut Connection,
+    subscription: &mut Subscription,
+    command: Command,
+    args: Vec<Bytes>,
+) -> GenericResult<()> {
+    if let Err(err) = command.check() {
+        conn.write_error(&err).await?;
+        return Ok(());
+    }
+
+    let name = [
+        SUBSCRIBE_CMD,
+        PSUBSCRIBE_CMD,
+        UNSUBSCRIBE_CMD,
+        PUNSUBSCRIBE_CMD,
+    ]
+    .into_iter()
+    .find(|name| command.is(name));
+
+    // The arguments include the command name
+    let args = args.into_iter().skip(1).collect::<Vec<_>>();
+
+    match name {
+        Some(name) => apply(conn, subscription, name, args).await?,
+        None if command.is(PING_CMD) => {
+            conn.write_array_len(2).await?;
+            conn.write_chunk(DataType::BulkString, b"pong").await?;
+            let value = args.first().cloned().unwrap_or_default();
+            conn.write_chunk(DataType::BulkString, &value).await?;
+        }
+        None => {
+            let error = CommandError::NotAllowed(format!(
+                "Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context",
+                command.name()
+            ));
+            conn.write_error(&error).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// (Un)subscribes from the channels one by one, each of them gets a reply with the number
+/// of channels and patterns the connection is subscribed to afterwards
+async fn apply(
+    conn: &mut Connection,
+    subscription: &mut Subscription,
+    name: &'static str,
+    channels: Vec<Bytes>,
+) -> GenericResult<()> {
+    let channels = match name {
+        UNSUBSCRIBE_CMD if channels.is_empty() => subscription.channels(),
+        PUNSUBSCRIBE_CMD if channels.is_empty() => subscription.patterns(),
+        _ => channels,
+    };
+
+    // Nothing to unsubscribe from, still replied to once
+    if channels.is_empty() {
+        conn.write_array_len(3).await?;
+        conn.write_chunk(DataType::BulkString, name.as_bytes())
+            .await?;
+        conn.write_null().await?;
+        conn.write_chunk(
+            DataType::Integer,
+            &u64_as_bytes(subscription.count() as u64),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    for channel in channels {
+        match name {
+            SUBSCRIBE_CMD => subscription.subscribe(channel.clone()),
+            PSUBSCRIBE_CMD => subscription.psubscribe(channel.clone()),
+            UNSUBSCRIBE_CMD => subscription.unsubscribe(&channel),
+            _ => subscription.punsubscribe(&channel),
+        }
+
+        conn.write_array_len(3).await?;
+        conn.write_chunk(DataType::BulkString, name.as_bytes())
+            .await?;
+        conn.write_chunk(DataType::BulkString, &channel).await?;
+        conn.write_chunk(
+            DataType::Integer,
+            &u64_as_bytes(subscription.count() as u64),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// message channel payload, or pmessage pattern channel payload when it matched a pattern
+async fn write_message(conn: &mut Connection, message: Message) -> GenericResult<()> {
+    match message.pattern {
+        Some(pattern) => {
+            conn.write_array_len(4).await?;
+            conn.write_chunk(DataType::BulkString, b"pmessage").await?;
+            conn.write_chunk(DataType::BulkString, &pattern).await?;
+        }
+        None => {
+            conn.write_array_len(3).await?;
+            conn.write_chunk(DataType::BulkString, b"message").await?;
+        }
+    }
+
+    conn.write_chunk(DataType::BulkString, &message.channel)
+        .await?;
+    conn.write_chunk(DataType::BulkString, &message.payload)
+        .await?;
+
+    Ok(())
+}
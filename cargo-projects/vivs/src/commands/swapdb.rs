@@ -0,0 +1,69 @@
+use crate::{
+    commands::{select::parse_index, select::DB_INDEX_ERR, DataType},
+    error::CommandError,
+    parser::Parser,
+    Connection, DataStore, GenericResult,
+};
+use log::info;
+
+pub const SWAPDB_CMD: &str = "swapdb";
+
+/// SWAPDB index1 index2 swaps two databases,
+/// connections that selected one of them see the other one straight away.
+#[derive(Debug)]
+pub struct SwapDb {
+    indexes: Result<(usize, usize), CommandError>,
+}
+
+fn parse_indexes(mut data: Parser) -> Result<(usize, usize), CommandError> {
+    let first = parse_index(&mut data, SWAPDB_CMD)?;
+    let second = parse_index(&mut data, SWAPDB_CMD)?;
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(SWAPDB_CMD));
+    }
+
+    Ok((first, second))
+}
+
+impl SwapDb {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            indexes: parse_indexes(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, store: &DataStore) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            SWAPDB_CMD.to_uppercase(),
+            self.indexes
+        );
+
+        let (first, second) = match self.indexes {
+            Ok(indexes) => indexes,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        if store.is_cluster() {
+            let error =
+                CommandError::NotAllowed("SWAPDB is not allowed in cluster mode".to_owned());
+            conn.write_error(&error).await?;
+            return Ok(());
+        }
+
+        if !store.swap(first, second) {
+            conn.write_error(&CommandError::OutOfRange(DB_INDEX_ERR))
+                .await?;
+            return Ok(());
+        }
+
+        conn.write_chunk(DataType::SimpleString, b"OK").await?;
+
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 use super::CommonCommand;
-use crate::commands::ARGS_NUM;
+use crate::error::CommandError;
 use crate::parser::Parser;
 use crate::utils::u64_as_bytes;
-use crate::{Connection, DataStore, GenericResult};
+use crate::{Connection, Db, GenericResult};
+use bytes::Bytes;
 use log::info;
 use std::time::{Duration, SystemTime};
 
@@ -10,21 +11,21 @@ pub const TTL_CMD: &str = "ttl";
 
 #[derive(Debug)]
 pub struct Ttl {
-    key: Option<String>,
+    pub key: Option<Bytes>,
 }
 
 impl CommonCommand for Ttl {
     fn parse(mut data: Parser) -> Self {
-        let Ok(key) = data.next_as_str() else {
+        let Ok(key) = data.next_bytes() else {
             return Self { key: None };
         };
 
         Self { key }
     }
 
-    async fn respond(&self, conn: &mut Connection, db: &DataStore) -> GenericResult<()> {
+    async fn respond(&self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
         let Some(key) = self.key.as_ref() else {
-            conn.write_error(ARGS_NUM.as_bytes()).await?;
+            conn.write_error(&CommandError::WrongArity(TTL_CMD)).await?;
             return Ok(());
         };
 
@@ -36,7 +37,7 @@ impl CommonCommand for Ttl {
                 "{:?} {:?} {:?}",
                 conn.connected_peer_addr(),
                 TTL_CMD.to_uppercase(),
-                self.key.as_ref().unwrap()
+                key
             )
         );
 
@@ -54,6 +55,7 @@ impl CommonCommand for Ttl {
 
                 let mut db_guard = db.db.write().await;
                 db_guard.remove(key);
+                db.expired(key);
 
                 Duration::from_secs(0)
             } else {
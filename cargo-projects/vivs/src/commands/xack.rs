@@ -0,0 +1,89 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    stream::{self, StreamId},
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XACK_CMD: &str = "xack";
+
+#[derive(Debug)]
+struct AckArgs {
+    key: Bytes,
+    group: Bytes,
+    ids: Vec<StreamId>,
+}
+
+/// XACK key group id [id ...] removes the entries from the pending entries of the group.
+///
+/// Replies with the number of entries that were pending.
+#[derive(Debug)]
+pub struct Xack {
+    args: Result<AckArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<AckArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(group))) = (data.next_bytes(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(XACK_CMD));
+    };
+
+    let mut ids = vec![];
+    while let Ok(Some(id)) = data.next_as_str() {
+        ids.push(StreamId::parse(&id, 0)?);
+    }
+
+    if ids.is_empty() {
+        return Err(CommandError::WrongArity(XACK_CMD));
+    }
+
+    Ok(AckArgs { key, group, ids })
+}
+
+impl Xack {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XACK_CMD.to_uppercase(),
+            self.args
+        );
+
+        let AckArgs { key, group, ids } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        // Same as in Redis, nothing is acknowledged if the key or the group does not exist
+        let acked = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            stream::get_mut(&mut db_guard, &key).map(|stream| {
+                stream
+                    .and_then(|stream| stream.groups.get_mut(&group))
+                    .map_or(0, |group| group.ack(&ids))
+            })
+        };
+
+        match acked {
+            Ok(acked) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(acked as u64))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    stream::{self, now_ms, NewId, StreamId, Trim},
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XADD_CMD: &str = "xadd";
+
+#[derive(Debug)]
+struct AddArgs {
+    key: Bytes,
+    // NOMKSTREAM, the stream is not created if it does not exist
+    no_create: bool,
+    // Trimming and whether it is approximate (~)
+    trim: Option<(Trim, bool)>,
+    id: NewId,
+    fields: Vec<(Bytes, Bytes)>,
+}
+
+/// XADD key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold] <*|id> field value [field value ...]
+/// appends an entry to the stream (created if the key does not exist) and trims it.
+///
+/// Replies with the ID of the entry, null if NOMKSTREAM is used and the key does not exist.
+#[derive(Debug)]
+pub struct Xadd {
+    args: Result<AddArgs, CommandError>,
+}
+
+/// Parses [=|~] threshold of MAXLEN / MINID
+pub(crate) fn parse_trim(data: &mut Parser, strategy: &str) -> Result<(Trim, bool), CommandError> {
+    let Ok(Some(mut threshold)) = data.next_as_str() else {
+        return Err(CommandError::Syntax);
+    };
+
+    let approximate = threshold == "~";
+    if approximate || threshold == "=" {
+        let Ok(Some(value)) = data.next_as_str() else {
+            return Err(CommandError::Syntax);
+        };
+        threshold = value;
+    }
+
+    let trim = match strategy {
+        "maxlen" => Trim::MaxLen(threshold.parse().map_err(|_| CommandError::NotInteger)?),
+        _ => Trim::MinId(StreamId::parse(&threshold, 0)?),
+    };
+
+    Ok((trim, approximate))
+}
+
+fn parse_args(mut data: Parser) -> Result<AddArgs, CommandError> {
+    let Ok(Some(key)) = data.next_bytes() else {
+        return Err(CommandError::WrongArity(XADD_CMD));
+    };
+
+    let mut no_create = false;
+    let mut trim = None;
+
+    // Options come first, the first argument that is not an option is the ID
+    let id = loop {
+        let Ok(Some(arg)) = data.next_as_str() else {
+            return Err(CommandError::WrongArity(XADD_CMD));
+        };
+
+        match &arg.to_lowercase()[..] {
+            "nomkstream" => no_create = true,
+            strategy @ ("maxlen" | "minid") => trim = Some(parse_trim(&mut data, strategy)?),
+            _ => break NewId::parse(&arg)?,
+        }
+    };
+
+    let mut fields = vec![];
+    while let Ok(Some(field)) = data.next_bytes() {
+        let Ok(Some(value)) = data.next_bytes() else {
+            return Err(CommandError::WrongArity(XADD_CMD));
+        };
+        fields.push((field, value));
+    }
+
+    if fields.is_empty() {
+        return Err(CommandError::WrongArity(XADD_CMD));
+    }
+
+    Ok(AddArgs {
+        key,
+        no_create,
+        trim,
+        id,
+        fields,
+    })
+}
+
+impl Xadd {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XADD_CMD.to_uppercase(),
+            self.args
+        );
+
+        let AddArgs {
+            key,
+            no_create,
+            trim,
+            id,
+            fields,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let added = {
+            let mut db_guard = db.write_live(&[&key]).await;
+
+            match stream::get(&db_guard, &key) {
+                Ok(None) if no_create => Ok(None),
+                Err(err) => Err(err),
+                _ => stream::get_or_create(&mut db_guard, &key).and_then(|stream| {
+                    let id = stream.next_id(id, now_ms())?;
+                    stream.add(id, fields);
+                    let trimmed = trim
+                        .map(|(trim, approximate)| stream.trim(trim, approximate))
+                        .unwrap_or_default();
+                    Ok(Some((id, trimmed)))
+                }),
+            }
+        };
+
+        match added {
+            Ok(Some((id, trimmed))) => {
+                db.notify(EventClass::Stream, XADD_CMD, &key);
+                if trimmed > 0 {
+                    db.notify(EventClass::Stream, "xtrim", &key);
+                }
+
+                // Clients reading the stream (XREAD BLOCK) get woken up once the command is done
+                db.blocked.signal(&key);
+                conn.write_chunk(DataType::BulkString, id.to_string().as_bytes())
+                    .await?;
+            }
+            Ok(None) => conn.write_null().await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
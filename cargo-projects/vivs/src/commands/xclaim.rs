@@ -0,0 +1,185 @@
+use crate::{
+    commands::{xrange::write_entries, DataType},
+    error::CommandError,
+    parser::Parser,
+    stream::{self, now_ms, Entry, StreamId},
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XCLAIM_CMD: &str = "xclaim";
+pub const XAUTOCLAIM_CMD: &str = "xautoclaim";
+
+// Same as in Redis, XAUTOCLAIM scans 100 pending entries unless COUNT is used
+const DEFAULT_COUNT: usize = 100;
+
+#[derive(Debug)]
+enum Claimed {
+    /// XCLAIM, the IDs to claim
+    Ids(Vec<StreamId>),
+    /// XAUTOCLAIM, where to start scanning the pending entries from and how many to scan
+    Scan { start: StreamId, count: usize },
+}
+
+#[derive(Debug)]
+struct ClaimArgs {
+    key: Bytes,
+    group: Bytes,
+    consumer: Bytes,
+    min_idle: u64,
+    claimed: Claimed,
+    // JUSTID, only the IDs are returned
+    just_id: bool,
+}
+
+/// XCLAIM key group consumer min-idle-time id [id ...] [JUSTID] gives the pending entries
+/// that were idle for at least min-idle-time milliseconds to the consumer.
+/// Replies with the claimed entries (or their IDs with JUSTID).
+///
+/// XAUTOCLAIM key group consumer min-idle-time start [COUNT count] [JUSTID] does the same for
+/// the pending entries starting from the start ID. Replies with
+/// [the ID to continue from (0-0 once done), claimed entries, IDs of the entries that were trimmed].
+#[derive(Debug)]
+pub struct Xclaim {
+    name: &'static str,
+    args: Result<ClaimArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser, name: &'static str) -> Result<ClaimArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(group)), Ok(Some(consumer)), Ok(Some(min_idle)), Ok(Some(first))) = (
+        data.next_bytes(),
+        data.next_bytes(),
+        data.next_bytes(),
+        data.next_as_str(),
+        data.next_as_str(),
+    ) else {
+        return Err(CommandError::WrongArity(name));
+    };
+    let min_idle = min_idle.parse().map_err(|_| CommandError::NotInteger)?;
+
+    let mut just_id = false;
+    let claimed = if name == XCLAIM_CMD {
+        let mut ids = vec![StreamId::parse(&first, 0)?];
+        while let Ok(Some(arg)) = data.next_as_str() {
+            match StreamId::parse(&arg, 0) {
+                Ok(id) if !just_id => ids.push(id),
+                _ if arg.to_lowercase() == "justid" => just_id = true,
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+        Claimed::Ids(ids)
+    } else {
+        let mut count = DEFAULT_COUNT;
+        while let Ok(Some(option)) = data.next_as_str() {
+            match &option.to_lowercase()[..] {
+                "justid" => just_id = true,
+                "count" => {
+                    let Ok(Some(value)) = data.next_as_str() else {
+                        return Err(CommandError::Syntax);
+                    };
+                    count = value.parse().map_err(|_| CommandError::NotInteger)?;
+                }
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+        Claimed::Scan {
+            start: StreamId::parse_start(&first)?,
+            count,
+        }
+    };
+
+    Ok(ClaimArgs {
+        key,
+        group,
+        consumer,
+        min_idle,
+        claimed,
+        just_id,
+    })
+}
+
+async fn write_claimed(
+    conn: &mut Connection,
+    entries: &[Entry],
+    just_id: bool,
+) -> GenericResult<()> {
+    if !just_id {
+        return write_entries(conn, entries).await;
+    }
+
+    conn.write_array_len(entries.len()).await?;
+    for entry in entries {
+        conn.write_chunk(DataType::BulkString, entry.id.to_string().as_bytes())
+            .await?;
+    }
+
+    Ok(())
+}
+
+impl Xclaim {
+    pub fn parse(data: Parser, name: &'static str) -> Self {
+        Self {
+            name,
+            args: parse_args(data, name),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            self.name.to_uppercase(),
+            self.args
+        );
+
+        let ClaimArgs {
+            key,
+            group,
+            consumer,
+            min_idle,
+            claimed,
+            just_id,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let now = now_ms();
+        let claimed = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            stream::with_group(&mut db_guard, &key, &group).and_then(|stream| {
+                let claimed = match claimed {
+                    Claimed::Ids(ids) => stream
+                        .claim(&group, &consumer, min_idle, &ids, now)
+                        .map(|claimed| (None, claimed, vec![])),
+                    Claimed::Scan { start, count } => stream
+                        .auto_claim(&group, &consumer, min_idle, start, count, now)
+                        .map(|(next, claimed, deleted)| (Some(next), claimed, deleted)),
+                };
+                claimed.ok_or_else(|| stream::no_group(&key, &group))
+            })
+        };
+
+        match claimed {
+            Ok((None, claimed, _)) => write_claimed(conn, &claimed, just_id).await?,
+            Ok((Some(next), claimed, deleted)) => {
+                conn.write_array_len(3).await?;
+                conn.write_chunk(DataType::BulkString, next.to_string().as_bytes())
+                    .await?;
+                write_claimed(conn, &claimed, just_id).await?;
+                conn.write_array_len(deleted.len()).await?;
+                for id in deleted {
+                    conn.write_chunk(DataType::BulkString, id.to_string().as_bytes())
+                        .await?;
+                }
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
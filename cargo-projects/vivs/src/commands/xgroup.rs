@@ -0,0 +1,170 @@
+use crate::{
+    commands::DataType,
+    db::Value,
+    error::CommandError,
+    notifications::EventClass,
+    parser::Parser,
+    stream::{self, StreamId},
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::collections::HashMap;
+
+pub const XGROUP_CMD: &str = "xgroup";
+
+const NO_KEY_ERR: &str = "The XGROUP subcommand requires the key to exist. \
+    Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+
+#[derive(Debug)]
+enum Subcommand {
+    /// CREATE key group <id|$> [MKSTREAM], None is `$`
+    Create {
+        key: Bytes,
+        group: Bytes,
+        id: Option<StreamId>,
+        create_stream: bool,
+    },
+    /// DESTROY key group
+    Destroy { key: Bytes, group: Bytes },
+}
+
+/// XGROUP CREATE key group <id|$> [MKSTREAM] creates a consumer group that reads the entries
+/// after the ID (`$` is the last entry). MKSTREAM creates the stream if the key does not exist.
+///
+/// XGROUP DESTROY key group removes the consumer group, replies with 1 if it existed.
+#[derive(Debug)]
+pub struct Xgroup {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let Ok(Some(subcommand)) = data.next_as_str() else {
+        return Err(CommandError::WrongArity(XGROUP_CMD));
+    };
+    let (Ok(Some(key)), Ok(Some(group))) = (data.next_bytes(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(XGROUP_CMD));
+    };
+
+    match &subcommand.to_lowercase()[..] {
+        "create" => {
+            let Ok(Some(id)) = data.next_as_str() else {
+                return Err(CommandError::WrongArity(XGROUP_CMD));
+            };
+            let id = match &id[..] {
+                "$" => None,
+                id => Some(StreamId::parse(id, 0)?),
+            };
+
+            let create_stream = match data.next_as_str() {
+                Ok(Some(option)) if option.to_lowercase() == "mkstream" => true,
+                Ok(None) => false,
+                _ => return Err(CommandError::Syntax),
+            };
+
+            Ok(Subcommand::Create {
+                key,
+                group,
+                id,
+                create_stream,
+            })
+        }
+        "destroy" if data.size() == 0 => Ok(Subcommand::Destroy { key, group }),
+        "destroy" => Err(CommandError::WrongArity(XGROUP_CMD)),
+        _ => Err(CommandError::UnknownSubcommand {
+            command: XGROUP_CMD,
+            subcommand,
+        }),
+    }
+}
+
+impl Xgroup {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XGROUP_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        match subcommand {
+            Subcommand::Create {
+                key,
+                group,
+                id,
+                create_stream,
+            } => {
+                let created = {
+                    let mut db_guard = db.write_live(&[&key]).await;
+                    create(&mut db_guard, &key, group, id, create_stream)
+                };
+
+                match created {
+                    Ok(()) => {
+                        db.notify(EventClass::Stream, "xgroup-create", &key);
+                        conn.write_chunk(DataType::SimpleString, b"OK").await?
+                    }
+                    Err(err) => conn.write_error(&err).await?,
+                }
+            }
+            Subcommand::Destroy { key, group } => {
+                let destroyed = {
+                    let mut db_guard = db.write_live(&[&key]).await;
+                    match stream::get_mut(&mut db_guard, &key) {
+                        Ok(Some(stream)) => Ok(stream.groups.remove(&group).is_some()),
+                        Ok(None) => Err(CommandError::Other(NO_KEY_ERR.to_owned())),
+                        Err(err) => Err(err),
+                    }
+                };
+
+                match destroyed {
+                    Ok(destroyed) => {
+                        if destroyed {
+                            db.notify(EventClass::Stream, "xgroup-destroy", &key);
+                        }
+                        conn.write_chunk(DataType::Integer, &u64_as_bytes(destroyed as u64))
+                            .await?
+                    }
+                    Err(err) => conn.write_error(&err).await?,
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn create(
+    db: &mut HashMap<Bytes, Value>,
+    key: &Bytes,
+    group: Bytes,
+    id: Option<StreamId>,
+    create_stream: bool,
+) -> Result<(), CommandError> {
+    if !create_stream && stream::get(db, key)?.is_none() {
+        return Err(CommandError::Other(NO_KEY_ERR.to_owned()));
+    }
+
+    let stream = stream::get_or_create(db, key)?;
+
+    let id = id.unwrap_or(stream.last_id);
+    match stream.create_group(group, id) {
+        true => Ok(()),
+        false => Err(CommandError::BusyGroup),
+    }
+}
@@ -0,0 +1,216 @@
+use crate::{
+    commands::{xrange::write_entry, DataType},
+    error::CommandError,
+    parser::Parser,
+    stream::{self, now_ms, Entry, Stream},
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XINFO_CMD: &str = "xinfo";
+
+const NO_KEY_ERR: &str = "no such key";
+
+#[derive(Debug)]
+enum Subcommand {
+    /// STREAM key
+    Stream(Bytes),
+    /// GROUPS key
+    Groups(Bytes),
+    /// CONSUMERS key group
+    Consumers(Bytes, Bytes),
+}
+
+/// XINFO STREAM key - length, number of blocks, IDs, number of groups, first and last entries
+///
+/// XINFO GROUPS key - name, number of consumers, number of pending entries and the last
+/// delivered ID of each consumer group
+///
+/// XINFO CONSUMERS key group - name, number of pending entries and idle milliseconds
+/// of each consumer of the group
+#[derive(Debug)]
+pub struct Xinfo {
+    subcommand: Result<Subcommand, CommandError>,
+}
+
+fn parse_subcommand(mut data: Parser) -> Result<Subcommand, CommandError> {
+    let (Ok(Some(subcommand)), Ok(Some(key))) = (data.next_as_str(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(XINFO_CMD));
+    };
+
+    let subcommand = match &subcommand.to_lowercase()[..] {
+        "stream" => Subcommand::Stream(key),
+        "groups" => Subcommand::Groups(key),
+        "consumers" => {
+            let Ok(Some(group)) = data.next_bytes() else {
+                return Err(CommandError::WrongArity(XINFO_CMD));
+            };
+            Subcommand::Consumers(key, group)
+        }
+        _ => {
+            return Err(CommandError::UnknownSubcommand {
+                command: XINFO_CMD,
+                subcommand,
+            })
+        }
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::WrongArity(XINFO_CMD));
+    }
+
+    Ok(subcommand)
+}
+
+/// A field of the reply, replies are flat lists of names and values
+enum Info {
+    Integer(u64),
+    Id(String),
+    Entry(Option<Entry>),
+}
+
+type Fields = Vec<(&'static str, Info)>;
+
+/// XINFO STREAM replies with the fields, the others with a list of them
+enum Reply {
+    Fields(Fields),
+    List(Vec<Fields>),
+}
+
+fn stream_info(stream: &Stream) -> Fields {
+    vec![
+        ("length", Info::Integer(stream.len() as u64)),
+        ("blocks", Info::Integer(stream.blocks() as u64)),
+        ("last-generated-id", Info::Id(stream.last_id.to_string())),
+        (
+            "max-deleted-entry-id",
+            Info::Id(stream.max_deleted_id.to_string()),
+        ),
+        ("entries-added", Info::Integer(stream.entries_added)),
+        ("groups", Info::Integer(stream.groups.len() as u64)),
+        ("first-entry", Info::Entry(stream.first().cloned())),
+        ("last-entry", Info::Entry(stream.last().cloned())),
+    ]
+}
+
+fn groups_info(stream: &Stream) -> Vec<Fields> {
+    stream
+        .groups
+        .iter()
+        .map(|(name, group)| {
+            vec![
+                ("name", Info::Id(String::from_utf8_lossy(name).into_owned())),
+                ("consumers", Info::Integer(group.consumers.len() as u64)),
+                ("pending", Info::Integer(group.pending.len() as u64)),
+                (
+                    "last-delivered-id",
+                    Info::Id(group.last_delivered.to_string()),
+                ),
+            ]
+        })
+        .collect()
+}
+
+fn consumers_info(stream: &Stream, group: &[u8], now: u64) -> Vec<Fields> {
+    let Some(group) = stream.groups.get(group) else {
+        return vec![];
+    };
+
+    group
+        .consumers
+        .iter()
+        .map(|(name, consumer)| {
+            vec![
+                ("name", Info::Id(String::from_utf8_lossy(name).into_owned())),
+                ("pending", Info::Integer(group.pending_for(name) as u64)),
+                ("idle", Info::Integer(now.saturating_sub(consumer.seen_at))),
+            ]
+        })
+        .collect()
+}
+
+async fn write_fields(conn: &mut Connection, fields: Fields) -> GenericResult<()> {
+    conn.write_array_len(fields.len() * 2).await?;
+
+    for (name, value) in fields {
+        conn.write_chunk(DataType::BulkString, name.as_bytes())
+            .await?;
+
+        match value {
+            Info::Integer(value) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(value))
+                    .await?
+            }
+            Info::Id(value) => {
+                conn.write_chunk(DataType::BulkString, value.as_bytes())
+                    .await?
+            }
+            Info::Entry(Some(entry)) => write_entry(conn, entry.id, Some(&entry.fields)).await?,
+            Info::Entry(None) => conn.write_null().await?,
+        }
+    }
+
+    Ok(())
+}
+
+impl Xinfo {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            subcommand: parse_subcommand(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XINFO_CMD.to_uppercase(),
+            self.subcommand
+        );
+
+        let subcommand = match self.subcommand {
+            Ok(subcommand) => subcommand,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let key = match &subcommand {
+            Subcommand::Stream(key) | Subcommand::Groups(key) | Subcommand::Consumers(key, _) => {
+                key.clone()
+            }
+        };
+
+        let info = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            match &subcommand {
+                Subcommand::Consumers(key, group) => stream::with_group(&mut db_guard, key, group)
+                    .map(|stream| Reply::List(consumers_info(stream, group, now_ms()))),
+                _ => match stream::get(&db_guard, &key) {
+                    Ok(Some(stream)) => match subcommand {
+                        Subcommand::Stream(_) => Ok(Reply::Fields(stream_info(stream))),
+                        _ => Ok(Reply::List(groups_info(stream))),
+                    },
+                    Ok(None) => Err(CommandError::Other(NO_KEY_ERR.to_owned())),
+                    Err(err) => Err(err),
+                },
+            }
+        };
+
+        match info {
+            Ok(Reply::Fields(fields)) => write_fields(conn, fields).await?,
+            Ok(Reply::List(list)) => {
+                conn.write_array_len(list.len()).await?;
+                for fields in list {
+                    write_fields(conn, fields).await?;
+                }
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
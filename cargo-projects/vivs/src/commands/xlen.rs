@@ -0,0 +1,57 @@
+use crate::{
+    commands::DataType, error::CommandError, parser::Parser, stream, utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XLEN_CMD: &str = "xlen";
+
+/// XLEN key replies with the number of entries in the stream, 0 if the key does not exist.
+#[derive(Debug)]
+pub struct Xlen {
+    key: Result<Bytes, CommandError>,
+}
+
+impl Xlen {
+    pub fn parse(mut data: Parser) -> Self {
+        let key = match data.next_bytes() {
+            Ok(Some(key)) if data.size() == 0 => Ok(key),
+            _ => Err(CommandError::WrongArity(XLEN_CMD)),
+        };
+
+        Self { key }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XLEN_CMD.to_uppercase(),
+            self.key
+        );
+
+        let key = match self.key {
+            Ok(key) => key,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let len = {
+            let db_guard = db.write_live(&[&key]).await;
+            stream::get(&db_guard, &key).map(|stream| stream.map_or(0, |stream| stream.len()))
+        };
+
+        match len {
+            Ok(len) => {
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(len as u64))
+                    .await?
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    stream::{self, now_ms, ConsumerGroup, StreamId},
+    utils::u64_as_bytes,
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+use std::collections::BTreeMap;
+
+pub const XPENDING_CMD: &str = "xpending";
+
+#[derive(Debug)]
+struct PendingRange {
+    min_idle: u64,
+    start: StreamId,
+    end: StreamId,
+    count: usize,
+    consumer: Option<Bytes>,
+}
+
+#[derive(Debug)]
+struct PendingArgs {
+    key: Bytes,
+    group: Bytes,
+    // Without a range only the summary is returned
+    range: Option<PendingRange>,
+}
+
+/// XPENDING key group replies with the summary of the pending entries of the group
+/// [count, smallest ID, largest ID, [[consumer, count] ...]].
+///
+/// XPENDING key group [IDLE min-idle-time] start end count [consumer] lists the pending entries
+/// [[id, consumer, idle milliseconds, deliveries] ...].
+#[derive(Debug)]
+pub struct Xpending {
+    args: Result<PendingArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser) -> Result<PendingArgs, CommandError> {
+    let (Ok(Some(key)), Ok(Some(group))) = (data.next_bytes(), data.next_bytes()) else {
+        return Err(CommandError::WrongArity(XPENDING_CMD));
+    };
+
+    let Ok(Some(mut start)) = data.next_as_str() else {
+        return Ok(PendingArgs {
+            key,
+            group,
+            range: None,
+        });
+    };
+
+    let mut min_idle = 0;
+    if start.to_lowercase() == "idle" {
+        let (Ok(Some(idle)), Ok(Some(next))) = (data.next_as_str(), data.next_as_str()) else {
+            return Err(CommandError::Syntax);
+        };
+        min_idle = idle.parse().map_err(|_| CommandError::NotInteger)?;
+        start = next;
+    }
+
+    let (Ok(Some(end)), Ok(Some(count))) = (data.next_as_str(), data.next_as_str()) else {
+        return Err(CommandError::Syntax);
+    };
+    let consumer = data.next_bytes().map_err(|_| CommandError::Syntax)?;
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    let range = PendingRange {
+        min_idle,
+        start: StreamId::parse_start(&start)?,
+        end: StreamId::parse_end(&end)?,
+        count: count.parse().map_err(|_| CommandError::NotInteger)?,
+        consumer,
+    };
+
+    Ok(PendingArgs {
+        key,
+        group,
+        range: Some(range),
+    })
+}
+
+#[derive(Debug)]
+enum Pending {
+    Summary {
+        count: usize,
+        first_last: Option<(StreamId, StreamId)>,
+        consumers: BTreeMap<Bytes, usize>,
+    },
+    // ID, consumer, idle time and number of deliveries of each entry
+    Entries(Vec<(StreamId, Bytes, u64, u64)>),
+}
+
+fn pending(group: &ConsumerGroup, range: Option<PendingRange>, now: u64) -> Pending {
+    let Some(range) = range else {
+        let mut consumers = BTreeMap::new();
+        for entry in group.pending.values() {
+            *consumers.entry(entry.consumer.clone()).or_default() += 1;
+        }
+
+        let first = group.pending.keys().next();
+        let last = group.pending.keys().next_back();
+
+        return Pending::Summary {
+            count: group.pending.len(),
+            first_last: first.copied().zip(last.copied()),
+            consumers,
+        };
+    };
+
+    if range.start > range.end {
+        return Pending::Entries(vec![]);
+    }
+
+    let entries = group
+        .pending
+        .range(range.start..=range.end)
+        .map(|(id, entry)| (id, entry, now.saturating_sub(entry.delivered_at)))
+        .filter(|(_, entry, idle)| {
+            *idle >= range.min_idle
+                && range
+                    .consumer
+                    .as_ref()
+                    .is_none_or(|consumer| *consumer == entry.consumer)
+        })
+        .take(range.count)
+        .map(|(id, entry, idle)| (*id, entry.consumer.clone(), idle, entry.deliveries))
+        .collect();
+
+    Pending::Entries(entries)
+}
+
+impl Xpending {
+    pub fn parse(data: Parser) -> Self {
+        Self {
+            args: parse_args(data),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            XPENDING_CMD.to_uppercase(),
+            self.args
+        );
+
+        let PendingArgs { key, group, range } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let pending = {
+            let mut db_guard = db.write_live(&[&key]).await;
+            stream::with_group(&mut db_guard, &key, &group)
+                .map(|stream| pending(&stream.groups[&group], range, now_ms()))
+        };
+
+        match pending {
+            Ok(Pending::Summary {
+                count,
+                first_last,
+                consumers,
+            }) => {
+                conn.write_array_len(4).await?;
+                conn.write_chunk(DataType::Integer, &u64_as_bytes(count as u64))
+                    .await?;
+
+                let Some((first, last)) = first_last else {
+                    for _ in 0..3 {
+                        conn.write_null().await?;
+                    }
+                    return Ok(());
+                };
+
+                conn.write_chunk(DataType::BulkString, first.to_string().as_bytes())
+                    .await?;
+                conn.write_chunk(DataType::BulkString, last.to_string().as_bytes())
+                    .await?;
+                conn.write_array_len(consumers.len()).await?;
+                for (consumer, count) in consumers {
+                    conn.write_array_len(2).await?;
+                    conn.write_chunk(DataType::BulkString, &consumer).await?;
+                    conn.write_chunk(DataType::BulkString, count.to_string().as_bytes())
+                        .await?;
+                }
+            }
+            Ok(Pending::Entries(entries)) => {
+                conn.write_array_len(entries.len()).await?;
+                for (id, consumer, idle, deliveries) in entries {
+                    conn.write_array_len(4).await?;
+                    conn.write_chunk(DataType::BulkString, id.to_string().as_bytes())
+                        .await?;
+                    conn.write_chunk(DataType::BulkString, &consumer).await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(idle))
+                        .await?;
+                    conn.write_chunk(DataType::Integer, &u64_as_bytes(deliveries))
+                        .await?;
+                }
+            }
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    commands::DataType,
+    error::CommandError,
+    parser::Parser,
+    stream::{self, Entry, Fields, StreamId},
+    Connection, Db, GenericResult,
+};
+use bytes::Bytes;
+use log::info;
+
+pub const XRANGE_CMD: &str = "xrange";
+pub const XREVRANGE_CMD: &str = "xrevrange";
+
+#[derive(Debug)]
+struct RangeArgs {
+    key: Bytes,
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+}
+
+/// XRANGE key start end [COUNT count] / XREVRANGE key end start [COUNT count]
+/// reply with the entries in between start and end (both inclusive) in ID order / reverse ID order.
+///
+/// `-` and `+` are the smallest and the largest IDs, `(` excludes the ID.
+#[derive(Debug)]
+pub struct Xrange {
+    rev: bool,
+    args: Result<RangeArgs, CommandError>,
+}
+
+/// Writes the entry as [id, [field, value ...]], or [id, null] if the entry was trimmed
+pub(crate) async fn write_entry(
+    conn: &mut Connection,
+    id: StreamId,
+    fields: Option<&Fields>,
+) -> GenericResult<()> {
+    conn.write_array_len(2).await?;
+    conn.write_chunk(DataType::BulkString, id.to_string().as_bytes())
+        .await?;
+
+    let Some(fields) = fields else {
+        conn.write_null().await?;
+        return Ok(());
+    };
+
+    conn.write_array_len(fields.len() * 2).await?;
+    for (field, value) in fields {
+        conn.write_chunk(DataType::BulkString, field).await?;
+        conn.write_chunk(DataType::BulkString, value).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) async fn write_entries(conn: &mut Connection, entries: &[Entry]) -> GenericResult<()> {
+    conn.write_array_len(entries.len()).await?;
+    for entry in entries {
+        write_entry(conn, entry.id, Some(&entry.fields)).await?;
+    }
+
+    Ok(())
+}
+
+/// Parses COUNT count, the only option range commands take
+pub(crate) fn parse_count(data: &mut Parser) -> Result<Option<usize>, CommandError> {
+    let Ok(Some(option)) = data.next_as_str() else {
+        return Ok(None);
+    };
+
+    let (true, Ok(Some(count))) = (option.to_lowercase() == "count", data.next_as_str()) else {
+        return Err(CommandError::Syntax);
+    };
+
+    if data.size() != 0 {
+        return Err(CommandError::Syntax);
+    }
+
+    count
+        .parse()
+        .map(Some)
+        .map_err(|_| CommandError::NotInteger)
+}
+
+fn parse_args(mut data: Parser, rev: bool) -> Result<RangeArgs, CommandError> {
+    let name = if rev { XREVRANGE_CMD } else { XRANGE_CMD };
+
+    let (Ok(Some(key)), Ok(Some(first)), Ok(Some(second))) =
+        (data.next_bytes(), data.next_as_str(), data.next_as_str())
+    else {
+        return Err(CommandError::WrongArity(name));
+    };
+
+    // XREVRANGE takes the end first
+    let (start, end) = if rev {
+        (second, first)
+    } else {
+        (first, second)
+    };
+
+    Ok(RangeArgs {
+        key,
+        start: StreamId::parse_start(&start)?,
+        end: StreamId::parse_end(&end)?,
+        count: parse_count(&mut data)?,
+    })
+}
+
+impl Xrange {
+    pub fn parse(data: Parser, rev: bool) -> Self {
+        Self {
+            rev,
+            args: parse_args(data, rev),
+        }
+    }
+
+    pub async fn respond(self, conn: &mut Connection, db: &Db) -> GenericResult<()> {
+        let name = if self.rev { XREVRANGE_CMD } else { XRANGE_CMD };
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            name.to_uppercase(),
+            self.args
+        );
+
+        let RangeArgs {
+            key,
+            start,
+            end,
+            count,
+        } = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+        let count = count.unwrap_or(usize::MAX);
+
+        let entries = {
+            let db_guard = db.write_live(&[&key]).await;
+            stream::get(&db_guard, &key).map(|stream| {
+                let Some(stream) = stream else {
+                    return vec![];
+                };
+
+                let entries = stream.range(start, end);
+                match self.rev {
+                    true => entries.rev().take(count).cloned().collect(),
+                    false => entries.take(count).cloned().collect::<Vec<_>>(),
+                }
+            })
+        };
+
+        match entries {
+            Ok(entries) => write_entries(conn, &entries).await?,
+            Err(err) => conn.write_error(&err).await?,
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    blocking::{parse_timeout_ms, BlockedOp},
+    commands::{xrange::write_entry, DataType},
+    db::Value,
+    error::CommandError,
+    parser::Parser,
+    stream::{self, now_ms, Fields, StreamId},
+    Connection, DataStore, Db, GenericResult, Shutdown,
+};
+use bytes::Bytes;
+use log::info;
+use std::{collections::HashMap, time::Duration};
+use tokio::time::Instant;
+
+pub const XREAD_CMD: &str = "xread";
+pub const XREADGROUP_CMD: &str = "xreadgroup";
+
+/// Where reading a stream starts from
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReadFrom {
+    /// Entries after the ID
+    After(StreamId),
+    /// `$`, entries added after the command was sent
+    Last,
+    /// `>` (XREADGROUP), entries that were never delivered to the group
+    New,
+    /// XREADGROUP with an ID, pending entries of the consumer after the ID
+    History(StreamId),
+}
+
+#[derive(Debug)]
+struct ReadArgs {
+    // Consumer group and consumer (XREADGROUP)
+    group: Option<(Bytes, Bytes)>,
+    count: Option<usize>,
+    // BLOCK milliseconds, None inside means no timeout
+    block: Option<Option<Duration>>,
+    noack: bool,
+    streams: Vec<(Bytes, ReadFrom)>,
+}
+
+/// XREAD [COUNT count] [BLOCK milliseconds] STREAMS key [key ...] id [id ...]
+/// reads the entries after the IDs (`$` is the last entry) from each of the streams.
+///
+/// XREADGROUP GROUP group consumer [COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]
+/// reads as the consumer of the group, `>` reads the entries that were never delivered to the group
+/// (they are pending until XACK unless NOACK is used), an ID reads the consumer's pending entries.
+///
+/// With BLOCK the client waits until one of the streams gets new entries or the timeout
+/// (0 blocks forever) passes. Replies with [[key, [entry ...]] ...], null if nothing was read.
+#[derive(Debug)]
+pub struct Xread {
+    name: &'static str,
+    args: Result<ReadArgs, CommandError>,
+}
+
+fn parse_args(mut data: Parser, name: &'static str) -> Result<ReadArgs, CommandError> {
+    let mut args = ReadArgs {
+        group: None,
+        count: None,
+        block: None,
+        noack: false,
+        streams: vec![],
+    };
+
+    loop {
+        let Ok(Some(option)) = data.next_as_str() else {
+            return Err(CommandError::WrongArity(name));
+        };
+
+        match &option.to_lowercase()[..] {
+            "group" if name == XREADGROUP_CMD => {
+                let (Ok(Some(group)), Ok(Some(consumer))) = (data.next_bytes(), data.next_bytes())
+                else {
+                    return Err(CommandError::Syntax);
+                };
+                args.group = Some((group, consumer));
+            }
+            "count" => {
+                let Ok(Some(count)) = data.next_as_str() else {
+                    return Err(CommandError::Syntax);
+                };
+                args.count = Some(count.parse().map_err(|_| CommandError::NotInteger)?);
+            }
+            "block" => {
+                let Ok(Some(timeout)) = data.next_as_str() else {
+                    return Err(CommandError::Syntax);
+                };
+                args.block = Some(parse_timeout_ms(&timeout)?);
+            }
+            "noack" if name == XREADGROUP_CMD => args.noack = true,
+            "streams" => break,
+            _ => return Err(CommandError::Syntax),
+        }
+    }
+
+    if name == XREADGROUP_CMD && args.group.is_none() {
+        return Err(CommandError::Syntax);
+    }
+
+    let mut rest = vec![];
+    while let Ok(Some(arg)) = data.next_bytes() {
+        rest.push(arg);
+    }
+
+    if rest.is_empty() || rest.len() % 2 != 0 {
+        return Err(CommandError::Other(format!(
+            "Unbalanced '{name}' list of streams: for each stream key an ID or '$' must be specified."
+        )));
+    }
+
+    let ids = rest.split_off(rest.len() / 2);
+    for (key, id) in rest.into_iter().zip(ids) {
+        let id = String::from_utf8_lossy(&id);
+        let from = match (&id[..], name) {
+            ("$", XREAD_CMD) => ReadFrom::Last,
+            (">", XREADGROUP_CMD) => ReadFrom::New,
+            (id, XREAD_CMD) => ReadFrom::After(StreamId::parse(id, 0)?),
+            (id, _) => ReadFrom::History(StreamId::parse(id, 0)?),
+        };
+        args.streams.push((key, from));
+    }
+
+    Ok(args)
+}
+
+/// Entries read from each stream, only the streams that had something to read are included
+type Read = Vec<(Bytes, Vec<(StreamId, Option<Fields>)>)>;
+
+impl ReadArgs {
+    /// Reads all the streams, `$` is resolved to the last entry the first time
+    fn read(&mut self, db: &mut HashMap<Bytes, Value>) -> Result<Read, CommandError> {
+        let count = self.count.unwrap_or(usize::MAX);
+        let now = now_ms();
+        let mut read = vec![];
+
+        for (key, from) in &mut self.streams {
+            if *from == ReadFrom::Last {
+                let last = stream::get(db, key)?.map_or(StreamId::MIN, |stream| stream.last_id);
+                *from = ReadFrom::After(last);
+            }
+
+            let entries = match (&self.group, *from) {
+                (Some((group, consumer)), from) => {
+                    let history = match from {
+                        ReadFrom::History(id) => Some(id),
+                        _ => None,
+                    };
+
+                    let entries = stream::with_group(db, key, group)?
+                        .read_group(group, consumer, history, count, self.noack, now)
+                        .ok_or_else(|| stream::no_group(key, group))?;
+
+                    // Pending entries are listed even when there are none
+                    if history.is_some() {
+                        read.push((key.clone(), entries));
+                        continue;
+                    }
+                    entries
+                }
+                (None, ReadFrom::After(after)) => {
+                    let (Some(stream), Some(start)) = (stream::get(db, key)?, after.next()) else {
+                        continue;
+                    };
+
+                    stream
+                        .range(start, StreamId::MAX)
+                        .take(count)
+                        .map(|entry| (entry.id, Some(entry.fields.clone())))
+                        .collect()
+                }
+                _ => continue,
+            };
+
+            if !entries.is_empty() {
+                read.push((key.clone(), entries));
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+async fn write_read(conn: &mut Connection, read: Read) -> GenericResult<()> {
+    conn.write_array_len(read.len()).await?;
+    for (key, entries) in read {
+        conn.write_array_len(2).await?;
+        conn.write_chunk(DataType::BulkString, &key).await?;
+        conn.write_array_len(entries.len()).await?;
+        for (id, fields) in entries {
+            write_entry(conn, id, fields.as_ref()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+impl Xread {
+    pub fn parse(data: Parser, name: &'static str) -> Self {
+        Self {
+            name,
+            args: parse_args(data, name),
+        }
+    }
+
+    /// Inside of MULTI / EXEC (`in_exec`) the client is never blocked, same as in Redis
+    pub async fn respond(
+        self,
+        conn: &mut Connection,
+        db: &Db,
+        store: &DataStore,
+        shutdown: &Shutdown,
+        in_exec: bool,
+        blocked_for: &mut Duration,
+    ) -> GenericResult<()> {
+        info!(
+            "{:?} {:?} {:?}",
+            conn.connected_peer_addr(),
+            self.name.to_uppercase(),
+            self.args
+        );
+
+        let mut args = match self.args {
+            Ok(args) => args,
+            Err(err) => {
+                conn.write_error(&err).await?;
+                return Ok(());
+            }
+        };
+
+        let deadline = args
+            .block
+            .flatten()
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+
+        loop {
+            let blocked = {
+                // EXEC holds the lock already
+                let _guard = match in_exec {
+                    true => None,
+                    false => Some(store.command_guard().await),
+                };
+
+                let keys = args
+                    .streams
+                    .iter()
+                    .map(|(key, _)| &key[..])
+                    .collect::<Vec<_>>();
+                let mut db_guard = db.write_live(&keys).await;
+
+                match args.read(&mut db_guard) {
+                    Ok(read) if !read.is_empty() => {
+                        drop(db_guard);
+                        write_read(conn, read).await?;
+                        return Ok(());
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        drop(db_guard);
+                        conn.write_error(&err).await?;
+                        return Ok(());
+                    }
+                }
+
+                if args.block.is_none() || in_exec {
+                    drop(db_guard);
+                    conn.write_null().await?;
+                    return Ok(());
+                }
+
+                // Blocked while the keys are still locked, so that an entry can't sneak in before
+                let keys = args.streams.iter().map(|(key, _)| key.clone()).collect();
+                db.blocked.block(keys, BlockedOp::Read)
+            };
+
+            let timeout = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(timeout) if !timeout.is_zero() => Some(timeout),
+                    _ => None,
+                },
+                None => None,
+            };
+            if deadline.is_some() && timeout.is_none() {
+                conn.write_null().await?;
+                return Ok(());
+            }
+
+            // Woken up by new entries, the streams are read again
+            match blocked.wait(timeout, conn, shutdown, blocked_for).await? {
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => conn.write_error(&err).await?,
+                None => conn.write_null().await?,
+            }
+
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,568 @@
+use crate::{
+    error::CommandError,
+    limits::{
+        ClientClass, DEFAULT_CLIENT_QUERY_BUFFER_LIMIT, DEFAULT_MAX_CLIENTS,
+        DEFAULT_PROTO_MAX_BULK_LEN, DEFAULT_TCP_KEEPALIVE, DEFAULT_TIMEOUT,
+    },
+    notifications::{self, Notifications},
+    slowlog::{DEFAULT_LOG_SLOWER_THAN, DEFAULT_MAX_LEN},
+    utils::glob_match,
+    Config, Limits, SlowLog,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use toml_edit::{DocumentMut, Item, Table};
+
+// Read from the current working directory unless --config points somewhere else
+pub const DEFAULT_CONFIG_PATH: &str = "config/config.toml";
+
+const NO_CONFIG_FILE_ERR: &str = "The server is running without a config file";
+
+/// Type of the value of a parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    /// Integer that can't be negative
+    Count,
+    /// Integer that can be negative
+    Integer,
+    /// notify-keyspace-events flag string
+    Flags,
+    /// Only changes with a restart (bind address, port etc), CONFIG SET rejects it
+    Immutable,
+}
+
+/// A parameter CONFIG GET / SET work with and where it lives in config.toml
+#[derive(Debug)]
+struct Param {
+    /// Same name as in Redis when Redis has the parameter
+    name: &'static str,
+    /// Table of the key in config.toml, None for top level keys
+    table: Option<&'static str>,
+    key: &'static str,
+    kind: Kind,
+}
+
+const PARAMS: [Param; 21] = [
+    Param {
+        name: "bind",
+        table: Some("connection"),
+        key: "address",
+        kind: Kind::Immutable,
+    },
+    Param {
+        name: "port",
+        table: Some("connection"),
+        key: "port",
+        kind: Kind::Immutable,
+    },
+    Param {
+        name: "databases",
+        table: None,
+        key: "databases",
+        kind: Kind::Immutable,
+    },
+    Param {
+        name: "cluster-enabled",
+        table: Some("cluster"),
+        key: "enabled",
+        kind: Kind::Immutable,
+    },
+    Param {
+        name: "notify-keyspace-events",
+        table: None,
+        key: "notify_keyspace_events",
+        kind: Kind::Flags,
+    },
+    Param {
+        name: "maxclients",
+        table: Some("limits"),
+        key: "maxclients",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "timeout",
+        table: Some("limits"),
+        key: "timeout",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "tcp-keepalive",
+        table: Some("limits"),
+        key: "tcp_keepalive",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "proto-max-bulk-len",
+        table: Some("limits"),
+        key: "proto_max_bulk_len",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "client-query-buffer-limit",
+        table: Some("limits"),
+        key: "client_query_buffer_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "normal-output-buffer-hard-limit",
+        table: Some("limits"),
+        key: "normal_output_buffer_hard_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "normal-output-buffer-soft-limit",
+        table: Some("limits"),
+        key: "normal_output_buffer_soft_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "normal-output-buffer-soft-seconds",
+        table: Some("limits"),
+        key: "normal_output_buffer_soft_seconds",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "pubsub-output-buffer-hard-limit",
+        table: Some("limits"),
+        key: "pubsub_output_buffer_hard_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "pubsub-output-buffer-soft-limit",
+        table: Some("limits"),
+        key: "pubsub_output_buffer_soft_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "pubsub-output-buffer-soft-seconds",
+        table: Some("limits"),
+        key: "pubsub_output_buffer_soft_seconds",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "monitor-output-buffer-hard-limit",
+        table: Some("limits"),
+        key: "monitor_output_buffer_hard_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "monitor-output-buffer-soft-limit",
+        table: Some("limits"),
+        key: "monitor_output_buffer_soft_limit",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "monitor-output-buffer-soft-seconds",
+        table: Some("limits"),
+        key: "monitor_output_buffer_soft_seconds",
+        kind: Kind::Count,
+    },
+    Param {
+        name: "slowlog-log-slower-than",
+        table: Some("slowlog"),
+        key: "log_slower_than",
+        kind: Kind::Integer,
+    },
+    Param {
+        name: "slowlog-max-len",
+        table: Some("slowlog"),
+        key: "max_len",
+        kind: Kind::Count,
+    },
+];
+
+/// Client class and limit of an output buffer parameter i.e. pubsub-output-buffer-soft-seconds
+fn buffer_param(name: &str) -> Option<(ClientClass, &str)> {
+    let (class, field) = name.split_once("-output-buffer-")?;
+    let class = ClientClass::ALL
+        .into_iter()
+        .find(|other| other.name() == class)?;
+
+    Some((class, field))
+}
+
+fn param(name: &str) -> Option<&'static Param> {
+    PARAMS
+        .iter()
+        .find(|param| param.name.eq_ignore_ascii_case(name))
+}
+
+/// Reads the config file passed with --config, or config/config.toml in the current directory.
+///
+/// Defaults are used when there's no --config and config/config.toml does not exist,
+/// the path of the file that was read is returned too (CONFIG REWRITE and SIGHUP use it).
+pub fn load(path: Option<&Path>) -> Result<(Config, Option<PathBuf>), String> {
+    let (path, explicit) = match path {
+        Some(path) => (path.to_path_buf(), true),
+        None => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+    };
+
+    if !explicit && !path.exists() {
+        return Ok((Config::default(), None));
+    }
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let config = contents
+        .parse::<Config>()
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+
+    Ok((config, Some(path)))
+}
+
+/// Parameters that can be tuned while the server is running (CONFIG GET / SET),
+/// together with the config file they get written to (CONFIG REWRITE) and reloaded from (SIGHUP).
+#[derive(Clone)]
+pub struct RuntimeConfig {
+    limits: Limits,
+    slowlog: SlowLog,
+    notifications: Notifications,
+    // None when the server was started without a config file
+    path: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl RuntimeConfig {
+    pub fn new(limits: Limits, slowlog: SlowLog, notifications: Notifications) -> Self {
+        Self {
+            limits,
+            slowlog,
+            notifications,
+            path: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn path(&self) -> Option<PathBuf> {
+        self.path.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set_path(&self, path: Option<PathBuf>) {
+        *self.path.lock().unwrap_or_else(|e| e.into_inner()) = path;
+    }
+
+    /// Applies the tunable parameters of the config, the ones that are not set go back to defaults
+    pub fn apply(&self, config: &Config) -> Result<(), String> {
+        // Checked first so that nothing changes when the config is invalid
+        let flags = config.notify_keyspace_events().unwrap_or_default();
+        notifications::parse_flags(flags).map_err(str::to_owned)?;
+        let _ = self.notifications.set_flags(flags);
+
+        let limits = config.limits().cloned().unwrap_or_default();
+        self.limits
+            .set_max_clients(limits.maxclients.unwrap_or(DEFAULT_MAX_CLIENTS));
+        self.limits
+            .set_timeout(limits.timeout.unwrap_or(DEFAULT_TIMEOUT));
+        self.limits
+            .set_tcp_keepalive(limits.tcp_keepalive.unwrap_or(DEFAULT_TCP_KEEPALIVE));
+        self.limits.set_proto_max_bulk_len(
+            limits
+                .proto_max_bulk_len
+                .unwrap_or(DEFAULT_PROTO_MAX_BULK_LEN),
+        );
+        self.limits.set_client_query_buffer_limit(
+            limits
+                .client_query_buffer_limit
+                .unwrap_or(DEFAULT_CLIENT_QUERY_BUFFER_LIMIT),
+        );
+        for class in ClientClass::ALL {
+            self.limits
+                .set_output_buffer_limit(class, limits.output_buffer_limit(class));
+        }
+
+        let slowlog = config.slowlog().cloned().unwrap_or_default();
+        self.slowlog
+            .set_log_slower_than(slowlog.log_slower_than.unwrap_or(DEFAULT_LOG_SLOWER_THAN));
+        self.slowlog
+            .set_max_len(slowlog.max_len.unwrap_or(DEFAULT_MAX_LEN));
+
+        Ok(())
+    }
+
+    /// Reads the config file again and applies it, the parameters that can't be tuned
+    /// while the server is running (bind address, port etc) need a restart
+    pub fn reload(&self) -> Result<(), String> {
+        let Some(path) = self.path() else {
+            return Err(NO_CONFIG_FILE_ERR.to_owned());
+        };
+
+        let (config, _) = load(Some(&path))?;
+        self.apply(&config)
+    }
+
+    /// Current value of a tunable parameter, None for unknown and immutable parameters
+    fn value(&self, name: &str) -> Option<String> {
+        if let Some((class, field)) = buffer_param(name) {
+            let limit = self.limits.output_buffer_limit(class);
+            return match field {
+                "hard-limit" => Some(limit.hard_limit.to_string()),
+                "soft-limit" => Some(limit.soft_limit.to_string()),
+                "soft-seconds" => Some(limit.soft_seconds.to_string()),
+                _ => None,
+            };
+        }
+
+        let seconds = |duration: Option<std::time::Duration>| duration.map_or(0, |d| d.as_secs());
+
+        let value = match name {
+            "notify-keyspace-events" => self.notifications.flags(),
+            "maxclients" => self.limits.max_clients().to_string(),
+            "timeout" => seconds(self.limits.timeout()).to_string(),
+            "tcp-keepalive" => seconds(self.limits.tcp_keepalive()).to_string(),
+            "proto-max-bulk-len" => self.limits.proto_max_bulk_len().to_string(),
+            "client-query-buffer-limit" => self.limits.client_query_buffer_limit().to_string(),
+            "slowlog-log-slower-than" => self.slowlog.log_slower_than().to_string(),
+            "slowlog-max-len" => self.slowlog.max_len().to_string(),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Names and values of the parameters that match any of the glob-style patterns,
+    /// `immutable` has the values of the parameters that can't be tuned
+    pub fn get(&self, patterns: &[String], immutable: &[(&str, String)]) -> Vec<(String, String)> {
+        PARAMS
+            .iter()
+            .filter(|param| {
+                patterns.iter().any(|pattern| {
+                    glob_match(pattern.to_lowercase().as_bytes(), param.name.as_bytes())
+                })
+            })
+            .filter_map(|param| {
+                let value = match param.kind {
+                    Kind::Immutable => immutable
+                        .iter()
+                        .find(|(name, _)| *name == param.name)
+                        .map(|(_, value)| value.to_owned()),
+                    _ => self.value(param.name),
+                }?;
+
+                Some((param.name.to_owned(), value))
+            })
+            .collect()
+    }
+
+    /// Sets the parameters, either all of them are set or none of them when one is invalid
+    pub fn set(&self, params: &[(String, String)]) -> Result<(), CommandError> {
+        for (name, value) in params {
+            validate(name, value)?;
+        }
+
+        for (name, value) in params {
+            let Some(param) = param(name) else {
+                continue;
+            };
+
+            let count = || value.parse::<u64>().unwrap_or_default();
+            if let Some((class, field)) = buffer_param(param.name) {
+                let mut limit = self.limits.output_buffer_limit(class);
+                match field {
+                    "hard-limit" => limit.hard_limit = count() as usize,
+                    "soft-limit" => limit.soft_limit = count() as usize,
+                    _ => limit.soft_seconds = count(),
+                }
+                self.limits.set_output_buffer_limit(class, limit);
+                continue;
+            }
+
+            match param.name {
+                "notify-keyspace-events" => {
+                    let _ = self.notifications.set_flags(value);
+                }
+                "maxclients" => self.limits.set_max_clients(count() as usize),
+                "timeout" => self.limits.set_timeout(count()),
+                "tcp-keepalive" => self.limits.set_tcp_keepalive(count()),
+                "proto-max-bulk-len" => self.limits.set_proto_max_bulk_len(count() as usize),
+                "client-query-buffer-limit" => {
+                    self.limits.set_client_query_buffer_limit(count() as usize)
+                }
+                "slowlog-log-slower-than" => self
+                    .slowlog
+                    .set_log_slower_than(value.parse().unwrap_or_default()),
+                "slowlog-max-len" => self.slowlog.set_max_len(count() as usize),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the current values of the tunable parameters to the config file.
+    ///
+    /// Same as in Redis, comments and everything else in the file are kept as they are,
+    /// parameters that are not in the file are only added when they are not set to defaults.
+    pub fn rewrite(&self) -> Result<(), CommandError> {
+        let Some(path) = self.path() else {
+            return Err(CommandError::Other(NO_CONFIG_FILE_ERR.to_owned()));
+        };
+        let rewrite_err = |e: String| CommandError::Other(format!("Rewriting config file: {e}"));
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| rewrite_err(e.to_string()))?;
+        let mut document = contents
+            .parse::<DocumentMut>()
+            .map_err(|e| rewrite_err(e.to_string()))?;
+
+        let defaults = RuntimeConfig::new(Limits::new(), SlowLog::new(), Notifications::default());
+
+        for param in PARAMS.iter().filter(|param| param.kind != Kind::Immutable) {
+            let (Some(value), Some(default)) = (self.value(param.name), defaults.value(param.name))
+            else {
+                continue;
+            };
+
+            let table = match param.table {
+                Some(table) => match document.get_mut(table).and_then(Item::as_table_mut) {
+                    Some(table) => table,
+                    None if value == default => continue,
+                    None => {
+                        document.insert(table, Item::Table(Table::new()));
+                        match document.get_mut(table).and_then(Item::as_table_mut) {
+                            Some(table) => table,
+                            None => continue,
+                        }
+                    }
+                },
+                None => document.as_table_mut(),
+            };
+
+            if !table.contains_key(param.key) && value == default {
+                continue;
+            }
+
+            let new_value = match param.kind {
+                Kind::Flags => toml_edit::Value::from(value),
+                _ => toml_edit::Value::from(
+                    value
+                        .parse::<i64>()
+                        .map_err(|e| rewrite_err(e.to_string()))?,
+                ),
+            };
+
+            match table.get_mut(param.key).and_then(Item::as_value_mut) {
+                // Comments around the value are kept
+                Some(current) => {
+                    let decor = current.decor().clone();
+                    *current = new_value;
+                    *current.decor_mut() = decor;
+                }
+                None => {
+                    table.insert(param.key, Item::Value(new_value));
+                }
+            }
+        }
+
+        std::fs::write(&path, document.to_string()).map_err(|e| rewrite_err(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+/// Checks that the parameter can be set to the value (CONFIG SET)
+fn validate(name: &str, value: &str) -> Result<(), CommandError> {
+    let Some(param) = param(name) else {
+        return Err(CommandError::Other(format!(
+            "Unknown option or number of arguments for CONFIG SET - '{name}'"
+        )));
+    };
+
+    let failed = |reason: &str| {
+        CommandError::Other(format!(
+            "CONFIG SET failed (possibly related to argument '{}') - {reason}",
+            param.name
+        ))
+    };
+
+    match param.kind {
+        Kind::Immutable => Err(failed("can't set immutable config")),
+        Kind::Count => value
+            .parse::<u64>()
+            .map(|_| ())
+            .map_err(|_| failed("argument couldn't be parsed into an integer")),
+        Kind::Integer => value
+            .parse::<i64>()
+            .map(|_| ())
+            .map_err(|_| failed("argument couldn't be parsed into an integer")),
+        Kind::Flags => notifications::parse_flags(value)
+            .map(|_| ())
+            .map_err(failed),
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    fn runtime_config() -> RuntimeConfig {
+        RuntimeConfig::new(Limits::new(), SlowLog::new(), Notifications::default())
+    }
+
+    #[test]
+    fn gets_and_sets_parameters() {
+        let config = runtime_config();
+
+        let params = [("TIMEOUT", "30"), ("slowlog-max-len", "10")]
+            .map(|(name, value)| (name.to_owned(), value.to_owned()));
+        config.set(&params).unwrap();
+
+        assert_eq!(
+            config.get(
+                &["time*".to_owned(), "port".to_owned()],
+                &[("port", "9000".to_owned())]
+            ),
+            vec![
+                ("port".to_owned(), "9000".to_owned()),
+                ("timeout".to_owned(), "30".to_owned())
+            ]
+        );
+
+        // Nothing is set when one of the parameters is invalid
+        let params = [("timeout", "0"), ("maxclients", "-1")]
+            .map(|(name, value)| (name.to_owned(), value.to_owned()));
+        assert!(config.set(&params).is_err());
+        assert_eq!(config.value("timeout"), Some("30".to_owned()));
+
+        let params = [("port".to_owned(), "1".to_owned())];
+        assert_eq!(
+            config.set(&params).unwrap_err().to_string(),
+            "ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config"
+        );
+    }
+
+    #[test]
+    fn rewrites_the_config_file_and_keeps_comments() {
+        let path = std::env::temp_dir().join(format!("vivs-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "# Number of databases\ndatabases = 4\n\n[connection]\naddress = \"127.0.0.1\"\nport = 9000\n\n[limits]\n# Seconds\ntimeout = 0\n",
+        )
+        .unwrap();
+
+        let config = runtime_config();
+        config.set_path(Some(path.clone()));
+
+        let params = [("timeout", "60"), ("notify-keyspace-events", "Ex")]
+            .map(|(name, value)| (name.to_owned(), value.to_owned()));
+        config.set(&params).unwrap();
+        config.rewrite().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("# Seconds\ntimeout = 60\n"));
+        assert!(contents.contains("notify_keyspace_events = \"xE\""));
+        // Defaults are not added
+        assert!(!contents.contains("maxclients"));
+
+        // The rewritten file is read back the same way
+        let (loaded, _) = load(Some(&path)).unwrap();
+        let reloaded = runtime_config();
+        reloaded.apply(&loaded).unwrap();
+        assert_eq!(reloaded.value("timeout"), Some("60".to_owned()));
+        assert_eq!(
+            reloaded.value("notify-keyspace-events"),
+            Some("xE".to_owned())
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(load(Some(&path)).is_err());
+    }
+}
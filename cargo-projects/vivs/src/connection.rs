@@ -1,12 +1,16 @@
 use crate::{
-    commands::DataType, data_chunk::DataChunk, parser::Parser, utils::u64_as_bytes,
-    utils::u8_as_bytes, GenericResult,
+    commands::DataType,
+    data_chunk::{ChunkProgress, DataChunk, DataChunkError},
+    error::CommandError,
+    limits::{OutputBuffer, OUTPUT_BUFFER_ERR, QUERY_BUFFER_ERR},
+    GenericResult,
 };
-use bytes::BytesMut;
+use bytes::{Buf, BytesMut};
 use std::{
     fmt::Display,
     io::{self, Cursor},
     net::SocketAddr,
+    time::Duration,
 };
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt, BufWriter},
@@ -15,20 +19,47 @@ use tokio::{
 
 // i.e. \r\n
 const END_OF_LINE: [u8; 2] = [13, 10];
+// The client is slow already, so the output buffer error is not waited on for long
+const OUTPUT_BUFFER_ERR_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Debug)]
 pub enum ConnectionError {
     TcpClosed,
+    // Client sent more than the query buffer limit without completing a command
+    QueryBufferLimit,
+    // Client sent data that is not valid RESP
+    Protocol(DataChunkError),
+    Io(io::Error),
 }
 
 impl std::error::Error for ConnectionError {}
 
+impl From<&ConnectionError> for CommandError {
+    fn from(e: &ConnectionError) -> Self {
+        match e {
+            ConnectionError::QueryBufferLimit => {
+                CommandError::Protocol(QUERY_BUFFER_ERR.to_owned())
+            }
+            ConnectionError::Protocol(DataChunkError::BulkLength(_)) => {
+                CommandError::Protocol("invalid bulk length".to_owned())
+            }
+            ConnectionError::Protocol(DataChunkError::Parse(e)) => {
+                CommandError::Protocol(e.clone())
+            }
+            e => CommandError::Protocol(e.to_string()),
+        }
+    }
+}
+
 impl Display for ConnectionError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             ConnectionError::TcpClosed => {
                 write!(f, "TCP connection closed")
             }
+            ConnectionError::QueryBufferLimit => write!(f, "Protocol error: {QUERY_BUFFER_ERR}"),
+            ConnectionError::Protocol(e) => write!(f, "{e}"),
+            ConnectionError::Io(e) => write!(f, "{e}"),
         }
     }
 }
@@ -37,6 +68,11 @@ impl Display for ConnectionError {
 pub struct Connection {
     stream: BufWriter<TcpStream>,
     buffer: BytesMut,
+    // How far the command that is in the buffer was scanned, so that it is parsed once it is complete
+    progress: ChunkProgress,
+    // Replies written while the output is held (see `hold()`), they go straight to the stream otherwise
+    held: Option<Vec<u8>>,
+    output: OutputBuffer,
 }
 
 /// Buffer allocation and frame (network data) parsing occurs here
@@ -48,9 +84,22 @@ impl Connection {
             // which acts as a buffer for a tcp stream read functionality
             // 1kb, for now but mostly will need to increase in the future
             buffer: BytesMut::with_capacity(1024),
+            progress: ChunkProgress::default(),
+            held: None,
+            output: OutputBuffer::default(),
         }
     }
 
+    /// Limits the output buffer of the connection, it is not limited otherwise
+    pub fn set_output_buffer(&mut self, output: OutputBuffer) {
+        self.output = output;
+    }
+
+    /// Output buffer the messages queued for the client (pub/sub, MONITOR) are accounted in
+    pub fn output_buffer(&self) -> &OutputBuffer {
+        &self.output
+    }
+
     /// Returns a remotely connected peer address. An empty string if no peer_addr is returned
     /// since this method is only used for logging purposes at the moment.
     pub fn connected_peer_addr(&self) -> String {
@@ -66,11 +115,97 @@ impl Connection {
         self.stream.get_ref().local_addr()
     }
 
+    /// Number of bytes that were read into the buffer from the stream the last time.
+    pub fn read_buffer_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of bytes that can still be read into the buffer without reallocating.
+    pub fn read_buffer_free(&self) -> usize {
+        self.buffer.capacity() - self.buffer.len()
+    }
+
+    /// Number of bytes that are buffered but have not been written to the stream yet.
+    pub fn write_buffer_len(&self) -> usize {
+        self.stream.buffer().len() + self.held.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Keeps the replies in memory until `release()`, so that a client that doesn't read them
+    /// can't stall everyone else while a lock is held (i.e. the one EXEC takes).
+    ///
+    /// Returns false when the output is held already, the caller that held it releases it.
+    pub fn hold(&mut self) -> bool {
+        if self.held.is_some() {
+            return false;
+        }
+
+        self.held = Some(vec![]);
+        true
+    }
+
+    /// Writes the replies that were held back to the stream
+    pub async fn release(&mut self) -> io::Result<()> {
+        match self.held.take() {
+            Some(held) if !held.is_empty() => self.write_parts(&[&held]).await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the parts one after another, they are only flushed to the stream when the output is not held.
+    ///
+    /// Fails once the client goes over its output buffer limit, a write that is stuck on a client
+    /// that does not read is given up on as soon as others queue too much for it.
+    async fn write_parts(&mut self, parts: &[&[u8]]) -> io::Result<()> {
+        let buffered = self.stream.buffer().len()
+            + self.held.as_ref().map_or(0, Vec::len)
+            + parts.iter().map(|part| part.len()).sum::<usize>();
+        if !self.output.fits(buffered) {
+            return Err(self.overflow().await);
+        }
+
+        if let Some(held) = self.held.as_mut() {
+            parts.iter().for_each(|part| held.extend_from_slice(part));
+            return Ok(());
+        }
+
+        let output = self.output.clone();
+        let stream = &mut self.stream;
+        let written = tokio::select! {
+            written = async {
+                for part in parts {
+                    stream.write_all(part).await?;
+                }
+                stream.flush().await
+            } => written,
+            _ = output.overflowed() => return Err(self.overflow().await),
+        };
+
+        written
+    }
+
+    /// Tells the client that it went over its output buffer limit,
+    /// returns the error the connection gets closed with
+    pub async fn overflow(&mut self) -> io::Error {
+        self.held = None;
+
+        let error = CommandError::Other(OUTPUT_BUFFER_ERR.to_owned()).to_string();
+        let _ = tokio::time::timeout(OUTPUT_BUFFER_ERR_TIMEOUT, async {
+            self.stream.write_all(b"-").await?;
+            self.stream.write_all(error.as_bytes()).await?;
+            self.stream.write_all(&END_OF_LINE).await?;
+            self.stream.flush().await
+        })
+        .await;
+
+        io::Error::other(OUTPUT_BUFFER_ERR)
+    }
+
     /// Reads and processes a stream of bytes from the TCP stream.
     pub async fn process_stream(&mut self) -> GenericResult<Cursor<&[u8]>> {
         // Buffer needs to be cleared since the same Connection instance runs for a single tcp connection
         // and unless cleared, it will be just appending to the buffer
         self.buffer.clear();
+        self.progress = ChunkProgress::default();
 
         // Pull bytes from the source/tcp stream into the buffer
         let bytes_read = self.stream.read_buf(&mut self.buffer).await?;
@@ -87,6 +222,77 @@ impl Connection {
         Err(Box::new(ConnectionError::TcpClosed))
     }
 
+    /// Reads a complete command (chunk) from the TCP stream.
+    ///
+    /// Unlike `process_stream()`, data is kept in the buffer across reads
+    /// so commands that arrive in multiple reads (or multiple commands in a single read)
+    /// are handled. Fails once the unparsed data grows over `query_buffer_limit` bytes
+    /// or a bulk string is longer than `max_bulk_len` bytes.
+    ///
+    /// The command is only parsed once all of it has arrived,
+    /// each read just scans the bytes that are new (see `ChunkProgress`).
+    ///
+    /// Nothing is lost if the returned future gets dropped before it completes.
+    pub async fn read_chunk(
+        &mut self,
+        query_buffer_limit: usize,
+        max_bulk_len: usize,
+    ) -> Result<DataChunk, ConnectionError> {
+        loop {
+            if !self.buffer.is_empty() {
+                // The rest of the command has not arrived yet when the scan is not done
+                if self.progress.scan(&self.buffer, max_bulk_len) {
+                    let mut cursored_buffer = Cursor::new(&self.buffer[..]);
+
+                    match DataChunk::read_chunk_with_limit(&mut cursored_buffer, max_bulk_len) {
+                        Ok(data_chunk) => {
+                            // Drop the bytes of the parsed command, the next command (if any) stays
+                            let position = cursored_buffer.position() as usize;
+                            self.buffer.advance(position);
+                            self.progress = ChunkProgress::default();
+                            return Ok(data_chunk);
+                        }
+                        Err(DataChunkError::Insufficient) => {}
+                        Err(e) => return Err(ConnectionError::Protocol(e)),
+                    }
+                }
+
+                if self.buffer.len() >= query_buffer_limit {
+                    return Err(ConnectionError::QueryBufferLimit);
+                }
+            }
+
+            let bytes_read = self
+                .stream
+                .read_buf(&mut self.buffer)
+                .await
+                .map_err(ConnectionError::Io)?;
+
+            if bytes_read == 0 {
+                return Err(ConnectionError::TcpClosed);
+            }
+        }
+    }
+
+    /// Waits for more data from the client without parsing it,
+    /// the data stays in the buffer for the next `read_chunk()`.
+    /// Fails once the client disconnects.
+    ///
+    /// Nothing is lost if the returned future gets dropped before it completes.
+    pub async fn read_more(&mut self) -> Result<(), ConnectionError> {
+        let bytes_read = self
+            .stream
+            .read_buf(&mut self.buffer)
+            .await
+            .map_err(ConnectionError::Io)?;
+
+        if bytes_read == 0 {
+            return Err(ConnectionError::TcpClosed);
+        }
+
+        Ok(())
+    }
+
     // Write chunk of data / frame to the stream
     // Frame is defined as bits of data in this context
     // Since data is buffered in BufWriter no excessive sys calls to write will occur here
@@ -104,104 +310,48 @@ impl Connection {
         // Response is different for these types
         // i.e. +OK\r\n (Simple String) OR :19 (Integer)
         if data_type == b'+' || data_type == b':' {
-            self.stream.write_u8(data_type).await?;
-            self.stream.write_all(data).await?;
-            self.stream.write_all(&END_OF_LINE).await?;
-            self.stream.flush().await
+            self.write_parts(&[&[data_type], data, &END_OF_LINE]).await
         } else {
-            // Since data type is u8 and from_utf8 requires a byte slice, we create one
-            let data_type_slice = &[data_type];
-            let length = data.len();
-            let length = length.try_into().unwrap(); // TODO: not very safe
-
-            let bytes_to_write = [
-                &data_type_slice[..1],
-                &[length],
+            // Length is sent as a decimal number i.e. $11 for "hello world"
+            let length = data.len().to_string();
+
+            self.write_parts(&[
+                &[data_type],
+                length.as_bytes(),
                 &END_OF_LINE,
-                &data[0..],
+                data,
                 &END_OF_LINE,
-            ]
-            .concat();
-
-            self.stream.write_all(&bytes_to_write[..]).await?;
-            self.stream.flush().await
+            ])
+            .await
         }
     }
 
-    //
-    pub async fn write_error(&mut self, err_msg_type_bytes: &[u8]) -> io::Result<()> {
-        // "-" - first byte denotes error data type
-        // "ERR" - generic error type
-        // TODO: as a future improvement we could differentiate between error types
-        self.stream.write_all(b"-").await?;
-        self.stream.write_all(err_msg_type_bytes).await?;
-        self.stream.write_all(&END_OF_LINE).await?;
-        self.stream.flush().await
+    /// Writes the error line i.e. -ERR syntax error\r\n
+    pub async fn write_error(&mut self, error: &CommandError) -> io::Result<()> {
+        // "-" - first byte denotes error data type, the error starts with its prefix (i.e. ERR)
+        let error = error.to_string();
+        self.write_parts(&[b"-", error.as_bytes(), &END_OF_LINE])
+            .await
     }
 
-    pub async fn write_error_with_msg(
-        &mut self,
-        err_msg_type_bytes: &[u8],
-        err_msg: &[u8],
-    ) -> io::Result<()> {
-        // "-" - first byte denotes error data type
-        // "ERR" - generic error type
-        // TODO: as a future improvement we could differentiate between error types
-        self.stream.write_all(b"-").await?;
-        self.stream.write_all(err_msg_type_bytes).await?;
-        self.stream.write_all(b" ").await?;
-        self.stream.write_all(err_msg).await?;
-        self.stream.write_all(&END_OF_LINE).await?;
-        self.stream.flush().await
+    /// Writes the array header i.e. *3\r\n, elements have to be written separately afterwards
+    pub async fn write_array_len(&mut self, length: usize) -> io::Result<()> {
+        let length = length.to_string();
+        self.write_parts(&[b"*", length.as_bytes(), &END_OF_LINE])
+            .await
     }
 
     pub async fn write_null(&mut self) -> io::Result<()> {
         // "_" - first byte denotes null which represents non-existent values
-        self.stream.write_u8(b'_').await?;
-        self.stream.write_all(&END_OF_LINE).await?;
-        self.stream.flush().await
-    }
-
-    pub async fn write_chunk_frame(&mut self, data: &mut Parser) -> io::Result<()> {
-        self.stream.write_all(b"*").await?; // *
-        self.stream
-            .write_all(data.size().to_string().as_bytes())
-            .await?; // 1
-        self.stream.write_all(&END_OF_LINE).await?;
-
-        for chunk in data.into_iter() {
-            match chunk {
-                DataChunk::Bulk(str) => {
-                    // string length minus "\r\n"
-                    let bulk_str_len = if str[str.len() - 1] == b'\n' {
-                        str.len() - 2
-                    } else {
-                        str.len()
-                    };
-
-                    // TODO: do we need to convert to string and bytes here?
-                    // What we need is the integer representation
-                    let bulk_str_len = bulk_str_len.to_string();
-                    let bulk_str_len = bulk_str_len.as_bytes();
-
-                    self.stream.write_all(b"$").await?;
-                    self.stream.write_all(bulk_str_len).await?;
-                    self.stream.write_all(&END_OF_LINE).await?;
-                    self.stream.write_all(&str).await?;
-                    self.stream.write_all(&END_OF_LINE).await?;
-                }
-                _ => {
-                    // TODO: Handle other data types
-                    todo!();
-                }
-            }
-        }
+        self.write_parts(&[b"_", &END_OF_LINE]).await
+    }
 
-        self.stream.flush().await
+    /// Writes an already encoded frame (i.e. a command built by the client) as is.
+    pub async fn write_frame(&mut self, data: &[u8]) -> io::Result<()> {
+        self.write_parts(&[data]).await
     }
 
     pub async fn write_complete_frame(&mut self, data: &str) -> io::Result<()> {
-        self.stream.write_all(data.as_bytes()).await?;
-        self.stream.flush().await
+        self.write_parts(&[data.as_bytes()]).await
     }
 }
@@ -4,10 +4,15 @@ use std::{fmt, io::Cursor, num::TryFromIntError, str::Utf8Error};
 
 use crate::{commands::ping::PONG, parser::Parser, GenericResult};
 
+// Arrays nested deeper than this are rejected instead of being parsed recursively
+pub const MAX_NESTING_DEPTH: usize = 32;
+
 #[derive(Debug, PartialEq)]
 pub enum DataChunkError {
     Insufficient,
     Parse(String),
+    // Bulk string is longer than the allowed maximum
+    BulkLength(usize),
     NoBytesRemaining,
     Utf8(Utf8Error),
     Other(String),
@@ -18,6 +23,7 @@ impl fmt::Display for DataChunkError {
         match self {
             DataChunkError::Parse(e) => format!("Protocol error: {:?}", e).fmt(f),
             DataChunkError::Insufficient => "Insufficient data to parse".fmt(f),
+            DataChunkError::BulkLength(_) => "Protocol error: invalid bulk length".fmt(f),
             DataChunkError::NoBytesRemaining => "Client has disconnected".fmt(f),
             DataChunkError::Utf8(e) => e.fmt(f),
             DataChunkError::Other(e) => e.fmt(f),
@@ -75,7 +81,8 @@ fn line<'a>(cursored_buffer: &'a mut Cursor<&[u8]>) -> Result<&'a [u8], DataChun
         return Err(DataChunkError::Insufficient);
     }
 
-    for position in current_position..length {
+    // Stops one byte short since \r has to be followed by \n
+    for position in current_position..length.saturating_sub(1) {
         // checks current and next bytes
         if cursored_buffer.get_ref()[position] == b'\r'
             && cursored_buffer.get_ref()[position + 1] == b'\n'
@@ -88,6 +95,128 @@ fn line<'a>(cursored_buffer: &'a mut Cursor<&[u8]>) -> Result<&'a [u8], DataChun
     Err(DataChunkError::Insufficient)
 }
 
+/// How far a chunk that has not fully arrived yet was scanned, so that each read only looks at
+/// the bytes that are new instead of parsing the whole chunk again.
+///
+/// Only the framing (array lengths, bulk string lengths and line ends) is followed and nothing is copied,
+/// anything that doesn't look like a valid chunk is left to `DataChunk::read_chunk_with_limit()` to reject.
+#[derive(Debug, Default)]
+pub struct ChunkProgress {
+    // Start of the first element that was not scanned yet
+    position: usize,
+    // Where the search for the end of the current line continues from
+    searched: usize,
+    // Length the buffer has to reach before scanning again (the end of a bulk string)
+    needed: usize,
+    // Elements that are left in each of the arrays the next element is inside of
+    arrays: Vec<u64>,
+}
+
+impl ChunkProgress {
+    /// Scans what was added to the buffer since the last call,
+    /// returns true once the chunk is complete (or malformed) and should be parsed.
+    ///
+    /// The buffer has to start with the same chunk on every call and can only grow.
+    pub fn scan(&mut self, buffer: &[u8], max_bulk_len: usize) -> bool {
+        if buffer.len() < self.needed {
+            return false;
+        }
+
+        loop {
+            let Some(&data_type) = buffer.get(self.position) else {
+                return false;
+            };
+            let start = self.position + 1;
+
+            if data_type == b'*' && self.arrays.len() >= MAX_NESTING_DEPTH {
+                return true;
+            }
+
+            // Same as in parse_integer(), 8 raw bytes when they are followed by \r\n
+            let raw_integer = data_type == b':' && buffer.len() >= start + 10;
+            if raw_integer && buffer[start + 8..start + 10] == *b"\r\n" {
+                self.position = start + 10;
+                if self.element_done() {
+                    return true;
+                }
+                continue;
+            }
+
+            let Some(line_end) = self.line_end(buffer, start) else {
+                return false;
+            };
+            let line = &buffer[start..line_end];
+            let next = line_end + 2;
+
+            match data_type {
+                b'*' => {
+                    let Some(count) = atoi::<u64>(line) else {
+                        return true;
+                    };
+                    self.position = next;
+                    if count > 0 {
+                        self.arrays.push(count);
+                        continue;
+                    }
+                }
+                b'$' => {
+                    let Some(len) = atoi::<usize>(line).filter(|len| *len <= max_bulk_len) else {
+                        return true;
+                    };
+                    let end = next.saturating_add(len).saturating_add(2);
+                    if buffer.len() < end {
+                        self.needed = end;
+                        return false;
+                    }
+                    self.position = end;
+                }
+                // Whether the integer is raw or a line depends on bytes that have not arrived yet
+                b':' if !raw_integer => return true,
+                b'+' | b'-' | b'_' | b':' => self.position = next,
+                _ => return true,
+            }
+
+            if self.element_done() {
+                return true;
+            }
+        }
+    }
+
+    /// Finds the \r\n that ends the line starting at `start`,
+    /// bytes that were searched already are not searched again
+    fn line_end(&mut self, buffer: &[u8], start: usize) -> Option<usize> {
+        let from = self.searched.max(start);
+        match buffer[from.min(buffer.len())..]
+            .windows(2)
+            .position(|bytes| bytes == b"\r\n")
+        {
+            Some(offset) => {
+                self.searched = from + offset;
+                Some(from + offset)
+            }
+            None => {
+                // \r at the very end can still be followed by \n
+                self.searched = buffer.len().saturating_sub(1).max(start);
+                None
+            }
+        }
+    }
+
+    /// Counts the element that was just scanned in the arrays it is inside of,
+    /// returns true when it was the last element of the chunk
+    fn element_done(&mut self) -> bool {
+        while let Some(left) = self.arrays.last_mut() {
+            *left -= 1;
+            if *left > 0 {
+                return false;
+            }
+            self.arrays.pop();
+        }
+
+        true
+    }
+}
+
 #[derive(Debug, Default, PartialEq)]
 pub enum DataChunk {
     /// Commands as arrays
@@ -186,13 +315,29 @@ impl DataChunk {
         format!("*{commands_count}{commands}")
     }
 
-    fn parse_array(cursored_buffer: &mut Cursor<&[u8]>) -> Result<DataChunk, DataChunkError> {
+    fn parse_array(
+        cursored_buffer: &mut Cursor<&[u8]>,
+        max_bulk_len: usize,
+        depth: usize,
+    ) -> Result<DataChunk, DataChunkError> {
+        if depth >= MAX_NESTING_DEPTH {
+            return Err(DataChunkError::Parse("too many nested arrays".to_owned()));
+        }
+
         let number = number_of(cursored_buffer)?;
 
         // Using range expression ([start position]..[end position]) which implements Iterator trait,
         // enables to map over each element then collect iterator into a vector.
         let commands = (0..number)
-            .map(|_| DataChunk::read_chunk(cursored_buffer))
+            .map(|_| {
+                DataChunk::read_nested_chunk(cursored_buffer, max_bulk_len, depth + 1).map_err(
+                    |e| match e {
+                        // The rest of the elements have not arrived yet
+                        DataChunkError::NoBytesRemaining => DataChunkError::Insufficient,
+                        e => e,
+                    },
+                )
+            })
             .collect::<Result<Vec<_>, DataChunkError>>();
 
         Ok(DataChunk::Array(commands?))
@@ -200,15 +345,20 @@ impl DataChunk {
 
     fn parse_bulk_strings(
         cursored_buffer: &mut Cursor<&[u8]>,
+        max_bulk_len: usize,
     ) -> Result<DataChunk, DataChunkError> {
         // Not parsing, just getting length of string and converting to usize
-        let str_len = number_of(cursored_buffer)?.try_into()?;
+        let str_len: usize = number_of(cursored_buffer)?.try_into()?;
 
-        // TODO: handle the case where we haven't received CR and LF
+        // Checked before waiting for the rest of the string so that
+        // clients cannot make the server buffer endless strings
+        if str_len > max_bulk_len {
+            return Err(DataChunkError::BulkLength(str_len));
+        }
 
-        // Compare if indicated ($[4]) and actual lengths are the same,
+        // Compare if indicated ($[4]) and actual lengths (including CR and LF) are the same,
         // since string length cannot be more that the length of the buffer itself.
-        if str_len > cursored_buffer.chunk().len() {
+        if str_len.saturating_add(2) > cursored_buffer.chunk().len() {
             return Err(DataChunkError::Insufficient);
         }
 
@@ -224,48 +374,33 @@ impl DataChunk {
         cursored_buffer: &mut Cursor<&[u8]>,
     ) -> Result<DataChunk, DataChunkError> {
         // up to \r\n
-        let str_line = line(cursored_buffer);
-        let default = [];
-        let len = str_line.as_ref().unwrap_or(&&default[0..]).len();
-        let bulk_str_data = Bytes::copy_from_slice(&str_line.unwrap_or_default().chunk()[..len]);
-        Ok(DataChunk::Bulk(bulk_str_data))
+        let str_line = line(cursored_buffer)?;
+        Ok(DataChunk::Bulk(Bytes::copy_from_slice(str_line)))
     }
 
     fn parse_integer(cursored_buffer: &mut Cursor<&[u8]>) -> Result<DataChunk, DataChunkError> {
-        let n = line(cursored_buffer);
-        let integer = Bytes::copy_from_slice(n.unwrap_or_default());
-        Ok(DataChunk::Integer(integer))
+        // Vivs sends integers as 8 raw bytes (see utils::u64_as_bytes) which can contain \r\n themselves,
+        // so the 8 bytes are taken as they are when they are followed by \r\n
+        let chunk = cursored_buffer.chunk();
+        if chunk.len() >= 10 && chunk[8..10] == *b"\r\n" {
+            let integer = Bytes::copy_from_slice(&chunk[..8]);
+            cursored_buffer.advance(10);
+            return Ok(DataChunk::Integer(integer));
+        }
+
+        let n = line(cursored_buffer)?;
+        Ok(DataChunk::Integer(Bytes::copy_from_slice(n)))
     }
 
     fn parse_simple_errors(
         cursored_buffer: &mut Cursor<&[u8]>,
     ) -> Result<DataChunk, DataChunkError> {
         // e.g. ['ASK', '1234', '127.0.0.1']
-        let mut data_chunks = vec![];
-        let mut pos_start = 0_usize;
-        let mut pos_end = 0_usize;
-
-        for (index, char) in cursored_buffer.get_ref().iter().enumerate() {
-            if char == &b'-' {
-                pos_start += 1;
-                pos_end += 1;
-                continue;
-            }
-
-            if char == &b' ' {
-                data_chunks.push(DataChunk::Bulk(Bytes::copy_from_slice(
-                    &cursored_buffer.get_ref()[pos_start..pos_end],
-                )));
-                pos_start = index + 1;
-                pos_end = pos_start;
-                continue;
-            }
-            pos_end += 1;
-        }
-
-        data_chunks.push(DataChunk::Bulk(Bytes::copy_from_slice(
-            &cursored_buffer.get_ref()[pos_start..pos_end - 2],
-        )));
+        let data_chunks = line(cursored_buffer)?
+            .split(|byte| *byte == b' ')
+            .filter(|word| !word.is_empty())
+            .map(|word| DataChunk::Bulk(Bytes::copy_from_slice(word)))
+            .collect();
 
         Ok(DataChunk::SimpleError(data_chunks))
     }
@@ -283,6 +418,24 @@ impl DataChunk {
     /// [SET, "greeting", "hi"].
     pub fn read_chunk(
         cursored_buffer: &mut Cursor<&[u8]>,
+    ) -> std::result::Result<DataChunk, DataChunkError> {
+        Self::read_chunk_with_limit(cursored_buffer, usize::MAX)
+    }
+
+    /// Same as `read_chunk()` but fails with `DataChunkError::BulkLength`
+    /// when a bulk string is longer than `max_bulk_len` bytes.
+    pub fn read_chunk_with_limit(
+        cursored_buffer: &mut Cursor<&[u8]>,
+        max_bulk_len: usize,
+    ) -> std::result::Result<DataChunk, DataChunkError> {
+        Self::read_nested_chunk(cursored_buffer, max_bulk_len, 0)
+    }
+
+    /// `depth` is the number of arrays the chunk is inside of
+    fn read_nested_chunk(
+        cursored_buffer: &mut Cursor<&[u8]>,
+        max_bulk_len: usize,
+        depth: usize,
     ) -> std::result::Result<DataChunk, DataChunkError> {
         // Client disconnects
         if !cursored_buffer.has_remaining() {
@@ -293,17 +446,17 @@ impl DataChunk {
 
         match first_byte {
             // e.g. *1 (denotes the number of elements in the commands / values array: 1 element)
-            b'*' => Self::parse_array(cursored_buffer),
+            b'*' => Self::parse_array(cursored_buffer, max_bulk_len, depth),
             // e.g. $4 (denotes the length of the next element in the array: 4 bytes)
-            b'$' => Self::parse_bulk_strings(cursored_buffer),
+            b'$' => Self::parse_bulk_strings(cursored_buffer, max_bulk_len),
             // e.g. +PING (generally used as a response to a command,
             // for example is the incoming command is PING, the response would be +PONG)
             b'+' => Self::parse_simple_string(cursored_buffer),
             // e.g. :1 (denotes integer response type,
             // for example DELETE <key> will return :1 if one record was deleted)
             b':' => Self::parse_integer(cursored_buffer),
-            // null value
-            b'_' => Ok(DataChunk::Null),
+            // null value (followed by \r\n)
+            b'_' => line(cursored_buffer).map(|_| DataChunk::Null),
             // error value
             b'-' => Self::parse_simple_errors(cursored_buffer),
             // everything else, catch-all case
@@ -336,10 +489,14 @@ impl DataChunk {
                 // convert Bytes to bytes array
                 // then determine endianness to create u64 integer value from the bytes array
                 // and return integer as string
-                let bytes_slice = val.slice(0..8);
-
-                // converts the slice to an array of u8 elements (since u64 is 8 bytes)
-                let arr_u8: [u8; 8] = bytes_slice[0..8].try_into().unwrap();
+                // converts the slice to an array of u8 elements (since u64 is 8 bytes),
+                // integers sent as text (i.e. by Redis) are written as they are
+                let Ok(arr_u8) = <[u8; 8]>::try_from(&val[..]) else {
+                    return Ok(Bytes::from(format!(
+                        "(integer) {}",
+                        String::from_utf8_lossy(&val)
+                    )));
+                };
                 let integer_as_string = if cfg!(target_endian = "big") {
                     u64::from_be_bytes(arr_u8)
                 } else {
@@ -354,6 +511,33 @@ impl DataChunk {
         }
     }
 
+    /// Whether the chunk is a command sent by a client i.e. an array of bulk strings
+    pub fn is_command(&self) -> bool {
+        match self {
+            DataChunk::Array(chunks) => chunks
+                .iter()
+                .all(|chunk| matches!(chunk, DataChunk::Bulk(_))),
+            DataChunk::Bulk(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns bulk string values of the chunk i.e. command and its arguments.
+    /// Values that are not bulk strings are skipped.
+    pub fn bulk_values(&self) -> Vec<Bytes> {
+        match self {
+            DataChunk::Array(chunks) => chunks
+                .iter()
+                .filter_map(|chunk| match chunk {
+                    DataChunk::Bulk(value) => Some(value.clone()),
+                    _ => None,
+                })
+                .collect(),
+            DataChunk::Bulk(value) => vec![value.clone()],
+            _ => vec![],
+        }
+    }
+
     pub fn as_string(data_chunk: DataChunk) -> GenericResult<String> {
         match data_chunk {
             DataChunk::Bulk(val) => Ok(std::str::from_utf8(&val[..])?.to_owned()),
@@ -515,4 +699,62 @@ mod data_chunk_tests {
             ))
         );
     }
+
+    #[test]
+    fn parse_bulk_string_insufficient_without_eol() {
+        // the value is there but CR and LF have not arrived yet
+        let command_as_bytes = b"*1\r\n$4\r\nPING";
+        let mut cursored_buffer = Cursor::new(&command_as_bytes[..]);
+
+        let actual = DataChunk::read_chunk(&mut cursored_buffer);
+
+        assert_eq!(actual, Err(DataChunkError::Insufficient));
+    }
+
+    #[test]
+    fn parse_bulk_string_over_the_limit() {
+        let command_as_bytes = b"*1\r\n$1000\r\n";
+        let mut cursored_buffer = Cursor::new(&command_as_bytes[..]);
+
+        let actual = DataChunk::read_chunk_with_limit(&mut cursored_buffer, 999);
+
+        assert_eq!(actual, Err(DataChunkError::BulkLength(1000)));
+    }
+
+    #[test]
+    fn parse_array_too_deeply_nested() {
+        let command_as_bytes = "*1\r\n".repeat(MAX_NESTING_DEPTH + 1);
+        let mut cursored_buffer = Cursor::new(command_as_bytes.as_bytes());
+
+        let actual = DataChunk::read_chunk(&mut cursored_buffer);
+
+        assert_eq!(
+            actual,
+            Err(DataChunkError::Parse("too many nested arrays".to_owned()))
+        );
+    }
+
+    #[test]
+    fn chunk_progress_waits_for_the_whole_chunk() {
+        let command_as_bytes = b"*2\r\n$4\r\nECHO\r\n*3\r\n+hi\r\n-ERR no\r\n_\r\n";
+        let mut progress = ChunkProgress::default();
+
+        for len in 0..command_as_bytes.len() {
+            assert!(
+                !progress.scan(&command_as_bytes[..len], usize::MAX),
+                "{len}"
+            );
+        }
+        assert!(progress.scan(command_as_bytes, usize::MAX));
+    }
+
+    #[test]
+    fn chunk_progress_leaves_malformed_chunks_to_the_parser() {
+        let malformed: [&[u8]; 4] = [b"PING\r\n", b"*x\r\n", b"*1\r\n$1000\r\n", b"*1\r\n:5\r\n"];
+
+        for command_as_bytes in malformed {
+            let mut progress = ChunkProgress::default();
+            assert!(progress.scan(command_as_bytes, 999));
+        }
+    }
 }
@@ -1,5 +1,216 @@
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::RwLock;
+use crate::{
+    blocking::{BlockedClients, BlockedOp},
+    list,
+    notifications::{EventClass, Notifications},
+    sorted_set::SortedSet,
+    stats::KeyspaceStats,
+    stream::{now_ms, Stream, StreamId},
+};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock as SyncRwLock,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock, RwLockWriteGuard};
+
+// Same as in Redis, 16 databases (0 to 15) unless configured otherwise
+pub const DEFAULT_DATABASES: usize = 16;
+
+// Bytes and HashMap entry overheads that memory estimates add to the data itself
+const STRING_OVERHEAD: usize = std::mem::size_of::<Bytes>();
+const EXPIRY_OVERHEAD: usize = STRING_OVERHEAD + std::mem::size_of::<u64>();
+
+// Same as in Redis (lfu-log-factor and lfu-decay-time), new keys start at 5 so that they
+// don't look like the least used ones straight away and the counter drops by 1 every minute
+const LFU_INIT_VAL: u8 = 5;
+const LFU_LOG_FACTOR: f64 = 10.0;
+const LFU_DECAY_MS: u64 = 60_000;
+
+/// Value of a key
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Bytes),
+    List(VecDeque<Bytes>),
+    Stream(Box<Stream>),
+    SortedSet(Box<SortedSet>),
+}
+
+impl Value {
+    /// Same names as the TYPE command in Redis uses
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Stream(_) => "stream",
+            Value::SortedSet(_) => "zset",
+        }
+    }
+
+    /// Size of the data (in bytes) without any overheads
+    pub fn len(&self) -> usize {
+        match self {
+            Value::String(value) => value.len(),
+            Value::List(list) => list.iter().map(Bytes::len).sum(),
+            Value::Stream(stream) => stream.size(),
+            Value::SortedSet(set) => set.size(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Estimated memory the value takes up (in bytes), overheads included.
+    ///
+    /// Same as MEMORY USAGE in Redis, lists etc with more than `samples` elements are estimated
+    /// from the average size of the first `samples` ones (0 looks at all of them).
+    pub fn memory_usage(&self, samples: usize) -> usize {
+        match self {
+            Value::String(value) => value.len() + STRING_OVERHEAD,
+            Value::List(list) => sampled(
+                list.len(),
+                list.iter().map(|value| value.len() + STRING_OVERHEAD),
+                samples,
+            ),
+            Value::Stream(stream) => sampled(
+                stream.len(),
+                stream.range(StreamId::MIN, StreamId::MAX).map(|entry| {
+                    let fields = entry.fields.iter();
+                    fields
+                        .map(|(field, value)| field.len() + value.len())
+                        .sum::<usize>()
+                        + STRING_OVERHEAD
+                }),
+                samples,
+            ),
+            Value::SortedSet(set) => sampled(
+                set.len(),
+                set.iter()
+                    .map(|(member, _)| member.len() + std::mem::size_of::<f64>() + STRING_OVERHEAD),
+                samples,
+            ),
+        }
+    }
+
+    /// Name of the encoding Redis would use for a value like this one (OBJECT ENCODING),
+    /// tools that look for big keys expect these names
+    pub fn encoding(&self) -> &'static str {
+        // Same limits as Redis' defaults (list-max-listpack-size etc)
+        const MAX_EMBSTR: usize = 44;
+        const MAX_LISTPACK_ENTRIES: usize = 128;
+        const MAX_LISTPACK_VALUE: usize = 64;
+
+        fn small(len: usize, mut sizes: impl Iterator<Item = usize>) -> bool {
+            len <= MAX_LISTPACK_ENTRIES && sizes.all(|size| size <= MAX_LISTPACK_VALUE)
+        }
+
+        match self {
+            Value::String(value)
+                if value.len() <= 20
+                    && std::str::from_utf8(value)
+                        .is_ok_and(|value| value.parse::<i64>().is_ok()) =>
+            {
+                "int"
+            }
+            Value::String(value) if value.len() <= MAX_EMBSTR => "embstr",
+            Value::String(_) => "raw",
+            Value::List(list) if small(list.len(), list.iter().map(Bytes::len)) => "listpack",
+            Value::List(_) => "quicklist",
+            Value::Stream(_) => "stream",
+            Value::SortedSet(set)
+                if small(set.len(), set.iter().map(|(member, _)| member.len())) =>
+            {
+                "listpack"
+            }
+            Value::SortedSet(_) => "skiplist",
+        }
+    }
+}
+
+/// Sum of the sizes, estimated from the first `samples` ones when there are more of them
+fn sampled(len: usize, sizes: impl Iterator<Item = usize>, samples: usize) -> usize {
+    if samples == 0 || len <= samples {
+        return sizes.sum();
+    }
+
+    sizes.take(samples).sum::<usize>() * len / samples
+}
+
+/// When a key was accessed the last time and how often it is accessed (OBJECT IDLETIME / FREQ)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Access {
+    /// Unix time in milliseconds
+    pub last_ms: u64,
+    // Logarithmic counter, same as LFU in Redis it takes ~1M accesses to get to 255
+    counter: u8,
+}
+
+impl Access {
+    pub fn new(now: u64) -> Self {
+        Self {
+            last_ms: now,
+            counter: LFU_INIT_VAL,
+        }
+    }
+
+    /// Access that happened `idle` milliseconds ago with the counter at `frequency` (RESTORE)
+    pub fn with(now: u64, idle: Option<u64>, frequency: Option<u8>) -> Self {
+        Self {
+            last_ms: now.saturating_sub(idle.unwrap_or_default()),
+            counter: frequency.unwrap_or(LFU_INIT_VAL),
+        }
+    }
+
+    /// Seconds since the last access
+    pub fn idle_time(&self, now: u64) -> u64 {
+        now.saturating_sub(self.last_ms) / 1000
+    }
+
+    /// The counter, minus 1 for every minute since the last access
+    pub fn frequency(&self, now: u64) -> u8 {
+        let periods = now.saturating_sub(self.last_ms) / LFU_DECAY_MS;
+        self.counter
+            .saturating_sub(u8::try_from(periods).unwrap_or(u8::MAX))
+    }
+
+    /// The more accesses the counter has seen, the less likely it is to go up
+    fn touch(&mut self, now: u64) {
+        let mut counter = self.frequency(now);
+        let base = f64::from(counter.saturating_sub(LFU_INIT_VAL));
+        if counter < u8::MAX && random() < 1.0 / (base * LFU_LOG_FACTOR + 1.0) {
+            counter += 1;
+        }
+
+        self.counter = counter;
+        self.last_ms = now;
+    }
+}
+
+/// A number from 0 to 1, random enough for the access counter
+fn random() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.subsec_nanos())
+        .unwrap_or_default();
+
+    // splitmix64 spreads the nanoseconds over all of the bits
+    let mut z = u64::from(nanos).wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^= z >> 31;
+
+    (z >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+impl From<Bytes> for Value {
+    fn from(value: Bytes) -> Self {
+        Value::String(value)
+    }
+}
 
 /// Expirations implementation options:
 ///
@@ -11,18 +222,413 @@ use tokio::sync::RwLock;
 /// [CURRENT] Option 2: 1) key and value Store AND 2) key and expiration Store
 /// { [key]: [value] } AND { [key]: [expiry] }
 /// We only store keys that have expiration set
-
+///
+/// A single numbered database (the one a connection SELECTs).
 #[derive(Clone, Default)]
+pub struct Db {
+    // Keys are raw bytes, they don't have to be valid UTF-8 (same goes for values)
+    pub db: Arc<RwLock<HashMap<Bytes, Value>>>,
+    pub expirations: Arc<RwLock<HashMap<Bytes, u64>>>,
+    // Last access and access frequency of the keys, updated after every command that uses them
+    pub accesses: Arc<RwLock<HashMap<Bytes, Access>>>,
+    // Hits, misses and expired keys, shared by all the databases
+    pub stats: Arc<KeyspaceStats>,
+    // Clients waiting for values to be pushed to lists (BLPOP etc)
+    pub blocked: Arc<BlockedClients>,
+    // Keyspace notifications, shared by all the databases
+    pub notifications: Notifications,
+    // Index of the database, SWAPDB changes it
+    index: Arc<AtomicUsize>,
+}
+
+impl Db {
+    fn new(stats: Arc<KeyspaceStats>, notifications: Notifications, index: usize) -> Self {
+        Self {
+            db: Arc::new(RwLock::new(HashMap::new())),
+            expirations: Arc::new(RwLock::new(HashMap::new())),
+            accesses: Arc::new(RwLock::new(HashMap::new())),
+            stats,
+            blocked: Arc::new(BlockedClients::default()),
+            notifications,
+            index: Arc::new(AtomicUsize::new(index)),
+        }
+    }
+
+    /// Index of the database, the one SELECT picks it with
+    pub fn index(&self) -> usize {
+        self.index.load(Ordering::Relaxed)
+    }
+
+    /// Publishes a keyspace notification about the key (if the class of events is turned on)
+    pub fn notify(&self, class: EventClass, event: &str, key: &[u8]) {
+        self.notifications.notify(class, event, key, self.index());
+    }
+
+    /// Records that the key expired, it has to be removed by the caller
+    pub fn expired(&self, key: &[u8]) {
+        self.stats.expired();
+        self.notify(EventClass::Expired, "expired", key);
+    }
+
+    /// Locks the keys for writing, any of the `keys` that have expired are removed first
+    pub async fn write_live(&self, keys: &[&[u8]]) -> RwLockWriteGuard<'_, HashMap<Bytes, Value>> {
+        let mut db_guard = self.db.write().await;
+        let mut expiries_guard = self.expirations.write().await;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+
+        for key in keys {
+            if expiries_guard
+                .get(*key)
+                .is_some_and(|expiry| *expiry <= now)
+            {
+                expiries_guard.remove(*key);
+                db_guard.remove(*key);
+                self.expired(key);
+            }
+        }
+
+        db_guard
+    }
+
+    /// Serves the clients blocked on the keys that were pushed to,
+    /// in the order the clients blocked.
+    pub async fn serve_blocked(&self) {
+        loop {
+            let ready = self.blocked.take_ready();
+            if ready.is_empty() {
+                return;
+            }
+
+            let keys = ready.iter().map(|key| &key[..]).collect::<Vec<_>>();
+            let mut db_guard = self.write_live(&keys).await;
+
+            for key in &ready {
+                // Clients reading the stream (XREAD etc) read it again
+                if let Some(Value::Stream(_)) = db_guard.get(key) {
+                    self.blocked.wake_readers(key);
+                    continue;
+                }
+
+                // Empty lists are removed, so any list has a value for the next client
+                while matches!(db_guard.get(key), Some(Value::List(_))) {
+                    let Some((op, sender)) = self.blocked.next(key) else {
+                        break;
+                    };
+
+                    match op {
+                        BlockedOp::Pop(end) => {
+                            let Ok(Some(value)) = list::pop(&mut db_guard, key, end) else {
+                                break;
+                            };
+
+                            // The client went away in the meantime, the value goes back
+                            match sender.send(Ok((key.clone(), value))) {
+                                Err(Ok((_, value))) => {
+                                    let _ = list::push(&mut db_guard, key, [value], end);
+                                }
+                                _ => list::notify_pop(self, &db_guard, key, end),
+                            }
+                        }
+                        BlockedOp::Move {
+                            from,
+                            destination,
+                            to,
+                        } => match list::move_value(&mut db_guard, key, &destination, from, to) {
+                            Ok(Some(value)) => {
+                                let _ = sender.send(Ok((key.clone(), value)));
+                                list::notify_move(self, &db_guard, key, &destination, from, to);
+                                // Clients blocked on the destination get served next
+                                self.blocked.signal(&destination);
+                            }
+                            Ok(None) => break,
+                            Err(e) => {
+                                let _ = sender.send(Err(e));
+                            }
+                        },
+                        // Readers are woken up by `wake_readers()`, `next()` skips them
+                        BlockedOp::Read => break,
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of keys and number of keys with an expiry set
+    pub async fn keyspace(&self) -> (usize, usize) {
+        let keys = self.db.read().await.len();
+        let expires = self.expirations.read().await.len();
+        (keys, expires)
+    }
+
+    /// A rough estimate of how much memory keys and values take up (in bytes).
+    /// It only accounts for the data itself plus Bytes and HashMap entry overheads.
+    pub async fn memory_usage(&self) -> u64 {
+        let data = self
+            .db
+            .read()
+            .await
+            .iter()
+            .map(|(key, value)| key.len() + STRING_OVERHEAD + value.memory_usage(0))
+            .sum::<usize>();
+        let expirations = self
+            .expirations
+            .read()
+            .await
+            .keys()
+            .map(|key| key.len() + EXPIRY_OVERHEAD)
+            .sum::<usize>();
+
+        (data + expirations) as u64
+    }
+
+    /// Estimated memory the key takes up (MEMORY USAGE), None if the key does not exist
+    pub async fn key_memory_usage(&self, key: &[u8], samples: usize) -> Option<u64> {
+        let db_guard = self.write_live(&[key]).await;
+        let value = db_guard.get(key)?;
+        let expiry = match self.expirations.read().await.contains_key(key) {
+            true => key.len() + EXPIRY_OVERHEAD,
+            false => 0,
+        };
+
+        Some((key.len() + STRING_OVERHEAD + value.memory_usage(samples) + expiry) as u64)
+    }
+
+    /// Records an access to the keys that exist, the ones that don't are forgotten
+    pub async fn touch(&self, keys: &[Bytes]) {
+        let db_guard = self.db.read().await;
+        let mut accesses = self.accesses.write().await;
+        let now = now_ms();
+
+        for key in keys {
+            if db_guard.contains_key(key) {
+                accesses
+                    .entry(key.clone())
+                    .or_insert_with(|| Access::new(now))
+                    .touch(now);
+            } else {
+                accesses.remove(key);
+            }
+        }
+    }
+
+    /// Last access of the key, a key that has not been accessed yet was just created
+    pub async fn access(&self, key: &[u8]) -> Access {
+        let now = now_ms();
+        self.accesses
+            .read()
+            .await
+            .get(key)
+            .copied()
+            .unwrap_or_else(|| Access::new(now))
+    }
+
+    /// Removes every key, returns the number of keys that were removed.
+    ///
+    /// Same as FLUSHDB ASYNC in Redis, `lazy` frees the memory in the background.
+    pub async fn flush(&self, lazy: bool) -> usize {
+        let mut db_guard = self.db.write().await;
+        let mut expiries_guard = self.expirations.write().await;
+
+        let data = std::mem::take(&mut *db_guard);
+        let expirations = std::mem::take(&mut *expiries_guard);
+        self.accesses.write().await.clear();
+        let flushed = data.len();
+
+        if lazy {
+            tokio::task::spawn_blocking(move || drop((data, expirations)));
+        }
+
+        flushed
+    }
+}
+
+/// Numbered databases, connections pick one with SELECT (db 0 by default).
+///
+/// Same as in Redis, cluster mode only has db 0.
+///
+/// Every connection gets a clone, all of them point to the same databases.
+#[derive(Clone)]
 pub struct DataStore {
-    pub db: Arc<RwLock<HashMap<String, String>>>,
-    pub expirations: Arc<RwLock<HashMap<String, u64>>>,
+    // SWAPDB swaps the databases for every connection at once
+    databases: Arc<SyncRwLock<Vec<Db>>>,
+    // Hits, misses and expired keys
+    pub stats: Arc<KeyspaceStats>,
+    // Keyspace notifications (and the pub/sub channels they are published to)
+    pub notifications: Notifications,
+    // EXEC holds it exclusively, every other command holds it shared,
+    // so that nothing runs in between the commands of a MULTI / EXEC block
+    transactions: Arc<RwLock<()>>,
+    cluster: bool,
+}
+
+impl Default for DataStore {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DataStore {
     pub fn new() -> Self {
+        Self::with_databases(DEFAULT_DATABASES)
+    }
+
+    /// Data store with `count` databases (at least one)
+    pub fn with_databases(count: usize) -> Self {
+        let stats = Arc::new(KeyspaceStats::default());
+        let notifications = Notifications::default();
+        let databases = (0..count.max(1))
+            .map(|index| Db::new(stats.clone(), notifications.clone(), index))
+            .collect();
+
         Self {
-            db: Arc::new(RwLock::new(HashMap::new())),
-            expirations: Arc::new(RwLock::new(HashMap::new())),
+            databases: Arc::new(SyncRwLock::new(databases)),
+            stats,
+            notifications,
+            transactions: Arc::new(RwLock::new(())),
+            cluster: false,
+        }
+    }
+
+    /// Data store of a cluster node, only db 0 is available
+    pub fn cluster() -> Self {
+        Self {
+            cluster: true,
+            ..Self::with_databases(1)
         }
     }
+
+    pub fn is_cluster(&self) -> bool {
+        self.cluster
+    }
+
+    /// Number of databases
+    pub fn len(&self) -> usize {
+        self.databases
+            .read()
+            .map(|databases| databases.len())
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Database by its index, None if the index is out of range
+    pub fn db(&self, index: usize) -> Option<Db> {
+        self.databases.read().ok()?.get(index).cloned()
+    }
+
+    fn databases(&self) -> Vec<Db> {
+        self.databases
+            .read()
+            .map(|databases| databases.clone())
+            .unwrap_or_default()
+    }
+
+    /// Swaps two databases, connections that selected one of them see the other one from now on
+    pub fn swap(&self, first: usize, second: usize) -> bool {
+        let Ok(mut databases) = self.databases.write() else {
+            return false;
+        };
+
+        if first >= databases.len() || second >= databases.len() {
+            return false;
+        }
+
+        databases.swap(first, second);
+        databases[first].index.store(first, Ordering::Relaxed);
+        databases[second].index.store(second, Ordering::Relaxed);
+        true
+    }
+
+    /// Held while a command runs, commands run concurrently with each other
+    pub async fn command_guard(&self) -> OwnedRwLockReadGuard<()> {
+        self.transactions.clone().read_owned().await
+    }
+
+    /// Held while EXEC runs the queued commands, no other command runs in the meantime
+    pub async fn exec_guard(&self) -> OwnedRwLockWriteGuard<()> {
+        self.transactions.clone().write_owned().await
+    }
+
+    /// Serves the clients blocked on the keys that were pushed to, in every database
+    pub async fn serve_blocked(&self) {
+        for db in self.databases() {
+            db.serve_blocked().await;
+        }
+    }
+
+    /// Number of clients that are blocked on list keys
+    pub fn blocked_clients(&self) -> usize {
+        self.databases().iter().map(|db| db.blocked.len()).sum()
+    }
+
+    /// Removes every key from every database
+    pub async fn flush_all(&self, lazy: bool) -> usize {
+        let mut flushed = 0;
+        for db in self.databases() {
+            flushed += db.flush(lazy).await;
+        }
+        flushed
+    }
+
+    /// Number of keys and number of keys with an expiry set in each database, i.e. (0, 100, 2)
+    pub async fn keyspace(&self) -> Vec<(usize, usize, usize)> {
+        let mut keyspace = vec![];
+        for (index, db) in self.databases().iter().enumerate() {
+            let (keys, expires) = db.keyspace().await;
+            keyspace.push((index, keys, expires));
+        }
+        keyspace
+    }
+
+    /// A rough estimate of how much memory keys and values of all the databases take up (in bytes)
+    pub async fn memory_usage(&self) -> u64 {
+        let mut memory = 0;
+        for db in self.databases() {
+            memory += db.memory_usage().await;
+        }
+        memory
+    }
+}
+
+#[cfg(test)]
+mod db_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn swaps_and_flushes_databases() {
+        let store = DataStore::with_databases(3);
+        assert_eq!(store.len(), 3);
+        assert!(store.db(3).is_none());
+
+        let first = store.db(0).unwrap();
+        first
+            .db
+            .write()
+            .await
+            .insert("a".into(), Value::String("1".into()));
+        first.expirations.write().await.insert("a".into(), 1);
+
+        assert!(store.swap(0, 2));
+        assert!(!store.swap(0, 3));
+        assert_eq!(store.db(2).unwrap().index(), 2);
+        assert_eq!(store.db(2).unwrap().keyspace().await, (1, 1));
+        assert_eq!(store.db(0).unwrap().keyspace().await, (0, 0));
+        assert_eq!(
+            store.keyspace().await,
+            vec![(0, 0, 0), (1, 0, 0), (2, 1, 1)]
+        );
+
+        assert_eq!(store.flush_all(true).await, 1);
+        assert_eq!(store.db(2).unwrap().keyspace().await, (0, 0));
+
+        let cluster = DataStore::cluster();
+        assert!(cluster.is_cluster());
+        assert_eq!(cluster.len(), 1);
+    }
 }
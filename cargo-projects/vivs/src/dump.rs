@@ -0,0 +1,312 @@
+use crate::{
+    db::Value,
+    error::CommandError,
+    sorted_set::SortedSet,
+    stream::{Consumer, ConsumerGroup, Fields, PendingEntry, Stream, StreamId},
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::collections::VecDeque;
+
+// Bumped whenever the format changes, payloads of other versions are rejected
+pub const DUMP_VERSION: u16 = 1;
+
+const STRING_TYPE: u8 = 0;
+const LIST_TYPE: u8 = 1;
+const STREAM_TYPE: u8 = 2;
+const SORTED_SET_TYPE: u8 = 3;
+
+// Same checksum as Redis uses for its DUMP payloads
+const CRC64: crc::Crc<u64> = crc::Crc::<u64>::new(&crc::CRC_64_REDIS);
+const TRAILER_SIZE: usize = 2 + 8;
+
+const CHECKSUM_ERR: &str = "DUMP payload version or checksum are wrong";
+const FORMAT_ERR: &str = "Bad data format";
+
+fn bad_format() -> CommandError {
+    CommandError::Other(FORMAT_ERR.to_owned())
+}
+
+/// Serializes the value together with its time to live (in milliseconds), the payload is:
+///
+/// type (1 byte) | time to live (8 bytes, 0 if there is none) | value | version (2 bytes) | CRC64 (8 bytes)
+///
+/// Numbers are little endian, lengths and counts are 8 bytes long and come before what they count.
+pub fn dump(value: &Value, ttl: Option<u64>) -> Bytes {
+    let mut payload = BytesMut::new();
+
+    let value_type = match value {
+        Value::String(_) => STRING_TYPE,
+        Value::List(_) => LIST_TYPE,
+        Value::Stream(_) => STREAM_TYPE,
+        Value::SortedSet(_) => SORTED_SET_TYPE,
+    };
+    payload.put_u8(value_type);
+    payload.put_u64_le(ttl.unwrap_or_default());
+
+    match value {
+        Value::String(value) => put_bytes(&mut payload, value),
+        Value::List(list) => {
+            payload.put_u64_le(list.len() as u64);
+            for value in list {
+                put_bytes(&mut payload, value);
+            }
+        }
+        Value::Stream(stream) => put_stream(&mut payload, stream),
+        Value::SortedSet(set) => {
+            payload.put_u64_le(set.len() as u64);
+            for (member, score) in set.iter() {
+                put_bytes(&mut payload, member);
+                payload.put_f64_le(score);
+            }
+        }
+    }
+
+    payload.put_u16_le(DUMP_VERSION);
+    let checksum = CRC64.checksum(&payload);
+    payload.put_u64_le(checksum);
+
+    payload.freeze()
+}
+
+/// The value and its time to live (in milliseconds) out of a DUMP payload
+pub fn restore(payload: &[u8]) -> Result<(Value, Option<u64>), CommandError> {
+    let checksum_err = || CommandError::Other(CHECKSUM_ERR.to_owned());
+
+    let Some(body_len) = payload.len().checked_sub(TRAILER_SIZE) else {
+        return Err(checksum_err());
+    };
+    let (body, trailer) = payload.split_at(body_len);
+    let version = u16::from_le_bytes([trailer[0], trailer[1]]);
+    let checksum = u64::from_le_bytes(trailer[2..].try_into().map_err(|_| checksum_err())?);
+
+    if version != DUMP_VERSION || CRC64.checksum(&payload[..body_len + 2]) != checksum {
+        return Err(checksum_err());
+    }
+
+    let mut reader = Reader { data: body };
+    let value_type = reader.u8()?;
+    let ttl = Some(reader.u64()?).filter(|ttl| *ttl > 0);
+
+    let value = match value_type {
+        STRING_TYPE => Value::String(reader.bytes()?),
+        LIST_TYPE => {
+            let mut list = VecDeque::new();
+            for _ in 0..reader.u64()? {
+                list.push_back(reader.bytes()?);
+            }
+            Value::List(list)
+        }
+        STREAM_TYPE => Value::Stream(Box::new(reader.stream()?)),
+        SORTED_SET_TYPE => {
+            let mut set = SortedSet::default();
+            for _ in 0..reader.u64()? {
+                let member = reader.bytes()?;
+                let score = f64::from_bits(reader.u64()?);
+                if score.is_nan() || set.insert(member, score).is_some() {
+                    return Err(bad_format());
+                }
+            }
+            Value::SortedSet(Box::new(set))
+        }
+        _ => return Err(bad_format()),
+    };
+
+    // Anything left over means the payload is not what it claims to be
+    if !reader.data.is_empty() {
+        return Err(bad_format());
+    }
+
+    Ok((value, ttl))
+}
+
+fn put_bytes(payload: &mut BytesMut, value: &[u8]) {
+    payload.put_u64_le(value.len() as u64);
+    payload.put_slice(value);
+}
+
+fn put_id(payload: &mut BytesMut, id: StreamId) {
+    payload.put_u64_le(id.ms);
+    payload.put_u64_le(id.seq);
+}
+
+/// Entries, then the IDs and counters of the stream, then the consumer groups
+fn put_stream(payload: &mut BytesMut, stream: &Stream) {
+    payload.put_u64_le(stream.len() as u64);
+    for entry in stream.range(StreamId::MIN, StreamId::MAX) {
+        put_id(payload, entry.id);
+        payload.put_u64_le(entry.fields.len() as u64);
+        for (field, value) in &entry.fields {
+            put_bytes(payload, field);
+            put_bytes(payload, value);
+        }
+    }
+
+    put_id(payload, stream.last_id);
+    put_id(payload, stream.max_deleted_id);
+    payload.put_u64_le(stream.entries_added);
+
+    payload.put_u64_le(stream.groups.len() as u64);
+    for (name, group) in &stream.groups {
+        put_bytes(payload, name);
+        put_id(payload, group.last_delivered);
+
+        payload.put_u64_le(group.pending.len() as u64);
+        for (id, pending) in &group.pending {
+            put_id(payload, *id);
+            put_bytes(payload, &pending.consumer);
+            payload.put_u64_le(pending.delivered_at);
+            payload.put_u64_le(pending.deliveries);
+        }
+
+        payload.put_u64_le(group.consumers.len() as u64);
+        for (name, consumer) in &group.consumers {
+            put_bytes(payload, name);
+            payload.put_u64_le(consumer.seen_at);
+        }
+    }
+}
+
+/// Reads the payload front to back, running out of bytes is a format error
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: u64) -> Result<&'a [u8], CommandError> {
+        let len = usize::try_from(len).map_err(|_| bad_format())?;
+        if len > self.data.len() {
+            return Err(bad_format());
+        }
+
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, CommandError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64, CommandError> {
+        let bytes = self.take(8)?.try_into().map_err(|_| bad_format())?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn bytes(&mut self) -> Result<Bytes, CommandError> {
+        let len = self.u64()?;
+        Ok(Bytes::copy_from_slice(self.take(len)?))
+    }
+
+    fn id(&mut self) -> Result<StreamId, CommandError> {
+        Ok(StreamId {
+            ms: self.u64()?,
+            seq: self.u64()?,
+        })
+    }
+
+    fn stream(&mut self) -> Result<Stream, CommandError> {
+        let mut stream = Stream::default();
+
+        for _ in 0..self.u64()? {
+            let id = self.id()?;
+            let mut fields: Fields = vec![];
+            for _ in 0..self.u64()? {
+                fields.push((self.bytes()?, self.bytes()?));
+            }
+
+            // Entries are in ID order, same as XADD would have added them
+            if id <= stream.last_id {
+                return Err(bad_format());
+            }
+            stream.add(id, fields);
+        }
+
+        let last_id = self.id()?;
+        if last_id < stream.last_id {
+            return Err(bad_format());
+        }
+        stream.last_id = last_id;
+        stream.max_deleted_id = self.id()?;
+        stream.entries_added = self.u64()?;
+
+        for _ in 0..self.u64()? {
+            let name = self.bytes()?;
+            let mut group = ConsumerGroup {
+                last_delivered: self.id()?,
+                ..Default::default()
+            };
+
+            for _ in 0..self.u64()? {
+                let id = self.id()?;
+                let pending = PendingEntry {
+                    consumer: self.bytes()?,
+                    delivered_at: self.u64()?,
+                    deliveries: self.u64()?,
+                };
+                group.pending.insert(id, pending);
+            }
+
+            for _ in 0..self.u64()? {
+                let name = self.bytes()?;
+                let consumer = Consumer {
+                    seen_at: self.u64()?,
+                };
+                group.consumers.insert(name, consumer);
+            }
+
+            stream.groups.insert(name, group);
+        }
+
+        Ok(stream)
+    }
+}
+
+#[cfg(test)]
+mod dump_tests {
+    use super::*;
+    use crate::stream::NewId;
+
+    #[test]
+    fn restores_what_was_dumped() {
+        let mut stream = Stream::default();
+        for ms in 1..=3 {
+            let id = stream.next_id(NewId::AutoSeq(ms), 0).unwrap();
+            stream.add(id, vec![("n".into(), ms.to_string().into())]);
+        }
+        stream.create_group("group".into(), StreamId::MIN);
+        stream.read_group(b"group", &"alice".into(), None, 2, false, 100);
+
+        let mut set = SortedSet::default();
+        set.insert("a".into(), 1.5);
+        set.insert("b".into(), -2.0);
+
+        let values = [
+            (Value::String("hello".into()), None),
+            (Value::List(["a".into(), "b".into()].into()), Some(5000)),
+            (Value::Stream(Box::new(stream)), None),
+            (Value::SortedSet(Box::new(set)), Some(1)),
+        ];
+
+        for (value, ttl) in values {
+            let payload = dump(&value, ttl);
+            assert_eq!(restore(&payload), Ok((value, ttl)));
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_payloads() {
+        let payload = dump(&Value::String("hello".into()), None);
+        let checksum_err = Err(CommandError::Other(CHECKSUM_ERR.to_owned()));
+
+        let mut corrupted = payload.to_vec();
+        corrupted[10] ^= 1;
+        assert_eq!(restore(&corrupted), checksum_err);
+
+        let mut other_version = payload.to_vec();
+        let len = other_version.len();
+        other_version[len - TRAILER_SIZE] = 2;
+        assert_eq!(restore(&other_version), checksum_err);
+
+        assert_eq!(restore(b"short"), checksum_err);
+    }
+}
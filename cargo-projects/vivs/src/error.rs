@@ -0,0 +1,169 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// Errors that get sent back to clients, each one turns into a single RESP error line
+/// with the same prefix Redis uses i.e. -ERR syntax error or -WRONGTYPE Operation against ...
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The frame sent by the client could not be parsed
+    Protocol(String),
+    /// The command is not in the command table
+    UnknownCommand(String),
+    /// The command exists but the subcommand does not i.e. CLIENT NOPE
+    UnknownSubcommand {
+        command: &'static str,
+        subcommand: String,
+    },
+    /// Wrong number of arguments for the command
+    WrongArity(&'static str),
+    Syntax,
+    /// The argument is not an integer or does not fit into one
+    NotInteger,
+    /// The argument is not a number i.e. a longitude
+    NotFloat,
+    /// The argument is a valid integer but is not allowed i.e. DB index is out of range
+    OutOfRange(&'static str),
+    /// The key holds a different kind of value than what the command works with
+    WrongType,
+    /// PF* command on a string that is not a HyperLogLog
+    NotHyperLogLog,
+    /// The client has not authenticated yet
+    NoAuth,
+    /// The hash slot is permanently served by another node
+    Moved {
+        slot: u16,
+        address: String,
+    },
+    /// The hash slot is temporarily served by another node (only for the next command)
+    Ask {
+        slot: u16,
+        address: String,
+    },
+    /// The keys of the command hash to different slots (in cluster mode)
+    CrossSlot,
+    /// The command would use more memory than allowed
+    Oom,
+    /// EXEC of a transaction that had commands which could not be queued
+    ExecAbort,
+    /// The stream or its consumer group does not exist
+    NoGroup {
+        key: String,
+        group: String,
+    },
+    /// XGROUP CREATE of a consumer group that already exists
+    BusyGroup,
+    /// RESTORE of a key that already exists (without REPLACE)
+    BusyKey,
+    /// The command can't run in the current state i.e. SELECT in cluster mode
+    NotAllowed(String),
+    /// Anything else, the message is sent after the ERR prefix
+    Other(String),
+}
+
+impl CommandError {
+    /// First word of the error line, clients can use it to tell errors apart
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            CommandError::WrongType | CommandError::NotHyperLogLog => "WRONGTYPE",
+            CommandError::NoAuth => "NOAUTH",
+            CommandError::Moved { .. } => "MOVED",
+            CommandError::Ask { .. } => "ASK",
+            CommandError::CrossSlot => "CROSSSLOT",
+            CommandError::Oom => "OOM",
+            CommandError::ExecAbort => "EXECABORT",
+            CommandError::NoGroup { .. } => "NOGROUP",
+            CommandError::BusyGroup => "BUSYGROUP",
+            CommandError::BusyKey => "BUSYKEY",
+            _ => "ERR",
+        }
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} ", self.prefix())?;
+
+        match self {
+            CommandError::Protocol(e) => write!(f, "Protocol error: {e}"),
+            CommandError::UnknownCommand(command) => write!(f, "unknown command '{command}'"),
+            CommandError::UnknownSubcommand {
+                command,
+                subcommand,
+            } => write!(
+                f,
+                "unknown subcommand '{subcommand}' for '{}'",
+                command.to_uppercase()
+            ),
+            CommandError::WrongArity(command) => {
+                write!(f, "wrong number of arguments for '{command}' command")
+            }
+            CommandError::Syntax => write!(f, "syntax error"),
+            CommandError::NotInteger => write!(f, "value is not an integer or out of range"),
+            CommandError::NotFloat => write!(f, "value is not a valid float"),
+            CommandError::OutOfRange(what) => write!(f, "{what} is out of range"),
+            CommandError::WrongType => {
+                write!(f, "Operation against a key holding the wrong kind of value")
+            }
+            CommandError::NotHyperLogLog => {
+                write!(f, "Key is not a valid HyperLogLog string value.")
+            }
+            CommandError::NoAuth => write!(f, "Authentication required."),
+            CommandError::Moved { slot, address } | CommandError::Ask { slot, address } => {
+                write!(f, "{slot} {address}")
+            }
+            CommandError::CrossSlot => write!(f, "Keys in request don't hash to the same slot"),
+            CommandError::Oom => {
+                write!(f, "command not allowed when used memory > 'maxmemory'.")
+            }
+            CommandError::ExecAbort => {
+                write!(f, "Transaction discarded because of previous errors.")
+            }
+            CommandError::NoGroup { key, group } => {
+                write!(f, "No such key '{key}' or consumer group '{group}'")
+            }
+            CommandError::BusyGroup => write!(f, "Consumer Group name already exists"),
+            CommandError::BusyKey => write!(f, "Target key name already exists."),
+            CommandError::NotAllowed(e) | CommandError::Other(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    #[test]
+    fn errors_start_with_redis_prefixes() {
+        assert_eq!(
+            CommandError::WrongArity("get").to_string(),
+            "ERR wrong number of arguments for 'get' command"
+        );
+        assert_eq!(
+            CommandError::WrongType.to_string(),
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+        assert_eq!(
+            CommandError::Ask {
+                slot: 7162,
+                address: "127.0.0.1:9001".to_owned()
+            }
+            .to_string(),
+            "ASK 7162 127.0.0.1:9001"
+        );
+        assert_eq!(
+            CommandError::OutOfRange("DB index").to_string(),
+            "ERR DB index is out of range"
+        );
+        assert_eq!(
+            CommandError::UnknownSubcommand {
+                command: "client",
+                subcommand: "nope".to_owned()
+            }
+            .to_string(),
+            "ERR unknown subcommand 'nope' for 'CLIENT'"
+        );
+        assert_eq!(CommandError::NoAuth.prefix(), "NOAUTH");
+        assert_eq!(CommandError::Oom.prefix(), "OOM");
+    }
+}
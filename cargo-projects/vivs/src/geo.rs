@@ -0,0 +1,496 @@
+use crate::{error::CommandError, sorted_set::SortedSet};
+use bytes::Bytes;
+
+// Same limits as in Redis (EPSG:3857), latitudes closer to the poles can't be indexed
+pub const LONGITUDE_MIN: f64 = -180.0;
+pub const LONGITUDE_MAX: f64 = 180.0;
+pub const LATITUDE_MIN: f64 = -85.05112878;
+pub const LATITUDE_MAX: f64 = 85.05112878;
+
+// Bits per coordinate, 52 bit scores are exactly representable by a f64
+const STEP_MAX: u32 = 26;
+
+// Same values as in Redis so that distances match
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Longitude and latitude (in degrees)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+impl Point {
+    /// Parses a longitude and latitude pair, it has to be within the limits that can be indexed
+    pub fn parse(longitude: &str, latitude: &str) -> Result<Point, CommandError> {
+        let point = Point {
+            longitude: parse_float(longitude)?,
+            latitude: parse_float(latitude)?,
+        };
+
+        if !(LONGITUDE_MIN..=LONGITUDE_MAX).contains(&point.longitude)
+            || !(LATITUDE_MIN..=LATITUDE_MAX).contains(&point.latitude)
+        {
+            return Err(CommandError::Other(format!(
+                "invalid longitude,latitude pair {:.6},{:.6}",
+                point.longitude, point.latitude
+            )));
+        }
+
+        Ok(point)
+    }
+
+    /// 52 bit score of the point, longitude and latitude bits are interleaved
+    pub fn score(self) -> u64 {
+        let (longitude, latitude) = cell(self, STEP_MAX, LATITUDE_MIN, LATITUDE_MAX);
+        interleave(longitude, latitude)
+    }
+
+    /// Center of the cell of the score (the point is within 0.6 meters of where it was added)
+    pub fn from_score(score: u64) -> Point {
+        let area = Area::decode(score, STEP_MAX);
+        Point {
+            longitude: ((area.longitude_min + area.longitude_max) / 2.0)
+                .clamp(LONGITUDE_MIN, LONGITUDE_MAX),
+            latitude: ((area.latitude_min + area.latitude_max) / 2.0)
+                .clamp(LATITUDE_MIN, LATITUDE_MAX),
+        }
+    }
+
+    /// Standard 11 character geohash, the standard uses -90 to 90 for latitudes
+    pub fn geohash(self) -> String {
+        let (longitude, latitude) = cell(self, STEP_MAX, -90.0, 90.0);
+        let bits = interleave(longitude, latitude);
+
+        // 52 bits make up 10 characters, same as in Redis the last one is always 0
+        (0..11)
+            .map(|index| match index {
+                10 => GEOHASH_ALPHABET[0],
+                _ => GEOHASH_ALPHABET[((bits >> (52 - (index + 1) * 5)) & 0x1f) as usize],
+            })
+            .map(char::from)
+            .collect()
+    }
+
+    /// Longitude and latitude as they are replied with
+    pub fn coordinates(self) -> [String; 2] {
+        [self.longitude.to_string(), self.latitude.to_string()]
+    }
+
+    /// Distance to the other point (in meters), calculated with the haversine formula
+    pub fn distance(self, other: Point) -> f64 {
+        let (longitude1, latitude1) = (self.longitude.to_radians(), self.latitude.to_radians());
+        let (longitude2, latitude2) = (other.longitude.to_radians(), other.latitude.to_radians());
+
+        let v = ((longitude2 - longitude1) / 2.0).sin();
+        // Same meridian, the distance is the distance between latitudes
+        if v == 0.0 {
+            return latitude_distance(self.latitude, other.latitude);
+        }
+
+        let u = ((latitude2 - latitude1) / 2.0).sin();
+        let a = u * u + latitude1.cos() * latitude2.cos() * v * v;
+        2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+    }
+}
+
+fn latitude_distance(latitude1: f64, latitude2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (latitude2.to_radians() - latitude1.to_radians()).abs()
+}
+
+pub fn parse_float(value: &str) -> Result<f64, CommandError> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| !value.is_nan())
+        .ok_or(CommandError::NotFloat)
+}
+
+/// Indexes of the longitude and latitude cells the point falls into, `step` bits each
+fn cell(point: Point, step: u32, latitude_min: f64, latitude_max: f64) -> (u32, u32) {
+    let cells = (1_u64 << step) as f64;
+    let longitude = (point.longitude - LONGITUDE_MIN) / (LONGITUDE_MAX - LONGITUDE_MIN) * cells;
+    let latitude = (point.latitude - latitude_min) / (latitude_max - latitude_min) * cells;
+
+    // The maximum falls into the last cell
+    let last = (1_u64 << step) - 1;
+    (
+        (longitude as u64).min(last) as u32,
+        (latitude as u64).min(last) as u32,
+    )
+}
+
+/// Spreads the bits so that longitude bits are the odd ones and latitude bits the even ones
+fn interleave(longitude: u32, latitude: u32) -> u64 {
+    fn spread(value: u32) -> u64 {
+        (0..32).fold(0, |bits, bit| {
+            bits | ((u64::from(value) >> bit) & 1) << (bit * 2)
+        })
+    }
+
+    spread(longitude) << 1 | spread(latitude)
+}
+
+fn deinterleave(bits: u64) -> (u32, u32) {
+    fn squash(bits: u64) -> u32 {
+        (0..32).fold(0, |value, bit| {
+            value | (((bits >> (bit * 2)) & 1) as u32) << bit
+        })
+    }
+
+    (squash(bits >> 1), squash(bits))
+}
+
+/// Geohash of a cell, `step` bits per coordinate
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hash {
+    bits: u64,
+    step: u32,
+}
+
+impl Hash {
+    fn new(point: Point, step: u32) -> Hash {
+        let (longitude, latitude) = cell(point, step, LATITUDE_MIN, LATITUDE_MAX);
+        Hash {
+            bits: interleave(longitude, latitude),
+            step,
+        }
+    }
+
+    /// Cell that is `east` cells to the east and `north` cells to the north, it wraps around
+    fn moved(self, east: i64, north: i64) -> Hash {
+        let (longitude, latitude) = deinterleave(self.bits);
+        let mask = (1_i64 << self.step) - 1;
+        let longitude = ((i64::from(longitude) + east) & mask) as u32;
+        let latitude = ((i64::from(latitude) + north) & mask) as u32;
+
+        Hash {
+            bits: interleave(longitude, latitude),
+            step: self.step,
+        }
+    }
+
+    /// Range of 52 bit scores within the cell, the end is excluded
+    fn scores(self) -> (u64, u64) {
+        let shift = (STEP_MAX - self.step) * 2;
+        (self.bits << shift, (self.bits + 1) << shift)
+    }
+}
+
+/// Bounds of a cell
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    longitude_min: f64,
+    longitude_max: f64,
+    latitude_min: f64,
+    latitude_max: f64,
+}
+
+impl Area {
+    fn decode(bits: u64, step: u32) -> Area {
+        let (longitude, latitude) = deinterleave(bits);
+        let cells = (1_u64 << step) as f64;
+        let longitude_scale = LONGITUDE_MAX - LONGITUDE_MIN;
+        let latitude_scale = LATITUDE_MAX - LATITUDE_MIN;
+
+        Area {
+            longitude_min: LONGITUDE_MIN + f64::from(longitude) / cells * longitude_scale,
+            longitude_max: LONGITUDE_MIN + (f64::from(longitude) + 1.0) / cells * longitude_scale,
+            latitude_min: LATITUDE_MIN + f64::from(latitude) / cells * latitude_scale,
+            latitude_max: LATITUDE_MIN + (f64::from(latitude) + 1.0) / cells * latitude_scale,
+        }
+    }
+}
+
+/// Unit of distances, M | KM | FT | MI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Unit {
+    /// Meters in one unit
+    pub meters: f64,
+}
+
+impl Unit {
+    pub const METERS: Unit = Unit { meters: 1.0 };
+
+    pub fn parse(value: &str) -> Result<Unit, CommandError> {
+        let meters = match &value.to_lowercase()[..] {
+            "m" => 1.0,
+            "km" => 1000.0,
+            "ft" => 0.3048,
+            "mi" => 1609.34,
+            _ => {
+                return Err(CommandError::Other(
+                    "unsupported unit provided. please use M, KM, FT, MI".to_owned(),
+                ))
+            }
+        };
+
+        Ok(Unit { meters })
+    }
+}
+
+/// Distance in the unit, with 4 decimals same as in Redis
+pub fn format_distance(meters: f64, unit: Unit) -> String {
+    format!("{:.4}", meters / unit.meters)
+}
+
+/// Shape of GEOSEARCH (in meters)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
+}
+
+impl Shape {
+    /// Distance from the center if the point is within the shape
+    fn distance(self, center: Point, point: Point) -> Option<f64> {
+        match self {
+            Shape::Radius(radius) => Some(center.distance(point)).filter(|d| *d <= radius),
+            Shape::Box { width, height } => {
+                // Latitude distance is cheaper, so it's checked first
+                if latitude_distance(point.latitude, center.latitude) > height / 2.0 {
+                    return None;
+                }
+
+                // Along the parallel of the point, to the meridian of the center
+                let meridian = Point {
+                    longitude: center.longitude,
+                    latitude: point.latitude,
+                };
+                if point.distance(meridian) > width / 2.0 {
+                    return None;
+                }
+
+                Some(center.distance(point))
+            }
+        }
+    }
+
+    /// Half of the height and half of the width of the shape
+    fn half_extents(self) -> (f64, f64) {
+        match self {
+            Shape::Radius(radius) => (radius, radius),
+            Shape::Box { width, height } => (height / 2.0, width / 2.0),
+        }
+    }
+
+    /// Distance from the center to the furthest point of the shape
+    fn reach(self) -> f64 {
+        match self {
+            Shape::Radius(radius) => radius,
+            Shape::Box { width, height } => (width / 2.0).hypot(height / 2.0),
+        }
+    }
+}
+
+/// Longitude and latitude bounds of the shape around the center (min longitude, min latitude,
+/// max longitude, max latitude)
+fn bounding_box(center: Point, shape: Shape) -> (f64, f64, f64, f64) {
+    let (height, width) = shape.half_extents();
+
+    let latitude_delta = (height / EARTH_RADIUS_IN_METERS).to_degrees();
+    let longitude_delta =
+        |latitude: f64| (width / EARTH_RADIUS_IN_METERS / latitude.to_radians().cos()).to_degrees();
+    // The hemispheres are mirrored, so the edge that is closer to the equator is picked
+    let longitude_delta = if center.latitude < 0.0 {
+        longitude_delta(center.latitude - latitude_delta)
+    } else {
+        longitude_delta(center.latitude + latitude_delta)
+    };
+
+    (
+        center.longitude - longitude_delta,
+        center.latitude - latitude_delta,
+        center.longitude + longitude_delta,
+        center.latitude + latitude_delta,
+    )
+}
+
+/// Bits per coordinate of the cells that are big enough to cover the distance
+fn estimate_step(meters: f64, latitude: f64) -> u32 {
+    if meters == 0.0 {
+        return STEP_MAX;
+    }
+
+    let mut step: i32 = 1;
+    let mut meters = meters;
+    while meters < MERCATOR_MAX {
+        meters *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+
+    // Cells get narrower towards the poles
+    if !(-66.0..=66.0).contains(&latitude) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&latitude) {
+            step -= 1;
+        }
+    }
+
+    step.clamp(1, STEP_MAX as i32) as u32
+}
+
+/// Cells to look in for members within the shape, the same ones Redis picks:
+/// the cell of the center and the 8 cells around it, minus the ones that are outside of the shape.
+fn search_cells(center: Point, shape: Shape) -> Vec<Hash> {
+    let (longitude_min, latitude_min, longitude_max, latitude_max) = bounding_box(center, shape);
+
+    let mut step = estimate_step(shape.reach(), center.latitude);
+    let mut hash = Hash::new(center, step);
+
+    // Cells around the center might not reach the edges of the shape, bigger cells do
+    let north = Area::decode(hash.moved(0, 1).bits, step);
+    let south = Area::decode(hash.moved(0, -1).bits, step);
+    let east = Area::decode(hash.moved(1, 0).bits, step);
+    let west = Area::decode(hash.moved(-1, 0).bits, step);
+    if step > 1
+        && (north.latitude_max < latitude_max
+            || south.latitude_min > latitude_min
+            || east.longitude_max < longitude_max
+            || west.longitude_min > longitude_min)
+    {
+        step -= 1;
+        hash = Hash::new(center, step);
+    }
+
+    let area = Area::decode(hash.bits, step);
+    let mut norths = vec![-1, 0, 1];
+    let mut easts = vec![-1, 0, 1];
+    if step >= 2 {
+        if area.latitude_min < latitude_min {
+            norths.retain(|north| *north != -1);
+        }
+        if area.latitude_max > latitude_max {
+            norths.retain(|north| *north != 1);
+        }
+        if area.longitude_min < longitude_min {
+            easts.retain(|east| *east != -1);
+        }
+        if area.longitude_max > longitude_max {
+            easts.retain(|east| *east != 1);
+        }
+    }
+
+    let mut cells: Vec<Hash> = vec![];
+    for north in &norths {
+        for east in &easts {
+            let cell = hash.moved(*east, *north);
+            // Huge shapes wrap around, the same cell is only looked in once
+            if !cells.contains(&cell) {
+                cells.push(cell);
+            }
+        }
+    }
+
+    cells
+}
+
+/// Member that GEOSEARCH found
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    pub member: Bytes,
+    pub score: u64,
+    pub point: Point,
+    /// In meters
+    pub distance: f64,
+}
+
+/// Members within the shape around the center, `limit` stops the search once that many are found
+pub fn search(set: &SortedSet, center: Point, shape: Shape, limit: Option<usize>) -> Vec<Found> {
+    let mut found = vec![];
+
+    for cell in search_cells(center, shape) {
+        let (min, max) = cell.scores();
+        for (member, score) in set.range(min as f64, max as f64) {
+            if limit.is_some_and(|limit| found.len() >= limit) {
+                return found;
+            }
+
+            let score = score as u64;
+            let point = Point::from_score(score);
+            if let Some(distance) = shape.distance(center, point) {
+                found.push(Found {
+                    member: member.clone(),
+                    score,
+                    point,
+                    distance,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod geo_tests {
+    use super::*;
+
+    fn palermo() -> Point {
+        Point::parse("13.361389", "38.115556").unwrap()
+    }
+
+    fn catania() -> Point {
+        Point::parse("15.087269", "37.502669").unwrap()
+    }
+
+    #[test]
+    fn scores_match_redis() {
+        // GEOADD Sicily 13.361389 38.115556 "Palermo" 15.087269 37.502669 "Catania"
+        assert_eq!(palermo().score(), 3479099956230698);
+        assert_eq!(catania().score(), 3479447370796909);
+
+        let decoded = Point::from_score(palermo().score());
+        assert!((decoded.longitude - 13.361389338970184).abs() < 1e-12);
+        assert!((decoded.latitude - 38.1155563954963).abs() < 1e-12);
+
+        assert_eq!(palermo().geohash(), "sqc8b49rny0");
+        assert_eq!(catania().geohash(), "sqdtr74hyu0");
+    }
+
+    #[test]
+    fn distances_match_redis() {
+        // GEODIST Sicily Palermo Catania
+        let palermo = Point::from_score(palermo().score());
+        let catania = Point::from_score(catania().score());
+        assert!((palermo.distance(catania) - 166274.1516).abs() < 0.001);
+    }
+
+    #[test]
+    fn searches_around_the_center() {
+        let mut set = SortedSet::default();
+        set.insert("Palermo".into(), palermo().score() as f64);
+        set.insert("Catania".into(), catania().score() as f64);
+
+        let center = Point::parse("15", "37").unwrap();
+        let members = |shape| {
+            let mut found = search(&set, center, shape, None);
+            found.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+            found
+                .into_iter()
+                .map(|found| found.member)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(members(Shape::Radius(100_000.0)), ["Catania"]);
+        assert_eq!(members(Shape::Radius(200_000.0)), ["Catania", "Palermo"]);
+        assert_eq!(
+            members(Shape::Box {
+                width: 400_000.0,
+                height: 400_000.0
+            }),
+            ["Catania", "Palermo"]
+        );
+        assert_eq!(
+            members(Shape::Box {
+                width: 100_000.0,
+                height: 400_000.0
+            }),
+            ["Catania"]
+        );
+    }
+}
@@ -1,12 +1,21 @@
-use crate::commands::{Command, ParseCommandErr};
-use crate::data_chunk::{DataChunk, DataChunkError};
-use crate::{parser::Parser, Connection, DataStore, GenericError};
+use crate::cluster::Topology;
+use crate::commands::{client::CLIENT_CMD, multi::Transaction, Command, ParseCommandErr};
+use crate::connection::ConnectionError;
+use crate::data_chunk::DataChunkError;
+use crate::{
+    config::RuntimeConfig, error::CommandError, parser::Parser, pubsub::PubSub, Clients,
+    Connection, DataStore, GenericError, GenericResult, Limits, Listener, Monitors, Shutdown,
+    SlowLog, Stats,
+};
+use bytes::Bytes;
 use std::fmt::{Debug, Display, Formatter, Result};
+use std::time::{Duration, Instant};
 
 #[derive(Debug)]
 pub enum HandlerError {
     CommandParsing(ParseCommandErr),
     DataChunk(DataChunkError),
+    Connection(ConnectionError),
     ClientDisconnected,
     Other(GenericError),
 }
@@ -17,11 +26,14 @@ impl Display for HandlerError {
             HandlerError::ClientDisconnected => write!(f, "Client disconnected"),
             HandlerError::CommandParsing(err) => write!(f, "Failed when parsing {:?}", err),
             HandlerError::DataChunk(err) => write!(f, "Data chunk error: {}", err),
+            HandlerError::Connection(err) => write!(f, "Connection error: {}", err),
             HandlerError::Other(err) => write!(f, "Error: {}", err),
         }
     }
 }
 
+impl std::error::Error for HandlerError {}
+
 impl From<ParseCommandErr> for HandlerError {
     fn from(e: ParseCommandErr) -> Self {
         Self::CommandParsing(e)
@@ -34,6 +46,15 @@ impl From<GenericError> for HandlerError {
     }
 }
 
+impl From<ConnectionError> for HandlerError {
+    fn from(e: ConnectionError) -> Self {
+        match e {
+            ConnectionError::TcpClosed => HandlerError::ClientDisconnected,
+            _ => HandlerError::Connection(e),
+        }
+    }
+}
+
 impl From<DataChunkError> for HandlerError {
     fn from(e: DataChunkError) -> Self {
         match e {
@@ -46,26 +67,169 @@ impl From<DataChunkError> for HandlerError {
 pub struct Handler {
     pub db: DataStore,
     pub connection: Connection,
+    pub clients: Clients,
+    pub stats: Stats,
+    pub slowlog: SlowLog,
+    pub monitors: Monitors,
+    pub pubsub: PubSub,
+    pub shutdown: Shutdown,
+    pub limits: Limits,
+    pub config: RuntimeConfig,
+    pub topology: Topology,
+    // Id of the connection in the clients registry
+    pub client_id: u64,
+    // Set by ASKING, lets the next command use a slot that the node is importing
+    pub asking: bool,
+    // Database picked with SELECT
+    pub db_index: usize,
+    // Commands queued after MULTI
+    pub transaction: Option<Transaction>,
+    // Set while EXEC runs the queued commands
+    pub in_exec: bool,
+    // Time the command that is running spent blocked (BLPOP etc), left out of its duration
+    pub blocked_for: Duration,
 }
 
 impl Handler {
-    pub fn new(db: DataStore, connection: Connection) -> Self {
-        Handler { db, connection }
+    /// Creates a `Handler` for a connection accepted by the `Listener`,
+    /// sharing the data store, clients, stats etc. of the listener.
+    pub fn new(listener: &Listener, connection: Connection, client_id: u64) -> Self {
+        Handler {
+            db: listener.db.clone(),
+            connection,
+            clients: listener.clients.clone(),
+            stats: listener.stats.clone(),
+            slowlog: listener.slowlog.clone(),
+            monitors: listener.monitors.clone(),
+            pubsub: listener.pubsub.clone(),
+            shutdown: listener.shutdown.clone(),
+            limits: listener.limits.clone(),
+            config: listener.config.clone(),
+            topology: listener.topology.clone(),
+            client_id,
+            asking: false,
+            db_index: 0,
+            transaction: None,
+            in_exec: false,
+            blocked_for: Duration::ZERO,
+        }
     }
 
+    /// Reads the next command from the connection and runs it.
     pub async fn run(&mut self) -> std::result::Result<(), HandlerError> {
-        // TODO: should probably have a separate parser module
-        // read a frame, should probably live in connection
-        // read bits that host/client can send (frame)
-        // this should return array of commands which will later parse
-        let mut cursored_buffer = self.connection.process_stream().await?;
+        let (command, args) = self.read_command().await?;
+        self.execute(command, args).await
+    }
+
+    /// Reads and parses the next command sent by the client,
+    /// together with the raw command and arguments (needed for MONITOR and SLOWLOG).
+    ///
+    /// Nothing is lost if the returned future gets dropped before it completes,
+    /// so it can be raced against other futures (i.e. shutdown).
+    pub async fn read_command(
+        &mut self,
+    ) -> std::result::Result<(Command, Vec<Bytes>), HandlerError> {
+        // read a complete command (chunk), it can arrive in multiple reads
+        let data = self
+            .connection
+            .read_chunk(
+                self.limits.client_query_buffer_limit(),
+                self.limits.proto_max_bulk_len(),
+            )
+            .await;
 
-        // read chunk
-        let data = DataChunk::read_chunk(&mut cursored_buffer);
-        let data = Parser::new(data?);
+        let data = match data {
+            Ok(data) => data,
+            // The client is told why before the connection gets closed
+            Err(e @ (ConnectionError::QueryBufferLimit | ConnectionError::Protocol(_))) => {
+                // Best effort, the connection gets closed either way
+                let _ = self.connection.write_error(&CommandError::from(&e)).await;
+                return Err(e.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        // Commands are arrays of bulk strings, anything else is a protocol error too
+        if !data.is_command() {
+            let e = ConnectionError::Protocol(DataChunkError::Parse(
+                "expected an array of bulk strings".to_owned(),
+            ));
+            let _ = self.connection.write_error(&CommandError::from(&e)).await;
+            return Err(e.into());
+        }
+
+        let args = data.bulk_values();
+        let data = Parser::new(data);
 
         let command = Command::parse_cmd(data?)?;
-        command.run(&mut self.connection, &self.db).await?;
+
+        Ok((command, args))
+    }
+
+    /// Runs the command, keeping track of stats, slow log and monitoring clients.
+    pub async fn execute(
+        &mut self,
+        command: Command,
+        args: Vec<Bytes>,
+    ) -> std::result::Result<(), HandlerError> {
+        let name = command.name();
+
+        self.clients
+            .touch(self.client_id, &name, &self.connection)
+            .await;
+
+        // Commands sent after MULTI only run once EXEC is sent
+        if let Some(transaction) = self.transaction.as_mut() {
+            if Transaction::queues(&command) {
+                transaction
+                    .queue(command, args, &mut self.connection)
+                    .await?;
+                return Ok(());
+            }
+        }
+
+        // CLIENT commands are never held back, otherwise there'd be no way to CLIENT UNPAUSE
+        if !command.is(CLIENT_CMD) {
+            self.clients.wait_if_paused(command.is_write()).await;
+        }
+
+        self.run_recorded(command, args).await?;
+
+        Ok(())
+    }
+
+    /// Runs the command and records it in stats, slow log and monitoring clients
+    /// (EXEC runs the queued commands with it).
+    pub async fn run_recorded(&mut self, command: Command, args: Vec<Bytes>) -> GenericResult<()> {
+        let name = command.name();
+
+        // Unknown commands are not recorded,
+        // otherwise any garbage sent by clients would end up in per command stats
+        if matches!(command, Command::None | Command::Unknown(_)) {
+            return command.run(self).await;
+        }
+
+        let client_addr = self.connection.connected_peer_addr();
+        self.monitors.publish(self.db_index, &client_addr, &args);
+
+        self.blocked_for = Duration::ZERO;
+        let started_at = Instant::now();
+        command.run(self).await?;
+        let duration = started_at.elapsed().saturating_sub(self.blocked_for);
+
+        self.stats.command_processed(&name, duration).await;
+
+        if self.slowlog.is_slow(duration) {
+            let client_name = self
+                .clients
+                .get(self.client_id)
+                .await
+                .and_then(|client| client.name);
+
+            self.slowlog
+                .record(&args, duration, &client_addr, client_name)
+                .await;
+        }
 
         Ok(())
     }
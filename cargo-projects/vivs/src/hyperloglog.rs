@@ -0,0 +1,430 @@
+use crate::{db::Value, error::CommandError};
+use bytes::Bytes;
+use std::collections::HashMap;
+
+// Same parameters and encoding as Redis uses so that the error bounds are the same (0.81%)
+// and HyperLogLogs can be moved between Vivs and Redis as they are.
+//
+// 2^14 registers, 14 bits of the hash pick the register, the other 50 bits its value
+const P: u32 = 14;
+const REGISTERS: usize = 1 << P;
+const Q: u32 = 64 - P;
+// Registers are 6 bits long in the dense encoding
+const REGISTER_BITS: usize = 6;
+const REGISTER_MAX: u8 = (1 << REGISTER_BITS) - 1;
+
+// "HYLL", encoding, 3 unused bytes and the cached cardinality (8 bytes, little endian)
+const HEADER_SIZE: usize = 16;
+const MAGIC: &[u8] = b"HYLL";
+const DENSE: u8 = 0;
+const SPARSE: u8 = 1;
+// 12kb of registers
+const DENSE_SIZE: usize = HEADER_SIZE + (REGISTERS * REGISTER_BITS).div_ceil(8);
+// The most significant bit of the cached cardinality is set when it needs to be computed again
+const STALE_CARDINALITY: u8 = 1 << 7;
+
+// Sparse encoding opcodes:
+// ZERO  00xxxxxx          - 1 to 64 registers set to 0
+// XZERO 01xxxxxx yyyyyyyy - 1 to 16384 registers set to 0
+// VAL   1vvvvvxx          - 1 to 4 registers set to 1 to 32
+const ZERO_MAX_LEN: usize = 64;
+const XZERO_MAX_LEN: usize = 16384;
+const VAL_MAX_LEN: usize = 4;
+const VAL_MAX_VALUE: u8 = 32;
+// Same as Redis' hll-sparse-max-bytes default, bigger ones are turned into dense ones
+const SPARSE_MAX_BYTES: usize = 3000;
+
+const HASH_SEED: u64 = 0xadc8_3b19;
+// 1 / (2 * ln 2)
+const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+
+/// HyperLogLog registers together with how they are encoded in the string value of the key.
+///
+/// Registers are always decoded into a byte per register (16kb), so that adding, merging and
+/// counting are the same for both encodings. They are encoded back the same way as Redis does it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+    // New HyperLogLogs are sparse, once they are dense they stay dense
+    sparse: bool,
+    cardinality: Option<u64>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+            sparse: true,
+            cardinality: Some(0),
+        }
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes a string value, it has to be a HyperLogLog (i.e. written by PFADD)
+    pub fn decode(value: &[u8]) -> Result<Self, CommandError> {
+        if value.len() < HEADER_SIZE || &value[..MAGIC.len()] != MAGIC {
+            return Err(CommandError::NotHyperLogLog);
+        }
+
+        let (header, data) = value.split_at(HEADER_SIZE);
+        let registers = match header[4] {
+            DENSE if value.len() == DENSE_SIZE => decode_dense(data),
+            SPARSE => decode_sparse(data).ok_or(CommandError::NotHyperLogLog)?,
+            _ => return Err(CommandError::NotHyperLogLog),
+        };
+
+        Ok(Self {
+            registers,
+            sparse: header[4] == SPARSE,
+            cardinality: cached_cardinality(value),
+        })
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        self.sparse
+    }
+
+    /// Adds the element, returns whether any of the registers changed
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let hash = murmur_hash64a(element, HASH_SEED);
+        let index = (hash & (REGISTERS as u64 - 1)) as usize;
+
+        // Position of the first set bit in the remaining 50 bits (the 51st bit is always set)
+        let value = ((hash >> P) | (1 << Q)).trailing_zeros() as u8 + 1;
+
+        if value <= self.registers[index] {
+            return false;
+        }
+
+        self.registers[index] = value;
+        self.cardinality = None;
+        true
+    }
+
+    /// Merges the other HyperLogLog into this one, each register keeps the bigger value
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            if *other > *register {
+                *register = *other;
+                self.cardinality = None;
+            }
+        }
+
+        self.sparse &= other.sparse;
+    }
+
+    /// Whether the cardinality is cached, so that `count` does not have to compute it
+    pub fn is_count_cached(&self) -> bool {
+        self.cardinality.is_some()
+    }
+
+    /// Estimated number of unique elements, it is cached until an element gets added
+    pub fn count(&mut self) -> u64 {
+        if let Some(cardinality) = self.cardinality {
+            return cardinality;
+        }
+
+        let cardinality = estimate(&self.registers);
+        self.cardinality = Some(cardinality);
+        cardinality
+    }
+
+    /// Encodes the registers, sparse HyperLogLogs turn into dense ones once
+    /// the sparse encoding can't hold them (or gets too big)
+    pub fn encode(&mut self) -> Bytes {
+        let sparse = self
+            .sparse
+            .then(|| encode_sparse(&self.registers))
+            .flatten();
+        self.sparse = sparse.is_some();
+
+        let mut value = MAGIC.to_vec();
+        value.push(if self.sparse { SPARSE } else { DENSE });
+        value.extend([0; 3]);
+
+        let mut cardinality = self.cardinality.unwrap_or_default().to_le_bytes();
+        if self.cardinality.is_none() {
+            cardinality[7] |= STALE_CARDINALITY;
+        }
+        value.extend(cardinality);
+
+        match sparse {
+            Some(data) => value.extend(data),
+            None => value.extend(encode_dense(&self.registers)),
+        }
+
+        value.into()
+    }
+}
+
+/// HyperLogLog of the key, None if the key does not exist
+pub fn get(keys: &HashMap<Bytes, Value>, key: &[u8]) -> Result<Option<HyperLogLog>, CommandError> {
+    match keys.get(key) {
+        Some(Value::String(value)) => HyperLogLog::decode(value).map(Some),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// Cardinality cached in the header of a HyperLogLog string, None when it has to be computed again
+pub fn cached_cardinality(value: &[u8]) -> Option<u64> {
+    let cardinality = <[u8; 8]>::try_from(value.get(8..HEADER_SIZE)?).ok()?;
+    (cardinality[7] & STALE_CARDINALITY == 0).then(|| u64::from_le_bytes(cardinality))
+}
+
+/// Registers are packed 6 bits each, least significant bits first
+fn decode_dense(data: &[u8]) -> Vec<u8> {
+    (0..REGISTERS)
+        .map(|register| {
+            let bit = register * REGISTER_BITS;
+            let (byte, shift) = (bit / 8, bit % 8);
+            let low = u16::from(data[byte]);
+            let high = u16::from(data.get(byte + 1).copied().unwrap_or_default());
+
+            (((high << 8 | low) >> shift) as u8) & REGISTER_MAX
+        })
+        .collect()
+}
+
+fn encode_dense(registers: &[u8]) -> Vec<u8> {
+    let mut data = vec![0; DENSE_SIZE - HEADER_SIZE];
+
+    for (register, value) in registers.iter().enumerate() {
+        let bit = register * REGISTER_BITS;
+        let (byte, shift) = (bit / 8, bit % 8);
+        let value = u16::from(*value) << shift;
+
+        data[byte] |= value as u8;
+        if let Some(next) = data.get_mut(byte + 1) {
+            *next |= (value >> 8) as u8;
+        }
+    }
+
+    data
+}
+
+/// None when the opcodes do not add up to exactly 16384 registers
+fn decode_sparse(data: &[u8]) -> Option<Vec<u8>> {
+    let mut registers = Vec::with_capacity(REGISTERS);
+    let mut opcodes = data.iter();
+
+    while let Some(opcode) = opcodes.next() {
+        let (value, len) = match opcode >> 6 {
+            0b00 => (0, usize::from(opcode & 0x3f) + 1),
+            0b01 => {
+                let low = *opcodes.next()?;
+                (0, (usize::from(opcode & 0x3f) << 8 | usize::from(low)) + 1)
+            }
+            _ => ((opcode >> 2 & 0x1f) + 1, usize::from(opcode & 0b11) + 1),
+        };
+
+        if registers.len() + len > REGISTERS {
+            return None;
+        }
+        registers.extend(std::iter::repeat_n(value, len));
+    }
+
+    (registers.len() == REGISTERS).then_some(registers)
+}
+
+/// None when a register does not fit into the sparse encoding or it gets too big
+fn encode_sparse(registers: &[u8]) -> Option<Vec<u8>> {
+    let mut data = vec![];
+    let mut index = 0;
+
+    while index < registers.len() {
+        let value = registers[index];
+        let run = registers[index..]
+            .iter()
+            .take_while(|register| **register == value)
+            .count();
+        index += run;
+
+        let mut left = run;
+        while left > 0 {
+            match value {
+                0 if left > ZERO_MAX_LEN => {
+                    let len = left.min(XZERO_MAX_LEN);
+                    data.push(0x40 | ((len - 1) >> 8) as u8);
+                    data.push(((len - 1) & 0xff) as u8);
+                    left -= len;
+                }
+                0 => {
+                    data.push((left - 1) as u8);
+                    left = 0;
+                }
+                1..=VAL_MAX_VALUE => {
+                    let len = left.min(VAL_MAX_LEN);
+                    data.push(0x80 | (value - 1) << 2 | (len - 1) as u8);
+                    left -= len;
+                }
+                _ => return None,
+            }
+        }
+
+        if HEADER_SIZE + data.len() > SPARSE_MAX_BYTES {
+            return None;
+        }
+    }
+
+    Some(data)
+}
+
+/// Cardinality estimate of "New cardinality estimation algorithms for HyperLogLog sketches"
+/// (Otmar Ertl, 2017), the same one Redis uses
+fn estimate(registers: &[u8]) -> u64 {
+    let m = REGISTERS as f64;
+
+    let mut histogram = [0u32; 64];
+    for register in registers {
+        histogram[usize::from(*register)] += 1;
+    }
+
+    let q = Q as usize;
+    let mut z = m * tau((m - f64::from(histogram[q + 1])) / m);
+    for count in histogram[1..=q].iter().rev() {
+        z += f64::from(*count);
+        z *= 0.5;
+    }
+    z += m * sigma(f64::from(histogram[0]) / m);
+
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+
+        if z == previous {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+
+        if z == previous {
+            return z / 3.0;
+        }
+    }
+}
+
+/// MurmurHash2, 64-bit version by Austin Appleby (same as in Redis)
+fn murmur_hash64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+
+    let mut chunks = key.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (index, byte) in rest.iter().enumerate() {
+            h ^= u64::from(*byte) << (8 * index);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+
+    h
+}
+
+#[cfg(test)]
+mod hyperloglog_tests {
+    use super::*;
+
+    #[test]
+    fn estimates_within_one_percent() {
+        let mut hll = HyperLogLog::new();
+
+        let mut added = 0;
+
+        for total in [1_000, 100_000, 1_000_000] {
+            for element in added..total {
+                hll.add(format!("user:{element}").as_bytes());
+            }
+            added = total;
+
+            let count = hll.count() as f64;
+            let error = (count - total as f64).abs() / total as f64;
+            assert!(error < 0.01, "{count} estimated for {total}");
+        }
+    }
+
+    #[test]
+    fn turns_from_sparse_into_dense() {
+        let mut hll = HyperLogLog::new();
+        let empty = hll.encode();
+        // Header and a single XZERO opcode that covers every register
+        assert_eq!(&empty[..], b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff");
+        assert_eq!(cached_cardinality(&empty), Some(0));
+
+        assert!(hll.add(b"a"));
+        assert!(!hll.add(b"a"));
+        let sparse = hll.encode();
+        assert_eq!(cached_cardinality(&sparse), None);
+        assert_eq!(HyperLogLog::decode(&sparse).unwrap(), hll);
+        assert_eq!(hll.count(), 1);
+
+        for element in 0..5_000 {
+            hll.add(element.to_string().as_bytes());
+        }
+        let dense = hll.encode();
+        assert!(!hll.is_sparse());
+        assert_eq!(dense.len(), DENSE_SIZE);
+        assert_eq!(HyperLogLog::decode(&dense).unwrap(), hll);
+
+        // Merging a dense one makes the result dense too
+        let mut merged = HyperLogLog::new();
+        merged.merge(&hll);
+        assert!(!merged.is_sparse());
+        assert_eq!(merged.count(), hll.count());
+
+        assert_eq!(
+            HyperLogLog::decode(b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f"),
+            Err(CommandError::NotHyperLogLog)
+        );
+        assert_eq!(
+            HyperLogLog::decode(b"not a hyperloglog"),
+            Err(CommandError::NotHyperLogLog)
+        );
+    }
+}
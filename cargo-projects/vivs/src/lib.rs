@@ -1,11 +1,13 @@
 #![deny(clippy::unwrap_in_result)]
 
+use limits::{ClientClass, OutputBufferLimit};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, sync::LazyLock};
-use tokio::net::TcpStream;
+use std::{collections::HashMap, fmt::Display, time::Duration};
 
 pub mod data_chunk;
-use data_chunk::DataChunk;
+
+pub mod error;
+pub use error::CommandError;
 
 pub mod listener;
 pub use listener::Listener;
@@ -13,8 +15,23 @@ pub use listener::Listener;
 pub mod node_listener;
 pub use node_listener::NodeListener;
 
+pub mod metrics_listener;
+pub use metrics_listener::MetricsListener;
+
 pub mod db;
-pub use db::DataStore;
+pub use db::{DataStore, Db};
+
+pub mod bitmap;
+pub mod blocking;
+pub mod dump;
+pub mod geo;
+pub mod hyperloglog;
+pub mod list;
+pub mod sorted_set;
+pub mod stream;
+
+pub mod clients;
+pub use clients::Clients;
 
 pub mod handler;
 pub use handler::Handler;
@@ -26,11 +43,33 @@ pub mod commands;
 pub use commands::Command;
 
 pub mod cluster;
+pub mod config;
 pub mod parser;
 pub mod server;
 pub mod utils;
 
-use parser::Parser;
+pub mod stats;
+pub use stats::Stats;
+
+pub mod slowlog;
+pub use slowlog::SlowLog;
+
+pub mod monitors;
+pub use monitors::Monitors;
+
+pub mod notifications;
+pub mod pubsub;
+
+pub mod shutdown;
+pub use shutdown::Shutdown;
+
+pub mod testing;
+
+pub mod limits;
+pub use limits::Limits;
+
+pub mod client;
+pub use client::Client;
 
 // Boxing errors is a good starting point but would need to be reconsidered.
 //
@@ -57,12 +96,71 @@ pub type GenericError = Box<dyn std::error::Error + Send + Sync + 'static>;
 // For example, Result<bool> is interpreted as Result<bool, Error>
 pub type GenericResult<T> = std::result::Result<T, GenericError>;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 pub struct ClusterConfig {
     pub id: String,
     pub ip: String,
     pub is_self: bool,
-    pub position: (usize, usize),
+    /// Ranges of hash slots (both ends are included) served by the node.
+    /// Configs written before resharding was supported contain a single `position = [start, end]`.
+    #[serde(default, alias = "position", deserialize_with = "slot_ranges")]
+    pub slots: Vec<(usize, usize)>,
+    /// Slots that are being moved from this node to another node
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub migrating: Vec<SlotMigration>,
+    /// Slots that are being moved to this node from another node
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub importing: Vec<SlotMigration>,
+}
+
+impl ClusterConfig {
+    /// Whether the hash slot is served by the node
+    pub fn serves(&self, slot: usize) -> bool {
+        self.slots
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&slot))
+    }
+
+    /// Number of hash slots served by the node
+    pub fn slots_count(&self) -> usize {
+        self.slots
+            .iter()
+            .map(|(start, end)| end.saturating_sub(*start) + 1)
+            .sum()
+    }
+}
+
+/// Ranges of hash slots that are being moved, `node` is the other side of the migration
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SlotMigration {
+    pub slots: Vec<(usize, usize)>,
+    pub node: String,
+}
+
+impl SlotMigration {
+    pub fn covers(&self, slot: usize) -> bool {
+        self.slots
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&slot))
+    }
+}
+
+/// Reads either a single range of slots ([0, 8191]) or a list of ranges ([[0, 100], [200, 300]])
+fn slot_ranges<'de, D>(deserializer: D) -> Result<Vec<(usize, usize)>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum SlotRanges {
+        Single((usize, usize)),
+        Many(Vec<(usize, usize)>),
+    }
+
+    Ok(match SlotRanges::deserialize(deserializer)? {
+        SlotRanges::Single(range) => vec![range],
+        SlotRanges::Many(ranges) => ranges,
+    })
 }
 
 pub type ClusterInstanceConfig = HashMap<String, ClusterConfig>;
@@ -70,150 +168,165 @@ pub type ClusterInstanceConfig = HashMap<String, ClusterConfig>;
 // This is the default port the server listens on
 pub const PORT: u16 = 9000;
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Config {
+    // Number of databases, SELECT picks one of them
+    databases: Option<usize>,
+    // Keyspace notifications flag string i.e. "KEA", off when not set
+    notify_keyspace_events: Option<String>,
+    #[serde(default)]
     connection: ConnectionState,
     cluster: Option<Cluster>,
+    metrics: Option<Metrics>,
+    slowlog: Option<SlowLogConfig>,
+    limits: Option<LimitsConfig>,
 }
 
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Clone)]
 struct ConnectionState {
     address: String,
     port: u16,
+    // Milliseconds in-flight commands are given to finish on shutdown
+    shutdown_timeout: Option<u64>,
 }
 
-#[derive(Deserialize, Debug, Default)]
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1".to_owned(),
+            port: PORT,
+            shutdown_timeout: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
 struct Cluster {
     enabled: bool,
+    // Milliseconds a node waits for its peers to respond
     node_timeout: u16,
     port: Option<u16>,
 }
 
-impl std::error::Error for Config {}
+#[derive(Deserialize, Debug, Clone, Default)]
+struct Metrics {
+    port: u16,
+}
 
-impl Display for Config {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Could not load global config")
+/// Slow log settings, defaults are used for the ones that are not set
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct SlowLogConfig {
+    // In microseconds, negative value disables the slow log
+    pub log_slower_than: Option<i64>,
+    pub max_len: Option<usize>,
+}
+
+/// Client limits, Redis defaults are used for the ones that are not set
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct LimitsConfig {
+    pub maxclients: Option<usize>,
+    // In seconds, 0 disables the idle timeout
+    pub timeout: Option<u64>,
+    // In seconds, 0 disables TCP keepalive
+    pub tcp_keepalive: Option<u64>,
+    // In bytes
+    pub proto_max_bulk_len: Option<usize>,
+    pub client_query_buffer_limit: Option<usize>,
+    // Output buffers of normal, pub/sub and MONITOR clients, hard and soft limits are in bytes
+    pub normal_output_buffer_hard_limit: Option<usize>,
+    pub normal_output_buffer_soft_limit: Option<usize>,
+    pub normal_output_buffer_soft_seconds: Option<u64>,
+    pub pubsub_output_buffer_hard_limit: Option<usize>,
+    pub pubsub_output_buffer_soft_limit: Option<usize>,
+    pub pubsub_output_buffer_soft_seconds: Option<u64>,
+    pub monitor_output_buffer_hard_limit: Option<usize>,
+    pub monitor_output_buffer_soft_limit: Option<usize>,
+    pub monitor_output_buffer_soft_seconds: Option<u64>,
+}
+
+impl LimitsConfig {
+    /// Output buffer limit of the client class, defaults are used for the values that are not set
+    pub fn output_buffer_limit(&self, class: ClientClass) -> OutputBufferLimit {
+        let (hard_limit, soft_limit, soft_seconds) = match class {
+            ClientClass::Normal => (
+                self.normal_output_buffer_hard_limit,
+                self.normal_output_buffer_soft_limit,
+                self.normal_output_buffer_soft_seconds,
+            ),
+            ClientClass::Pubsub => (
+                self.pubsub_output_buffer_hard_limit,
+                self.pubsub_output_buffer_soft_limit,
+                self.pubsub_output_buffer_soft_seconds,
+            ),
+            ClientClass::Monitor => (
+                self.monitor_output_buffer_hard_limit,
+                self.monitor_output_buffer_soft_limit,
+                self.monitor_output_buffer_soft_seconds,
+            ),
+        };
+        let default = class.default_output_buffer_limit();
+
+        OutputBufferLimit {
+            hard_limit: hard_limit.unwrap_or(default.hard_limit),
+            soft_limit: soft_limit.unwrap_or(default.soft_limit),
+            soft_seconds: soft_seconds.unwrap_or(default.soft_seconds),
+        }
     }
 }
 
-// Load up the global config lazily
-pub static VIVS_CONFIG_LAZY: LazyLock<Result<Config, String>> = LazyLock::new(|| {
-    let Ok(config_dir) = std::env::current_dir() else {
-        return Err("No current dir".to_owned());
-    };
+impl Config {
+    pub fn cluster_enabled(&self) -> bool {
+        self.cluster.as_ref().is_some_and(|cluster| cluster.enabled)
+    }
 
-    let config_dir = config_dir.join("config/config.toml");
-    let Ok(file_contents_as_string) = std::fs::read_to_string(config_dir) else {
-        return Err("Could not read file".to_owned());
-    };
+    /// Number of databases, cluster mode only has db 0
+    pub fn databases(&self) -> usize {
+        self.databases.unwrap_or(db::DEFAULT_DATABASES)
+    }
 
-    let toml_config = toml::from_str::<Config>(&file_contents_as_string);
+    pub fn notify_keyspace_events(&self) -> Option<&str> {
+        self.notify_keyspace_events.as_deref()
+    }
 
-    if let Ok(config) = toml_config {
-        Ok(config)
-    } else {
-        let connection = ConnectionState {
-            port: 9000,
-            address: "127.0.0.1".to_owned(),
-        };
-        Ok(Config {
-            connection,
-            cluster: None,
-        })
+    pub fn shutdown_timeout(&self) -> Option<Duration> {
+        self.connection.shutdown_timeout.map(Duration::from_millis)
     }
-});
 
-pub struct Client {
-    connection: Connection,
-}
+    /// How long to wait for peer nodes, only set in the cluster mode
+    pub fn node_timeout(&self) -> Option<Duration> {
+        self.cluster
+            .as_ref()
+            .filter(|cluster| cluster.enabled)
+            .map(|cluster| Duration::from_millis(cluster.node_timeout.into()))
+    }
 
-impl Client {
-    pub async fn new() -> GenericResult<Self> {
-        let stream = TcpStream::connect(format!("127.0.0.1:{}", PORT)).await?;
-        let connection = Connection::new(stream);
-        Ok(Client { connection })
+    pub fn slowlog(&self) -> Option<&SlowLogConfig> {
+        self.slowlog.as_ref()
     }
 
-    /// TODO: IMPROVEMENT
-    ///
-    /// Current implementation is very manual and basic.
-    /// The idea is to eventually move to something like:
-    /// client::command.set("get").set("key").conn(self.connection)?;
-    pub async fn get(&mut self, value: String) -> Option<String> {
-        // e.g. *2\r\n$3\r\GET\r\n$4\r\nMary\r\n
-        let frame = format!("*2\r\n$3\r\nGET\r\n${}\r\n{}\r\n", value.len(), value);
-        let _ = self.connection.write_complete_frame(&frame).await;
+    pub fn limits(&self) -> Option<&LimitsConfig> {
+        self.limits.as_ref()
+    }
 
-        let cursored_buffer = self.connection.process_stream().await;
-        let data_chunk = DataChunk::read_chunk(&mut cursored_buffer.unwrap());
-        let parser = Parser::new(data_chunk.unwrap());
+    /// Port of the Prometheus metrics HTTP listener, if it is enabled
+    pub fn metrics_port(&self) -> Option<u16> {
+        self.metrics.as_ref().map(|metrics| metrics.port)
+    }
+}
 
-        let Ok(mut parser) = parser else {
-            return None;
-        };
+/// Parses the contents of a config file, i.e. to configure servers started by `vivs::testing`
+impl std::str::FromStr for Config {
+    type Err = String;
 
-        match parser.next() {
-            Some(DataChunk::Null) => None,
-            Some(DataChunk::Bulk(b)) => Some(std::str::from_utf8(&b).unwrap().to_owned()),
-            _ => None,
-        }
+    fn from_str(contents: &str) -> Result<Self, Self::Err> {
+        toml::from_str(contents).map_err(|e| e.to_string())
     }
+}
 
-    /// TODO: IMPROVEMENT
-    ///
-    /// Same as get()
-    pub async fn set(&mut self, key: String, value: String) -> String {
-        // e.g. *3\r\n$3\r\nSET\r\n$4\r\nname\r\n$4\r\nMary\r\n
-        let frame = format!(
-            "*3\r\n$3\r\nSET\r\n${}\r\n{}\r\n${}\r\n{}\r\n",
-            key.len(),
-            key,
-            value.len(),
-            value
-        );
-        let _ = self.connection.write_complete_frame(&frame).await;
-
-        let mut data_chunk = self.connection.process_stream().await.unwrap();
-        let data_chunk = Parser::new(DataChunk::read_chunk(&mut data_chunk).unwrap());
-        let frame = DataChunk::read_chunk_frame(&mut data_chunk.unwrap())
-            .await
-            .unwrap();
-        let frame_utf8 = std::str::from_utf8(&frame);
-        frame_utf8.unwrap().to_owned()
-    }
-
-    pub async fn delete(&mut self, key: String) -> String {
-        let frame = format!("*2\r\n$6\r\nDELETE\r\n${}\r\n{}\r\n", key.len(), key);
-        let _ = self.connection.write_complete_frame(&frame).await;
-
-        let frame = self.connection.process_stream().await;
-        let processed_stream = DataChunk::read_chunk(&mut frame.unwrap());
-        let frame = Parser::new(processed_stream.unwrap());
-
-        if let Ok(mut frame) = frame {
-            return match frame.next() {
-                Some(DataChunk::Null) => "0".to_owned(),
-                Some(DataChunk::Integer(val)) => {
-                    // convert Bytes to bytes array
-                    // then determine endianness to create u64 integer value from the bytes array
-                    // and return integer as string
-                    let bytes_slice = val.slice(0..8);
-
-                    // converts the slice to an array of u8 elements (since u64 is 8 bytes)
-                    let arr_u8: [u8; 8] = bytes_slice[0..8].try_into().unwrap();
-
-                    if cfg!(target_endian = "big") {
-                        u64::from_be_bytes(arr_u8)
-                    } else {
-                        u64::from_le_bytes(arr_u8)
-                    }
-                    .to_string()
-                }
-                _ => "0".to_owned(),
-            };
-        }
+impl std::error::Error for Config {}
 
-        "0".to_owned()
+impl Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Could not load global config")
     }
 }
@@ -0,0 +1,394 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{sync::Notify, time::Instant};
+
+// Same defaults as in Redis
+pub const DEFAULT_MAX_CLIENTS: usize = 10000;
+// Seconds a client can stay idle before it gets disconnected, 0 disables the timeout
+pub const DEFAULT_TIMEOUT: u64 = 0;
+// Seconds between TCP keepalive probes, 0 disables keepalive
+pub const DEFAULT_TCP_KEEPALIVE: u64 = 300;
+// Largest bulk string (in bytes) that a client can send, 512mb
+pub const DEFAULT_PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
+// Largest (in bytes) a client's unparsed query can grow to, 1gb
+pub const DEFAULT_CLIENT_QUERY_BUFFER_LIMIT: usize = 1024 * 1024 * 1024;
+// Output buffer limits of pub/sub and MONITOR clients, 32mb hard, 8mb soft for 60 seconds
+pub const DEFAULT_OUTPUT_BUFFER_LIMIT: OutputBufferLimit = OutputBufferLimit {
+    hard_limit: 32 * 1024 * 1024,
+    soft_limit: 8 * 1024 * 1024,
+    soft_seconds: 60,
+};
+
+pub const MAX_CLIENTS_ERR: &str = "max number of clients reached";
+pub const IDLE_TIMEOUT_ERR: &str = "idle timeout reached, closing connection";
+pub const QUERY_BUFFER_ERR: &str = "query buffer limit reached";
+pub const OUTPUT_BUFFER_ERR: &str = "output buffer limit reached";
+
+/// Kind of a client, each kind has its own output buffer limit (same as in Redis)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ClientClass {
+    Normal,
+    /// Subscribed to channels or patterns
+    Pubsub,
+    /// Issued MONITOR
+    Monitor,
+}
+
+impl ClientClass {
+    pub const ALL: [ClientClass; 3] = [
+        ClientClass::Normal,
+        ClientClass::Pubsub,
+        ClientClass::Monitor,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientClass::Normal => "normal",
+            ClientClass::Pubsub => "pubsub",
+            ClientClass::Monitor => "monitor",
+        }
+    }
+
+    /// Normal clients are not limited by default, same as in Redis
+    pub fn default_output_buffer_limit(&self) -> OutputBufferLimit {
+        match self {
+            ClientClass::Normal => OutputBufferLimit {
+                hard_limit: 0,
+                soft_limit: 0,
+                soft_seconds: 0,
+            },
+            _ => DEFAULT_OUTPUT_BUFFER_LIMIT,
+        }
+    }
+}
+
+/// Output buffer limit of a client, 0 disables the respective limit.
+///
+/// A client gets disconnected straight away once its output buffer goes over
+/// the hard limit or when it stays over the soft limit for longer than soft seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputBufferLimit {
+    pub hard_limit: usize,
+    pub soft_limit: usize,
+    pub soft_seconds: u64,
+}
+
+impl OutputBufferLimit {
+    /// Whether `pending` bytes go over the limit.
+    ///
+    /// `soft_limit_reached_at` keeps track of when the soft limit was first reached,
+    /// it gets reset once the output buffer goes back under the soft limit.
+    pub fn is_reached(&self, pending: usize, soft_limit_reached_at: &mut Option<Instant>) -> bool {
+        if self.hard_limit > 0 && pending >= self.hard_limit {
+            return true;
+        }
+
+        if self.soft_limit == 0 || pending < self.soft_limit {
+            *soft_limit_reached_at = None;
+            return false;
+        }
+
+        let reached_at = soft_limit_reached_at.get_or_insert_with(Instant::now);
+        reached_at.elapsed() >= Duration::from_secs(self.soft_seconds)
+    }
+}
+
+/// Limits that protect the server from clients that misbehave,
+/// i.e. open too many connections, stay idle or send queries that are too large.
+///
+/// CONFIG SET updates the limits in place, clones held by the listener and by open connections
+/// see the new values right away.
+#[derive(Clone)]
+pub struct Limits {
+    max_clients: Arc<AtomicUsize>,
+    timeout: Arc<AtomicU64>,
+    tcp_keepalive: Arc<AtomicU64>,
+    proto_max_bulk_len: Arc<AtomicUsize>,
+    client_query_buffer_limit: Arc<AtomicUsize>,
+    // By `ClientClass`, in the same order as `ClientClass::ALL`
+    output_buffer_limits: Arc<Mutex<[OutputBufferLimit; 3]>>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self {
+            max_clients: Arc::new(AtomicUsize::new(DEFAULT_MAX_CLIENTS)),
+            timeout: Arc::new(AtomicU64::new(DEFAULT_TIMEOUT)),
+            tcp_keepalive: Arc::new(AtomicU64::new(DEFAULT_TCP_KEEPALIVE)),
+            proto_max_bulk_len: Arc::new(AtomicUsize::new(DEFAULT_PROTO_MAX_BULK_LEN)),
+            client_query_buffer_limit: Arc::new(AtomicUsize::new(
+                DEFAULT_CLIENT_QUERY_BUFFER_LIMIT,
+            )),
+            output_buffer_limits: Arc::new(Mutex::new(
+                ClientClass::ALL.map(|class| class.default_output_buffer_limit()),
+            )),
+        }
+    }
+
+    pub fn max_clients(&self) -> usize {
+        self.max_clients.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_clients(&self, max_clients: usize) {
+        self.max_clients.store(max_clients, Ordering::Relaxed);
+    }
+
+    /// How long a client can stay idle for, None when idle clients are never disconnected
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout.load(Ordering::Relaxed) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    pub fn set_timeout(&self, seconds: u64) {
+        self.timeout.store(seconds, Ordering::Relaxed);
+    }
+
+    /// Time between TCP keepalive probes, None when keepalive is disabled
+    pub fn tcp_keepalive(&self) -> Option<Duration> {
+        match self.tcp_keepalive.load(Ordering::Relaxed) {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+
+    pub fn set_tcp_keepalive(&self, seconds: u64) {
+        self.tcp_keepalive.store(seconds, Ordering::Relaxed);
+    }
+
+    pub fn proto_max_bulk_len(&self) -> usize {
+        self.proto_max_bulk_len.load(Ordering::Relaxed)
+    }
+
+    pub fn set_proto_max_bulk_len(&self, bytes: usize) {
+        self.proto_max_bulk_len.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn client_query_buffer_limit(&self) -> usize {
+        self.client_query_buffer_limit.load(Ordering::Relaxed)
+    }
+
+    pub fn set_client_query_buffer_limit(&self, bytes: usize) {
+        self.client_query_buffer_limit
+            .store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn output_buffer_limit(&self, class: ClientClass) -> OutputBufferLimit {
+        self.output_buffer_limits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())[class as usize]
+    }
+
+    pub fn set_output_buffer_limit(&self, class: ClientClass, limit: OutputBufferLimit) {
+        self.output_buffer_limits
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())[class as usize] = limit;
+    }
+}
+
+#[derive(Debug)]
+struct OutputState {
+    class: ClientClass,
+    // Bytes queued for the client (pub/sub messages, MONITOR lines) that the connection has not taken yet
+    queued: usize,
+    soft_limit_reached_at: Option<Instant>,
+    overflowed: bool,
+}
+
+/// Output buffer of a single client: what got queued for it by other clients
+/// plus what its connection has not written to the socket yet.
+///
+/// The connection and everyone who queues messages for the client share it (clones point
+/// to the same buffer), so a client that can't keep up is noticed even while
+/// its connection is stuck writing to it.
+#[derive(Clone)]
+pub struct OutputBuffer {
+    // None for connections that are not limited (i.e. the ones opened by clients)
+    limits: Option<Limits>,
+    state: Arc<Mutex<OutputState>>,
+    overflow: Arc<Notify>,
+}
+
+impl std::fmt::Debug for OutputBuffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("OutputBuffer")
+            .field("limited", &self.limits.is_some())
+            .field("state", &*state)
+            .finish()
+    }
+}
+
+impl Default for OutputBuffer {
+    fn default() -> Self {
+        Self {
+            limits: None,
+            state: Arc::new(Mutex::new(OutputState {
+                class: ClientClass::Normal,
+                queued: 0,
+                soft_limit_reached_at: None,
+                overflowed: false,
+            })),
+            overflow: Arc::new(Notify::new()),
+        }
+    }
+}
+
+impl OutputBuffer {
+    /// Output buffer of a client that starts as a normal client
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits: Some(limits),
+            ..Self::default()
+        }
+    }
+
+    /// The client changes its class by subscribing to channels or issuing MONITOR
+    pub fn set_class(&self, class: ClientClass) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.class = class;
+        state.soft_limit_reached_at = None;
+    }
+
+    /// Adds the bytes queued for the client, returns false (and nothing gets queued)
+    /// once the client went over its limit
+    pub fn queue(&self, bytes: usize) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.overflowed {
+            return false;
+        }
+
+        state.queued += bytes;
+        let pending = state.queued;
+        !self.is_reached(&mut state, pending)
+    }
+
+    /// The connection took the bytes off the queue
+    pub fn dequeue(&self, bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.queued = state.queued.saturating_sub(bytes);
+    }
+
+    /// Whether `buffered` more bytes (that the connection has not written yet) fit into the limit
+    pub fn fits(&self, buffered: usize) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if state.overflowed {
+            return false;
+        }
+
+        let pending = state.queued + buffered;
+        !self.is_reached(&mut state, pending)
+    }
+
+    pub fn is_overflowed(&self) -> bool {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .overflowed
+    }
+
+    /// Completes once the client goes over its limit
+    pub async fn overflowed(&self) {
+        loop {
+            let notified = self.overflow.notified();
+            if self.is_overflowed() {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    fn is_reached(&self, state: &mut OutputState, pending: usize) -> bool {
+        let Some(limits) = &self.limits else {
+            return false;
+        };
+
+        let limit = limits.output_buffer_limit(state.class);
+        if limit.is_reached(pending, &mut state.soft_limit_reached_at) {
+            state.overflowed = true;
+            self.overflow.notify_waiters();
+        }
+
+        state.overflowed
+    }
+}
+
+#[cfg(test)]
+mod limits_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn output_buffer_limit_depends_on_the_client_class() {
+        let limits = Limits::new();
+        let limit = OutputBufferLimit {
+            hard_limit: 100,
+            soft_limit: 0,
+            soft_seconds: 0,
+        };
+        limits.set_output_buffer_limit(ClientClass::Pubsub, limit);
+
+        // Normal clients are not limited
+        let output = OutputBuffer::new(limits);
+        assert!(output.queue(200));
+        output.dequeue(200);
+
+        output.set_class(ClientClass::Pubsub);
+        assert!(output.queue(60));
+        assert!(output.fits(39));
+        assert!(!output.queue(40));
+
+        // Stays overflowed, the client gets disconnected
+        output.dequeue(100);
+        assert!(!output.fits(0));
+        output.overflowed().await;
+    }
+
+    #[test]
+    fn output_buffer_hard_limit() {
+        let limit = OutputBufferLimit {
+            hard_limit: 100,
+            soft_limit: 0,
+            soft_seconds: 0,
+        };
+        let mut reached_at = None;
+
+        assert!(!limit.is_reached(99, &mut reached_at));
+        assert!(limit.is_reached(100, &mut reached_at));
+    }
+
+    #[test]
+    fn output_buffer_soft_limit() {
+        let limit = OutputBufferLimit {
+            hard_limit: 0,
+            soft_limit: 10,
+            soft_seconds: 60,
+        };
+        let mut reached_at = None;
+
+        // Has to stay over the soft limit for a while
+        assert!(!limit.is_reached(10, &mut reached_at));
+        assert!(reached_at.is_some());
+
+        // Going back under the soft limit resets it
+        assert!(!limit.is_reached(9, &mut reached_at));
+        assert!(reached_at.is_none());
+
+        let limit = OutputBufferLimit {
+            soft_seconds: 0,
+            ..limit
+        };
+        assert!(limit.is_reached(10, &mut reached_at));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::{db::Value, error::CommandError, notifications::EventClass, Db};
+use bytes::Bytes;
+use std::collections::{HashMap, VecDeque};
+
+/// End of a list, left is the head and right is the tail (same as in Redis)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum End {
+    Left,
+    Right,
+}
+
+impl End {
+    /// Parses LEFT | RIGHT (i.e. the wherefrom and whereto of LMOVE)
+    pub fn parse(value: &str) -> Result<End, CommandError> {
+        match &value.to_lowercase()[..] {
+            "left" => Ok(End::Left),
+            "right" => Ok(End::Right),
+            _ => Err(CommandError::Syntax),
+        }
+    }
+
+    /// Keyspace event of popping from this end i.e. lpop
+    pub fn pop_event(self) -> &'static str {
+        match self {
+            End::Left => "lpop",
+            End::Right => "rpop",
+        }
+    }
+
+    /// Keyspace event of pushing to this end i.e. rpush
+    pub fn push_event(self) -> &'static str {
+        match self {
+            End::Left => "lpush",
+            End::Right => "rpush",
+        }
+    }
+}
+
+/// Length of the list, 0 if the key does not exist
+pub fn len(keys: &HashMap<Bytes, Value>, key: &[u8]) -> Result<usize, CommandError> {
+    match keys.get(key) {
+        Some(Value::List(list)) => Ok(list.len()),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(0),
+    }
+}
+
+/// Values of the list, empty if the key does not exist
+pub fn values<'a>(
+    keys: &'a HashMap<Bytes, Value>,
+    key: &[u8],
+) -> Result<Option<&'a VecDeque<Bytes>>, CommandError> {
+    match keys.get(key) {
+        Some(Value::List(list)) => Ok(Some(list)),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// Pushes the values one after another (the list is created if the key does not exist),
+/// returns the length of the list
+pub fn push(
+    keys: &mut HashMap<Bytes, Value>,
+    key: &Bytes,
+    values: impl IntoIterator<Item = Bytes>,
+    end: End,
+) -> Result<usize, CommandError> {
+    let value = keys
+        .entry(key.clone())
+        .or_insert_with(|| Value::List(VecDeque::new()));
+
+    let Value::List(list) = value else {
+        return Err(CommandError::WrongType);
+    };
+
+    for value in values {
+        match end {
+            End::Left => list.push_front(value),
+            End::Right => list.push_back(value),
+        }
+    }
+
+    Ok(list.len())
+}
+
+/// Pops a value, same as in Redis the key is removed once the list is empty
+pub fn pop(
+    keys: &mut HashMap<Bytes, Value>,
+    key: &[u8],
+    end: End,
+) -> Result<Option<Bytes>, CommandError> {
+    let Some(value) = keys.get_mut(key) else {
+        return Ok(None);
+    };
+
+    let Value::List(list) = value else {
+        return Err(CommandError::WrongType);
+    };
+
+    let popped = match end {
+        End::Left => list.pop_front(),
+        End::Right => list.pop_back(),
+    };
+
+    if list.is_empty() {
+        keys.remove(key);
+    }
+
+    Ok(popped)
+}
+
+/// Pops a value from the source and pushes it to the destination (LMOVE),
+/// the source and the destination can be the same list which rotates it
+pub fn move_value(
+    keys: &mut HashMap<Bytes, Value>,
+    source: &[u8],
+    destination: &Bytes,
+    from: End,
+    to: End,
+) -> Result<Option<Bytes>, CommandError> {
+    // Checked first so that nothing gets popped when it can't be pushed
+    if let Some(value) = keys.get(destination) {
+        if !matches!(value, Value::List(_)) {
+            return Err(CommandError::WrongType);
+        }
+    }
+
+    let Some(value) = pop(keys, source, from)? else {
+        return Ok(None);
+    };
+    push(keys, destination, [value.clone()], to)?;
+
+    Ok(Some(value))
+}
+
+/// Publishes the keyspace events of a pop, del too when the list got removed
+pub fn notify_pop(db: &Db, keys: &HashMap<Bytes, Value>, key: &[u8], end: End) {
+    db.notify(EventClass::List, end.pop_event(), key);
+
+    if !keys.contains_key(key) {
+        db.notify(EventClass::Generic, "del", key);
+    }
+}
+
+/// Publishes the keyspace events of a move, same as in Redis it is a pop and a push
+pub fn notify_move(
+    db: &Db,
+    keys: &HashMap<Bytes, Value>,
+    source: &[u8],
+    destination: &[u8],
+    from: End,
+    to: End,
+) {
+    notify_pop(db, keys, source, from);
+    db.notify(EventClass::List, to.push_event(), destination);
+}
+
+#[cfg(test)]
+mod list_tests {
+    use super::*;
+
+    #[test]
+    fn pushes_pops_and_moves() {
+        let mut keys = HashMap::new();
+        let queue = Bytes::from("queue");
+
+        assert_eq!(
+            push(&mut keys, &queue, ["a", "b"].map(Bytes::from), End::Right),
+            Ok(2)
+        );
+        assert_eq!(
+            push(&mut keys, &queue, [Bytes::from("z")], End::Left),
+            Ok(3)
+        );
+        assert_eq!(pop(&mut keys, &queue, End::Right), Ok(Some("b".into())));
+
+        // Rotates the list
+        assert_eq!(
+            move_value(&mut keys, &queue, &queue, End::Left, End::Right),
+            Ok(Some("z".into()))
+        );
+        assert_eq!(
+            values(&keys, &queue).unwrap().unwrap(),
+            &VecDeque::from(["a", "z"].map(Bytes::from))
+        );
+
+        keys.insert("string".into(), Value::String("1".into()));
+        assert_eq!(
+            move_value(&mut keys, &queue, &"string".into(), End::Left, End::Left),
+            Err(CommandError::WrongType)
+        );
+        assert_eq!(len(&keys, &queue), Ok(2));
+
+        // Empty lists are removed
+        pop(&mut keys, &queue, End::Left).unwrap();
+        pop(&mut keys, &queue, End::Left).unwrap();
+        assert!(!keys.contains_key(&queue));
+        assert_eq!(pop(&mut keys, &queue, End::Left), Ok(None));
+    }
+}
@@ -1,10 +1,39 @@
-use crate::{Connection, DataStore, GenericResult, Handler};
+use crate::{
+    cluster::Topology,
+    config::RuntimeConfig,
+    error::CommandError,
+    limits::{OutputBuffer, IDLE_TIMEOUT_ERR, MAX_CLIENTS_ERR},
+    pubsub::PubSub,
+    shutdown::DEFAULT_SHUTDOWN_TIMEOUT,
+    stats::OPS_SAMPLE_INTERVAL,
+    Clients, Connection, DataStore, GenericResult, Handler, Limits, Monitors, Shutdown, SlowLog,
+    Stats,
+};
 use log::{error, info};
-use tokio::net::TcpListener;
+use socket2::{SockRef, TcpKeepalive};
+use std::time::Duration;
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
 
 pub struct Listener {
     pub tcp_listener: TcpListener,
     pub db: DataStore,
+    pub clients: Clients,
+    pub stats: Stats,
+    pub slowlog: SlowLog,
+    pub monitors: Monitors,
+    // Same channels keyspace notifications of the data store are published to
+    pub pubsub: PubSub,
+    pub shutdown: Shutdown,
+    pub limits: Limits,
+    // Runtime tunable parameters (CONFIG GET / SET), shares limits, slow log etc with the fields above
+    pub config: RuntimeConfig,
+    // Cluster topology, loaded from the <port>.toml cluster config
+    pub topology: Topology,
+    // How long in-flight commands are waited for once shutdown is triggered
+    pub shutdown_timeout: Duration,
 }
 
 impl Listener {
@@ -12,47 +41,195 @@ impl Listener {
     ///
     /// `TcpListener` and `DataStore` get injected via the two parameters.
     pub fn new(tcp_listener: TcpListener, db: DataStore) -> Self {
-        Listener { tcp_listener, db }
+        let slowlog = SlowLog::new();
+        let monitors = Monitors::new();
+        let limits = Limits::new();
+        let config = RuntimeConfig::new(limits.clone(), slowlog.clone(), db.notifications.clone());
+
+        Listener {
+            tcp_listener,
+            pubsub: db.notifications.pubsub.clone(),
+            db,
+            clients: Clients::new(),
+            stats: Stats::new(),
+            slowlog,
+            monitors,
+            shutdown: Shutdown::new(),
+            limits,
+            config,
+            topology: Topology::new(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
     }
 
     /// Starts listening to the incoming connections and processes accordingly.
+    ///
+    /// Returns once shutdown is triggered and in-flight commands have finished
+    /// (or `shutdown_timeout` has passed).
     pub async fn run(self) -> GenericResult<()> {
         info!(
             "Listening for connections on {}",
             self.tcp_listener.local_addr()?
         );
 
+        // Cluster topology is loaded up front and reloaded in the background when it changes on disk
+        let port = self.tcp_listener.local_addr()?.port();
+        if let Err(e) = self.topology.reload(port).await {
+            error!("Failed to load cluster topology: {e}");
+        }
+        tokio::spawn(self.topology.clone().watch(port, self.shutdown.clone()));
+
+        // Samples the number of processed commands in the background for INFO ops/sec
+        let stats = self.stats.clone();
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(OPS_SAMPLE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => stats.sample_ops().await,
+                    _ = shutdown.wait() => break,
+                }
+            }
+        });
+
+        // Each connection task holds a sender, once all of them are dropped
+        // the receiver gets notified which means that all the connections are closed
+        let (in_flight_sender, mut in_flight) = mpsc::channel::<()>(1);
+
         // To accept multiple incoming connections,
         // a loop construct is used here to handle each connection.
         // It is handled as a separate task (either on the current or different thread).
         // Then a loop inside each thread is used to handle incoming data from the client socket.
         loop {
-            // waits to accept a new connection from the tcp listener
-            let (tcp_stream, socket_addr) = self.tcp_listener.accept().await?;
+            // waits to accept a new connection from the tcp listener (unless shutting down)
+            let (tcp_stream, socket_addr) = tokio::select! {
+                accepted = self.tcp_listener.accept() => accepted?,
+                _ = self.shutdown.wait() => break,
+            };
 
             info!("Incoming connection request from {:?}", socket_addr);
+            self.stats.connection_received();
 
-            let mut handler = Handler::new(self.db.clone(), Connection::new(tcp_stream));
+            if let Some(keepalive) = self.limits.tcp_keepalive() {
+                set_keepalive(&tcp_stream, keepalive);
+            }
+
+            // Replies are written piece by piece, without this pipelined replies
+            // get held back by Nagle's algorithm until the client ACKs (up to 40ms)
+            if let Err(e) = tcp_stream.set_nodelay(true) {
+                error!("Failed to set TCP_NODELAY: {e}");
+            }
+
+            let mut connection = Connection::new(tcp_stream);
+            connection.set_output_buffer(OutputBuffer::new(self.limits.clone()));
+
+            if self.clients.count().await >= self.limits.max_clients() {
+                info!("Rejecting {socket_addr}, {MAX_CLIENTS_ERR}");
+                self.stats.connection_rejected();
+
+                // The client is told why before the connection gets closed
+                let _ = connection
+                    .write_error(&CommandError::Other(MAX_CLIENTS_ERR.to_owned()))
+                    .await;
+                continue;
+            }
+            // Every connection is tracked so that it can be listed and killed
+            let (client_id, killed) = self.clients.register(&connection).await;
+
+            let mut handler = Handler::new(&self, connection, client_id);
+            let in_flight_sender = in_flight_sender.clone();
+            let shutdown = self.shutdown.clone();
+            let limits = self.limits.clone();
 
             // Creates a new task.
             // A Tokio task is an async green (aka virtual) thread that is created by a runtime of VM (instead of OS).
             // Tasks are created by passing an async block to spawn().
             tokio::spawn(async move {
+                // Dropped (together with the task) once the connection is closed
+                let _in_flight_sender = in_flight_sender;
+
                 info!("Connection established with {:?}", socket_addr);
                 // Wait for the data from the connected sockets.
                 // By looping here the connection does not close.
                 // If we don't loop and when a client tries to send data continuously on the socket,
                 // we'll get the "broken pipe" error message.
                 loop {
-                    match handler.run().await {
-                        Ok(_) => (),
-                        Err(e) => {
-                            error!("Failed to handle {socket_addr} request: {e}");
+                    let command = tokio::select! {
+                        biased;
+                        // CLIENT KILL was issued against this connection
+                        _ = killed.notified() => {
+                            info!("Connection with {:?} killed", socket_addr);
+                            break;
+                        }
+                        // Connections are only closed in between commands on shutdown
+                        _ = shutdown.wait() => break,
+                        command = handler.read_command() => command,
+                        // Client has not sent anything for too long
+                        _ = idle(limits.timeout()) => {
+                            info!("Connection with {:?} timed out", socket_addr);
+                            let _ = handler
+                                .connection
+                                .write_error(&CommandError::Other(IDLE_TIMEOUT_ERR.to_owned()))
+                                .await;
                             break;
                         }
                     };
+
+                    let result = match command {
+                        Ok((command, args)) => tokio::select! {
+                            result = handler.execute(command, args) => result,
+                            _ = killed.notified() => {
+                                info!("Connection with {:?} killed", socket_addr);
+                                break;
+                            }
+                        },
+                        Err(e) => Err(e),
+                    };
+
+                    if let Err(e) = result {
+                        error!("Failed to handle {socket_addr} request: {e}");
+                        break;
+                    }
                 }
+
+                handler.clients.unregister(client_id).await;
             });
         }
+
+        info!("Shutting down, waiting for in-flight commands to finish");
+
+        // Only the connection tasks hold senders now
+        drop(in_flight_sender);
+
+        if tokio::time::timeout(self.shutdown_timeout, in_flight.recv())
+            .await
+            .is_err()
+        {
+            info!(
+                "{} connection(s) did not finish in time",
+                self.clients.count().await
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolves once `timeout` passes, never resolves if there's no timeout.
+async fn idle(timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep(timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Enables TCP keepalive so that connections to peers that went away get closed.
+fn set_keepalive(tcp_stream: &TcpStream, interval: Duration) {
+    let keepalive = TcpKeepalive::new()
+        .with_time(interval)
+        .with_interval(interval);
+
+    if let Err(e) = SockRef::from(tcp_stream).set_tcp_keepalive(&keepalive) {
+        error!("Failed to set TCP keepalive: {e}");
     }
 }
@@ -0,0 +1,299 @@
+use crate::{
+    cluster::{ClusterState, Topology},
+    stats::{rss_bytes, LATENCY_BUCKETS},
+    Clients, DataStore, GenericResult, Listener, Stats,
+};
+use log::{error, info};
+use std::{fmt::Write, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    time::timeout,
+};
+
+pub const METRICS_PATH: &str = "/metrics";
+pub const HEALTH_PATH: &str = "/healthz";
+
+// Requests are tiny (no body), anything bigger than this is rejected
+const MAX_REQUEST_SIZE: usize = 8192;
+// Connections that don't send a complete request in time are dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP listener that exposes server statistics in the Prometheus text format
+/// (on `/metrics`) and the health of the node (on `/healthz`).
+pub struct MetricsListener {
+    tcp_listener: TcpListener,
+    db: DataStore,
+    clients: Clients,
+    stats: Stats,
+    topology: Topology,
+    cluster_enabled: bool,
+}
+
+/// Reads until the end of headers (an empty line)
+async fn read_request(stream: &mut TcpStream) -> GenericResult<String> {
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+    let mut bytes_read = 0;
+
+    loop {
+        let read = stream.read(&mut buffer[bytes_read..]).await?;
+        bytes_read += read;
+
+        if read == 0 || buffer[..bytes_read].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+
+        if bytes_read == MAX_REQUEST_SIZE {
+            Err("Request is too large")?;
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&buffer[..bytes_read]).into_owned())
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl MetricsListener {
+    /// Creates a `MetricsListener` that reports on the data store, clients and stats of the `Listener`.
+    pub fn new(
+        tcp_listener: TcpListener,
+        listener: &Listener,
+        cluster_enabled: bool,
+    ) -> GenericResult<Self> {
+        Ok(MetricsListener {
+            tcp_listener,
+            db: listener.db.clone(),
+            clients: listener.clients.clone(),
+            stats: listener.stats.clone(),
+            topology: listener.topology.clone(),
+            cluster_enabled,
+        })
+    }
+
+    /// Starts listening to the incoming HTTP requests.
+    pub async fn run(self) -> GenericResult<()> {
+        info!(
+            "Listening for metrics requests on {}",
+            self.tcp_listener.local_addr()?
+        );
+
+        let listener = Arc::new(self);
+        loop {
+            let (tcp_stream, socket_addr) = listener.tcp_listener.accept().await?;
+
+            // Each request gets its own task, so that a slow client can't hold up health checks
+            let listener = listener.clone();
+            tokio::spawn(async move {
+                if let Err(e) = listener.handle(tcp_stream).await {
+                    error!("Failed to handle {socket_addr} metrics request: {e}");
+                }
+            });
+        }
+    }
+
+    async fn handle(&self, mut stream: TcpStream) -> GenericResult<()> {
+        let Ok(request) = timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await else {
+            Err("Request timed out")?
+        };
+        let request = request?;
+        let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+
+        let response = match (method, path) {
+            ("GET", METRICS_PATH) => Response {
+                status: "200 OK",
+                content_type: "text/plain; version=0.0.4",
+                body: self.render_metrics().await?,
+            },
+            ("GET", HEALTH_PATH) => self.health().await,
+            ("GET", _) => Response {
+                status: "404 Not Found",
+                content_type: "text/plain",
+                body: "Not Found\n".to_owned(),
+            },
+            _ => Response {
+                status: "405 Method Not Allowed",
+                content_type: "text/plain",
+                body: "Method Not Allowed\n".to_owned(),
+            },
+        };
+
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        );
+
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(response.body.as_bytes()).await?;
+        stream.flush().await?;
+
+        Ok(())
+    }
+
+    async fn health(&self) -> Response {
+        let state = self.topology.state(self.cluster_enabled);
+
+        // Orchestrators only look at the status code, the body is for humans
+        let (status, health, reason) = match &state {
+            ClusterState::Fail(reason) => ("503 Service Unavailable", "fail", reason.as_str()),
+            _ => ("200 OK", "ok", ""),
+        };
+
+        Response {
+            status,
+            content_type: "application/json",
+            body: format!(
+                "{{\"status\":\"{health}\",\"cluster_state\":\"{}\",\"reason\":\"{reason}\"}}\n",
+                state.as_str()
+            ),
+        }
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub async fn render_metrics(&self) -> GenericResult<String> {
+        let mut metrics = String::new();
+        // db 0 is always reported, other databases only once they have keys
+        let keyspace = self
+            .db
+            .keyspace()
+            .await
+            .into_iter()
+            .filter(|(index, keys, _)| *index == 0 || *keys > 0)
+            .collect::<Vec<_>>();
+
+        let gauges = [
+            (
+                "vivs_connected_clients",
+                "Number of client connections",
+                self.clients.count().await as f64,
+            ),
+            (
+                "vivs_uptime_seconds",
+                "Number of seconds since the server started",
+                self.stats.uptime().as_secs() as f64,
+            ),
+            (
+                "vivs_memory_used_bytes",
+                "Estimated number of bytes used by keys and values",
+                self.db.memory_usage().await as f64,
+            ),
+            (
+                "vivs_memory_rss_bytes",
+                "Resident set size of the server process",
+                rss_bytes() as f64,
+            ),
+            (
+                "vivs_instantaneous_ops_per_sec",
+                "Number of commands processed per second",
+                self.stats.ops_per_sec().await as f64,
+            ),
+        ];
+
+        for (name, help, value) in gauges {
+            writeln!(metrics, "# HELP {name} {help}")?;
+            writeln!(metrics, "# TYPE {name} gauge")?;
+            writeln!(metrics, "{name} {value}")?;
+        }
+
+        let counters = [
+            (
+                "vivs_connections_received_total",
+                "Number of connections accepted by the server",
+                self.stats.total_connections(),
+            ),
+            (
+                "vivs_keyspace_hits_total",
+                "Number of successful key lookups",
+                self.db.stats.hits(),
+            ),
+            (
+                "vivs_keyspace_misses_total",
+                "Number of failed key lookups",
+                self.db.stats.misses(),
+            ),
+            (
+                "vivs_expired_keys_total",
+                "Number of keys removed because they expired",
+                self.db.stats.expired_keys(),
+            ),
+            (
+                "vivs_evicted_keys_total",
+                "Number of keys evicted due to memory limits",
+                self.db.stats.evicted_keys(),
+            ),
+        ];
+
+        for (name, help, value) in counters {
+            writeln!(metrics, "# HELP {name} {help}")?;
+            writeln!(metrics, "# TYPE {name} counter")?;
+            writeln!(metrics, "{name} {value}")?;
+        }
+
+        writeln!(metrics, "# HELP vivs_keys Number of keys")?;
+        writeln!(metrics, "# TYPE vivs_keys gauge")?;
+        for (index, keys, _) in &keyspace {
+            writeln!(metrics, "vivs_keys{{db=\"{index}\"}} {keys}")?;
+        }
+        writeln!(
+            metrics,
+            "# HELP vivs_keys_with_expiry Number of keys with an expiry set"
+        )?;
+        writeln!(metrics, "# TYPE vivs_keys_with_expiry gauge")?;
+        for (index, _, expires) in &keyspace {
+            writeln!(metrics, "vivs_keys_with_expiry{{db=\"{index}\"}} {expires}")?;
+        }
+
+        let commands = self.stats.commands().await;
+
+        writeln!(
+            metrics,
+            "# HELP vivs_commands_processed_total Number of commands processed"
+        )?;
+        writeln!(metrics, "# TYPE vivs_commands_processed_total counter")?;
+        for (command, stat) in &commands {
+            writeln!(
+                metrics,
+                "vivs_commands_processed_total{{command=\"{command}\"}} {}",
+                stat.calls
+            )?;
+        }
+
+        writeln!(
+            metrics,
+            "# HELP vivs_command_duration_seconds Time spent running commands"
+        )?;
+        writeln!(metrics, "# TYPE vivs_command_duration_seconds histogram")?;
+        for (command, stat) in &commands {
+            for (bucket, upper_bound) in stat.buckets.iter().zip(LATENCY_BUCKETS) {
+                writeln!(
+                    metrics,
+                    "vivs_command_duration_seconds_bucket{{command=\"{command}\",le=\"{upper_bound}\"}} {bucket}"
+                )?;
+            }
+            writeln!(
+                metrics,
+                "vivs_command_duration_seconds_bucket{{command=\"{command}\",le=\"+Inf\"}} {}",
+                stat.calls
+            )?;
+            writeln!(
+                metrics,
+                "vivs_command_duration_seconds_sum{{command=\"{command}\"}} {}",
+                stat.usec as f64 / 1_000_000.0
+            )?;
+            writeln!(
+                metrics,
+                "vivs_command_duration_seconds_count{{command=\"{command}\"}} {}",
+                stat.calls
+            )?;
+        }
+
+        Ok(metrics)
+    }
+}
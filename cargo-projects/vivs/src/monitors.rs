@@ -0,0 +1,182 @@
+use crate::limits::OutputBuffer;
+use bytes::Bytes;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+
+struct Subscriber {
+    sender: mpsc::UnboundedSender<String>,
+    // Output buffer of the monitoring client, lines are accounted in it until they get written
+    output: OutputBuffer,
+}
+
+/// Receiving end of a monitoring client, the lines are queued in it until the connection takes them.
+pub struct MonitorReceiver {
+    receiver: mpsc::UnboundedReceiver<String>,
+    output: OutputBuffer,
+}
+
+impl MonitorReceiver {
+    /// Returns the next command, None once the client stopped getting them
+    /// because it went over its output buffer limit.
+    pub async fn recv(&mut self) -> Option<String> {
+        let line = self.receiver.recv().await?;
+        self.output.dequeue(line.len());
+
+        Some(line)
+    }
+}
+
+/// Streams every processed command to the clients that issued MONITOR.
+///
+/// Lines are queued in the output buffer of each monitoring client, clients that cannot keep up
+/// stop getting them once they go over the output buffer limit of MONITOR clients.
+///
+/// Every connection holds a clone to publish the commands it runs to the same monitoring clients.
+#[derive(Clone)]
+pub struct Monitors {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    // Number of subscribers, so that there's no need to lock when no one is monitoring
+    active: Arc<AtomicUsize>,
+}
+
+impl Default for Monitors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Monitors {
+    pub fn new() -> Self {
+        Self {
+            subscribers: Arc::new(Mutex::new(vec![])),
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Whether any client is currently monitoring
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed) > 0
+    }
+
+    /// Starts sending the commands to the client that owns the output buffer
+    pub fn subscribe(&self, output: OutputBuffer) -> MonitorReceiver {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        subscribers.push(Subscriber {
+            sender,
+            output: output.clone(),
+        });
+        self.active.store(subscribers.len(), Ordering::Relaxed);
+
+        MonitorReceiver { receiver, output }
+    }
+
+    /// Sends the command to all the monitoring clients.
+    /// The format is the same as in Redis, i.e. 1339518083.107412 [0 127.0.0.1:60866] "get" "key"
+    /// where 0 is the database the client selected.
+    pub fn publish(&self, db_index: usize, client_addr: &str, args: &[Bytes]) {
+        if !self.is_active() {
+            return;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let args = args
+            .iter()
+            .map(|arg| repr(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let line = format!(
+            "{}.{:06} [{db_index} {client_addr}] {args}",
+            now.as_secs(),
+            now.subsec_micros()
+        );
+
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Clients that stopped monitoring or went over the limit get dropped
+        subscribers.retain(|subscriber| {
+            subscriber.output.queue(line.len()) && subscriber.sender.send(line.clone()).is_ok()
+        });
+        self.active.store(subscribers.len(), Ordering::Relaxed);
+    }
+}
+
+/// Quotes the argument and escapes characters that cannot be printed as is.
+pub fn repr(arg: &[u8]) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+
+    for byte in arg {
+        match byte {
+            b'\\' => quoted.push_str("\\\\"),
+            b'"' => quoted.push_str("\\\""),
+            b'\n' => quoted.push_str("\\n"),
+            b'\r' => quoted.push_str("\\r"),
+            b'\t' => quoted.push_str("\\t"),
+            byte if byte.is_ascii_graphic() || *byte == b' ' => quoted.push(*byte as char),
+            byte => quoted.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod monitors_tests {
+    use super::*;
+    use crate::{
+        limits::{ClientClass, OutputBufferLimit},
+        Limits,
+    };
+
+    #[test]
+    fn repr_escapes_special_characters() {
+        assert_eq!(repr(b"hello world"), "\"hello world\"");
+        assert_eq!(repr(b"a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(repr(b"line\r\n"), "\"line\\r\\n\"");
+        assert_eq!(repr(&[0xff, b'a']), "\"\\xffa\"");
+    }
+
+    #[tokio::test]
+    async fn slow_monitor_goes_over_output_buffer_limit() {
+        let limits = Limits::new();
+        limits.set_output_buffer_limit(
+            ClientClass::Monitor,
+            OutputBufferLimit {
+                hard_limit: 100,
+                soft_limit: 0,
+                soft_seconds: 0,
+            },
+        );
+        let output = OutputBuffer::new(limits);
+        output.set_class(ClientClass::Monitor);
+
+        let monitors = Monitors::new();
+        let mut receiver = monitors.subscribe(output.clone());
+        let args = [Bytes::from("SET"), Bytes::from("key"), Bytes::from("value")];
+
+        monitors.publish(3, "127.0.0.1:1", &args);
+        let line = receiver.recv().await.unwrap();
+        assert!(line.ends_with(" [3 127.0.0.1:1] \"SET\" \"key\" \"value\""));
+
+        // Nothing gets read by the client from now on
+        for _ in 0..5 {
+            monitors.publish(0, "127.0.0.1:1", &args);
+        }
+
+        assert!(output.is_overflowed());
+        assert!(!monitors.is_active());
+    }
+}
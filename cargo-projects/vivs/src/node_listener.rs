@@ -1,17 +1,36 @@
-use crate::{Connection, DataStore, GenericResult, Handler};
+use crate::{
+    cluster::{Topology, CLUSTER_NODE_LEAVING, CLUSTER_TOPOLOGY_CHANGED},
+    data_chunk::DataChunk,
+    parser::Parser,
+    Connection, GenericResult, Shutdown,
+};
 use log::{error, info};
 use tokio::net::TcpListener;
 
 pub struct NodeListener {
     tcp_listener: TcpListener,
+    shutdown: Shutdown,
+    topology: Topology,
+    // Port that clients connect to, the cluster config is named after it
+    node_port: u16,
 }
 
 impl NodeListener {
     /// Creates a `Listener`.
     ///
-    /// `TcpListener` and `DataStore` get injected via the two parameters.
-    pub fn new(tcp_listener: TcpListener) -> Self {
-        NodeListener { tcp_listener }
+    /// `TcpListener`, `Shutdown` and `Topology` (of the node listening on `node_port`) get injected.
+    pub fn new(
+        tcp_listener: TcpListener,
+        shutdown: Shutdown,
+        topology: Topology,
+        node_port: u16,
+    ) -> Self {
+        NodeListener {
+            tcp_listener,
+            shutdown,
+            topology,
+            node_port,
+        }
     }
 
     /// Starts listening to the incoming connections and processes accordingly.
@@ -26,20 +45,54 @@ impl NodeListener {
         // It is handled as a separate task (either on the current or different thread).
         // Then a loop inside each thread is used to handle incoming data from the client socket.
         loop {
-            // waits to accept a new connection from the tcp listener
-            let (tcp_stream, socket_addr) = self.tcp_listener.accept().await?;
+            // waits to accept a new connection from the tcp listener (unless shutting down)
+            let (tcp_stream, socket_addr) = tokio::select! {
+                accepted = self.tcp_listener.accept() => accepted?,
+                _ = self.shutdown.wait() => return Ok(()),
+            };
 
             info!("Incoming connection request from {:?}", socket_addr);
 
-            // let mut handler = Handler::new(self.db.clone(), Connection::new(tcp_stream));
-
             // Creates a new task.
             // A Tokio task is an async green (aka virtual) thread that is created by a runtime of VM (instead of OS).
             // Tasks are created by passing an async block to spawn().
+            let topology = self.topology.clone();
+            let node_port = self.node_port;
+
             tokio::spawn(async move {
                 info!("Connection established with {:?}", socket_addr);
-                // TODO
+
+                let mut connection = Connection::new(tcp_stream);
+                if let Err(e) = handle_message(&mut connection, &topology, node_port).await {
+                    error!("Failed to handle {socket_addr} node message: {e}");
+                }
             });
         }
     }
 }
+
+/// Reads a single message sent by a peer node.
+// TODO: the cluster bus only understands LEAVING and TOPOLOGY messages at the moment
+async fn handle_message(
+    connection: &mut Connection,
+    topology: &Topology,
+    node_port: u16,
+) -> GenericResult<()> {
+    let mut buffer = connection.process_stream().await?;
+    let mut parser = Parser::new(DataChunk::read_chunk(&mut buffer)?)?;
+
+    let message = parser.next_as_str()?.unwrap_or_default();
+
+    if message.to_uppercase() == CLUSTER_NODE_LEAVING {
+        let node = parser.next_as_str()?.unwrap_or_default();
+        info!("Node {node} is leaving the cluster");
+    } else if message.to_uppercase() == CLUSTER_TOPOLOGY_CHANGED {
+        // A peer changed the cluster config, the topology gets reloaded straight away
+        let epoch = topology.reload(node_port).await?;
+        info!("Cluster topology reloaded, epoch {epoch}");
+    } else {
+        info!("Unknown node message {message:?}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use crate::pubsub::PubSub;
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+pub const INVALID_EVENTS_ERR: &str =
+    "Invalid event class character. Use 'Ag$lshzxetmKE' (i.e. 'KEA' or 'Ex').";
+
+// K - events are published to __keyspace@<db>__:<key> with the event as the message
+const KEYSPACE: u32 = 1 << 0;
+// E - events are published to __keyevent@<db>__:<event> with the key as the message
+const KEYEVENT: u32 = 1 << 1;
+
+/// Kinds of events that can be turned on, same classes (and characters) as in Redis
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventClass {
+    /// g - DEL, EXPIRE, MOVE etc.
+    Generic,
+    /// $ - string commands
+    String,
+    /// l - list commands
+    List,
+    /// s - set commands
+    Set,
+    /// h - hash commands
+    Hash,
+    /// z - sorted set commands
+    SortedSet,
+    /// x - a key expired
+    Expired,
+    /// e - a key was evicted because of maxmemory
+    Evicted,
+    /// t - stream commands
+    Stream,
+    /// m - a key that does not exist was read (not included in A)
+    KeyMiss,
+}
+
+impl EventClass {
+    const ALL: [EventClass; 10] = [
+        EventClass::Generic,
+        EventClass::String,
+        EventClass::List,
+        EventClass::Set,
+        EventClass::Hash,
+        EventClass::SortedSet,
+        EventClass::Expired,
+        EventClass::Evicted,
+        EventClass::Stream,
+        EventClass::KeyMiss,
+    ];
+
+    fn flag(self) -> u32 {
+        1 << (self as u32 + 2)
+    }
+
+    fn char(self) -> char {
+        match self {
+            EventClass::Generic => 'g',
+            EventClass::String => '$',
+            EventClass::List => 'l',
+            EventClass::Set => 's',
+            EventClass::Hash => 'h',
+            EventClass::SortedSet => 'z',
+            EventClass::Expired => 'x',
+            EventClass::Evicted => 'e',
+            EventClass::Stream => 't',
+            EventClass::KeyMiss => 'm',
+        }
+    }
+}
+
+/// Parses a `notify-keyspace-events` flag string i.e. "KEA", an empty string turns them off
+pub fn parse_flags(flags: &str) -> Result<u32, &'static str> {
+    let all = EventClass::ALL
+        .iter()
+        .filter(|class| **class != EventClass::KeyMiss)
+        .fold(0, |all, class| all | class.flag());
+
+    flags.chars().try_fold(0, |parsed, char| {
+        let flag = match char {
+            'K' => KEYSPACE,
+            'E' => KEYEVENT,
+            'A' => all,
+            char => EventClass::ALL
+                .iter()
+                .find(|class| class.char() == char)
+                .map(|class| class.flag())
+                .ok_or(INVALID_EVENTS_ERR)?,
+        };
+
+        Ok(parsed | flag)
+    })
+}
+
+/// Flag string of the parsed flags, `A` is used when all the classes it covers are on
+pub fn flags_to_string(flags: u32) -> String {
+    let all = parse_flags("A").unwrap_or_default();
+    let mut string = String::new();
+
+    if flags & all == all {
+        string.push('A');
+    }
+
+    for class in EventClass::ALL {
+        let covered = flags & all == all && class.flag() & all != 0;
+        if flags & class.flag() != 0 && !covered {
+            string.push(class.char());
+        }
+    }
+
+    if flags & KEYSPACE != 0 {
+        string.push('K');
+    }
+    if flags & KEYEVENT != 0 {
+        string.push('E');
+    }
+
+    string
+}
+
+/// Keyspace notifications, they let clients know (through pub/sub) when keys change or expire.
+///
+/// Off by default, same as in Redis they are turned on with a flag string where `K` / `E`
+/// pick keyspace / keyevent channels and the rest of the characters pick the classes of events.
+#[derive(Clone, Default)]
+pub struct Notifications {
+    flags: Arc<AtomicU32>,
+    pub pubsub: PubSub,
+}
+
+impl Notifications {
+    pub fn new(pubsub: PubSub) -> Self {
+        Self {
+            flags: Arc::new(AtomicU32::new(0)),
+            pubsub,
+        }
+    }
+
+    /// Turns on the events in the flag string, the ones that are not in it get turned off
+    pub fn set_flags(&self, flags: &str) -> Result<(), &'static str> {
+        self.flags.store(parse_flags(flags)?, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn flags(&self) -> String {
+        flags_to_string(self.flags.load(Ordering::Relaxed))
+    }
+
+    /// Publishes the event i.e. "set" on __keyspace@0__:key and key on __keyevent@0__:set
+    pub fn notify(&self, class: EventClass, event: &str, key: &[u8], db: usize) {
+        let flags = self.flags.load(Ordering::Relaxed);
+
+        if flags & class.flag() == 0 || !self.pubsub.is_active() {
+            return;
+        }
+
+        if flags & KEYSPACE != 0 {
+            let channel = [format!("__keyspace@{db}__:").as_bytes(), key].concat();
+            self.pubsub.publish(&channel, event.as_bytes());
+        }
+
+        if flags & KEYEVENT != 0 {
+            let channel = format!("__keyevent@{db}__:{event}");
+            self.pubsub.publish(channel.as_bytes(), key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod notifications_tests {
+    use super::*;
+    use crate::limits::OutputBuffer;
+
+    #[test]
+    fn parses_flag_strings() {
+        assert_eq!(parse_flags(""), Ok(0));
+        assert_eq!(flags_to_string(parse_flags("KEA").unwrap()), "AKE");
+        assert_eq!(flags_to_string(parse_flags("Ex").unwrap()), "xE");
+        assert_eq!(flags_to_string(parse_flags("Kg$lshzxetm").unwrap()), "AmK");
+        assert_eq!(parse_flags("KEQ"), Err(INVALID_EVENTS_ERR));
+    }
+
+    #[tokio::test]
+    async fn publishes_enabled_events() {
+        let notifications = Notifications::default();
+        let mut subscription = notifications.pubsub.subscription(OutputBuffer::default());
+        subscription.psubscribe("__key*".into());
+
+        // Nothing is published while notifications are off
+        notifications.notify(EventClass::Generic, "del", b"key", 0);
+
+        notifications.set_flags("Ex").unwrap();
+        notifications.notify(EventClass::Generic, "del", b"key", 0);
+        notifications.notify(EventClass::Expired, "expired", b"key", 3);
+
+        let message = subscription.recv().await.unwrap();
+        assert_eq!(message.channel, "__keyevent@3__:expired");
+        assert_eq!(message.payload, "key");
+
+        notifications.set_flags("K$").unwrap();
+        notifications.notify(EventClass::String, "set", b"key", 0);
+
+        let message = subscription.recv().await.unwrap();
+        assert_eq!(message.channel, "__keyspace@0__:key");
+        assert_eq!(message.payload, "set");
+    }
+}
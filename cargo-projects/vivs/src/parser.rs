@@ -44,16 +44,28 @@ impl Parser {
     /// The reason for the Result return type is because we attempt to convert a
     /// slice of bytes to a string slice in the match expression if it can potentially error.
     pub fn next_as_str(&mut self) -> Result<Option<String>, DataChunkError> {
+        let Some(value) = self.next_bytes()? else {
+            return Ok(None);
+        };
+
+        let value = std::str::from_utf8(value.chunk())?;
+        Ok(Some(value.to_owned()))
+    }
+
+    /// Tries to get next element in the collection/segments as raw bytes.
+    ///
+    /// Unlike `next_as_str`, the bytes don't have to be valid UTF-8,
+    /// which is what keys and values need in order to be binary-safe.
+    pub fn next_bytes(&mut self) -> Result<Option<Bytes>, DataChunkError> {
         let Some(segment) = self.segments.next() else {
             return Ok(None);
         };
 
         match segment {
-            DataChunk::Bulk(value) => {
-                let value = std::str::from_utf8(value.chunk())?;
-                Ok(Some(value.to_owned()))
-            }
-            _ => unimplemented!(),
+            DataChunk::Bulk(value) => Ok(Some(value)),
+            _ => Err(DataChunkError::Parse(
+                "expected a bulk string argument".to_owned(),
+            )),
         }
     }
 
@@ -65,29 +77,6 @@ impl Parser {
         self.segments.peek()
     }
 
-    #[allow(clippy::unwrap_in_result)]
-    pub fn peek_as_str(&mut self) -> Option<String> {
-        let segment = self.segments.peek();
-
-        match segment {
-            Some(DataChunk::Bulk(value)) => {
-                Some(std::str::from_utf8(value.chunk()).unwrap().to_owned())
-            }
-            Some(DataChunk::SimpleError(value)) => {
-                let val = value.first().unwrap();
-
-                let bytes = match val {
-                    DataChunk::Bulk(bulk_val) => bulk_val,
-                    // TODO
-                    _ => "hi".as_bytes(),
-                };
-
-                Some(std::str::from_utf8(bytes).unwrap().to_owned())
-            }
-            _ => None,
-        }
-    }
-
     pub fn enumerate(self) -> std::iter::Enumerate<Peekable<IntoIter<DataChunk>>> {
         self.segments.enumerate()
     }
@@ -128,6 +117,14 @@ impl Parser {
     }
 }
 
+impl From<Vec<DataChunk>> for Parser {
+    fn from(data_chunks: Vec<DataChunk>) -> Self {
+        Parser {
+            segments: data_chunks.into_iter().peekable(),
+        }
+    }
+}
+
 impl Iterator for Parser {
     type Item = DataChunk;
 
@@ -0,0 +1,271 @@
+use crate::{limits::OutputBuffer, utils::glob_match};
+use bytes::Bytes;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::mpsc;
+
+/// A message published to a channel, `pattern` is set when it matched a PSUBSCRIBE pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub pattern: Option<Bytes>,
+    pub channel: Bytes,
+    pub payload: Bytes,
+}
+
+impl Message {
+    /// Bytes the message takes in the output buffer of a subscriber
+    fn len(&self) -> usize {
+        self.pattern.as_ref().map_or(0, Bytes::len) + self.channel.len() + self.payload.len()
+    }
+}
+
+#[derive(Clone)]
+struct Subscriber {
+    sender: mpsc::UnboundedSender<Message>,
+    output: OutputBuffer,
+}
+
+impl Subscriber {
+    /// Queues the message unless the subscriber went over its output buffer limit
+    fn send(&self, message: Message) -> bool {
+        self.output.queue(message.len()) && self.sender.send(message).is_ok()
+    }
+}
+
+// Subscribers of a channel (or a pattern) by their subscription id
+type Subscribers = HashMap<Bytes, HashMap<u64, Subscriber>>;
+
+#[derive(Default)]
+struct Registry {
+    channels: Subscribers,
+    patterns: Subscribers,
+}
+
+/// Channels and patterns clients subscribed to (SUBSCRIBE / PSUBSCRIBE),
+/// PUBLISH and keyspace notifications send messages through it.
+///
+/// Clones share the registry, so a message published on one connection reaches subscribers on any other.
+#[derive(Clone, Default)]
+pub struct PubSub {
+    registry: Arc<Mutex<Registry>>,
+    next_id: Arc<AtomicU64>,
+    // Number of subscriptions, so that there's no need to lock when no one is subscribed
+    active: Arc<AtomicUsize>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether any client is subscribed to a channel or a pattern
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed) > 0
+    }
+
+    /// Subscription of a single connection, it starts with no channels.
+    /// Messages are accounted in the output buffer of the connection until they are received.
+    pub fn subscription(&self, output: OutputBuffer) -> Subscription {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        Subscription {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            pubsub: self.clone(),
+            subscriber: Subscriber { sender, output },
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+        }
+    }
+
+    /// Sends the message to the subscribers of the channel and of the patterns that match it,
+    /// returns the number of clients that received it
+    pub fn publish(&self, channel: &[u8], payload: &[u8]) -> usize {
+        if !self.is_active() {
+            return 0;
+        }
+
+        let channel = Bytes::copy_from_slice(channel);
+        let payload = Bytes::copy_from_slice(payload);
+        let registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let mut received = 0;
+
+        for subscriber in registry
+            .channels
+            .get(&channel)
+            .into_iter()
+            .flat_map(|s| s.values())
+        {
+            let message = Message {
+                pattern: None,
+                channel: channel.clone(),
+                payload: payload.clone(),
+            };
+            received += usize::from(subscriber.send(message));
+        }
+
+        for (pattern, subscribers) in &registry.patterns {
+            if !glob_match(pattern, &channel) {
+                continue;
+            }
+
+            for subscriber in subscribers.values() {
+                let message = Message {
+                    pattern: Some(pattern.clone()),
+                    channel: channel.clone(),
+                    payload: payload.clone(),
+                };
+                received += usize::from(subscriber.send(message));
+            }
+        }
+
+        received
+    }
+
+    fn add(&self, pattern: bool, name: &Bytes, id: u64, subscriber: &Subscriber) {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let subscribers = match pattern {
+            true => &mut registry.patterns,
+            false => &mut registry.channels,
+        };
+
+        subscribers
+            .entry(name.clone())
+            .or_default()
+            .insert(id, subscriber.clone());
+        self.active.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn remove(&self, pattern: bool, name: &[u8], id: u64) {
+        let mut registry = self.registry.lock().unwrap_or_else(|e| e.into_inner());
+        let subscribers = match pattern {
+            true => &mut registry.patterns,
+            false => &mut registry.channels,
+        };
+
+        let Some(channel) = subscribers.get_mut(name) else {
+            return;
+        };
+
+        if channel.remove(&id).is_some() {
+            self.active.fetch_sub(1, Ordering::Relaxed);
+        }
+        if channel.is_empty() {
+            subscribers.remove(name);
+        }
+    }
+}
+
+/// Channels and patterns a connection is subscribed to, together with the messages
+/// that were published to them. Everything is unsubscribed once it is dropped.
+pub struct Subscription {
+    id: u64,
+    pubsub: PubSub,
+    subscriber: Subscriber,
+    receiver: mpsc::UnboundedReceiver<Message>,
+    channels: BTreeSet<Bytes>,
+    patterns: BTreeSet<Bytes>,
+}
+
+impl Subscription {
+    /// Number of channels and patterns the connection is subscribed to
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub fn channels(&self) -> Vec<Bytes> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub fn patterns(&self) -> Vec<Bytes> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    pub fn subscribe(&mut self, channel: Bytes) {
+        if !self.channels.contains(&channel) {
+            self.pubsub.add(false, &channel, self.id, &self.subscriber);
+            self.channels.insert(channel);
+        }
+    }
+
+    pub fn psubscribe(&mut self, pattern: Bytes) {
+        if !self.patterns.contains(&pattern) {
+            self.pubsub.add(true, &pattern, self.id, &self.subscriber);
+            self.patterns.insert(pattern);
+        }
+    }
+
+    pub fn unsubscribe(&mut self, channel: &[u8]) {
+        if self.channels.remove(channel) {
+            self.pubsub.remove(false, channel, self.id);
+        }
+    }
+
+    pub fn punsubscribe(&mut self, pattern: &[u8]) {
+        if self.patterns.remove(pattern) {
+            self.pubsub.remove(true, pattern, self.id);
+        }
+    }
+
+    /// Next message published to one of the channels or patterns
+    pub async fn recv(&mut self) -> Option<Message> {
+        let message = self.receiver.recv().await?;
+        self.subscriber.output.dequeue(message.len());
+
+        Some(message)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        for channel in std::mem::take(&mut self.channels) {
+            self.pubsub.remove(false, &channel, self.id);
+        }
+        for pattern in std::mem::take(&mut self.patterns) {
+            self.pubsub.remove(true, &pattern, self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod pubsub_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn publishes_to_channels_and_patterns() {
+        let pubsub = PubSub::new();
+        let mut first = pubsub.subscription(OutputBuffer::default());
+        let mut second = pubsub.subscription(OutputBuffer::default());
+
+        first.subscribe("news".into());
+        second.psubscribe("n*".into());
+        second.psubscribe("other".into());
+        assert_eq!(second.count(), 2);
+
+        assert_eq!(pubsub.publish(b"news", b"hello"), 2);
+        assert_eq!(
+            first.recv().await,
+            Some(Message {
+                pattern: None,
+                channel: "news".into(),
+                payload: "hello".into(),
+            })
+        );
+        assert_eq!(
+            second.recv().await.and_then(|message| message.pattern),
+            Some("n*".into())
+        );
+
+        first.unsubscribe(b"news");
+        assert_eq!(pubsub.publish(b"news", b"hello"), 1);
+
+        drop(second);
+        assert!(!pubsub.is_active());
+        assert_eq!(pubsub.publish(b"news", b"hello"), 0);
+    }
+}
@@ -1,23 +1,37 @@
-use crate::{Config, DataStore, GenericResult, Listener, NodeListener, VIVS_CONFIG_LAZY};
+use crate::{
+    cluster::{notify_peers_leaving, CLUSTER_BUS_PORT_OFFSET},
+    config::{self, RuntimeConfig, DEFAULT_CONFIG_PATH},
+    Config, DataStore, GenericResult, Listener, MetricsListener, NodeListener, Shutdown,
+};
 use clap::Parser;
 use log::{error, info};
+use std::path::PathBuf;
 use tokio::net::TcpListener;
 
 #[derive(Parser)]
 struct Cli {
     #[arg(long, short)]
     port: Option<u16>,
+    /// Path of the config file, config/config.toml in the current directory by default
+    #[arg(long, short)]
+    config: Option<PathBuf>,
 }
 
 pub async fn start() -> GenericResult<()> {
-    let vivs_config = &*VIVS_CONFIG_LAZY;
-    let vivs_config = vivs_config.as_ref();
+    let args = Cli::parse();
+
+    // A config file that can't be read or parsed stops the server from starting
+    let (config, config_path) = config::load(args.config.as_deref())?;
+    if config_path.is_none() {
+        info!("No config file at {DEFAULT_CONFIG_PATH}, using defaults");
+    }
+
     let Config {
         connection,
         cluster,
-    } = &vivs_config.unwrap();
+        ..
+    } = &config;
 
-    let args = Cli::parse();
     let port = args.port.unwrap_or(connection.port);
     let address = &connection.address;
     let cluster = cluster.as_ref();
@@ -33,7 +47,41 @@ pub async fn start() -> GenericResult<()> {
             error!("Failed to bind: {err}");
             err
         })?;
-    let listener = Listener::new(tcp_listener, DataStore::new());
+    let listener = listener(tcp_listener, &config, config_path)?;
+
+    // SIGINT / SIGTERM stop the server the same way the SHUTDOWN command does
+    let shutdown = listener.shutdown.clone();
+    tokio::spawn(async move {
+        wait_for_signal().await;
+        info!("Received shutdown signal");
+        shutdown.trigger();
+    });
+
+    // SIGHUP re-reads the config file
+    tokio::spawn(reload_on_hangup(
+        listener.config.clone(),
+        listener.shutdown.clone(),
+    ));
+
+    // Metrics are served in the background, alongside client connections
+    if let Some(metrics_port) = config.metrics_port() {
+        info!("Attempting to bind on port {metrics_port}");
+
+        let metrics_tcp_listener = TcpListener::bind(format!("{address}:{metrics_port}"))
+            .await
+            .map_err(|err| {
+                error!("Failed to bind: {err}");
+                err
+            })?;
+        let metrics_listener =
+            MetricsListener::new(metrics_tcp_listener, &listener, config.cluster_enabled())?;
+
+        tokio::spawn(async move {
+            if let Err(e) = metrics_listener.run().await {
+                error!("Metrics listener stopped: {e}");
+            }
+        });
+    }
 
     // Cluster mode enabled
     if let Some(cluster) = cluster.as_ref() {
@@ -41,7 +89,7 @@ pub async fn start() -> GenericResult<()> {
             let cluster_port = if let Some(cluster_port) = cluster.port {
                 cluster_port
             } else {
-                port + 5000
+                port + CLUSTER_BUS_PORT_OFFSET
             };
 
             info!("Attempting to bind on port {cluster_port}");
@@ -53,16 +101,123 @@ pub async fn start() -> GenericResult<()> {
                     error!("Failed to bind: {err}");
                     err
                 })?;
-            let node_listener = NodeListener::new(node_tcp_listener);
+            let node_listener = NodeListener::new(
+                node_tcp_listener,
+                listener.shutdown.clone(),
+                listener.topology.clone(),
+                port,
+            );
+            let topology = listener.topology.clone();
 
+            // Enables to wait on concurrent branches, returning when all branches complete
             let _ = tokio::join!(listener.run(), node_listener.run());
 
-            return Ok(());
+            if let Some(node_timeout) = config.node_timeout() {
+                notify_peers_leaving(&topology, node_timeout).await;
+            }
+
+            return exit();
         }
     }
 
-    // Enables to wait on concurrent branches, returning when all branches complete
     let _ = listener.run().await;
 
+    exit()
+}
+
+/// Creates the listener for client connections, with the data store and the parameters the config asks for
+pub fn listener(
+    tcp_listener: TcpListener,
+    config: &Config,
+    config_path: Option<PathBuf>,
+) -> GenericResult<Listener> {
+    let db = if config.cluster_enabled() {
+        DataStore::cluster()
+    } else {
+        DataStore::with_databases(config.databases())
+    };
+    let mut listener = Listener::new(tcp_listener, db);
+
+    // Limits, slow log and notifications, the same ones CONFIG SET changes
+    listener.config.set_path(config_path);
+    listener
+        .config
+        .apply(config)
+        .map_err(|err| format!("Invalid config: {err}"))?;
+
+    if let Some(shutdown_timeout) = config.shutdown_timeout() {
+        listener.shutdown_timeout = shutdown_timeout;
+    }
+
+    Ok(listener)
+}
+
+fn exit() -> GenericResult<()> {
+    // Data only lives in memory at the moment so there is nothing to save on the way out
+    info!("No persistence configured, nothing to flush");
+    info!("Vivs is now ready to exit, bye bye...");
+
     Ok(())
 }
+
+/// Resolves once the process receives SIGINT (Ctrl+C) or SIGTERM.
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = match signal(SignalKind::terminate()) {
+            Ok(sigterm) => sigterm,
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {e}");
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = sigterm.recv() => {},
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Reloads the runtime tunable parameters from the config file every time the process receives SIGHUP,
+/// the old values are kept when the file can't be read or parsed.
+async fn reload_on_hangup(config: RuntimeConfig, shutdown: Shutdown) {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sighup = match signal(SignalKind::hangup()) {
+            Ok(sighup) => sighup,
+            Err(e) => {
+                error!("Failed to listen for SIGHUP: {e}");
+                return;
+            }
+        };
+
+        loop {
+            tokio::select! {
+                _ = sighup.recv() => {},
+                _ = shutdown.wait() => return,
+            }
+
+            info!("Received SIGHUP, reloading config");
+            match config.reload() {
+                Ok(()) => info!("Config reloaded"),
+                Err(e) => error!("Failed to reload config: {e}"),
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (config, shutdown);
+    }
+}
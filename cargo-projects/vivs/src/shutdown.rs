@@ -0,0 +1,45 @@
+use std::{sync::Arc, time::Duration};
+use tokio::sync::watch;
+
+// How long in-flight commands are given to finish once shutdown is triggered
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Signals the server to stop accepting connections and to shut down.
+///
+/// Shutdown can be triggered by SIGINT / SIGTERM, the SHUTDOWN command
+/// or directly (i.e. from tests). Clones share one channel, so the listener
+/// and every connection are told to stop at once.
+#[derive(Clone)]
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(false);
+        Self {
+            sender: Arc::new(sender),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.sender.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.sender.borrow()
+    }
+
+    /// Waits until shutdown is triggered, returns straight away if it already has been.
+    pub async fn wait(&self) {
+        let mut receiver = self.sender.subscribe();
+        // Only fails if the sender is dropped, which cannot happen while self is alive
+        let _ = receiver.wait_for(|triggered| *triggered).await;
+    }
+}
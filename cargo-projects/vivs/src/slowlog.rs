@@ -0,0 +1,208 @@
+use bytes::Bytes;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::RwLock;
+
+// Commands that take longer than this (in microseconds) get logged
+pub const DEFAULT_LOG_SLOWER_THAN: i64 = 10000;
+// Maximum number of entries kept, the oldest entries get dropped first
+pub const DEFAULT_MAX_LEN: usize = 128;
+
+// Same as Redis, arguments are trimmed so that a single entry cannot take up too much memory
+const MAX_ARGS: usize = 32;
+const MAX_ARG_LEN: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct SlowLogEntry {
+    pub id: u64,
+    // Unix time (in seconds) of when the command was processed
+    pub timestamp: u64,
+    pub duration: Duration,
+    pub args: Vec<Bytes>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// Bounded log of commands that took longer than the configured threshold to run.
+///
+/// Connections add their slow commands through their own clones, SLOWLOG RESET clears the log for all of them.
+#[derive(Clone)]
+pub struct SlowLog {
+    entries: Arc<RwLock<VecDeque<SlowLogEntry>>>,
+    next_id: Arc<AtomicU64>,
+    // A negative value disables the log, 0 logs every command
+    log_slower_than: Arc<AtomicI64>,
+    max_len: Arc<AtomicUsize>,
+}
+
+impl Default for SlowLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SlowLog {
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            next_id: Arc::new(AtomicU64::new(0)),
+            log_slower_than: Arc::new(AtomicI64::new(DEFAULT_LOG_SLOWER_THAN)),
+            max_len: Arc::new(AtomicUsize::new(DEFAULT_MAX_LEN)),
+        }
+    }
+
+    pub fn log_slower_than(&self) -> i64 {
+        self.log_slower_than.load(Ordering::Relaxed)
+    }
+
+    pub fn set_log_slower_than(&self, microseconds: i64) {
+        self.log_slower_than.store(microseconds, Ordering::Relaxed);
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len.load(Ordering::Relaxed)
+    }
+
+    pub fn set_max_len(&self, max_len: usize) {
+        self.max_len.store(max_len, Ordering::Relaxed);
+    }
+
+    /// Whether a command that took `duration` to run should be logged
+    pub fn is_slow(&self, duration: Duration) -> bool {
+        let log_slower_than = self.log_slower_than();
+        log_slower_than >= 0 && duration.as_micros() as i64 >= log_slower_than
+    }
+
+    /// Adds the command to the log if it took longer than the threshold.
+    pub async fn record(
+        &self,
+        args: &[Bytes],
+        duration: Duration,
+        client_addr: &str,
+        client_name: Option<String>,
+    ) {
+        if !self.is_slow(duration) {
+            return;
+        }
+
+        let mut trimmed_args: Vec<Bytes> = args
+            .iter()
+            .take(MAX_ARGS)
+            .map(|arg| {
+                if arg.len() > MAX_ARG_LEN {
+                    let more = format!("... ({} more bytes)", arg.len() - MAX_ARG_LEN);
+                    Bytes::from([&arg[..MAX_ARG_LEN], more.as_bytes()].concat())
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+
+        if args.len() > MAX_ARGS {
+            let more = format!("... ({} more arguments)", args.len() - MAX_ARGS + 1);
+            trimmed_args[MAX_ARGS - 1] = Bytes::from(more);
+        }
+
+        let entry = SlowLogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+            duration,
+            args: trimmed_args,
+            client_addr: client_addr.to_owned(),
+            client_name: client_name.unwrap_or_default(),
+        };
+
+        let max_len = self.max_len();
+        let mut entries = self.entries.write().await;
+
+        entries.push_front(entry);
+        entries.truncate(max_len);
+    }
+
+    /// Returns the latest `count` entries, newest first (all of them when `count` is None)
+    pub async fn get(&self, count: Option<usize>) -> Vec<SlowLogEntry> {
+        let entries = self.entries.read().await;
+        let count = count.unwrap_or(entries.len());
+
+        entries.iter().take(count).cloned().collect()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.entries.read().await.is_empty()
+    }
+
+    pub async fn reset(&self) {
+        self.entries.write().await.clear();
+    }
+}
+
+#[cfg(test)]
+mod slowlog_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_only_slow_commands() {
+        let slowlog = SlowLog::new();
+        slowlog.set_log_slower_than(1000);
+
+        let args = [Bytes::from("GET"), Bytes::from("key")];
+        slowlog
+            .record(&args, Duration::from_micros(999), "127.0.0.1:1", None)
+            .await;
+        slowlog
+            .record(&args, Duration::from_micros(1000), "127.0.0.1:1", None)
+            .await;
+
+        let entries = slowlog.get(None).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].args, args);
+        assert_eq!(entries[0].duration, Duration::from_micros(1000));
+    }
+
+    #[tokio::test]
+    async fn drops_oldest_entries() {
+        let slowlog = SlowLog::new();
+        slowlog.set_log_slower_than(0);
+        slowlog.set_max_len(2);
+
+        for command in ["first", "second", "third"] {
+            slowlog
+                .record(&[Bytes::from(command)], Duration::ZERO, "", None)
+                .await;
+        }
+
+        let entries = slowlog.get(None).await;
+        assert_eq!(entries.len(), 2);
+        // Newest entries come first
+        assert_eq!(entries[0].args, [Bytes::from("third")]);
+        assert_eq!(entries[1].args, [Bytes::from("second")]);
+        assert_eq!(entries[1].id, 1);
+    }
+
+    #[tokio::test]
+    async fn trims_long_arguments() {
+        let slowlog = SlowLog::new();
+        slowlog.set_log_slower_than(0);
+
+        let value = Bytes::from(vec![b'a'; MAX_ARG_LEN + 10]);
+        slowlog
+            .record(&[Bytes::from("SET"), value], Duration::ZERO, "", None)
+            .await;
+
+        let entries = slowlog.get(Some(1)).await;
+        assert!(entries[0].args[1].ends_with(b"... (10 more bytes)"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{db::Value, error::CommandError};
+use bytes::Bytes;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeSet, HashMap},
+    ops::Bound,
+};
+
+/// Score of a member, floats are ordered with `total_cmp` so they can be kept in a BTreeSet
+#[derive(Debug, Clone, Copy)]
+pub struct Score(pub f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Members ordered by score (members with the same score are ordered by their bytes, same as in Redis).
+/// Scores of members are looked up in the map, ranges of scores are read from the ordered set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: BTreeSet<(Score, Bytes)>,
+}
+
+impl SortedSet {
+    /// Number of members
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    /// Size of the members and their scores (in bytes)
+    pub fn size(&self) -> usize {
+        self.scores
+            .keys()
+            .map(|member| member.len() + std::mem::size_of::<f64>())
+            .sum()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Adds the member or changes its score, returns the score it had before
+    pub fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        let old = self.scores.insert(member.clone(), score);
+        if let Some(old) = old {
+            self.ordered.remove(&(Score(old), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+
+        old
+    }
+
+    /// Members with scores from `min` (included) to `max` (excluded), in score order
+    pub fn range(&self, min: f64, max: f64) -> impl Iterator<Item = (&Bytes, f64)> + '_ {
+        let start = Bound::Included((Score(min), Bytes::new()));
+        let end = Bound::Excluded((Score(max), Bytes::new()));
+
+        // Empty ranges make BTreeSet::range() panic
+        let range = (min < max).then(|| self.ordered.range((start, end)));
+        range
+            .into_iter()
+            .flatten()
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// Members and their scores, in score order
+    pub fn iter(&self) -> impl Iterator<Item = (&Bytes, f64)> + '_ {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+}
+
+/// The sorted set, None if the key does not exist
+pub fn get<'a>(
+    keys: &'a HashMap<Bytes, Value>,
+    key: &[u8],
+) -> Result<Option<&'a SortedSet>, CommandError> {
+    match keys.get(key) {
+        Some(Value::SortedSet(set)) => Ok(Some(set)),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// The sorted set, an empty one is created if the key does not exist
+pub fn get_or_create<'a>(
+    keys: &'a mut HashMap<Bytes, Value>,
+    key: &Bytes,
+) -> Result<&'a mut SortedSet, CommandError> {
+    let value = keys
+        .entry(key.clone())
+        .or_insert_with(|| Value::SortedSet(Box::default()));
+
+    match value {
+        Value::SortedSet(set) => Ok(set),
+        _ => Err(CommandError::WrongType),
+    }
+}
+
+#[cfg(test)]
+mod sorted_set_tests {
+    use super::*;
+
+    #[test]
+    fn keeps_members_ordered_by_score() {
+        let mut set = SortedSet::default();
+        assert_eq!(set.insert("b".into(), 2.0), None);
+        assert_eq!(set.insert("a".into(), 2.0), None);
+        assert_eq!(set.insert("c".into(), 1.0), None);
+        assert_eq!(set.insert("c".into(), 3.0), Some(1.0));
+
+        let members = set
+            .iter()
+            .map(|(member, _)| member.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(members, ["a", "b", "c"]);
+
+        let members = set
+            .range(2.0, 3.0)
+            .map(|(member, _)| member.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(members, ["a", "b"]);
+        assert_eq!(set.range(3.0, 3.0).count(), 0);
+
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.score(b"c"), Some(3.0));
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::{sync::RwLock, time::Instant};
+
+// How often the number of processed commands is sampled to work out ops/sec
+pub const OPS_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+// Number of samples that ops/sec gets averaged over
+const OPS_SAMPLES: usize = 16;
+
+// Upper bounds (in seconds) of the command latency histogram buckets
+pub const LATENCY_BUCKETS: [f64; 10] = [
+    0.00001, 0.00005, 0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5,
+];
+
+/// Calls and total time spent in a single command
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CommandStat {
+    pub calls: u64,
+    pub usec: u64,
+    // Number of calls that took at most the respective LATENCY_BUCKETS value,
+    // calls that took longer than the last bucket are only counted in `calls`
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+impl CommandStat {
+    /// Records a single call
+    pub fn record(&mut self, duration: Duration) {
+        self.calls += 1;
+        self.usec += duration.as_micros() as u64;
+
+        let seconds = duration.as_secs_f64();
+        for (bucket, upper_bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= upper_bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    pub fn usec_per_call(&self) -> f64 {
+        if self.calls == 0 {
+            return 0.0;
+        }
+        self.usec as f64 / self.calls as f64
+    }
+}
+
+#[derive(Debug)]
+struct OpsSamples {
+    last_sampled_at: Instant,
+    last_total: u64,
+    // Operations per second, one entry per sample
+    samples: VecDeque<f64>,
+}
+
+impl Default for OpsSamples {
+    fn default() -> Self {
+        Self {
+            last_sampled_at: Instant::now(),
+            last_total: 0,
+            samples: VecDeque::with_capacity(OPS_SAMPLES),
+        }
+    }
+}
+
+/// Server-wide statistics, collected by the `Listener` and while running commands.
+///
+/// The counters are shared atomics, connections bump them through their own clones without locking.
+#[derive(Clone)]
+pub struct Stats {
+    pub started_at: Instant,
+    total_commands: Arc<AtomicU64>,
+    total_connections: Arc<AtomicU64>,
+    rejected_connections: Arc<AtomicU64>,
+    commands: Arc<RwLock<HashMap<String, CommandStat>>>,
+    ops: Arc<RwLock<OpsSamples>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            total_commands: Arc::new(AtomicU64::new(0)),
+            total_connections: Arc::new(AtomicU64::new(0)),
+            rejected_connections: Arc::new(AtomicU64::new(0)),
+            commands: Arc::new(RwLock::new(HashMap::new())),
+            ops: Arc::new(RwLock::new(OpsSamples::default())),
+        }
+    }
+
+    pub fn connection_received(&self) {
+        self.total_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn total_connections(&self) -> u64 {
+        self.total_connections.load(Ordering::Relaxed)
+    }
+
+    /// Records a connection that was closed straight away because of the maxclients limit
+    pub fn connection_rejected(&self) {
+        self.rejected_connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn rejected_connections(&self) -> u64 {
+        self.rejected_connections.load(Ordering::Relaxed)
+    }
+
+    pub fn total_commands(&self) -> u64 {
+        self.total_commands.load(Ordering::Relaxed)
+    }
+
+    /// Records a single call of a command and how long it took to run.
+    pub async fn command_processed(&self, command: &str, duration: Duration) {
+        self.total_commands.fetch_add(1, Ordering::Relaxed);
+
+        let mut commands = self.commands.write().await;
+        commands
+            .entry(command.to_owned())
+            .or_default()
+            .record(duration);
+    }
+
+    /// Per command stats sorted by command name
+    pub async fn commands(&self) -> Vec<(String, CommandStat)> {
+        let mut commands: Vec<(String, CommandStat)> = self
+            .commands
+            .read()
+            .await
+            .iter()
+            .map(|(name, stat)| (name.clone(), *stat))
+            .collect();
+        commands.sort_by(|a, b| a.0.cmp(&b.0));
+        commands
+    }
+
+    /// Takes a sample of the number of commands processed since the previous sample.
+    /// This is meant to be called every `OPS_SAMPLE_INTERVAL`.
+    pub async fn sample_ops(&self) {
+        let total = self.total_commands();
+        let mut ops = self.ops.write().await;
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(ops.last_sampled_at).as_secs_f64();
+
+        if elapsed > 0.0 {
+            let ops_per_sec = (total - ops.last_total) as f64 / elapsed;
+
+            if ops.samples.len() == OPS_SAMPLES {
+                ops.samples.pop_front();
+            }
+            ops.samples.push_back(ops_per_sec);
+        }
+
+        ops.last_sampled_at = now;
+        ops.last_total = total;
+    }
+
+    /// Average of the latest ops/sec samples
+    pub async fn ops_per_sec(&self) -> u64 {
+        let ops = self.ops.read().await;
+
+        if ops.samples.is_empty() {
+            return 0;
+        }
+
+        (ops.samples.iter().sum::<f64>() / ops.samples.len() as f64).round() as u64
+    }
+
+    pub fn uptime(&self) -> Duration {
+        Instant::now().duration_since(self.started_at)
+    }
+}
+
+/// Counters that are updated when keys are being accessed.
+#[derive(Debug, Default)]
+pub struct KeyspaceStats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    expired: AtomicU64,
+    evicted: AtomicU64,
+}
+
+impl KeyspaceStats {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn expired(&self) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn evicted(&self) {
+        self.evicted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    pub fn expired_keys(&self) -> u64 {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+}
+
+/// Reads resident set size of the process (Linux only), 0 otherwise
+pub fn rss_bytes() -> u64 {
+    let Ok(statm) = std::fs::read_to_string("/proc/self/statm") else {
+        return 0;
+    };
+
+    // The second value is the number of resident pages, assuming 4kb pages
+    statm
+        .split_whitespace()
+        .nth(1)
+        .and_then(|pages| pages.parse::<u64>().ok())
+        .map(|pages| pages * 4096)
+        .unwrap_or(0)
+}
+
+/// Formats bytes in a human readable way i.e. 1.50K, 2.00M
+pub fn bytes_human(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = "";
+    for next_unit in UNITS {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{value:.2}{unit}")
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+
+    #[test]
+    fn bytes_human_works() {
+        assert_eq!(bytes_human(512), "512B");
+        assert_eq!(bytes_human(1536), "1.50K");
+        assert_eq!(bytes_human(3 * 1024 * 1024), "3.00M");
+    }
+
+    #[test]
+    fn usec_per_call_works() {
+        let mut stat = CommandStat::default();
+        assert_eq!(stat.usec_per_call(), 0.0);
+
+        stat.record(Duration::from_micros(2));
+        stat.record(Duration::from_micros(3));
+        assert_eq!(stat.usec_per_call(), 2.5);
+    }
+
+    #[test]
+    fn record_fills_cumulative_buckets() {
+        let mut stat = CommandStat::default();
+
+        stat.record(Duration::from_micros(5));
+        stat.record(Duration::from_millis(2));
+        stat.record(Duration::from_secs(1));
+
+        assert_eq!(stat.calls, 3);
+        // <= 10µs
+        assert_eq!(stat.buckets[0], 1);
+        // <= 5ms
+        assert_eq!(stat.buckets[5], 2);
+        // <= 500ms, a call that took a second does not fall into any bucket
+        assert_eq!(stat.buckets[9], 2);
+    }
+}
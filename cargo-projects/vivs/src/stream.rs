@@ -0,0 +1,726 @@
+use crate::{db::Value, error::CommandError};
+use bytes::Bytes;
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// Entries per block, range scans find the first block with a binary search and skip the rest
+const BLOCK_SIZE: usize = 128;
+
+const INVALID_ID_ERR: &str = "Invalid stream ID specified as stream command argument";
+const ZERO_ID_ERR: &str = "The ID specified in XADD must be greater than 0-0";
+const SMALLER_ID_ERR: &str =
+    "The ID specified in XADD is equal or smaller than the target stream top item";
+
+/// Field value pairs of an entry
+pub type Fields = Vec<(Bytes, Bytes)>;
+
+/// Milliseconds since the unix epoch, auto-generated IDs and consumer idle times use it
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn invalid_id() -> CommandError {
+    CommandError::Other(INVALID_ID_ERR.to_owned())
+}
+
+/// ID of an entry, i.e. 1526919030474-0 (milliseconds and sequence number)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    /// Parses ms-seq, a missing sequence number is `missing_seq`
+    /// (same as in Redis 0 for the start of a range and the largest one for the end)
+    pub fn parse(value: &str, missing_seq: u64) -> Result<StreamId, CommandError> {
+        let (ms, seq) = match value.split_once('-') {
+            Some((ms, seq)) => (ms, Some(seq)),
+            None => (value, None),
+        };
+
+        let ms = ms.parse::<u64>().map_err(|_| invalid_id())?;
+        let seq = match seq {
+            Some(seq) => seq.parse::<u64>().map_err(|_| invalid_id())?,
+            None => missing_seq,
+        };
+
+        Ok(StreamId { ms, seq })
+    }
+
+    /// Start of a range, `-` is the smallest ID and `(` excludes the ID
+    pub fn parse_start(value: &str) -> Result<StreamId, CommandError> {
+        match value {
+            "-" => Ok(StreamId::MIN),
+            "+" => Ok(StreamId::MAX),
+            _ => match value.strip_prefix('(') {
+                Some(id) => StreamId::parse(id, 0)?.next().ok_or_else(invalid_id),
+                None => StreamId::parse(value, 0),
+            },
+        }
+    }
+
+    /// End of a range, `+` is the largest ID and `(` excludes the ID
+    pub fn parse_end(value: &str) -> Result<StreamId, CommandError> {
+        match value {
+            "-" => Ok(StreamId::MIN),
+            "+" => Ok(StreamId::MAX),
+            _ => match value.strip_prefix('(') {
+                Some(id) => StreamId::parse(id, u64::MAX)?.prev().ok_or_else(invalid_id),
+                None => StreamId::parse(value, u64::MAX),
+            },
+        }
+    }
+
+    /// The smallest ID that is larger than this one
+    pub fn next(self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => self.ms.checked_add(1).map(|ms| StreamId { ms, seq: 0 }),
+        }
+    }
+
+    /// The largest ID that is smaller than this one
+    pub fn prev(self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId { ms: self.ms, seq }),
+            None => self
+                .ms
+                .checked_sub(1)
+                .map(|ms| StreamId { ms, seq: u64::MAX }),
+        }
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// ID argument of XADD
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NewId {
+    /// `*`, both parts are generated
+    Auto,
+    /// `ms-*`, only the sequence number is generated
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+impl NewId {
+    pub fn parse(value: &str) -> Result<NewId, CommandError> {
+        if value == "*" {
+            return Ok(NewId::Auto);
+        }
+
+        match value.strip_suffix("-*") {
+            Some(ms) => ms
+                .parse::<u64>()
+                .map(NewId::AutoSeq)
+                .map_err(|_| invalid_id()),
+            None => StreamId::parse(value, 0).map(NewId::Explicit),
+        }
+    }
+}
+
+/// How XADD / XTRIM trim the stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trim {
+    /// Keeps at most that many entries
+    MaxLen(usize),
+    /// Removes entries with smaller IDs
+    MinId(StreamId),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: StreamId,
+    pub fields: Fields,
+}
+
+/// An entry that was delivered to a consumer but was not acknowledged yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEntry {
+    pub consumer: Bytes,
+    // Unix time in milliseconds of the last delivery
+    pub delivered_at: u64,
+    pub deliveries: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Consumer {
+    // Unix time in milliseconds when the consumer read or claimed entries the last time
+    pub seen_at: u64,
+}
+
+/// Consumers of a group share the entries of the stream, each entry is delivered to one of them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConsumerGroup {
+    pub last_delivered: StreamId,
+    // Entries that were delivered but not acknowledged yet (PEL in Redis)
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<Bytes, Consumer>,
+}
+
+impl ConsumerGroup {
+    /// The consumer is created the first time it is used
+    fn seen(&mut self, consumer: &Bytes, now: u64) {
+        self.consumers.entry(consumer.clone()).or_default().seen_at = now;
+    }
+
+    /// Number of entries pending for the consumer
+    pub fn pending_for(&self, consumer: &[u8]) -> usize {
+        self.pending
+            .values()
+            .filter(|pending| pending.consumer == consumer)
+            .count()
+    }
+
+    /// Acknowledges the entries, returns how many of them were pending
+    pub fn ack(&mut self, ids: &[StreamId]) -> usize {
+        ids.iter()
+            .filter(|id| self.pending.remove(id).is_some())
+            .count()
+    }
+
+    fn deliver(&mut self, id: StreamId, consumer: &Bytes, now: u64) {
+        let pending = self.pending.entry(id).or_insert_with(|| PendingEntry {
+            consumer: consumer.clone(),
+            delivered_at: now,
+            deliveries: 0,
+        });
+        pending.consumer = consumer.clone();
+        pending.delivered_at = now;
+        pending.deliveries += 1;
+    }
+}
+
+/// An append-only log of entries (XADD etc).
+///
+/// Entries are kept in ID order in a sequence of blocks, so appending and trimming
+/// only touch the ends and range scans start with a binary search over the blocks.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stream {
+    blocks: VecDeque<Vec<Entry>>,
+    len: usize,
+    pub last_id: StreamId,
+    // The largest ID that was trimmed
+    pub max_deleted_id: StreamId,
+    // Entries ever added, trimmed ones included
+    pub entries_added: u64,
+    pub groups: BTreeMap<Bytes, ConsumerGroup>,
+}
+
+impl Stream {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of blocks the entries are stored in
+    pub fn blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Size of the fields and values (in bytes) without any overheads
+    pub fn size(&self) -> usize {
+        self.blocks
+            .iter()
+            .flatten()
+            .flat_map(|entry| &entry.fields)
+            .map(|(field, value)| field.len() + value.len())
+            .sum()
+    }
+
+    pub fn first(&self) -> Option<&Entry> {
+        self.blocks.front().and_then(|block| block.first())
+    }
+
+    pub fn last(&self) -> Option<&Entry> {
+        self.blocks.back().and_then(|block| block.last())
+    }
+
+    /// ID of the next entry, auto-generated IDs are based on the unix time in milliseconds
+    pub fn next_id(&self, id: NewId, now: u64) -> Result<StreamId, CommandError> {
+        let last = self.last_id;
+
+        let id = match id {
+            NewId::Auto if now > last.ms => StreamId { ms: now, seq: 0 },
+            NewId::Auto => last
+                .next()
+                .ok_or_else(|| CommandError::Other(SMALLER_ID_ERR.to_owned()))?,
+            NewId::AutoSeq(ms) if ms > last.ms => StreamId { ms, seq: 0 },
+            NewId::AutoSeq(ms) if ms == last.ms => StreamId {
+                ms,
+                seq: last
+                    .seq
+                    .checked_add(1)
+                    .ok_or_else(|| CommandError::Other(SMALLER_ID_ERR.to_owned()))?,
+            },
+            NewId::AutoSeq(_) => return Err(CommandError::Other(SMALLER_ID_ERR.to_owned())),
+            NewId::Explicit(id) => id,
+        };
+
+        if id == StreamId::MIN {
+            return Err(CommandError::Other(ZERO_ID_ERR.to_owned()));
+        }
+
+        if id <= last {
+            return Err(CommandError::Other(SMALLER_ID_ERR.to_owned()));
+        }
+
+        Ok(id)
+    }
+
+    /// Appends the entry, the ID has to come from `next_id()`
+    pub fn add(&mut self, id: StreamId, fields: Fields) {
+        match self.blocks.back_mut() {
+            Some(block) if block.len() < BLOCK_SIZE => block.push(Entry { id, fields }),
+            _ => {
+                let mut block = Vec::with_capacity(BLOCK_SIZE);
+                block.push(Entry { id, fields });
+                self.blocks.push_back(block);
+            }
+        }
+
+        self.len += 1;
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    /// Removes the oldest entries, returns how many were removed.
+    /// Approximate trimming (`~`) only removes whole blocks, which is cheaper.
+    pub fn trim(&mut self, trim: Trim, approximate: bool) -> usize {
+        let mut removed = 0;
+
+        while let Some(block) = self.blocks.front_mut() {
+            let excess = match trim {
+                Trim::MaxLen(max_len) => self.len.saturating_sub(max_len),
+                Trim::MinId(min_id) => block.partition_point(|entry| entry.id < min_id),
+            };
+
+            if excess == 0 || (approximate && excess < block.len()) {
+                break;
+            }
+
+            let excess = excess.min(block.len());
+            if let Some(last) = block.drain(..excess).next_back() {
+                self.max_deleted_id = self.max_deleted_id.max(last.id);
+            }
+            if block.is_empty() {
+                self.blocks.pop_front();
+            }
+
+            self.len -= excess;
+            removed += excess;
+        }
+
+        removed
+    }
+
+    /// Entries with IDs between `start` and `end` (both included), in ID order
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+    ) -> impl DoubleEndedIterator<Item = &Entry> + '_ {
+        range(&self.blocks, start, end)
+    }
+
+    pub fn get(&self, id: StreamId) -> Option<&Entry> {
+        self.range(id, id).next()
+    }
+
+    /// Creates the consumer group, returns false if it already exists
+    pub fn create_group(&mut self, name: Bytes, last_delivered: StreamId) -> bool {
+        if self.groups.contains_key(&name) {
+            return false;
+        }
+
+        let group = ConsumerGroup {
+            last_delivered,
+            ..Default::default()
+        };
+        self.groups.insert(name, group);
+
+        true
+    }
+
+    /// Reads entries for the consumer of the group (XREADGROUP), None if there is no such group.
+    ///
+    /// Without `history` the entries that were never delivered to the group are read and become
+    /// pending (unless `noack`). With `history` the consumer's pending entries after that ID
+    /// are read again, the ones that were trimmed in the meantime come without fields.
+    pub fn read_group(
+        &mut self,
+        group: &[u8],
+        consumer: &Bytes,
+        history: Option<StreamId>,
+        count: usize,
+        noack: bool,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Option<Fields>)>> {
+        let Stream { blocks, groups, .. } = self;
+        let group = groups.get_mut(group)?;
+        group.seen(consumer, now);
+
+        let Some(after) = history else {
+            let Some(start) = group.last_delivered.next() else {
+                return Some(vec![]);
+            };
+
+            let entries = range(blocks, start, StreamId::MAX)
+                .take(count)
+                .map(|entry| (entry.id, Some(entry.fields.clone())))
+                .collect::<Vec<_>>();
+
+            for (id, _) in &entries {
+                group.last_delivered = *id;
+                if !noack {
+                    group.deliver(*id, consumer, now);
+                }
+            }
+
+            return Some(entries);
+        };
+
+        let entries = group
+            .pending
+            .range(after..)
+            .filter(|(id, pending)| **id > after && pending.consumer == consumer)
+            .take(count)
+            .map(|(id, _)| (*id, find(blocks, *id).map(|entry| entry.fields.clone())))
+            .collect();
+
+        Some(entries)
+    }
+
+    /// Gives the pending entries that were idle for at least `min_idle` milliseconds to
+    /// the consumer (XCLAIM), None if there is no such group.
+    ///
+    /// Entries that were trimmed in the meantime are no longer pending and are not claimed.
+    pub fn claim(
+        &mut self,
+        group: &[u8],
+        consumer: &Bytes,
+        min_idle: u64,
+        ids: &[StreamId],
+        now: u64,
+    ) -> Option<Vec<Entry>> {
+        let Stream { blocks, groups, .. } = self;
+        let group = groups.get_mut(group)?;
+        group.seen(consumer, now);
+
+        let mut claimed = vec![];
+        for id in ids {
+            let Some(pending) = group.pending.get(id) else {
+                continue;
+            };
+
+            if now.saturating_sub(pending.delivered_at) < min_idle {
+                continue;
+            }
+
+            match find(blocks, *id) {
+                Some(entry) => {
+                    group.deliver(*id, consumer, now);
+                    claimed.push(entry.clone());
+                }
+                None => {
+                    group.pending.remove(id);
+                }
+            }
+        }
+
+        Some(claimed)
+    }
+
+    /// Same as `claim()` for up to `count` pending entries starting from `start` (XAUTOCLAIM),
+    /// None if there is no such group.
+    ///
+    /// Returns the ID to continue from (0-0 once all pending entries were scanned),
+    /// the claimed entries and the IDs of the entries that were trimmed in the meantime.
+    pub fn auto_claim(
+        &mut self,
+        group: &[u8],
+        consumer: &Bytes,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        now: u64,
+    ) -> Option<(StreamId, Vec<Entry>, Vec<StreamId>)> {
+        let scanned = self
+            .groups
+            .get(group)?
+            .pending
+            .range(start..)
+            .take(count.saturating_add(1))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        let next = match scanned.get(count) {
+            Some(next) => *next,
+            None => StreamId::MIN,
+        };
+        let scanned = &scanned[..scanned.len().min(count)];
+
+        let deleted = scanned
+            .iter()
+            .filter(|id| find(&self.blocks, **id).is_none())
+            .copied()
+            .collect();
+        let claimed = self.claim(group, consumer, min_idle, scanned, now)?;
+
+        Some((next, claimed, deleted))
+    }
+}
+
+/// The stream, None if the key does not exist
+pub fn get<'a>(
+    keys: &'a HashMap<Bytes, Value>,
+    key: &[u8],
+) -> Result<Option<&'a Stream>, CommandError> {
+    match keys.get(key) {
+        Some(Value::Stream(stream)) => Ok(Some(stream)),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+pub fn get_mut<'a>(
+    keys: &'a mut HashMap<Bytes, Value>,
+    key: &[u8],
+) -> Result<Option<&'a mut Stream>, CommandError> {
+    match keys.get_mut(key) {
+        Some(Value::Stream(stream)) => Ok(Some(stream)),
+        Some(_) => Err(CommandError::WrongType),
+        None => Ok(None),
+    }
+}
+
+/// The stream that has the consumer group, NOGROUP if either of them does not exist
+pub fn with_group<'a>(
+    keys: &'a mut HashMap<Bytes, Value>,
+    key: &[u8],
+    group: &[u8],
+) -> Result<&'a mut Stream, CommandError> {
+    match get_mut(keys, key)? {
+        Some(stream) if stream.groups.contains_key(group) => Ok(stream),
+        _ => Err(no_group(key, group)),
+    }
+}
+
+pub fn no_group(key: &[u8], group: &[u8]) -> CommandError {
+    CommandError::NoGroup {
+        key: String::from_utf8_lossy(key).into_owned(),
+        group: String::from_utf8_lossy(group).into_owned(),
+    }
+}
+
+/// The stream, an empty one is created if the key does not exist
+pub fn get_or_create<'a>(
+    keys: &'a mut HashMap<Bytes, Value>,
+    key: &Bytes,
+) -> Result<&'a mut Stream, CommandError> {
+    let value = keys
+        .entry(key.clone())
+        .or_insert_with(|| Value::Stream(Box::default()));
+
+    match value {
+        Value::Stream(stream) => Ok(stream),
+        _ => Err(CommandError::WrongType),
+    }
+}
+
+/// Entries of the blocks with IDs between `start` and `end` (both included), in ID order
+fn range(
+    blocks: &VecDeque<Vec<Entry>>,
+    start: StreamId,
+    end: StreamId,
+) -> impl DoubleEndedIterator<Item = &Entry> + '_ {
+    // Blocks are sorted, so only the blocks that overlap the range are visited
+    let first = blocks.partition_point(|block| block.last().is_some_and(|entry| entry.id < start));
+    let last = blocks
+        .partition_point(|block| block.first().is_some_and(|entry| entry.id <= end))
+        .max(first);
+
+    blocks
+        .range(first..last)
+        .flatten()
+        .filter(move |entry| entry.id >= start && entry.id <= end)
+}
+
+/// Entry with the ID, if it was not trimmed
+fn find(blocks: &VecDeque<Vec<Entry>>, id: StreamId) -> Option<&Entry> {
+    let index = blocks.partition_point(|block| block.last().is_some_and(|entry| entry.id < id));
+    let block = blocks.get(index)?;
+    block
+        .binary_search_by_key(&id, |entry| entry.id)
+        .ok()
+        .map(|position| &block[position])
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+
+    fn stream_of(count: u64) -> Stream {
+        let mut stream = Stream::default();
+        for ms in 1..=count {
+            let id = stream.next_id(NewId::AutoSeq(ms), 0).unwrap();
+            stream.add(id, vec![("n".into(), ms.to_string().into())]);
+        }
+        stream
+    }
+
+    #[test]
+    fn parses_ids() {
+        assert_eq!(StreamId::parse_start("5"), Ok(StreamId { ms: 5, seq: 0 }));
+        assert_eq!(
+            StreamId::parse_end("5"),
+            Ok(StreamId {
+                ms: 5,
+                seq: u64::MAX
+            })
+        );
+        assert_eq!(
+            StreamId::parse_start("(5-1"),
+            Ok(StreamId { ms: 5, seq: 2 })
+        );
+        assert_eq!(
+            StreamId::parse_end("(6-0"),
+            Ok(StreamId {
+                ms: 5,
+                seq: u64::MAX
+            })
+        );
+        assert_eq!(NewId::parse("7-*"), Ok(NewId::AutoSeq(7)));
+        assert!(StreamId::parse("1-x", 0).is_err());
+    }
+
+    #[test]
+    fn ids_only_go_up() {
+        let mut stream = Stream::default();
+        assert_eq!(
+            stream.next_id(NewId::Explicit(StreamId::MIN), 0),
+            Err(CommandError::Other(ZERO_ID_ERR.to_owned()))
+        );
+
+        let id = stream.next_id(NewId::Auto, 100).unwrap();
+        assert_eq!(id, StreamId { ms: 100, seq: 0 });
+        stream.add(id, vec![]);
+
+        // The clock went backwards, the sequence number goes up instead
+        assert_eq!(
+            stream.next_id(NewId::Auto, 50),
+            Ok(StreamId { ms: 100, seq: 1 })
+        );
+        assert_eq!(
+            stream.next_id(NewId::AutoSeq(100), 0),
+            Ok(StreamId { ms: 100, seq: 1 })
+        );
+        assert!(stream.next_id(NewId::AutoSeq(99), 0).is_err());
+        assert!(stream
+            .next_id(NewId::Explicit(StreamId { ms: 100, seq: 0 }), 0)
+            .is_err());
+    }
+
+    #[test]
+    fn scans_and_trims_across_blocks() {
+        let mut stream = stream_of(300);
+        assert_eq!(stream.blocks(), 3);
+
+        let ids =
+            |entries: Vec<&Entry>| entries.iter().map(|entry| entry.id.ms).collect::<Vec<_>>();
+        let start = StreamId { ms: 127, seq: 0 };
+        let end = StreamId { ms: 130, seq: 0 };
+        assert_eq!(
+            ids(stream.range(start, end).collect()),
+            [127, 128, 129, 130]
+        );
+        assert_eq!(ids(stream.range(start, end).rev().take(1).collect()), [130]);
+        assert_eq!(stream.range(end, start).count(), 0);
+
+        // Approximate trimming keeps the partly trimmed block
+        assert_eq!(stream.trim(Trim::MaxLen(100), true), 128);
+        assert_eq!(stream.len(), 172);
+        assert_eq!(
+            stream.trim(Trim::MinId(StreamId { ms: 250, seq: 0 }), false),
+            121
+        );
+        assert_eq!(stream.first().unwrap().id.ms, 250);
+        assert_eq!(stream.max_deleted_id, StreamId { ms: 249, seq: 0 });
+        assert_eq!(stream.entries_added, 300);
+    }
+
+    #[test]
+    fn consumer_groups_track_pending_entries() {
+        let mut stream = stream_of(3);
+        let (alice, bob) = (Bytes::from("alice"), Bytes::from("bob"));
+        assert!(stream.create_group("group".into(), StreamId::MIN));
+        assert!(!stream.create_group("group".into(), StreamId::MIN));
+
+        let read = stream
+            .read_group(b"group", &alice, None, 2, false, 10)
+            .unwrap();
+        assert_eq!(read.len(), 2);
+        let read = stream
+            .read_group(b"group", &bob, None, 10, false, 10)
+            .unwrap();
+        assert_eq!(read[0].0.ms, 3);
+        assert!(stream
+            .read_group(b"nope", &bob, None, 10, false, 10)
+            .is_none());
+
+        // History reads only return the consumer's own pending entries
+        let history = stream
+            .read_group(b"group", &alice, Some(StreamId::MIN), 10, false, 10)
+            .unwrap();
+        assert_eq!(history.len(), 2);
+
+        let first = StreamId { ms: 1, seq: 0 };
+        assert!(stream
+            .claim(b"group", &bob, 100, &[first], 50)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            stream
+                .claim(b"group", &bob, 100, &[first], 110)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            stream.groups[&Bytes::from("group")].pending[&first].deliveries,
+            2
+        );
+
+        // Trimmed entries are dropped from the pending entries when claimed
+        stream.trim(Trim::MaxLen(1), false);
+        let (next, claimed, deleted) = stream
+            .auto_claim(b"group", &alice, 0, StreamId::MIN, 10, 120)
+            .unwrap();
+        assert_eq!((next, claimed.len(), deleted.len()), (StreamId::MIN, 1, 2));
+
+        let group = stream.groups.get_mut(&Bytes::from("group")).unwrap();
+        assert_eq!(group.pending_for(b"alice"), 1);
+        assert_eq!(group.ack(&[StreamId { ms: 3, seq: 0 }, first]), 1);
+        assert!(group.pending.is_empty());
+    }
+}
@@ -0,0 +1,269 @@
+//! In-process Vivs servers for tests.
+//!
+//! Servers listen on ports picked by the OS and are configured in memory, nothing is read from
+//! or written to disk. Dropping a server (or a cluster) shuts it down.
+//!
+//! ```no_run
+//! use vivs::testing::{TestCluster, TestServer};
+//!
+//! # async fn example() -> vivs::GenericResult<()> {
+//! let server = TestServer::builder()
+//!     .config("databases = 4".parse()?)
+//!     .start()
+//!     .await?;
+//! let mut client = server.client().await?;
+//! client.set("greeting", "hello").await?;
+//!
+//! // Three nodes, each of them serves a third of the hash slots
+//! let cluster = TestCluster::start(3).await?;
+//! let mut client = cluster.client().await?;
+//! client.set("greeting", "hello").await?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    client::{ClientError, ClusterClient},
+    cluster::{admin, key_slot, Topology, CELLS_TOTAL},
+    server, Client, ClusterConfig, ClusterInstanceConfig, Config, DataStore, GenericResult,
+    Shutdown,
+};
+use std::net::SocketAddr;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+// Servers only listen on the loopback interface, on a port picked by the OS
+const ADDRESS: &str = "127.0.0.1:0";
+
+const NO_NODES_ERR: &str = "A cluster needs at least one node";
+
+/// Configures and starts a `TestServer`.
+#[derive(Debug, Default)]
+pub struct TestServerBuilder {
+    config: Config,
+}
+
+impl TestServerBuilder {
+    /// Config of the server, same as the config file (`[connection]` address and port are ignored)
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub async fn start(self) -> GenericResult<TestServer> {
+        TestServer::run(TcpListener::bind(ADDRESS).await?, &self.config, None)
+    }
+}
+
+/// Vivs server running in the background of the test.
+///
+/// Only client connections are served, the metrics listener and the cluster bus are not started.
+pub struct TestServer {
+    addr: SocketAddr,
+    db: DataStore,
+    topology: Topology,
+    shutdown: Shutdown,
+    // Taken once the server is shut down
+    task: Option<JoinHandle<GenericResult<()>>>,
+}
+
+impl TestServer {
+    pub fn builder() -> TestServerBuilder {
+        TestServerBuilder::default()
+    }
+
+    /// Starts a standalone server with the default config
+    pub async fn start() -> GenericResult<Self> {
+        Self::builder().start().await
+    }
+
+    /// Runs the listener in the background, `nodes` pins the cluster topology of the node
+    fn run(
+        tcp_listener: TcpListener,
+        config: &Config,
+        nodes: Option<ClusterInstanceConfig>,
+    ) -> GenericResult<Self> {
+        let addr = tcp_listener.local_addr()?;
+        let listener = server::listener(tcp_listener, config, None)?;
+        if let Some(nodes) = nodes {
+            listener.topology.pin(nodes);
+        }
+
+        Ok(Self {
+            addr,
+            db: listener.db.clone(),
+            topology: listener.topology.clone(),
+            shutdown: listener.shutdown.clone(),
+            task: Some(tokio::spawn(listener.run())),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Address of the server i.e. 127.0.0.1:9000, same as the addresses in the cluster topology
+    pub fn address(&self) -> String {
+        self.addr.to_string()
+    }
+
+    /// Data store of the server, keys can be set up or checked without a client
+    pub fn db(&self) -> &DataStore {
+        &self.db
+    }
+
+    /// Cluster topology of the server, `Topology::set` changes it (i.e. to move slots around)
+    pub fn topology(&self) -> &Topology {
+        &self.topology
+    }
+
+    /// Client connected to the server
+    pub async fn client(&self) -> Result<Client, ClientError> {
+        Client::builder().address(self.address()).connect().await
+    }
+
+    /// Stops the server and waits until in-flight commands have finished,
+    /// dropping the server stops it without waiting
+    pub async fn shutdown(mut self) -> GenericResult<()> {
+        self.shutdown.trigger();
+
+        match self.task.take() {
+            Some(task) => task.await?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.shutdown.trigger();
+    }
+}
+
+/// Configures and starts a `TestCluster`.
+#[derive(Debug)]
+pub struct TestClusterBuilder {
+    nodes: usize,
+    config: Config,
+}
+
+impl Default for TestClusterBuilder {
+    fn default() -> Self {
+        Self {
+            nodes: 3,
+            config: Config::default(),
+        }
+    }
+}
+
+impl TestClusterBuilder {
+    /// Number of nodes, 3 by default
+    pub fn nodes(mut self, nodes: usize) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    /// Config every node is started with, the cluster mode is turned on regardless
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub async fn start(self) -> GenericResult<TestCluster> {
+        if self.nodes == 0 {
+            Err(NO_NODES_ERR)?
+        }
+
+        let mut config = self.config;
+        config.cluster.get_or_insert_with(Default::default).enabled = true;
+
+        // Every node is bound before any of them starts, the topology needs all of the addresses
+        let mut tcp_listeners = vec![];
+        for _ in 0..self.nodes {
+            tcp_listeners.push(TcpListener::bind(ADDRESS).await?);
+        }
+
+        // Slots are split evenly, in the order the nodes were started
+        let mut topology = ClusterInstanceConfig::new();
+        for (index, tcp_listener) in tcp_listeners.iter().enumerate() {
+            let address = tcp_listener.local_addr()?.to_string();
+            let start = CELLS_TOTAL * index / self.nodes;
+            let end = CELLS_TOTAL * (index + 1) / self.nodes - 1;
+            let node = ClusterConfig {
+                id: admin::node_id().await?,
+                ip: address.clone(),
+                slots: vec![(start, end)],
+                ..Default::default()
+            };
+            topology.insert(address, node);
+        }
+
+        let mut nodes = vec![];
+        for tcp_listener in tcp_listeners {
+            let address = tcp_listener.local_addr()?.to_string();
+            let mut own = topology.clone();
+            for (node_address, node) in own.iter_mut() {
+                node.is_self = *node_address == address;
+            }
+
+            nodes.push(TestServer::run(tcp_listener, &config, Some(own))?);
+        }
+
+        Ok(TestCluster { nodes })
+    }
+}
+
+/// Vivs nodes that make up a cluster, running in the background of the test.
+///
+/// The topology is pinned in memory (nodes don't read `<port>.toml` configs), so `vivs-repl --cluster`
+/// admin commands don't work against it.
+pub struct TestCluster {
+    nodes: Vec<TestServer>,
+}
+
+impl TestCluster {
+    pub fn builder() -> TestClusterBuilder {
+        TestClusterBuilder::default()
+    }
+
+    /// Starts a cluster of `nodes` nodes with the default config
+    pub async fn start(nodes: usize) -> GenericResult<Self> {
+        Self::builder().nodes(nodes).start().await
+    }
+
+    /// Nodes in the order they serve the hash slots (the first one serves slot 0)
+    pub fn nodes(&self) -> &[TestServer] {
+        &self.nodes
+    }
+
+    /// Node that serves the hash slot of the key, according to the node itself
+    pub fn node_for(&self, key: impl AsRef<[u8]>) -> Option<&TestServer> {
+        let slot = usize::from(key_slot(key.as_ref()));
+
+        self.nodes.iter().find(|node| {
+            node.topology.nodes().is_some_and(|nodes| {
+                nodes
+                    .get(&node.address())
+                    .is_some_and(|own| own.serves(slot))
+            })
+        })
+    }
+
+    /// Cluster client that knows about every node
+    pub async fn client(&self) -> Result<ClusterClient, ClientError> {
+        self.nodes
+            .iter()
+            .fold(ClusterClient::builder(), |builder, node| {
+                builder.seed(node.address())
+            })
+            .connect()
+            .await
+    }
+
+    /// Stops every node and waits until in-flight commands have finished
+    pub async fn shutdown(self) -> GenericResult<()> {
+        for node in self.nodes {
+            node.shutdown().await?;
+        }
+
+        Ok(())
+    }
+}
@@ -13,6 +13,11 @@ pub fn u64_as_bytes(integer: u64) -> [u8; 8] {
     }
 }
 
+/// Negative integers (i.e. -1 of BITPOS) are sent as their two's complement
+pub fn i64_as_bytes(integer: i64) -> [u8; 8] {
+    u64_as_bytes(integer as u64)
+}
+
 pub fn u8_as_bytes(integer: u8) -> [u8; 1] {
     if cfg!(target_endian = "big") {
         integer.to_be_bytes()
@@ -20,3 +25,117 @@ pub fn u8_as_bytes(integer: u8) -> [u8; 1] {
         integer.to_le_bytes()
     }
 }
+
+/// Glob-style matching, same as Redis does for SCAN MATCH / KEYS patterns.
+///
+/// Supports `*`, `?`, `[abc]`, `[^abc]`, `[a-z]` and `\` to escape special characters.
+///
+/// Only the last `*` is ever backtracked to, so matching takes at most
+/// pattern length * value length steps no matter how many stars the pattern has.
+pub fn glob_match(pattern: &[u8], value: &[u8]) -> bool {
+    let (mut p, mut v) = (0, 0);
+    // Pattern position after the last `*` and the value position it matched up to
+    let mut star = None;
+
+    while v < value.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, v));
+            continue;
+        }
+
+        if let Some(len) = match_byte(&pattern[p..], value[v]) {
+            p += len;
+            v += 1;
+            continue;
+        }
+
+        // The last `*` takes one more byte and the rest of the pattern is tried again from there
+        let Some((star_p, star_v)) = star.as_mut() else {
+            return false;
+        };
+        *star_v += 1;
+        (p, v) = (*star_p, *star_v);
+    }
+
+    // Stars at the end of the pattern match nothing
+    pattern[p..].iter().all(|byte| *byte == b'*')
+}
+
+/// Matches the byte against the first element of the pattern (anything but `*`),
+/// returns how many bytes of the pattern the element takes when it matches
+fn match_byte(pattern: &[u8], byte: u8) -> Option<usize> {
+    match pattern {
+        [] | [b'*', ..] => None,
+        [b'?', ..] => Some(1),
+        [b'[', rest @ ..] => {
+            let (negate, mut class) = match rest.split_first() {
+                Some((b'^', class)) => (true, class),
+                _ => (false, rest),
+            };
+
+            let mut matched = false;
+            loop {
+                match class {
+                    // Unclosed class, same as Redis the end of the pattern closes it
+                    [] => break,
+                    [b']', ..] => {
+                        class = &class[1..];
+                        break;
+                    }
+                    [b'\\', escaped, ..] => {
+                        matched |= *escaped == byte;
+                        class = &class[2..];
+                    }
+                    [start, b'-', end, ..] if *end != b']' => {
+                        let (start, end) = (*start.min(end), *start.max(end));
+                        matched |= (start..=end).contains(&byte);
+                        class = &class[3..];
+                    }
+                    [other, ..] => {
+                        matched |= *other == byte;
+                        class = &class[1..];
+                    }
+                }
+            }
+
+            (matched != negate).then_some(pattern.len() - class.len())
+        }
+        [b'\\', escaped, ..] => (*escaped == byte).then_some(2),
+        [other, ..] => (*other == byte).then_some(1),
+    }
+}
+
+#[cfg(test)]
+mod utils_tests {
+    use super::glob_match;
+
+    #[test]
+    fn matches_glob_patterns() {
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"user:*", b"user:1"));
+        assert!(!glob_match(b"user:*", b"session:1"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(!glob_match(b"h?llo", b"hllo"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"key[0-9]", b"key7"));
+        assert!(glob_match(b"a\\*b", b"a*b"));
+        assert!(!glob_match(b"a\\*b", b"axb"));
+        assert!(glob_match(b"a*b*c", b"aXbYbZc"));
+        assert!(glob_match(b"*[0-9]?", b"key:12"));
+        assert!(!glob_match(b"*.rs", b"main.rss"));
+        assert!(glob_match(b"key\\", b"key\\"));
+    }
+
+    #[test]
+    fn pathological_patterns_do_not_backtrack_exponentially() {
+        let value = vec![b'a'; 100];
+        let pattern = [b"*a".repeat(30), b"b".to_vec()].concat();
+
+        let started_at = std::time::Instant::now();
+        assert!(!glob_match(&pattern, &value));
+        assert!(glob_match(&b"*a".repeat(30), &value));
+        assert!(started_at.elapsed() < std::time::Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,580 @@
+#[cfg(test)]
+mod client {
+    use bytes::Bytes;
+    use std::{collections::HashMap, env::current_dir, net::SocketAddr, time::Duration};
+    use tokio::net::TcpListener;
+    use vivs::{
+        client::{ClientError, ClusterClient, Cmd, FromReply, Pipeline},
+        cluster::{admin, key_slot},
+        data_chunk::DataChunk,
+        Client, ClusterConfig, DataStore, Listener,
+    };
+
+    async fn init_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let address = listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        tokio::spawn(listener.run());
+
+        address
+    }
+
+    async fn connect(addr: SocketAddr) -> Client {
+        Client::builder()
+            .address(addr.to_string())
+            .connect()
+            .await
+            .expect("Failed to connect")
+    }
+
+    #[tokio::test]
+    async fn typed_replies() {
+        let addr = init_server().await;
+        let mut client = connect(addr).await;
+
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+
+        client.set("greeting", "hello world").await.unwrap();
+        let greeting: Option<String> = client.get("greeting").await.unwrap();
+        assert_eq!(greeting.as_deref(), Some("hello world"));
+
+        let missing: Option<String> = client.get("missing").await.unwrap();
+        assert_eq!(missing, None);
+
+        assert_eq!(client.delete("greeting").await.unwrap(), 1);
+
+        let unknown = client.query::<String>(&Cmd::new("NOPE")).await;
+        assert!(matches!(unknown, Err(ClientError::Server(_))));
+
+        // Values are returned byte for byte, even when they aren't valid UTF-8
+        let value = b"\x89PNG\r\n\x1a\n\xff\x00".to_vec();
+        client.set(b"\xfe\xff", &value).await.unwrap();
+        let image: Option<Bytes> = client.get(b"\xfe\xff").await.unwrap();
+        assert_eq!(image.as_deref(), Some(&value[..]));
+
+        let text = client.get::<Option<String>>(b"\xfe\xff").await;
+        assert!(matches!(text, Err(ClientError::Type(_))));
+    }
+
+    #[tokio::test]
+    async fn pipelined_commands() {
+        let addr = init_server().await;
+        let mut client = connect(addr).await;
+
+        let pipeline = Pipeline::new()
+            .cmd(Cmd::new("SET").arg("a").arg("1"))
+            .cmd(Cmd::new("SET").arg("b").arg("2"))
+            .cmd(Cmd::new("GET").arg("a"))
+            .cmd(Cmd::new("GET").arg("b"))
+            .cmd(Cmd::new("GET").arg("c"));
+
+        let mut replies = client.query_pipeline(&pipeline).await.unwrap().into_iter();
+        assert_eq!(replies.len(), 5);
+
+        let _ = replies.next();
+        let _ = replies.next();
+        let values = replies
+            .map(Option::<String>::from_reply)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(
+            values,
+            vec![Some("1".to_owned()), Some("2".to_owned()), None]
+        );
+    }
+
+    #[tokio::test]
+    async fn pooled_clients_are_reused() {
+        let addr = init_server().await;
+        let pool = Client::builder().address(addr.to_string()).pool(2);
+
+        let tasks = (0..10).map(|i| {
+            let pool = pool.clone();
+            tokio::spawn(async move {
+                let mut client = pool.get().await.unwrap();
+                client.set(format!("key{i}"), i.to_string()).await.unwrap();
+                client
+                    .get::<Option<String>>(format!("key{i}"))
+                    .await
+                    .unwrap()
+            })
+        });
+
+        for (i, task) in tasks.enumerate() {
+            assert_eq!(task.await.unwrap(), Some(i.to_string()));
+        }
+
+        assert!(pool.idle() <= 2);
+    }
+
+    #[tokio::test]
+    async fn pool_times_out_when_all_clients_are_in_use() {
+        let addr = init_server().await;
+        let pool = Client::builder()
+            .address(addr.to_string())
+            .pool_timeout(Duration::from_millis(100))
+            .pool(1);
+
+        let _client = pool.get().await.unwrap();
+
+        assert!(matches!(pool.get().await, Err(ClientError::PoolTimeout)));
+    }
+
+    #[tokio::test]
+    async fn reconnects_once_connection_is_lost() {
+        let addr = init_server().await;
+        let mut client = connect(addr).await;
+        let mut admin = connect(addr).await;
+
+        let id: i64 = client.query(&Cmd::new("CLIENT").arg("ID")).await.unwrap();
+        let killed: i64 = admin
+            .query(&Cmd::new("CLIENT").arg("KILL").arg("ID").arg(id.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(killed, 1);
+
+        // The first command notices that the connection is gone (if the kill was not noticed already)
+        let _ = client.ping().await;
+
+        assert_eq!(client.ping().await.unwrap(), "PONG");
+        assert!(client.is_connected());
+    }
+
+    #[tokio::test]
+    async fn replies_time_out() {
+        let addr = init_server().await;
+        let mut admin = connect(addr).await;
+        let mut client = Client::builder()
+            .address(addr.to_string())
+            .response_timeout(Duration::from_millis(100))
+            .connect()
+            .await
+            .unwrap();
+
+        admin
+            .query::<()>(&Cmd::new("CLIENT").arg("PAUSE").arg("500").arg("WRITE"))
+            .await
+            .unwrap();
+
+        let result = client.set("key", "value").await;
+        assert!(matches!(result, Err(ClientError::Timeout)));
+        assert!(!client.is_connected());
+    }
+
+    // Removes <port>.toml cluster configs once the test is done (even if it fails)
+    struct ClusterConfigs(Vec<u16>);
+
+    impl ClusterConfigs {
+        // Nodes reload their configs straight away
+        async fn write(addrs: &[(SocketAddr, (usize, usize))]) -> Self {
+            let nodes = addrs
+                .iter()
+                .map(|(addr, position)| {
+                    let config = ClusterConfig {
+                        id: addr.port().to_string(),
+                        ip: addr.to_string(),
+                        is_self: false,
+                        slots: vec![*position],
+                        ..Default::default()
+                    };
+                    (addr.to_string(), config)
+                })
+                .collect::<HashMap<_, _>>();
+
+            admin::save(&nodes).await.unwrap();
+
+            Self(addrs.iter().map(|(addr, _)| addr.port()).collect())
+        }
+    }
+
+    impl Drop for ClusterConfigs {
+        fn drop(&mut self) {
+            for port in &self.0 {
+                let _ = std::fs::remove_file(current_dir().unwrap().join(format!("{port}.toml")));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn cluster_client_follows_moved_redirects() {
+        let first = init_server().await;
+        let second = init_server().await;
+
+        // Key that ends up on the second node once the slots are split
+        let key = (0..)
+            .map(|i| format!("key{i}"))
+            .find(|key| key_slot(key.as_bytes()) >= 8192)
+            .unwrap();
+        connect(second).await.set(&key, "value").await.unwrap();
+
+        // No cluster configs yet, so the first node serves all the slots
+        let mut client = ClusterClient::builder()
+            .seed(first.to_string())
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(
+            client.slots().master(key_slot(key.as_bytes())),
+            Some(first.to_string().as_str())
+        );
+
+        let _configs = ClusterConfigs::write(&[(first, (0, 8191)), (second, (8192, 16383))]).await;
+
+        // Slot map of the client is stale now, first node replies with MOVED
+        let value: Option<String> = client.get(&key).await.unwrap();
+        assert_eq!(value.as_deref(), Some("value"));
+
+        // The slot map is updated by the redirect, no refresh needed
+        assert_eq!(
+            client.slots().master(key_slot(key.as_bytes())),
+            Some(second.to_string().as_str())
+        );
+        assert_eq!(client.slots().master(0), Some(first.to_string().as_str()));
+
+        // Every key based command is redirected, not only GET
+        let mut first_client = connect(first).await;
+        for result in [
+            first_client.set(&key, "other").await.map(|_| 0),
+            first_client.ttl(&key).await,
+            first_client.delete(&key).await,
+        ] {
+            assert!(matches!(result, Err(ClientError::Server(err)) if err.starts_with("MOVED")));
+        }
+
+        // The next request goes straight to the new owner
+        let gets_on_first = || async {
+            let stats: String = connect(first)
+                .await
+                .query(&Cmd::new("INFO").arg("commandstats"))
+                .await
+                .unwrap();
+            stats
+                .lines()
+                .find_map(|line| line.strip_prefix("cmdstat_get:calls="))
+                .and_then(|line| line.split(',').next())
+                .map_or(0, |calls| calls.parse::<u64>().unwrap())
+        };
+        let gets_before = gets_on_first().await;
+        let value: Option<String> = client.get(&key).await.unwrap();
+        assert_eq!(value.as_deref(), Some("value"));
+        assert_eq!(gets_on_first().await, gets_before);
+
+        let slot: i64 = client
+            .query(&Cmd::new("CLUSTER").arg("KEYSLOT").arg(&key))
+            .await
+            .unwrap();
+        assert_eq!(slot, key_slot(key.as_bytes()) as i64);
+
+        // Commands of the pipeline are split between the nodes, replies keep the order
+        let other_key = (0..)
+            .map(|i| format!("other{i}"))
+            .find(|key| key_slot(key.as_bytes()) < 8192)
+            .unwrap();
+        let pipeline = Pipeline::new()
+            .cmd(Cmd::new("SET").arg(&other_key).arg("other"))
+            .cmd(Cmd::new("GET").arg(&key))
+            .cmd(Cmd::new("GET").arg(&other_key));
+
+        let replies = client
+            .query_pipeline(&pipeline)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(Option::<String>::from_reply)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            replies,
+            vec![
+                Some("OK".to_owned()),
+                Some("value".to_owned()),
+                Some("other".to_owned())
+            ]
+        );
+    }
+
+    // Epoch of the node's cluster topology
+    async fn reload_topology(client: &mut Client) -> i64 {
+        client
+            .query(&Cmd::new("CLUSTER").arg("RELOAD"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn cluster_topology_is_reloaded_once_config_changes_on_disk() {
+        let first = init_server().await;
+        let second = init_server().await;
+        let mut client = connect(first).await;
+
+        let initial_epoch = reload_topology(&mut client).await;
+
+        let configs = ClusterConfigs::write(&[(first, (0, 8191)), (second, (8192, 16383))]).await;
+        assert_eq!(reload_topology(&mut client).await, initial_epoch + 1);
+
+        // Config is changed behind the node's back, the node picks it up on its own
+        let mut nodes = admin::load(&first.to_string()).await.unwrap();
+        nodes.get_mut(&first.to_string()).unwrap().slots = vec![(0, 16383)];
+        nodes.get_mut(&second.to_string()).unwrap().slots = vec![];
+        let path = current_dir()
+            .unwrap()
+            .join(format!("{}.toml", first.port()));
+        std::fs::write(path, toml::to_string(&nodes).unwrap()).unwrap();
+
+        let mut slots = vec![];
+        for _ in 0..50 {
+            slots = client
+                .query::<Vec<DataChunk>>(&Cmd::new("CLUSTER").arg("SLOTS"))
+                .await
+                .unwrap();
+            if slots.len() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(slots.len(), 1);
+
+        // Without the config the node goes back to the standalone mode
+        drop(configs);
+        assert_eq!(reload_topology(&mut client).await, initial_epoch + 3);
+        assert!(client
+            .query::<Vec<DataChunk>>(&Cmd::new("CLUSTER").arg("SLOTS"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn cluster_admin_reshards_keys_between_nodes() {
+        let first = init_server().await;
+        let second = init_server().await;
+
+        let mut configs = ClusterConfigs::write(&[(first, (0, 16383))]).await;
+        configs.0.push(second.port());
+
+        let mut client = connect(first).await;
+        for i in 0..100 {
+            client.set(format!("key{i}"), "value").await.unwrap();
+        }
+        client.set_ex("expiring", "value", 100).await.unwrap();
+
+        admin::add_node(&first.to_string(), &second.to_string())
+            .await
+            .unwrap();
+        assert!(admin::check(&first.to_string()).await.unwrap().is_ok());
+
+        // The last 8192 slots (and their keys) move to the second node
+        admin::reshard(
+            &first.to_string(),
+            &first.to_string(),
+            &second.to_string(),
+            8192,
+        )
+        .await
+        .unwrap();
+
+        let mut second_client = connect(second).await;
+        for i in 0..100 {
+            let key = format!("key{i}");
+            let (owner, other) = if key_slot(key.as_bytes()) >= 8192 {
+                (&mut second_client, &mut client)
+            } else {
+                (&mut client, &mut second_client)
+            };
+
+            assert_eq!(
+                owner.get::<Option<String>>(&key).await.unwrap().as_deref(),
+                Some("value")
+            );
+            assert!(matches!(
+                other.get::<Option<String>>(&key).await,
+                Err(ClientError::Server(err)) if err.starts_with("MOVED")
+            ));
+        }
+
+        let expiring_owner = if key_slot(b"expiring") >= 8192 {
+            &mut second_client
+        } else {
+            &mut client
+        };
+        assert!(expiring_owner.ttl("expiring").await.unwrap() > 0);
+
+        let report = admin::check(&second.to_string()).await.unwrap();
+        assert!(report.is_ok(), "{report}");
+
+        // Nodes that serve slots can't be removed
+        assert!(admin::del_node(&first.to_string(), &second.to_string())
+            .await
+            .is_err());
+
+        admin::reshard(
+            &first.to_string(),
+            &second.to_string(),
+            &first.to_string(),
+            8192,
+        )
+        .await
+        .unwrap();
+        admin::del_node(&first.to_string(), &second.to_string())
+            .await
+            .unwrap();
+        assert!(admin::check(&first.to_string()).await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn cluster_admin_fixes_duplicated_and_uncovered_slots() {
+        let first = init_server().await;
+        let second = init_server().await;
+
+        // Both nodes claim slots 0-100, nobody serves 8192-16383
+        let _configs = ClusterConfigs::write(&[(first, (0, 8191)), (second, (0, 100))]).await;
+
+        let key = (0..)
+            .map(|i| format!("key{i}"))
+            .find(|key| key_slot(key.as_bytes()) <= 100)
+            .unwrap();
+        connect(second).await.set(&key, "value").await.unwrap();
+
+        let report = admin::check(&first.to_string()).await.unwrap();
+        assert_eq!(report.uncovered, vec![(8192, 16383)]);
+        assert_eq!(report.duplicated.len(), 1);
+
+        // Second node keeps the slot of its key, the uncovered slots get assigned
+        let report = admin::fix(&first.to_string()).await.unwrap();
+        assert!(report.is_ok(), "{report}");
+
+        let mut client = ClusterClient::builder()
+            .seed(first.to_string())
+            .connect()
+            .await
+            .unwrap();
+        assert_eq!(
+            client.slots().master(key_slot(key.as_bytes())),
+            Some(second.to_string().as_str())
+        );
+        let value: Option<String> = client.get(&key).await.unwrap();
+        assert_eq!(value.as_deref(), Some("value"));
+    }
+
+    #[tokio::test]
+    async fn scan_iterates_over_matching_keys() {
+        let addr = init_server().await;
+        let mut client = connect(addr).await;
+
+        for i in 0..25 {
+            client.set(format!("user:{i}"), "1").await.unwrap();
+            client.set(format!("session:{i}"), "1").await.unwrap();
+        }
+
+        let mut cursor = "0".to_owned();
+        let mut keys = vec![];
+        loop {
+            let cmd = Cmd::new("SCAN")
+                .arg(&cursor)
+                .arg("MATCH")
+                .arg("user:*")
+                .arg("COUNT")
+                .arg("10");
+            let mut reply = client
+                .query::<Vec<DataChunk>>(&cmd)
+                .await
+                .unwrap()
+                .into_iter();
+
+            cursor = String::from_reply(reply.next().unwrap()).unwrap();
+            keys.extend(Vec::<String>::from_reply(reply.next().unwrap()).unwrap());
+
+            if cursor == "0" {
+                break;
+            }
+        }
+
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 25);
+        assert!(keys.iter().all(|key| key.starts_with("user:")));
+    }
+
+    #[tokio::test]
+    async fn command_table_introspection() {
+        let addr = init_server().await;
+        let mut client = connect(addr).await;
+
+        let count: i64 = client
+            .query(&Cmd::new("COMMAND").arg("COUNT"))
+            .await
+            .unwrap();
+        let all: Vec<DataChunk> = client.query(&Cmd::new("COMMAND")).await.unwrap();
+        assert_eq!(all.len() as i64, count);
+
+        // name, arity, flags, first key, last key, step
+        let mut info: Vec<Option<Vec<DataChunk>>> = client
+            .query(&Cmd::new("COMMAND").arg("INFO").arg("set").arg("nope"))
+            .await
+            .unwrap();
+        assert_eq!(info.pop(), Some(None));
+
+        let mut set = info.pop().flatten().unwrap().into_iter();
+        assert_eq!(String::from_reply(set.next().unwrap()).unwrap(), "set");
+        assert_eq!(i64::from_reply(set.next().unwrap()).unwrap(), -3);
+        assert_eq!(
+            Vec::<String>::from_reply(set.next().unwrap()).unwrap(),
+            vec!["write"]
+        );
+        let key_positions = set
+            .map(|position| i64::from_reply(position).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(key_positions, vec![1, 1, 1]);
+
+        let keys: Vec<String> = client
+            .query(
+                &Cmd::new("COMMAND")
+                    .arg("GETKEYS")
+                    .arg("SET")
+                    .arg("a")
+                    .arg("1"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(keys, vec!["a"]);
+
+        // Stream keys are the first half of the arguments after STREAMS
+        let keys: Vec<String> = client
+            .query(
+                &Cmd::new("COMMAND")
+                    .arg("GETKEYS")
+                    .arg("XREADGROUP")
+                    .arg("GROUP")
+                    .arg("streams")
+                    .arg("consumer")
+                    .arg("STREAMS")
+                    .arg("a")
+                    .arg("b")
+                    .arg(">")
+                    .arg(">"),
+            )
+            .await
+            .unwrap();
+        assert_eq!(keys, vec!["a", "b"]);
+
+        let no_keys = client
+            .query::<Vec<String>>(&Cmd::new("COMMAND").arg("GETKEYS").arg("PING"))
+            .await;
+        assert!(matches!(no_keys, Err(ClientError::Server(err)) if err.contains("no key")));
+
+        // Arity comes from the command table
+        let get = client.query::<String>(&Cmd::new("GET")).await;
+        assert!(
+            matches!(get, Err(ClientError::Server(err)) if err == "ERR wrong number of arguments for 'get' command")
+        );
+
+        let stats: String = client
+            .query(&Cmd::new("INFO").arg("commandstats"))
+            .await
+            .unwrap();
+        assert!(stats.contains("cmdstat_command|getkeys:calls=3"));
+    }
+}
@@ -1,11 +1,15 @@
 #[cfg(test)]
 mod server {
-    use std::net::SocketAddr;
+    use std::{net::SocketAddr, time::Duration};
     use tokio::{
         io::{AsyncReadExt, AsyncWriteExt},
         net::{TcpListener, TcpStream},
+        time::timeout,
+    };
+    use vivs::{
+        limits::{ClientClass, OutputBufferLimit},
+        DataStore, Limits, Listener,
     };
-    use vivs::{DataStore, Listener};
 
     async fn init_server() -> SocketAddr {
         let listener = TcpListener::bind("127.0.0.1:0")
@@ -23,6 +27,73 @@ mod server {
         address
     }
 
+    async fn init_server_with_limits(set_limits: impl FnOnce(&Limits)) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let address = listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        set_limits(&listener.limits);
+
+        tokio::spawn(listener.run());
+
+        address
+    }
+
+    // Reads a reply of the expected length, gives up after 5s
+    async fn read(stream: &mut TcpStream, reply_len: usize) -> Vec<u8> {
+        let mut buffer = vec![0; reply_len];
+        let _ = timeout(Duration::from_secs(5), stream.read_exact(&mut buffer)).await;
+        buffer
+    }
+
+    // Pub/sub reply of a single channel i.e. [subscribe, channel, 1] or [message, channel, payload]
+    fn push(kind: &str, channel: &str, last: Vec<u8>) -> Vec<u8> {
+        [b"*3\r\n".to_vec(), bulk(kind), bulk(channel), last].concat()
+    }
+
+    // Sends a command and reads a reply of the expected length
+    async fn send(stream: &mut TcpStream, command: &[u8], reply_len: usize) -> Vec<u8> {
+        stream.write_all(command).await.unwrap();
+
+        let mut buffer = vec![0; reply_len];
+        let _ = stream.read_exact(&mut buffer).await;
+        buffer
+    }
+
+    // Encodes the command as an array of bulk strings
+    fn command(args: &[&str]) -> Vec<u8> {
+        let mut command = format!("*{}\r\n", args.len());
+        for arg in args {
+            command.push_str(&format!("${}\r\n{arg}\r\n", arg.len()));
+        }
+        command.into_bytes()
+    }
+
+    fn bulk(value: &str) -> Vec<u8> {
+        format!("${}\r\n{value}\r\n", value.len()).into_bytes()
+    }
+
+    // Stream entry with a single field, i.e. [1-0, [field, value]]
+    fn entry(id: &str, field: &str, value: &str) -> Vec<u8> {
+        [
+            b"*2\r\n".to_vec(),
+            bulk(id),
+            b"*2\r\n".to_vec(),
+            bulk(field),
+            bulk(value),
+        ]
+        .concat()
+    }
+
+    fn integer(value: u64) -> Vec<u8> {
+        let mut reply = b":".to_vec();
+        reply.extend(value.to_ne_bytes());
+        reply.extend(b"\r\n");
+        reply
+    }
+
     #[tokio::test]
     async fn ping_without_value() {
         let addr = init_server().await;
@@ -96,10 +167,10 @@ mod server {
             .await
             .unwrap();
 
-        let mut buffer = [0; 8];
+        let mut buffer = [0; 11];
         let _ = stream.read_exact(&mut buffer).await;
 
-        assert_eq!(b"+hello\r\n", &buffer);
+        assert_eq!(b"$5\r\nhello\r\n", &buffer);
     }
 
     #[tokio::test]
@@ -169,10 +240,10 @@ mod server {
             .await
             .unwrap();
 
-        let mut buffer = [0; 8];
+        let mut buffer = [0; 11];
         let _ = stream.read_exact(&mut buffer).await;
 
-        assert_eq!(b"+hello\r\n", &buffer);
+        assert_eq!(b"$5\r\nhello\r\n", &buffer);
 
         // TTL
         stream
@@ -188,4 +259,1908 @@ mod server {
 
         assert_eq!(expected, buffer);
     }
+
+    #[tokio::test]
+    async fn client_id_setname_and_getname() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // CLIENT ID
+        stream
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$2\r\nID\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 11];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        // First connection to the server gets id 1
+        let expected: [u8; 11] = [58, 1, 0, 0, 0, 0, 0, 0, 0, 13, 10];
+        assert_eq!(expected, buffer);
+
+        // CLIENT GETNAME (no name set yet)
+        stream
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 3];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        assert_eq!(b"_\r\n", &buffer);
+
+        // CLIENT SETNAME
+        stream
+            .write_all(b"*3\r\n$6\r\nCLIENT\r\n$7\r\nSETNAME\r\n$6\r\nworker\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        assert_eq!(b"+OK\r\n", &buffer);
+
+        // CLIENT GETNAME
+        stream
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nGETNAME\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 12];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        assert_eq!(b"$6\r\nworker\r\n", &buffer);
+    }
+
+    #[tokio::test]
+    async fn client_list_and_kill() {
+        let addr = init_server().await;
+
+        let mut first_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut second_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Make sure both connections are registered before listing them
+        second_stream
+            .write_all(b"*1\r\n$4\r\nPING\r\n")
+            .await
+            .unwrap();
+        let mut buffer = [0; 7];
+        let _ = second_stream.read_exact(&mut buffer).await;
+
+        // CLIENT LIST
+        first_stream
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$4\r\nLIST\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 1024];
+        let bytes_read = first_stream.read(&mut buffer).await.unwrap();
+        let list = String::from_utf8_lossy(&buffer[..bytes_read]);
+
+        assert!(list.contains("id=1 "));
+        assert!(list.contains("id=2 "));
+        assert!(list.contains("cmd=client|list"));
+        assert!(list.contains("cmd=ping"));
+
+        // CLIENT KILL ID 2
+        first_stream
+            .write_all(b"*4\r\n$6\r\nCLIENT\r\n$4\r\nKILL\r\n$2\r\nID\r\n$1\r\n2\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 11];
+        let _ = first_stream.read_exact(&mut buffer).await;
+
+        // i.e. :1\r\n (one client killed)
+        let expected: [u8; 11] = [58, 1, 0, 0, 0, 0, 0, 0, 0, 13, 10];
+        assert_eq!(expected, buffer);
+
+        // The killed connection gets closed by the server
+        let mut buffer = [0; 1];
+        let bytes_read = second_stream.read(&mut buffer).await.unwrap_or(0);
+        assert_eq!(bytes_read, 0);
+    }
+
+    #[tokio::test]
+    async fn client_pause_holds_back_writes() {
+        let addr = init_server().await;
+
+        let mut admin_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // CLIENT PAUSE 10000 WRITE
+        admin_stream
+            .write_all(b"*4\r\n$6\r\nCLIENT\r\n$5\r\nPAUSE\r\n$5\r\n10000\r\n$5\r\nWRITE\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = admin_stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+OK\r\n", &buffer);
+
+        // Reads are still served
+        stream
+            .write_all(b"*2\r\n$3\r\nGET\r\n$5\r\nhello\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 3];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"_\r\n", &buffer);
+
+        // Writes are held back
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$5\r\nhello\r\n$5\r\nworld\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let paused = tokio::time::timeout(
+            std::time::Duration::from_millis(200),
+            stream.read_exact(&mut buffer),
+        )
+        .await;
+        assert!(paused.is_err());
+
+        // CLIENT UNPAUSE
+        admin_stream
+            .write_all(b"*2\r\n$6\r\nCLIENT\r\n$7\r\nUNPAUSE\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = admin_stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+OK\r\n", &buffer);
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+OK\r\n", &buffer);
+    }
+
+    #[tokio::test]
+    async fn info_reports_keyspace_and_command_stats() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$5\r\nhello\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        stream
+            .write_all(b"*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 3];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        // INFO keyspace stats commandstats
+        stream
+            .write_all(
+                b"*4\r\n$4\r\nINFO\r\n$8\r\nkeyspace\r\n$5\r\nstats\r\n$12\r\ncommandstats\r\n",
+            )
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 2048];
+        let bytes_read = stream.read(&mut buffer).await.unwrap();
+        let info = String::from_utf8_lossy(&buffer[..bytes_read]);
+
+        assert!(info.contains("db0:keys=1,expires=0"));
+        assert!(info.contains("keyspace_misses:1"));
+        assert!(info.contains("total_commands_processed:2"));
+        assert!(info.contains("cmdstat_set:calls=1"));
+        assert!(info.contains("cmdstat_get:calls=1"));
+        assert!(!info.contains("# Server"));
+    }
+
+    #[tokio::test]
+    async fn slowlog_get_len_and_reset() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        // Log every command
+        listener.slowlog.set_log_slower_than(0);
+        tokio::spawn(async move { listener.run().await });
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        stream
+            .write_all(b"*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        // SLOWLOG GET 1
+        stream
+            .write_all(b"*3\r\n$7\r\nSLOWLOG\r\n$3\r\nGET\r\n$1\r\n1\r\n")
+            .await
+            .unwrap();
+
+        // Elements are written one by one, read until the (empty) client name which comes last
+        let mut entries = vec![];
+        while !entries.ends_with(b"$0\r\n\r\n") {
+            let mut buffer = [0; 1024];
+            let bytes_read = stream.read(&mut buffer).await.unwrap();
+            entries.extend_from_slice(&buffer[..bytes_read]);
+        }
+
+        // One entry with 6 elements, the arguments are PING and hi
+        assert!(entries.starts_with(b"*1\r\n*6\r\n"));
+        assert!(entries
+            .windows(22)
+            .any(|w| w == b"*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n"));
+
+        // SLOWLOG RESET
+        stream
+            .write_all(b"*2\r\n$7\r\nSLOWLOG\r\n$5\r\nRESET\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+OK\r\n", &buffer);
+
+        // SLOWLOG LEN (the RESET itself got logged after the log was cleared)
+        stream
+            .write_all(b"*2\r\n$7\r\nSLOWLOG\r\n$3\r\nLEN\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 11];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        let expected: [u8; 11] = [58, 1, 0, 0, 0, 0, 0, 0, 0, 13, 10];
+        assert_eq!(expected, buffer);
+    }
+
+    #[tokio::test]
+    async fn slowlog_leaves_out_time_spent_blocked() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        listener.slowlog.set_log_slower_than(0);
+        tokio::spawn(async move { listener.run().await });
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Nothing gets pushed, the client waits for the whole timeout
+        let blpop = command(&["BLPOP", "nq", "0.5"]);
+        assert_eq!(send(&mut stream, &blpop, 3).await, b"_\r\n");
+
+        let slowlog = command(&["SLOWLOG", "GET", "1"]);
+        stream.write_all(&slowlog).await.unwrap();
+        let mut entries = vec![];
+        while !entries.ends_with(b"$0\r\n\r\n") {
+            let mut buffer = [0; 1024];
+            let bytes_read = stream.read(&mut buffer).await.unwrap();
+            entries.extend_from_slice(&buffer[..bytes_read]);
+        }
+
+        // *1, *6, then the ID, the timestamp and the duration (integers are 8 raw bytes)
+        assert!(entries.windows(11).any(|w| w == b"$5\r\nBLPOP\r\n"));
+        let duration = 8 + 11 + 11 + 1;
+        let duration = u64::from_ne_bytes(entries[duration..duration + 8].try_into().unwrap());
+        assert!(duration < 100_000, "{duration} microseconds");
+    }
+
+    #[tokio::test]
+    async fn monitor_streams_commands() {
+        let addr = init_server().await;
+
+        let mut monitor_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        monitor_stream
+            .write_all(b"*1\r\n$7\r\nMONITOR\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = monitor_stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+OK\r\n", &buffer);
+
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$11\r\nhello world\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        let mut buffer = [0; 1024];
+        let bytes_read = monitor_stream.read(&mut buffer).await.unwrap();
+        let line = String::from_utf8_lossy(&buffer[..bytes_read]);
+
+        assert!(line.starts_with('+'));
+        assert!(line.ends_with("] \"SET\" \"greeting\" \"hello world\"\r\n"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_connections_and_stops_server() {
+        let tcp_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = tcp_listener.local_addr().unwrap();
+
+        let listener = Listener::new(tcp_listener, DataStore::new());
+        let server = tokio::spawn(listener.run());
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream.write_all(b"*1\r\n$8\r\nSHUTDOWN\r\n").await.unwrap();
+
+        // Same as in Redis, there is no reply, the connection just gets closed
+        let mut buffer = [0; 16];
+        let bytes_read = stream.read(&mut buffer).await.unwrap();
+        assert_eq!(0, bytes_read);
+
+        let result = timeout(Duration::from_secs(5), server).await;
+        assert!(result.is_ok(), "Server did not stop in time");
+    }
+
+    #[tokio::test]
+    async fn shutdown_save_without_persistence() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream
+            .write_all(b"*2\r\n$8\r\nSHUTDOWN\r\n$4\r\nSAVE\r\n")
+            .await
+            .unwrap();
+
+        let expected = b"-ERR Errors trying to SHUTDOWN, persistence is not configured\r\n";
+        let mut buffer = [0; 63];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(expected, &buffer);
+
+        // The server is still up
+        stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+        let mut buffer = [0; 7];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+PONG\r\n", &buffer);
+    }
+
+    #[tokio::test]
+    async fn triggered_shutdown_stops_server() {
+        let tcp_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = tcp_listener.local_addr().unwrap();
+
+        let listener = Listener::new(tcp_listener, DataStore::new());
+        let shutdown = listener.shutdown.clone();
+        let server = tokio::spawn(listener.run());
+
+        // An idle client should not hold the server back
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+        let mut buffer = [0; 7];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+PONG\r\n", &buffer);
+
+        shutdown.trigger();
+
+        let result = timeout(Duration::from_secs(5), server).await;
+        assert!(result.is_ok(), "Server did not stop in time");
+
+        let bytes_read = stream.read(&mut buffer).await.unwrap_or_default();
+        assert_eq!(0, bytes_read);
+    }
+
+    #[tokio::test]
+    async fn pipelined_and_partial_commands() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Two commands in a single write
+        stream
+            .write_all(b"*1\r\n$4\r\nPING\r\n*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 12];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+PONG\r\n+hi\r\n", &buffer);
+
+        // A single command split across writes
+        stream.write_all(b"*2\r\n$4\r\nPI").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stream.write_all(b"NG\r\n$5\r\nhello\r\n").await.unwrap();
+
+        let mut buffer = [0; 8];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+hello\r\n", &buffer);
+    }
+
+    #[tokio::test]
+    async fn maxclients_rejects_new_connections() {
+        let addr = init_server_with_limits(|limits| limits.set_max_clients(1)).await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream.write_all(b"*1\r\n$4\r\nPING\r\n").await.unwrap();
+
+        let mut buffer = [0; 7];
+        let _ = stream.read_exact(&mut buffer).await;
+        assert_eq!(b"+PONG\r\n", &buffer);
+
+        let mut rejected_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let mut buffer = vec![];
+        let _ = rejected_stream.read_to_end(&mut buffer).await;
+        assert_eq!(b"-ERR max number of clients reached\r\n", &buffer[..]);
+    }
+
+    #[tokio::test]
+    async fn idle_clients_get_disconnected() {
+        let addr = init_server_with_limits(|limits| limits.set_timeout(1)).await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let mut buffer = vec![];
+        let read = timeout(Duration::from_secs(5), stream.read_to_end(&mut buffer)).await;
+
+        assert!(read.is_ok(), "Idle connection was not closed");
+        assert_eq!(
+            b"-ERR idle timeout reached, closing connection\r\n",
+            &buffer[..]
+        );
+    }
+
+    #[tokio::test]
+    async fn bulk_string_over_max_length() {
+        let addr = init_server_with_limits(|limits| limits.set_proto_max_bulk_len(8)).await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // The value is never sent, the length alone is enough to reject it
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$9\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = vec![];
+        let _ = stream.read_to_end(&mut buffer).await;
+        assert_eq!(b"-ERR Protocol error: invalid bulk length\r\n", &buffer[..]);
+    }
+
+    #[tokio::test]
+    async fn query_buffer_over_the_limit() {
+        let addr = init_server_with_limits(|limits| limits.set_client_query_buffer_limit(32)).await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Incomplete command that keeps growing
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$100\r\naaaaaaaaaaaaaaaaaaaa")
+            .await
+            .unwrap();
+
+        let mut buffer = vec![];
+        let _ = stream.read_to_end(&mut buffer).await;
+        assert_eq!(
+            b"-ERR Protocol error: query buffer limit reached\r\n",
+            &buffer[..]
+        );
+    }
+
+    #[tokio::test]
+    async fn command_sent_one_byte_at_a_time() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream.set_nodelay(true).unwrap();
+
+        let values = vec!["a"; 20_000];
+        let push = command(&[&["RPUSH", "list"], &values[..]].concat());
+
+        // Each byte is read on its own, the command is not parsed again for each of them
+        let started_at = std::time::Instant::now();
+        for byte in &push {
+            stream.write_all(&[*byte]).await.unwrap();
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(read(&mut stream, 11).await, integer(20_000));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn slow_subscriber_goes_over_output_buffer_limit() {
+        let addr = init_server_with_limits(|limits| {
+            let limit = OutputBufferLimit {
+                hard_limit: 1024 * 1024,
+                soft_limit: 0,
+                soft_seconds: 0,
+            };
+            limits.set_output_buffer_limit(ClientClass::Pubsub, limit);
+        })
+        .await;
+
+        let mut subscriber = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let subscribed = push("subscribe", "news", integer(1));
+        assert_eq!(
+            send(
+                &mut subscriber,
+                &command(&["SUBSCRIBE", "news"]),
+                subscribed.len()
+            )
+            .await,
+            subscribed
+        );
+
+        // The subscriber never reads the messages, they pile up until it goes over the limit
+        let mut publisher = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let payload = "a".repeat(64 * 1024);
+        let publish = command(&["PUBLISH", "news", &payload]);
+
+        let mut received = integer(1);
+        for _ in 0..1000 {
+            received = send(&mut publisher, &publish, integer(0).len()).await;
+            if received == integer(0) {
+                break;
+            }
+        }
+        assert_eq!(received, integer(0));
+
+        // The subscriber gets disconnected, normal clients are not limited
+        let mut buffer = vec![];
+        let read = timeout(Duration::from_secs(5), subscriber.read_to_end(&mut buffer)).await;
+        assert!(read.is_ok(), "Slow subscriber was not disconnected");
+
+        let pong = send(&mut publisher, &command(&["PING"]), 7).await;
+        assert_eq!(b"+PONG\r\n", &pong[..]);
+    }
+
+    #[tokio::test]
+    async fn select_swapdb_and_move_between_databases() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let ok = b"+OK\r\n";
+        let set_a = b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n";
+        let get_a = b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n";
+
+        assert_eq!(send(&mut stream, set_a, 5).await, ok);
+
+        // Keys of db 0 are not visible from db 1
+        let select_1 = b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n";
+        assert_eq!(send(&mut stream, select_1, 5).await, ok);
+        assert_eq!(send(&mut stream, get_a, 3).await, b"_\r\n");
+
+        let select_16 = b"*2\r\n$6\r\nSELECT\r\n$2\r\n16\r\n";
+        assert_eq!(
+            send(&mut stream, select_16, 31).await,
+            b"-ERR DB index is out of range\r\n"
+        );
+
+        // db 0 and db 1 swap places for every connection
+        let swapdb = b"*3\r\n$6\r\nSWAPDB\r\n$1\r\n0\r\n$1\r\n1\r\n";
+        assert_eq!(send(&mut stream, swapdb, 5).await, ok);
+        assert_eq!(send(&mut stream, get_a, 7).await, b"$1\r\n1\r\n");
+
+        let mut other_stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        assert_eq!(send(&mut other_stream, get_a, 3).await, b"_\r\n");
+
+        // MOVE a 0 moves the key back to db 0
+        let move_a = b"*3\r\n$4\r\nMOVE\r\n$1\r\na\r\n$1\r\n0\r\n";
+        let mut moved = b":".to_vec();
+        moved.extend(1_u64.to_ne_bytes());
+        moved.extend(b"\r\n");
+        assert_eq!(send(&mut stream, move_a, 11).await, moved);
+        assert_eq!(send(&mut stream, get_a, 3).await, b"_\r\n");
+        assert_eq!(send(&mut other_stream, get_a, 7).await, b"$1\r\n1\r\n");
+
+        // Nothing to move anymore
+        let mut not_moved = b":".to_vec();
+        not_moved.extend(0_u64.to_ne_bytes());
+        not_moved.extend(b"\r\n");
+        assert_eq!(send(&mut stream, move_a, 11).await, not_moved);
+
+        let move_to_same = b"*3\r\n$4\r\nMOVE\r\n$1\r\na\r\n$1\r\n1\r\n";
+        assert_eq!(
+            send(&mut stream, move_to_same, 50).await,
+            b"-ERR source and destination objects are the same\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn flushdb_and_flushall() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let ok = b"+OK\r\n";
+        let set_a = b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n";
+        let get_a = b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n";
+        let select = |index: u8| format!("*2\r\n$6\r\nSELECT\r\n$1\r\n{}\r\n", index as char);
+
+        assert_eq!(send(&mut stream, set_a, 5).await, ok);
+        assert_eq!(send(&mut stream, select(b'2').as_bytes(), 5).await, ok);
+        assert_eq!(send(&mut stream, set_a, 5).await, ok);
+
+        // INFO keyspace lists every database that has keys
+        stream
+            .write_all(b"*2\r\n$4\r\nINFO\r\n$8\r\nkeyspace\r\n")
+            .await
+            .unwrap();
+        let mut buffer = [0; 256];
+        let bytes_read = stream.read(&mut buffer).await.unwrap();
+        let info = String::from_utf8_lossy(&buffer[..bytes_read]);
+        assert!(info.contains("db0:keys=1,expires=0"));
+        assert!(info.contains("db2:keys=1,expires=0"));
+
+        // FLUSHDB only empties the selected database
+        let flushdb = b"*2\r\n$7\r\nFLUSHDB\r\n$5\r\nASYNC\r\n";
+        assert_eq!(send(&mut stream, flushdb, 5).await, ok);
+        assert_eq!(send(&mut stream, get_a, 3).await, b"_\r\n");
+        assert_eq!(send(&mut stream, select(b'0').as_bytes(), 5).await, ok);
+        assert_eq!(send(&mut stream, get_a, 7).await, b"$1\r\n1\r\n");
+
+        let flushdb_invalid = b"*2\r\n$7\r\nFLUSHDB\r\n$4\r\nLAZY\r\n";
+        assert_eq!(
+            send(&mut stream, flushdb_invalid, 19).await,
+            b"-ERR syntax error\r\n"
+        );
+
+        let flushall = b"*2\r\n$8\r\nFLUSHALL\r\n$4\r\nSYNC\r\n";
+        assert_eq!(send(&mut stream, flushall, 5).await, ok);
+        assert_eq!(send(&mut stream, get_a, 3).await, b"_\r\n");
+    }
+
+    #[tokio::test]
+    async fn cluster_mode_only_has_db_0() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Listener::new(listener, DataStore::cluster()).run());
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let select_0 = b"*2\r\n$6\r\nSELECT\r\n$1\r\n0\r\n";
+        assert_eq!(send(&mut stream, select_0, 5).await, b"+OK\r\n");
+
+        let select_1 = b"*2\r\n$6\r\nSELECT\r\n$1\r\n1\r\n";
+        assert_eq!(
+            send(&mut stream, select_1, 44).await,
+            b"-ERR SELECT is not allowed in cluster mode\r\n"
+        );
+
+        let swapdb = b"*3\r\n$6\r\nSWAPDB\r\n$1\r\n0\r\n$1\r\n1\r\n";
+        assert_eq!(
+            send(&mut stream, swapdb, 44).await,
+            b"-ERR SWAPDB is not allowed in cluster mode\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn keys_and_values_are_binary_safe() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Value with CRLF in it, key and value that are not valid UTF-8
+        let set_crlf = b"*3\r\n$3\r\nSET\r\n$4\r\ncrlf\r\n$12\r\nhello\r\nworld\r\n";
+        assert_eq!(send(&mut stream, set_crlf, 5).await, b"+OK\r\n");
+
+        let set_binary = b"*3\r\n$3\r\nSET\r\n$2\r\n\xff\xfe\r\n$4\r\n\x00\xc3\x28\xff\r\n";
+        assert_eq!(send(&mut stream, set_binary, 5).await, b"+OK\r\n");
+
+        let get_crlf = b"*2\r\n$3\r\nGET\r\n$4\r\ncrlf\r\n";
+        assert_eq!(
+            send(&mut stream, get_crlf, 19).await,
+            b"$12\r\nhello\r\nworld\r\n"
+        );
+
+        let get_binary = b"*2\r\n$3\r\nGET\r\n$2\r\n\xff\xfe\r\n";
+        assert_eq!(
+            send(&mut stream, get_binary, 10).await,
+            b"$4\r\n\x00\xc3\x28\xff\r\n"
+        );
+
+        // SCAN MATCH works on raw bytes too
+        let scan = b"*4\r\n$4\r\nSCAN\r\n$1\r\n0\r\n$5\r\nMATCH\r\n$2\r\n\xff*\r\n";
+        assert_eq!(
+            send(&mut stream, scan, 23).await,
+            b"*2\r\n$1\r\n0\r\n*1\r\n$2\r\n\xff\xfe\r\n"
+        );
+
+        let delete_binary = b"*2\r\n$6\r\nDELETE\r\n$2\r\n\xff\xfe\r\n";
+        let mut deleted = b":".to_vec();
+        deleted.extend(1_u64.to_ne_bytes());
+        deleted.extend(b"\r\n");
+        assert_eq!(send(&mut stream, delete_binary, 11).await, deleted);
+        assert_eq!(send(&mut stream, get_binary, 3).await, b"_\r\n");
+    }
+
+    #[tokio::test]
+    async fn errors_use_redis_prefixes() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[u8], &[u8]); 6] = [
+            (
+                b"*1\r\n$3\r\nGET\r\n",
+                b"-ERR wrong number of arguments for 'get' command\r\n",
+            ),
+            (
+                b"*1\r\n$4\r\nNOPE\r\n",
+                b"-ERR unknown command 'nope'\r\n",
+            ),
+            (
+                b"*2\r\n$6\r\nCLIENT\r\n$4\r\nNOPE\r\n",
+                b"-ERR unknown subcommand 'NOPE' for 'CLIENT'\r\n",
+            ),
+            (
+                b"*5\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n$2\r\nXS\r\n$3\r\nabc\r\n",
+                b"-ERR value is not an integer or out of range\r\n",
+            ),
+            // Expiry that does not fit the clock is rejected before anything is written
+            (
+                b"*5\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n$2\r\nXS\r\n$20\r\n18446744073709551615\r\n",
+                b"-ERR invalid expire time in 'set' command\r\n",
+            ),
+            (b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n", b"_\r\n"),
+        ];
+
+        for (command, reply) in replies {
+            assert_eq!(send(&mut stream, command, reply.len()).await, reply);
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_commands_get_protocol_errors() {
+        let addr = init_server().await;
+
+        let nested = "*1\r\n".repeat(1000);
+        let malformed: [(&[u8], &[u8]); 2] = [
+            // Arguments have to be bulk strings
+            (
+                b"*2\r\n$3\r\nGET\r\n:1\r\n",
+                b"-ERR Protocol error: expected an array of bulk strings\r\n",
+            ),
+            (
+                nested.as_bytes(),
+                b"-ERR Protocol error: too many nested arrays\r\n",
+            ),
+        ];
+
+        for (command, reply) in malformed {
+            let mut stream = TcpStream::connect(addr)
+                .await
+                .expect("Failed to open a TCP connection");
+            stream.write_all(command).await.unwrap();
+
+            // The connection gets closed after the error
+            let mut buffer = vec![];
+            let _ = stream.read_to_end(&mut buffer).await;
+            assert_eq!(reply, &buffer[..]);
+        }
+
+        // The server keeps serving other clients
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        assert_eq!(
+            send(&mut stream, b"*1\r\n$4\r\nPING\r\n", 7).await,
+            b"+PONG\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn lists_push_pop_and_move() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[&str], &[u8]); 11] = [
+            (&["RPUSH", "queue", "a", "b"], &integer(2)),
+            (&["LPUSH", "queue", "z"], &integer(3)),
+            (
+                &["LRANGE", "queue", "0", "-1"],
+                b"*3\r\n$1\r\nz\r\n$1\r\na\r\n$1\r\nb\r\n",
+            ),
+            (&["LPOP", "queue"], b"$1\r\nz\r\n"),
+            (&["RPOPLPUSH", "queue", "other"], b"$1\r\nb\r\n"),
+            (
+                &["LMOVE", "queue", "other", "LEFT", "RIGHT"],
+                b"$1\r\na\r\n",
+            ),
+            // Empty lists are removed
+            (&["LLEN", "queue"], &integer(0)),
+            (&["RPOP", "queue"], b"_\r\n"),
+            (&["LRANGE", "other", "-1", "5"], b"*1\r\n$1\r\na\r\n"),
+            (
+                &["GET", "other"],
+                b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+            ),
+            (
+                &["LMOVE", "other", "other", "UP", "DOWN"],
+                b"-ERR syntax error\r\n",
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+
+        let set = command(&["SET", "string", "1"]);
+        assert_eq!(send(&mut stream, &set, 5).await, b"+OK\r\n");
+        let wrong_type = b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        let lpush = command(&["LPUSH", "string", "x"]);
+        assert_eq!(
+            send(&mut stream, &lpush, wrong_type.len()).await,
+            wrong_type
+        );
+    }
+
+    #[tokio::test]
+    async fn blocking_pops_time_out_or_get_served_in_order() {
+        let addr = init_server().await;
+
+        let mut first = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut second = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut pusher = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let blpop = command(&["BLPOP", "queue", "0.1"]);
+        assert_eq!(send(&mut first, &blpop, 3).await, b"_\r\n");
+
+        let timeout_err = b"-ERR timeout is negative\r\n";
+        let blpop = command(&["BLPOP", "queue", "-1"]);
+        assert_eq!(
+            send(&mut first, &blpop, timeout_err.len()).await,
+            timeout_err
+        );
+
+        // Clients are served in the order they blocked
+        first
+            .write_all(&command(&["BLPOP", "empty", "queue", "0"]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        second
+            .write_all(&command(&["BRPOP", "queue", "0"]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let rpush = command(&["RPUSH", "queue", "1", "2", "3"]);
+        assert_eq!(send(&mut pusher, &rpush, 11).await, integer(3));
+
+        let first_reply = b"*2\r\n$5\r\nqueue\r\n$1\r\n1\r\n";
+        let mut buffer = vec![0; first_reply.len()];
+        first.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, first_reply);
+
+        let second_reply = b"*2\r\n$5\r\nqueue\r\n$1\r\n3\r\n";
+        let mut buffer = vec![0; second_reply.len()];
+        second.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, second_reply);
+
+        // BLMOVE pushes the value to the destination once it gets served
+        first
+            .write_all(&command(&[
+                "BLMOVE", "source", "queue", "LEFT", "LEFT", "0",
+            ]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let lpush = command(&["LPUSH", "source", "moved"]);
+        assert_eq!(send(&mut pusher, &lpush, 11).await, integer(1));
+
+        let mut buffer = vec![0; 11];
+        first.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, b"$5\r\nmoved\r\n");
+
+        let lrange = command(&["LRANGE", "queue", "0", "-1"]);
+        let values = b"*2\r\n$5\r\nmoved\r\n$1\r\n2\r\n";
+        assert_eq!(send(&mut pusher, &lrange, values.len()).await, values);
+    }
+
+    #[tokio::test]
+    async fn exec_wakes_blocked_clients_once_it_is_done() {
+        let addr = init_server().await;
+
+        let mut blocked = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        blocked
+            .write_all(&command(&["BLPOP", "queue", "0"]))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The value is gone by the time the transaction is done, the client stays blocked
+        let multi = command(&["MULTI"]);
+        let queued = b"+QUEUED\r\n";
+        assert_eq!(send(&mut stream, &multi, 5).await, b"+OK\r\n");
+        let lpush = command(&["LPUSH", "queue", "a"]);
+        assert_eq!(send(&mut stream, &lpush, queued.len()).await, queued);
+        let lpop = command(&["LPOP", "queue"]);
+        assert_eq!(send(&mut stream, &lpop, queued.len()).await, queued);
+
+        let mut exec_reply = b"*2\r\n".to_vec();
+        exec_reply.extend(integer(1));
+        exec_reply.extend(b"$1\r\na\r\n");
+        let exec = command(&["EXEC"]);
+        assert_eq!(send(&mut stream, &exec, exec_reply.len()).await, exec_reply);
+
+        let mut buffer = [0; 1];
+        let not_served = timeout(Duration::from_millis(100), blocked.read(&mut buffer)).await;
+        assert!(not_served.is_err());
+
+        // Blocking commands don't block inside of a transaction
+        assert_eq!(send(&mut stream, &multi, 5).await, b"+OK\r\n");
+        let blpop = command(&["BLPOP", "other", "0"]);
+        assert_eq!(send(&mut stream, &blpop, queued.len()).await, queued);
+        let rpush = command(&["RPUSH", "queue", "b"]);
+        assert_eq!(send(&mut stream, &rpush, queued.len()).await, queued);
+
+        let mut exec_reply = b"*2\r\n_\r\n".to_vec();
+        exec_reply.extend(integer(1));
+        assert_eq!(send(&mut stream, &exec, exec_reply.len()).await, exec_reply);
+
+        let served = b"*2\r\n$5\r\nqueue\r\n$1\r\nb\r\n";
+        let mut buffer = vec![0; served.len()];
+        blocked.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, served);
+    }
+
+    #[tokio::test]
+    async fn transactions_with_errors_are_discarded() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[&str], &[u8]); 10] = [
+            (&["EXEC"], b"-ERR EXEC without MULTI\r\n"),
+            (&["DISCARD"], b"-ERR DISCARD without MULTI\r\n"),
+            (&["MULTI"], b"+OK\r\n"),
+            (&["MULTI"], b"-ERR MULTI calls can not be nested\r\n"),
+            (&["SET", "a", "1"], b"+QUEUED\r\n"),
+            (&["NOPE"], b"-ERR unknown command 'nope'\r\n"),
+            (
+                &["EXEC"],
+                b"-EXECABORT Transaction discarded because of previous errors.\r\n",
+            ),
+            (&["MULTI"], b"+OK\r\n"),
+            (&["SET", "a", "1"], b"+QUEUED\r\n"),
+            (&["DISCARD"], b"+OK\r\n"),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+
+        // Nothing got written
+        let get = command(&["GET", "a"]);
+        assert_eq!(send(&mut stream, &get, 3).await, b"_\r\n");
+    }
+
+    #[tokio::test]
+    async fn exec_does_not_hold_the_lock_while_writing_replies() {
+        let addr = init_server().await;
+
+        let mut stuck = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut other = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Far more than the socket buffers can take
+        let value = "a".repeat(20 * 1024 * 1024);
+        let set = command(&["SET", "big", &value]);
+        assert_eq!(send(&mut stuck, &set, 5).await, b"+OK\r\n");
+
+        // The client never reads the reply of EXEC
+        let transaction = [
+            command(&["MULTI"]),
+            command(&["GET", "big"]),
+            command(&["EXEC"]),
+        ];
+        stuck.write_all(&transaction.concat()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let set = command(&["SET", "small", "1"]);
+        let reply = timeout(Duration::from_secs(2), send(&mut other, &set, 5)).await;
+        assert_eq!(reply.unwrap(), b"+OK\r\n");
+        let get = command(&["GET", "small"]);
+        let reply = timeout(Duration::from_secs(2), send(&mut other, &get, 7)).await;
+        assert_eq!(reply.unwrap(), bulk("1"));
+    }
+
+    #[tokio::test]
+    async fn streams_add_range_and_trim() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let wrong_type = b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        let replies: [(&[&str], Vec<u8>); 11] = [
+            (&["XADD", "events", "1-1", "a", "1"], bulk("1-1")),
+            (&["XADD", "events", "1-*", "b", "2"], bulk("1-2")),
+            (
+                &["XADD", "events", "1-1", "c", "3"],
+                b"-ERR The ID specified in XADD is equal or smaller than the target stream top item\r\n".to_vec(),
+            ),
+            (
+                &["XADD", "events", "MAXLEN", "2", "2-0", "c", "3"],
+                bulk("2-0"),
+            ),
+            (&["XLEN", "events"], integer(2)),
+            (
+                &["XRANGE", "events", "-", "+"],
+                [b"*2\r\n".to_vec(), entry("1-2", "b", "2"), entry("2-0", "c", "3")].concat(),
+            ),
+            (
+                &["XREVRANGE", "events", "+", "-", "COUNT", "1"],
+                [b"*1\r\n".to_vec(), entry("2-0", "c", "3")].concat(),
+            ),
+            // ( excludes the ID
+            (
+                &["XRANGE", "events", "(1-2", "+"],
+                [b"*1\r\n".to_vec(), entry("2-0", "c", "3")].concat(),
+            ),
+            (
+                &["XRANGE", "events", "nope", "+"],
+                b"-ERR Invalid stream ID specified as stream command argument\r\n".to_vec(),
+            ),
+            (&["GET", "events"], wrong_type.to_vec()),
+            (
+                &["XADD", "missing", "NOMKSTREAM", "*", "a", "1"],
+                b"_\r\n".to_vec(),
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+    }
+
+    #[tokio::test]
+    async fn xread_blocks_until_entries_are_added() {
+        let addr = init_server().await;
+
+        let mut first = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut second = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut writer = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let xread = command(&["XREAD", "BLOCK", "50", "STREAMS", "events", "$"]);
+        assert_eq!(send(&mut first, &xread, 3).await, b"_\r\n");
+
+        // Every client reading the stream gets the entry
+        let xread = command(&[
+            "XREAD", "BLOCK", "0", "STREAMS", "other", "events", "0", "$",
+        ]);
+        first.write_all(&xread).await.unwrap();
+        second.write_all(&xread).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let xadd = command(&["XADD", "events", "5-0", "f", "v"]);
+        assert_eq!(send(&mut writer, &xadd, 9).await, bulk("5-0"));
+
+        let read = [
+            b"*1\r\n*2\r\n".to_vec(),
+            bulk("events"),
+            b"*1\r\n".to_vec(),
+            entry("5-0", "f", "v"),
+        ]
+        .concat();
+        for reader in [&mut first, &mut second] {
+            let mut buffer = vec![0; read.len()];
+            reader.read_exact(&mut buffer).await.unwrap();
+            assert_eq!(buffer, read);
+        }
+
+        // Entries after the ID are read right away
+        let xread = command(&["XREAD", "COUNT", "1", "STREAMS", "events", "0-0"]);
+        assert_eq!(send(&mut writer, &xread, read.len()).await, read);
+    }
+
+    #[tokio::test]
+    async fn consumer_groups_share_and_claim_entries() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[&str], Vec<u8>); 13] = [
+            (
+                &["XGROUP", "CREATE", "jobs", "workers", "$"],
+                b"-ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.\r\n".to_vec(),
+            ),
+            (
+                &["XGROUP", "CREATE", "jobs", "workers", "$", "MKSTREAM"],
+                b"+OK\r\n".to_vec(),
+            ),
+            (
+                &["XGROUP", "CREATE", "jobs", "workers", "0"],
+                b"-BUSYGROUP Consumer Group name already exists\r\n".to_vec(),
+            ),
+            (&["XADD", "jobs", "1-0", "job", "a"], bulk("1-0")),
+            (&["XADD", "jobs", "2-0", "job", "b"], bulk("2-0")),
+            (
+                &["XREADGROUP", "GROUP", "workers", "alice", "COUNT", "1", "STREAMS", "jobs", ">"],
+                [b"*1\r\n*2\r\n".to_vec(), bulk("jobs"), b"*1\r\n".to_vec(), entry("1-0", "job", "a")].concat(),
+            ),
+            (
+                &["XREADGROUP", "GROUP", "workers", "bob", "STREAMS", "jobs", ">"],
+                [b"*1\r\n*2\r\n".to_vec(), bulk("jobs"), b"*1\r\n".to_vec(), entry("2-0", "job", "b")].concat(),
+            ),
+            (
+                &["XPENDING", "jobs", "workers"],
+                [
+                    b"*4\r\n".to_vec(),
+                    integer(2),
+                    bulk("1-0"),
+                    bulk("2-0"),
+                    b"*2\r\n*2\r\n".to_vec(),
+                    bulk("alice"),
+                    bulk("1"),
+                    b"*2\r\n".to_vec(),
+                    bulk("bob"),
+                    bulk("1"),
+                ]
+                .concat(),
+            ),
+            (&["XACK", "jobs", "workers", "1-0", "9-0"], integer(1)),
+            (
+                &["XCLAIM", "jobs", "workers", "alice", "0", "2-0", "JUSTID"],
+                [b"*1\r\n".to_vec(), bulk("2-0")].concat(),
+            ),
+            // History reads list the consumer's pending entries
+            (
+                &["XREADGROUP", "GROUP", "workers", "alice", "STREAMS", "jobs", "0"],
+                [b"*1\r\n*2\r\n".to_vec(), bulk("jobs"), b"*1\r\n".to_vec(), entry("2-0", "job", "b")].concat(),
+            ),
+            (
+                &["XAUTOCLAIM", "jobs", "workers", "bob", "0", "0-0"],
+                [b"*3\r\n".to_vec(), bulk("0-0"), b"*1\r\n".to_vec(), entry("2-0", "job", "b"), b"*0\r\n".to_vec()].concat(),
+            ),
+            (
+                &["XINFO", "GROUPS", "jobs"],
+                [
+                    b"*1\r\n*8\r\n".to_vec(),
+                    bulk("name"),
+                    bulk("workers"),
+                    bulk("consumers"),
+                    integer(2),
+                    bulk("pending"),
+                    integer(1),
+                    bulk("last-delivered-id"),
+                    bulk("2-0"),
+                ]
+                .concat(),
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+
+        let no_group = b"-NOGROUP No such key 'jobs' or consumer group 'nope'\r\n";
+        let xreadgroup = command(&[
+            "XREADGROUP",
+            "GROUP",
+            "nope",
+            "alice",
+            "STREAMS",
+            "jobs",
+            ">",
+        ]);
+        assert_eq!(
+            send(&mut stream, &xreadgroup, no_group.len()).await,
+            no_group
+        );
+    }
+
+    #[tokio::test]
+    async fn subscribers_get_published_messages() {
+        let addr = init_server().await;
+
+        let mut subscriber = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut publisher = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[&str], Vec<u8>); 2] = [
+            (
+                &["SUBSCRIBE", "news"],
+                push("subscribe", "news", integer(1)),
+            ),
+            (&["PSUBSCRIBE", "n*"], push("psubscribe", "n*", integer(2))),
+        ];
+        for (args, reply) in replies {
+            assert_eq!(
+                send(&mut subscriber, &command(args), reply.len()).await,
+                reply
+            );
+        }
+
+        // Subscribed to the channel and to a pattern that matches it
+        let publish = command(&["PUBLISH", "news", "hi"]);
+        assert_eq!(send(&mut publisher, &publish, 11).await, integer(2));
+        let publish = command(&["PUBLISH", "other", "hi"]);
+        assert_eq!(send(&mut publisher, &publish, 11).await, integer(0));
+
+        let messages = [
+            push("message", "news", bulk("hi")),
+            b"*4\r\n".to_vec(),
+            bulk("pmessage"),
+            bulk("n*"),
+            bulk("news"),
+            bulk("hi"),
+        ]
+        .concat();
+        assert_eq!(read(&mut subscriber, messages.len()).await, messages);
+
+        let not_allowed = b"-ERR Can't execute 'get': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context\r\n";
+        let get = command(&["GET", "key"]);
+        assert_eq!(
+            send(&mut subscriber, &get, not_allowed.len()).await,
+            not_allowed
+        );
+        let pong = [b"*2\r\n".to_vec(), bulk("pong"), bulk("")].concat();
+        let ping = command(&["PING"]);
+        assert_eq!(send(&mut subscriber, &ping, pong.len()).await, pong);
+
+        // Unsubscribing from everything leaves the subscribed mode
+        let unsubscribed = [
+            push("unsubscribe", "news", integer(1)),
+            push("punsubscribe", "n*", integer(0)),
+        ]
+        .concat();
+        subscriber
+            .write_all(&[command(&["UNSUBSCRIBE"]), command(&["PUNSUBSCRIBE"])].concat())
+            .await
+            .unwrap();
+        assert_eq!(
+            read(&mut subscriber, unsubscribed.len()).await,
+            unsubscribed
+        );
+        assert_eq!(send(&mut subscriber, &ping, 7).await, b"+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn keyspace_notifications_are_published() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let addr = listener.local_addr().unwrap();
+
+        let db = DataStore::new();
+        db.notifications.set_flags("KEA").unwrap();
+        tokio::spawn(Listener::new(listener, db).run());
+
+        let mut subscriber = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        let mut writer = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let subscribed = [
+            push("psubscribe", "__keyevent@0__:*", integer(1)),
+            push("subscribe", "__keyspace@0__:queue", integer(2)),
+        ]
+        .concat();
+        subscriber
+            .write_all(
+                &[
+                    command(&["PSUBSCRIBE", "__keyevent@0__:*"]),
+                    command(&["SUBSCRIBE", "__keyspace@0__:queue"]),
+                ]
+                .concat(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(read(&mut subscriber, subscribed.len()).await, subscribed);
+
+        let replies: [(&[&str], Vec<u8>); 4] = [
+            (&["SET", "key", "value"], b"+OK\r\n".to_vec()),
+            (&["EXPIRE", "key", "1"], integer(1)),
+            (&["RPUSH", "queue", "a"], integer(1)),
+            (&["LPOP", "queue"], bulk("a")),
+        ];
+        for (args, reply) in replies {
+            assert_eq!(send(&mut writer, &command(args), reply.len()).await, reply);
+        }
+
+        let keyevent = |event: &str, key: &str| {
+            [
+                b"*4\r\n".to_vec(),
+                bulk("pmessage"),
+                bulk("__keyevent@0__:*"),
+                bulk(&format!("__keyevent@0__:{event}")),
+                bulk(key),
+            ]
+            .concat()
+        };
+        let keyspace = |event: &str| push("message", "__keyspace@0__:queue", bulk(event));
+
+        let events = [
+            keyevent("set", "key"),
+            keyevent("expire", "key"),
+            keyspace("rpush"),
+            keyevent("rpush", "queue"),
+            keyspace("lpop"),
+            keyevent("lpop", "queue"),
+            // The list is removed once it is empty
+            keyspace("del"),
+            keyevent("del", "queue"),
+        ]
+        .concat();
+        assert_eq!(read(&mut subscriber, events.len()).await, events);
+
+        // Expired keys are noticed when they are read
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        let get = command(&["GET", "key"]);
+        assert_eq!(send(&mut writer, &get, 3).await, b"_\r\n");
+
+        let expired = keyevent("expired", "key");
+        assert_eq!(read(&mut subscriber, expired.len()).await, expired);
+    }
+
+    #[tokio::test]
+    async fn config_get_set_and_rewrite() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let port = addr.port().to_string();
+        let replies: [(&[&str], Vec<u8>); 8] = [
+            (
+                &["CONFIG", "GET", "port", "slowlog-*"],
+                [
+                    b"*6\r\n".to_vec(),
+                    bulk("port"),
+                    bulk(&port),
+                    bulk("slowlog-log-slower-than"),
+                    bulk("10000"),
+                    bulk("slowlog-max-len"),
+                    bulk("128"),
+                ]
+                .concat(),
+            ),
+            (
+                &["CONFIG", "SET", "timeout", "30", "maxclients", "5"],
+                b"+OK\r\n".to_vec(),
+            ),
+            (
+                &["CONFIG", "GET", "TIMEOUT"],
+                [b"*2\r\n".to_vec(), bulk("timeout"), bulk("30")].concat(),
+            ),
+            (
+                &["CONFIG", "SET", "port", "1"],
+                b"-ERR CONFIG SET failed (possibly related to argument 'port') - can't set immutable config\r\n".to_vec(),
+            ),
+            (
+                &["CONFIG", "SET", "nope", "1"],
+                b"-ERR Unknown option or number of arguments for CONFIG SET - 'nope'\r\n".to_vec(),
+            ),
+            (
+                &["CONFIG", "SET", "timeout"],
+                b"-ERR wrong number of arguments for 'config|set' command\r\n".to_vec(),
+            ),
+            (&["CONFIG", "GET", "nope"], b"*0\r\n".to_vec()),
+            (
+                &["CONFIG", "REWRITE"],
+                b"-ERR The server is running without a config file\r\n".to_vec(),
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+    }
+
+    #[tokio::test]
+    async fn bitmaps_set_count_and_combine_bits() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let wrong_type = b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n";
+        let replies: [(&[&str], Vec<u8>); 20] = [
+            (&["SETBIT", "bits", "7", "1"], integer(0)),
+            (&["SETBIT", "bits", "7", "1"], integer(1)),
+            (&["SETBIT", "bits", "2", "1"], integer(0)),
+            (
+                &["SETBIT", "bits", "0", "2"],
+                b"-ERR bit is not an integer or out of range\r\n".to_vec(),
+            ),
+            (&["GETBIT", "bits", "2"], integer(1)),
+            (&["GETBIT", "bits", "100"], integer(0)),
+            (&["GET", "bits"], bulk("!")),
+            (&["SET", "text", "foobar"], b"+OK\r\n".to_vec()),
+            (&["BITCOUNT", "text"], integer(26)),
+            (&["BITCOUNT", "text", "1", "1"], integer(6)),
+            (&["BITCOUNT", "text", "5", "30", "BIT"], integer(17)),
+            (&["BITPOS", "bits", "1"], integer(2)),
+            (&["BITPOS", "bits", "1", "3", "-1", "BIT"], integer(7)),
+            // Negative integers are sent in two's complement
+            (&["BITPOS", "missing", "1"], integer(-1_i64 as u64)),
+            (&["BITOP", "AND", "and", "text", "bits"], integer(6)),
+            (&["BITCOUNT", "and"], integer(1)),
+            (&["BITOP", "OR", "or", "bits", "bits"], integer(1)),
+            (&["GET", "or"], bulk("!")),
+            (
+                &["BITOP", "NOT", "not", "bits", "text"],
+                b"-ERR BITOP NOT must be called with a single source key.\r\n".to_vec(),
+            ),
+            (&["LPUSH", "list", "a"], integer(1)),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+
+        let getbit = command(&["GETBIT", "list", "0"]);
+        assert_eq!(
+            send(&mut stream, &getbit, wrong_type.len()).await,
+            wrong_type
+        );
+
+        // Overflow behaviour applies to the operations that follow it
+        let mut reply = b"*2\r\n".to_vec();
+        reply.extend(integer(0));
+        reply.extend(integer(255));
+        let bitfield = command(&[
+            "BITFIELD", "field", "SET", "u8", "0", "255", "GET", "u8", "0",
+        ]);
+        assert_eq!(send(&mut stream, &bitfield, reply.len()).await, reply);
+
+        let mut reply = b"*4\r\n".to_vec();
+        reply.extend(integer(9));
+        reply.extend(integer(255));
+        reply.extend(b"_\r\n");
+        reply.extend(integer(-1_i64 as u64));
+        let bitfield = command(&[
+            "BITFIELD", "field", "INCRBY", "u8", "0", "10", "OVERFLOW", "SAT", "INCRBY", "u8", "0",
+            "300", "OVERFLOW", "FAIL", "INCRBY", "u8", "0", "1", "GET", "i8", "0",
+        ]);
+        assert_eq!(send(&mut stream, &bitfield, reply.len()).await, reply);
+    }
+
+    #[tokio::test]
+    async fn hyperloglogs_count_unique_elements() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let replies: [(&[&str], Vec<u8>); 11] = [
+            (&["PFADD", "first", "a", "b", "c"], integer(1)),
+            (&["PFADD", "first", "a"], integer(0)),
+            (&["PFCOUNT", "first"], integer(3)),
+            (&["PFADD", "second", "c", "d"], integer(1)),
+            (&["PFCOUNT", "first", "second"], integer(4)),
+            (
+                &["PFMERGE", "merged", "first", "second"],
+                b"+OK\r\n".to_vec(),
+            ),
+            (&["PFCOUNT", "merged"], integer(4)),
+            // An empty HyperLogLog is created when there are no elements
+            (&["PFADD", "empty"], integer(1)),
+            (&["PFADD", "empty"], integer(0)),
+            (&["SET", "text", "foobar"], b"+OK\r\n".to_vec()),
+            (
+                &["PFCOUNT", "text"],
+                b"-WRONGTYPE Key is not a valid HyperLogLog string value.\r\n".to_vec(),
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+    }
+
+    #[tokio::test]
+    async fn geo_commands_match_redis() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // Same places as in the Redis docs, so the replies are the ones Redis gives
+        let geoadd = command(&[
+            "GEOADD",
+            "Sicily",
+            "13.361389",
+            "38.115556",
+            "Palermo",
+            "15.087269",
+            "37.502669",
+            "Catania",
+        ]);
+        assert_eq!(send(&mut stream, &geoadd, 11).await, integer(2));
+        let geoadd = command(&[
+            "GEOADD",
+            "Sicily",
+            "12.758489",
+            "38.788135",
+            "edge1",
+            "17.241510",
+            "38.788135",
+            "edge2",
+        ]);
+        assert_eq!(send(&mut stream, &geoadd, 11).await, integer(2));
+
+        let replies: [(&[&str], Vec<u8>); 9] = [
+            (
+                &["GEOADD", "Sicily", "NX", "13", "38", "Palermo"],
+                integer(0),
+            ),
+            (
+                &["GEOADD", "Sicily", "13", "86", "Pole"],
+                b"-ERR invalid longitude,latitude pair 13.000000,86.000000\r\n".to_vec(),
+            ),
+            (
+                &["GEODIST", "Sicily", "Palermo", "Catania"],
+                bulk("166274.1516"),
+            ),
+            (
+                &["GEODIST", "Sicily", "Palermo", "Catania", "KM"],
+                bulk("166.2742"),
+            ),
+            (&["GEODIST", "Sicily", "Palermo", "nope"], b"_\r\n".to_vec()),
+            (
+                &["GEOHASH", "Sicily", "Palermo", "Catania", "nope"],
+                [
+                    b"*3\r\n".to_vec(),
+                    bulk("sqc8b49rny0"),
+                    bulk("sqdtr74hyu0"),
+                    b"_\r\n".to_vec(),
+                ]
+                .concat(),
+            ),
+            (&["GEOPOS", "Sicily", "nope"], b"*1\r\n_\r\n".to_vec()),
+            (
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMLONLAT",
+                    "15",
+                    "37",
+                    "BYRADIUS",
+                    "200",
+                    "KM",
+                    "ASC",
+                ],
+                [b"*2\r\n".to_vec(), bulk("Catania"), bulk("Palermo")].concat(),
+            ),
+            (
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMMEMBER",
+                    "nope",
+                    "BYRADIUS",
+                    "1",
+                    "M",
+                ],
+                b"-ERR could not decode requested zset member\r\n".to_vec(),
+            ),
+        ];
+
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(args), reply.len()).await, reply);
+        }
+
+        let with_dist = |member: &str, distance: &str| {
+            [b"*2\r\n".to_vec(), bulk(member), bulk(distance)].concat()
+        };
+        let reply = [
+            b"*4\r\n".to_vec(),
+            with_dist("Catania", "56.4413"),
+            with_dist("Palermo", "190.4424"),
+            with_dist("edge2", "279.7403"),
+            with_dist("edge1", "279.7405"),
+        ]
+        .concat();
+        let geosearch = command(&[
+            "GEOSEARCH",
+            "Sicily",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYBOX",
+            "400",
+            "400",
+            "km",
+            "ASC",
+            "WITHDIST",
+        ]);
+        assert_eq!(send(&mut stream, &geosearch, reply.len()).await, reply);
+
+        // COUNT keeps the closest members unless DESC asks for the furthest ones
+        let reply = [b"*1\r\n".to_vec(), bulk("Catania")].concat();
+        let geosearch = command(&[
+            "GEOSEARCH",
+            "Sicily",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYBOX",
+            "400",
+            "400",
+            "km",
+            "COUNT",
+            "1",
+        ]);
+        assert_eq!(send(&mut stream, &geosearch, reply.len()).await, reply);
+
+        let reply = [b"*1\r\n".to_vec(), bulk("edge1")].concat();
+        let geosearch = command(&[
+            "GEOSEARCH",
+            "Sicily",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYBOX",
+            "400",
+            "400",
+            "km",
+            "COUNT",
+            "1",
+            "DESC",
+        ]);
+        assert_eq!(send(&mut stream, &geosearch, reply.len()).await, reply);
+    }
+    #[tokio::test]
+    async fn dump_restore_and_inspect_keys() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        // RESTORE key ttl payload [options], the payload is binary so it can't go through command()
+        let restore = |key: &str, ttl: &str, payload: &[u8], options: &[&str]| {
+            let mut restore = format!(
+                "*{}\r\n$7\r\nRESTORE\r\n${}\r\n{key}\r\n${}\r\n{ttl}\r\n${}\r\n",
+                4 + options.len(),
+                key.len(),
+                ttl.len(),
+                payload.len()
+            )
+            .into_bytes();
+            restore.extend(payload);
+            restore.extend(b"\r\n");
+            restore.extend(
+                command(options)
+                    .into_iter()
+                    .skip_while(|byte| *byte != b'$'),
+            );
+            restore
+        };
+
+        let set = command(&["SET", "greeting", "hello"]);
+        assert_eq!(send(&mut stream, &set, 5).await, b"+OK\r\n");
+
+        // type, time to live, length and value, version and checksum
+        let payload_len = 1 + 8 + 8 + 5 + 2 + 8;
+        let header = format!("${payload_len}\r\n");
+        let dump = command(&["DUMP", "greeting"]);
+        let reply = send(&mut stream, &dump, header.len() + payload_len + 2).await;
+        assert!(reply.starts_with(header.as_bytes()));
+        let payload = reply[header.len()..header.len() + payload_len].to_vec();
+
+        let dump = command(&["DUMP", "nope"]);
+        assert_eq!(send(&mut stream, &dump, 3).await, b"_\r\n");
+
+        let reply = b"-BUSYKEY Target key name already exists.\r\n";
+        let restore_cmd = restore("greeting", "0", &payload, &[]);
+        assert_eq!(send(&mut stream, &restore_cmd, reply.len()).await, reply);
+        let restore_cmd = restore("greeting", "0", &payload, &["REPLACE"]);
+        assert_eq!(send(&mut stream, &restore_cmd, 5).await, b"+OK\r\n");
+
+        let mut corrupted = payload.clone();
+        corrupted[payload_len - 1] ^= 1;
+        let reply = b"-ERR DUMP payload version or checksum are wrong\r\n";
+        let restore_cmd = restore("copy", "0", &corrupted, &[]);
+        assert_eq!(send(&mut stream, &restore_cmd, reply.len()).await, reply);
+
+        let reply = b"-ERR Invalid TTL value, must be >= 0\r\n";
+        let restore_cmd = restore("copy", "-1", &payload, &[]);
+        assert_eq!(send(&mut stream, &restore_cmd, reply.len()).await, reply);
+
+        // A time to live in the past doesn't create the key
+        let restore_cmd = restore("gone", "1", &payload, &["ABSTTL"]);
+        assert_eq!(send(&mut stream, &restore_cmd, 5).await, b"+OK\r\n");
+        let get = command(&["GET", "gone"]);
+        assert_eq!(send(&mut stream, &get, 3).await, b"_\r\n");
+
+        let reply = b"-ERR syntax error\r\n";
+        let restore_cmd = restore("copy", "0", &payload, &["IDLETIME", "100", "FREQ", "20"]);
+        assert_eq!(send(&mut stream, &restore_cmd, reply.len()).await, reply);
+
+        let restore_cmd = restore("freq", "0", &payload, &["FREQ", "20"]);
+        assert_eq!(send(&mut stream, &restore_cmd, 5).await, b"+OK\r\n");
+        let restore_cmd = restore("copy", "0", &payload, &["IDLETIME", "100"]);
+        assert_eq!(send(&mut stream, &restore_cmd, 5).await, b"+OK\r\n");
+
+        // OBJECT does not count as an access
+        let object = command(&["OBJECT", "IDLETIME", "copy"]);
+        assert_eq!(send(&mut stream, &object, 11).await, integer(100));
+        let object = command(&["OBJECT", "FREQ", "freq"]);
+        assert_eq!(send(&mut stream, &object, 11).await, integer(20));
+
+        let get = command(&["GET", "copy"]);
+        assert_eq!(send(&mut stream, &get, 11).await, bulk("hello"));
+        let object = command(&["OBJECT", "IDLETIME", "copy"]);
+        assert_eq!(send(&mut stream, &object, 11).await, integer(0));
+
+        let set = command(&["SET", "number", "12345"]);
+        assert_eq!(send(&mut stream, &set, 5).await, b"+OK\r\n");
+        let replies = [
+            (["OBJECT", "ENCODING", "copy"], bulk("embstr")),
+            (["OBJECT", "ENCODING", "number"], bulk("int")),
+            (["OBJECT", "ENCODING", "nope"], b"_\r\n".to_vec()),
+        ];
+        for (args, reply) in replies {
+            assert_eq!(send(&mut stream, &command(&args), reply.len()).await, reply);
+        }
+
+        // The key and the value, each with the size of a Bytes on top
+        let overhead = std::mem::size_of::<bytes::Bytes>() as u64;
+        let memory = command(&["MEMORY", "USAGE", "greeting"]);
+        let reply = integer(8 + 5 + 2 * overhead);
+        assert_eq!(send(&mut stream, &memory, reply.len()).await, reply);
+        let memory = command(&["MEMORY", "USAGE", "nope", "SAMPLES", "0"]);
+        assert_eq!(send(&mut stream, &memory, 3).await, b"_\r\n");
+    }
+
+    #[tokio::test]
+    async fn dump_and_restore_keys_with_the_largest_expiry() {
+        let addr = init_server().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let set = command(&["SET", "greeting", "hello"]);
+        assert_eq!(send(&mut stream, &set, 5).await, b"+OK\r\n");
+        let expire = command(&["EXPIRE", "greeting", &i64::MAX.to_string()]);
+        assert_eq!(send(&mut stream, &expire, 11).await, integer(1));
+
+        // type, time to live, length and value, version and checksum
+        let payload_len = 1 + 8 + 8 + 5 + 2 + 8;
+        let header = format!("${payload_len}\r\n");
+        let dump = command(&["DUMP", "greeting"]);
+        let reply = send(&mut stream, &dump, header.len() + payload_len + 2).await;
+        assert!(reply.starts_with(header.as_bytes()));
+
+        // The milliseconds that are left don't fit, the largest time to live is dumped instead
+        let payload = &reply[header.len()..header.len() + payload_len];
+        let ttl = u64::from_le_bytes(payload[1..9].try_into().unwrap());
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        assert!(ttl >= u64::MAX - now);
+
+        let ttl = i64::MAX.to_string();
+        let mut restore = format!(
+            "*4\r\n$7\r\nRESTORE\r\n$4\r\ncopy\r\n${}\r\n{ttl}\r\n{header}",
+            ttl.len()
+        )
+        .into_bytes();
+        restore.extend(payload);
+        restore.extend(b"\r\n");
+        assert_eq!(send(&mut stream, &restore, 5).await, b"+OK\r\n");
+
+        let get = command(&["GET", "copy"]);
+        assert_eq!(send(&mut stream, &get, 11).await, bulk("hello"));
+        let dump = command(&["DUMP", "copy"]);
+        let reply = send(&mut stream, &dump, header.len() + payload_len + 2).await;
+        assert!(reply.starts_with(header.as_bytes()));
+    }
 }
@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod metrics {
+    use std::net::SocketAddr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use vivs::{DataStore, Listener, MetricsListener};
+
+    /// Starts a server and its metrics listener, returns both addresses
+    async fn init_servers() -> (SocketAddr, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let metrics_listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind to OS chosen port");
+        let address = listener.local_addr().unwrap();
+        let metrics_address = metrics_listener.local_addr().unwrap();
+
+        let listener = Listener::new(listener, DataStore::new());
+        let metrics_listener = MetricsListener::new(metrics_listener, &listener, false).unwrap();
+
+        tokio::spawn(async move { listener.run().await });
+        tokio::spawn(async move { metrics_listener.run().await });
+
+        (address, metrics_address)
+    }
+
+    async fn http_get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+
+        // The server closes the connection once the response is written
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn metrics_are_exported() {
+        let (addr, metrics_addr) = init_servers().await;
+
+        let mut stream = TcpStream::connect(addr)
+            .await
+            .expect("Failed to open a TCP connection");
+        stream
+            .write_all(b"*3\r\n$3\r\nSET\r\n$8\r\ngreeting\r\n$5\r\nhello\r\n")
+            .await
+            .unwrap();
+
+        let mut buffer = [0; 5];
+        let _ = stream.read_exact(&mut buffer).await;
+
+        let response = http_get(metrics_addr, "/metrics").await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("vivs_connected_clients 1\n"));
+        assert!(response.contains("vivs_keys{db=\"0\"} 1\n"));
+        assert!(response.contains("vivs_commands_processed_total{command=\"set\"} 1\n"));
+        assert!(response.contains("vivs_command_duration_seconds_count{command=\"set\"} 1\n"));
+        assert!(response
+            .contains("vivs_command_duration_seconds_bucket{command=\"set\",le=\"+Inf\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn health_check_reports_cluster_state() {
+        let (_, metrics_addr) = init_servers().await;
+
+        let response = http_get(metrics_addr, "/healthz").await;
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("\"cluster_state\":\"disabled\""));
+
+        let response = http_get(metrics_addr, "/nothing").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[tokio::test]
+    async fn idle_connection_does_not_block_health_checks() {
+        let (_, metrics_addr) = init_servers().await;
+
+        // Connects but never sends a request
+        let _idle = TcpStream::connect(metrics_addr)
+            .await
+            .expect("Failed to open a TCP connection");
+
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(2),
+            http_get(metrics_addr, "/healthz"),
+        )
+        .await
+        .expect("Health check was blocked by the idle connection");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+    }
+}
@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod testing {
+    use std::time::Duration;
+    use vivs::{
+        client::{ClientError, Cmd, FromReply},
+        cluster::key_slot,
+        data_chunk::DataChunk,
+        testing::{TestCluster, TestServer},
+        Client,
+    };
+
+    // Whether a client can still connect to the address
+    async fn is_listening(address: &str) -> bool {
+        Client::builder()
+            .address(address)
+            .reconnect(1, Duration::ZERO)
+            .connect()
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn server_uses_in_memory_config_and_shuts_down() {
+        let server = TestServer::builder()
+            .config("databases = 4".parse().unwrap())
+            .start()
+            .await
+            .unwrap();
+        let mut client = server.client().await.unwrap();
+
+        let databases: Vec<String> = client
+            .query(&Cmd::new("CONFIG").arg("GET").arg("databases"))
+            .await
+            .unwrap();
+        assert_eq!(databases, ["databases", "4"]);
+
+        let selected: String = client.query(&Cmd::new("SELECT").arg("3")).await.unwrap();
+        assert_eq!(selected, "OK");
+        let out_of_range = client.query::<String>(&Cmd::new("SELECT").arg("4")).await;
+        assert!(matches!(out_of_range, Err(ClientError::Server(_))));
+
+        // Keys can be checked without going through a client
+        client.set("greeting", "hello").await.unwrap();
+        let db = server.db().db(3).unwrap();
+        assert!(db.db.read().await.contains_key(&b"greeting"[..]));
+
+        let address = server.address();
+        server.shutdown().await.unwrap();
+        assert!(!is_listening(&address).await);
+
+        // Dropping the server stops it too
+        let server = TestServer::start().await.unwrap();
+        let address = server.address();
+        assert!(is_listening(&address).await);
+        drop(server);
+
+        let mut listening = true;
+        for _ in 0..50 {
+            listening = is_listening(&address).await;
+            if !listening {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(!listening);
+    }
+
+    #[tokio::test]
+    async fn cluster_splits_slots_between_nodes() {
+        let cluster = TestCluster::start(3).await.unwrap();
+        let mut client = cluster.client().await.unwrap();
+        assert_eq!(client.slots().ranges().len(), 3);
+
+        let keys = (0..100).map(|i| format!("key{i}")).collect::<Vec<_>>();
+        for key in &keys {
+            client.set(key, key).await.unwrap();
+        }
+
+        // Every key ended up on the node that serves its slot, and every node got some of them
+        let mut counts = vec![0; cluster.nodes().len()];
+        for key in &keys {
+            let value: Option<String> = client.get(key).await.unwrap();
+            assert_eq!(value.as_deref(), Some(key.as_str()));
+
+            let node = cluster.node_for(key).unwrap();
+            let db = node.db().db(0).unwrap();
+            assert!(db.db.read().await.contains_key(key.as_bytes()));
+
+            let index = cluster
+                .nodes()
+                .iter()
+                .position(|other| other.addr() == node.addr())
+                .unwrap();
+            counts[index] += 1;
+        }
+        assert!(counts.iter().all(|count| *count > 0), "{counts:?}");
+
+        // Nodes redirect keys that they don't serve
+        let first = &cluster.nodes()[0];
+        let key = keys
+            .iter()
+            .find(|key| cluster.node_for(key).unwrap().addr() != first.addr())
+            .unwrap();
+        let redirected = first
+            .client()
+            .await
+            .unwrap()
+            .get::<Option<String>>(key)
+            .await;
+        assert!(matches!(redirected, Err(ClientError::Server(err)) if err.starts_with("MOVED")));
+
+        let addresses = cluster
+            .nodes()
+            .iter()
+            .map(|node| node.address())
+            .collect::<Vec<_>>();
+        cluster.shutdown().await.unwrap();
+        for address in addresses {
+            assert!(!is_listening(&address).await);
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_reads_are_routed_by_their_keys() {
+        let cluster = TestCluster::start(2).await.unwrap();
+        let first = &cluster.nodes()[0];
+        let key = (0..)
+            .map(|i| format!("stream{i}"))
+            .find(|key| cluster.node_for(key).unwrap().addr() != first.addr())
+            .unwrap();
+        let owner = cluster.node_for(&key).unwrap();
+        let mut client = owner.client().await.unwrap();
+
+        let id: String = client
+            .query(
+                &Cmd::new("XADD")
+                    .arg(&key)
+                    .arg("*")
+                    .arg("field")
+                    .arg("value"),
+            )
+            .await
+            .unwrap();
+        assert!(!id.is_empty());
+
+        let xread = |keys: &[&str]| {
+            let mut cmd = Cmd::new("XREAD").arg("COUNT").arg("1").arg("STREAMS");
+            for key in keys {
+                cmd = cmd.arg(*key);
+            }
+            for _ in keys {
+                cmd = cmd.arg("0");
+            }
+            cmd
+        };
+
+        let redirected = first
+            .client()
+            .await
+            .unwrap()
+            .query::<String>(&xread(&[&key]))
+            .await;
+        assert!(matches!(redirected, Err(ClientError::Server(err)) if err.starts_with("MOVED")));
+
+        let same_slot = (0..)
+            .map(|i| format!("other{i}"))
+            .find(|other| key_slot(other.as_bytes()) == key_slot(key.as_bytes()))
+            .unwrap();
+        let mut read: Vec<Vec<DataChunk>> =
+            client.query(&xread(&[&key, &same_slot])).await.unwrap();
+        assert_eq!(read.len(), 1);
+        let stream = read.pop().unwrap().into_iter().next().unwrap();
+        assert_eq!(String::from_reply(stream).unwrap(), key);
+
+        let other = (0..)
+            .map(|i| format!("other{i}"))
+            .find(|other| cluster.node_for(other).unwrap().addr() != owner.addr())
+            .unwrap();
+        let cross_slot = client.query::<String>(&xread(&[&key, &other])).await;
+        assert!(
+            matches!(cross_slot, Err(ClientError::Server(err)) if err.starts_with("CROSSSLOT"))
+        );
+
+        cluster.shutdown().await.unwrap();
+    }
+}